@@ -0,0 +1,138 @@
+use kube::{
+    Api, Client,
+    core::{
+        DynamicObject,
+        admission::{AdmissionRequest, AdmissionResponse, AdmissionReview, Operation},
+    },
+};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::{clusters, crds::server::Server};
+
+/// Kinds whose `server_ref` the validating webhook checks.
+pub const VALIDATED_RESOURCES: [&str; 3] = ["indexes", "keys", "tenants"];
+
+/// Answer an API server AdmissionReview, denying objects whose `server_ref` names a Server
+/// that does not exist.
+pub async fn review(
+    client: &Client,
+    review: AdmissionReview<DynamicObject>,
+) -> AdmissionReview<DynamicObject> {
+    let request: AdmissionRequest<DynamicObject> = match review.try_into() {
+        Ok(r) => r,
+        Err(e) => return AdmissionResponse::invalid(e.to_string()).into_review(),
+    };
+    let response = AdmissionResponse::from(&request);
+    let Some((ns, name)) = checked_ref(&request) else {
+        return response.into_review();
+    };
+    match Api::<Server>::namespaced(client.clone(), &ns)
+        .get_metadata_opt(&name)
+        .await
+    {
+        Ok(Some(_)) => response,
+        Ok(None) => response.deny(clusters::missing_server_message(&ns, &name)),
+        // The controller reports it if the lookup was not just a hiccup
+        Err(e) => {
+            warn!(error = ?e, server = %name, namespace = %ns, "server_ref lookup failed");
+            response
+        }
+    }
+    .into_review()
+}
+
+/// Server a create, or an update that retargets `server_ref`, points at. Unchanged references
+/// are not checked again, so edits still go through after the Server was deleted.
+fn checked_ref(request: &AdmissionRequest<DynamicObject>) -> Option<(String, String)> {
+    if !matches!(request.operation, Operation::Create | Operation::Update) {
+        return None;
+    }
+    let ns = request.namespace.as_deref()?;
+    let obj = request.object.as_ref()?;
+    if obj.metadata.deletion_timestamp.is_some() {
+        return None;
+    }
+    let target = server_ref(&obj.data, ns)?;
+    let previous = request
+        .old_object
+        .as_ref()
+        .and_then(|o| server_ref(&o.data, ns));
+    (previous.as_ref() != Some(&target)).then_some(target)
+}
+
+/// Key names a Server in its own namespace, the others use a `ServerRef`.
+fn server_ref(data: &Value, ns: &str) -> Option<(String, String)> {
+    match &data["spec"]["server_ref"] {
+        Value::String(name) => Some((ns.to_string(), name.clone())),
+        Value::Object(r) => Some((
+            r.get("namespace")
+                .and_then(Value::as_str)
+                .unwrap_or(ns)
+                .to_string(),
+            r.get("name")?.as_str()?.to_string(),
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(
+        operation: &str,
+        object: Value,
+        old: Option<Value>,
+    ) -> AdmissionRequest<DynamicObject> {
+        let review: AdmissionReview<DynamicObject> = serde_json::from_value(json!({
+            "apiVersion": "admission.k8s.io/v1",
+            "kind": "AdmissionReview",
+            "request": {
+                "uid": "1",
+                "kind": {"group": "meili.operator.dev", "version": "v1beta1", "kind": "Index"},
+                "resource": {"group": "meili.operator.dev", "version": "v1beta1", "resource": "indexes"},
+                "operation": operation,
+                "namespace": "search",
+                "userInfo": {},
+                "object": object,
+                "oldObject": old,
+                "dryRun": false,
+            }
+        }))
+        .unwrap();
+        review.try_into().unwrap()
+    }
+
+    #[test]
+    fn checks_new_and_retargeted_server_refs() {
+        let index = |server: Value| {
+            json!({"apiVersion": "meili.operator.dev/v1beta1", "kind": "Index",
+                   "metadata": {"name": "movies"}, "spec": {"server_ref": server, "uid": "movies"}})
+        };
+        let created = request("CREATE", index(json!({"name": "meili"})), None);
+        assert_eq!(
+            checked_ref(&created),
+            Some(("search".into(), "meili".into()))
+        );
+        let unchanged = request(
+            "UPDATE",
+            index(json!({"name": "meili"})),
+            Some(index(json!({"name": "meili"}))),
+        );
+        assert_eq!(checked_ref(&unchanged), None);
+        let moved = request(
+            "UPDATE",
+            index(json!({"name": "meili", "namespace": "shared"})),
+            Some(index(json!({"name": "meili"}))),
+        );
+        assert_eq!(checked_ref(&moved), Some(("shared".into(), "meili".into())));
+
+        let key = json!({"spec": {"server_ref": "meili"}});
+        assert_eq!(
+            server_ref(&key, "search"),
+            Some(("search".into(), "meili".into()))
+        );
+    }
+}
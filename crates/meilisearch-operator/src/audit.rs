@@ -0,0 +1,70 @@
+use std::fmt::Display;
+
+use k8s_openapi::api::core::v1::ObjectReference;
+use kube::{
+    Resource,
+    runtime::events::{EventType, Recorder},
+};
+use tracing::info;
+
+use crate::events;
+
+/// Records mutating Meilisearch calls made on behalf of a CR, as `audit` log lines and as
+/// Events on that CR, for compliance reviews.
+pub struct Audit<'a> {
+    recorder: &'a Recorder,
+    reference: ObjectReference,
+    server: String,
+}
+
+impl<'a> Audit<'a> {
+    pub fn new<K: Resource<DynamicType = ()>>(
+        recorder: &'a Recorder,
+        obj: &K,
+        server: &str,
+    ) -> Self {
+        Self {
+            recorder,
+            reference: obj.object_ref(&()),
+            server: server.to_string(),
+        }
+    }
+
+    /// Record `operation` (e.g. "keys.create") on `target` with the call's outcome.
+    pub async fn record<T, E: Display>(
+        &self,
+        operation: &str,
+        target: &str,
+        result: &Result<T, E>,
+    ) {
+        let (outcome, type_, error) = match result {
+            Ok(_) => ("succeeded", EventType::Normal, None),
+            Err(e) => ("failed", EventType::Warning, Some(e.to_string())),
+        };
+        info!(
+            target: "audit",
+            operation,
+            target_object = target,
+            server = %self.server,
+            cr_kind = self.reference.kind.as_deref().unwrap_or_default(),
+            cr_namespace = self.reference.namespace.as_deref().unwrap_or_default(),
+            cr_name = self.reference.name.as_deref().unwrap_or_default(),
+            outcome,
+            error = error.as_deref().unwrap_or_default(),
+            "meilisearch mutation"
+        );
+        let note = match &error {
+            Some(e) => format!("{} {} on {} failed: {}", operation, target, self.server, e),
+            None => format!("{} {} on {}", operation, target, self.server),
+        };
+        events::publish(
+            self.recorder,
+            &self.reference,
+            type_,
+            "MeiliMutation",
+            operation,
+            note,
+        )
+        .await;
+    }
+}
@@ -1,9 +1,38 @@
-use kube::core::CustomResourceExt;
-use meilisearch_operator::crds::{index::Index, key::Key, server::Server};
+use kube::ResourceExt;
+use meilisearch_operator::{crds::index::ConversionWebhook, install};
 
 fn main() {
-    let crds = vec![Server::crd(), Index::crd(), Key::crd()];
-    for (i, crd) in crds.into_iter().enumerate() {
+    let mut namespace = "meilisearch-operator".to_string();
+    let mut webhook = true;
+    let mut only = None;
+    let mut skip = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--namespace" => namespace = args.next().expect("--namespace needs a value"),
+            "--no-webhook" => webhook = false,
+            "--only" => only = Some(args.next().expect("--only needs a CRD name")),
+            "--skip" => skip = Some(args.next().expect("--skip needs a CRD name")),
+            other => {
+                eprintln!(
+                    "usage: crdgen [--namespace NS] [--no-webhook] [--only CRD | --skip CRD]"
+                );
+                eprintln!("unknown argument {}", other);
+                std::process::exit(2);
+            }
+        }
+    }
+    // The Index CRD is converted by the webhook of the operator in `namespace`, with its CA
+    // injected by cert-manager, as manifests/deployment.yaml installs it
+    let conversion = webhook.then_some(ConversionWebhook {
+        namespace,
+        ca_bundle: None,
+    });
+    let crds = install::crds(conversion.as_ref())
+        .into_iter()
+        .filter(|crd| only.as_ref().is_none_or(|n| crd.name_any() == *n))
+        .filter(|crd| skip.as_ref().is_none_or(|n| crd.name_any() != *n));
+    for (i, crd) in crds.enumerate() {
         if i > 0 {
             println!("---");
         }
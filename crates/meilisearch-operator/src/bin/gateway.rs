@@ -0,0 +1,34 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use meilisearch_operator::{
+    gateway::{self, Config, Gateway},
+    shutdown,
+};
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+/// Where the SearchGateway controller mounts the rendered configuration.
+const DEFAULT_CONFIG: &str = "/etc/meilisearch-gateway/config.json";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_target(false)
+        .init();
+
+    let path = std::env::var("GATEWAY_CONFIG").unwrap_or_else(|_| DEFAULT_CONFIG.into());
+    let config: Config = serde_json::from_slice(&std::fs::read(&path)?)?;
+    let gateway = Gateway::new(config, |name| std::env::var(name).ok())?;
+    let port: u16 = std::env::var("PORT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(7700);
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    info!(%addr, config = %path, "meilisearch gateway listening");
+    tokio::select! {
+        res = gateway::serve(addr, Arc::new(gateway)) => res,
+        _ = shutdown::signal() => Ok(()),
+    }
+}
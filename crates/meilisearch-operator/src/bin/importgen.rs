@@ -0,0 +1,27 @@
+use meilisearch_operator::{import, install};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut server = None;
+    let mut endpoint = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server = Some(args.next().expect("--server needs a value")),
+            "--endpoint" => endpoint = Some(args.next().expect("--endpoint needs a value")),
+            other => {
+                eprintln!("usage: importgen --server NAMESPACE/NAME [--endpoint URL]");
+                eprintln!("unknown argument {}", other);
+                std::process::exit(2);
+            }
+        }
+    }
+    let Some((ns, name)) = server.as_deref().and_then(|s| s.split_once('/')) else {
+        eprintln!("usage: importgen --server NAMESPACE/NAME [--endpoint URL]");
+        std::process::exit(2);
+    };
+    let client = kube::Client::try_default().await?;
+    let docs = import::generate(&client, ns, name, endpoint.as_deref()).await?;
+    print!("{}", install::render(&docs));
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use meilisearch_operator::{
+    config::{DEFAULT_GATEWAY_IMAGE, OperatorConfig},
+    install::{self, InstallOptions},
+    meili_http::Limits,
+};
+
+fn main() {
+    let mut namespace = "meilisearch-operator".to_string();
+    let mut image = DEFAULT_GATEWAY_IMAGE.to_string();
+    let mut webhook = true;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--namespace" => namespace = args.next().expect("--namespace needs a value"),
+            "--image" => image = args.next().expect("--image needs a value"),
+            "--no-webhook" => webhook = false,
+            other => {
+                eprintln!("usage: installgen [--namespace NS] [--image IMAGE] [--no-webhook]");
+                eprintln!("unknown argument {}", other);
+                std::process::exit(2);
+            }
+        }
+    }
+    // Operator settings come from the same environment variables the operator reads
+    let mut config = OperatorConfig::from_env();
+    if std::env::var("GATEWAY_IMAGE").is_err() {
+        config.gateway_image = image.clone();
+    }
+    let opts = InstallOptions {
+        namespace,
+        image,
+        webhook,
+        config,
+        http: Limits::from_env(),
+    };
+    print!("{}", install::render(&install::bundle(&opts)));
+}
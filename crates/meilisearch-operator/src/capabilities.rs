@@ -0,0 +1,110 @@
+use kube::{Api, Client};
+use semver::Version;
+use serde::Deserialize;
+
+use crate::{
+    crds::server::Server,
+    error::ReconcileError,
+    meili_http::{self, MeiliSend},
+};
+
+/// Key actions and settings fields added after Meilisearch 1.0, with the release that
+/// introduced them. Anything not listed is assumed to be supported by every version.
+const GATED: &[(&str, u64, u64)] = &[
+    ("snapshots.create", 1, 4),
+    ("network.get", 1, 13),
+    ("network.update", 1, 13),
+    ("chatCompletions", 1, 15),
+    ("chats.get", 1, 15),
+    ("chats.delete", 1, 15),
+    ("chatsSettings.get", 1, 15),
+    ("chatsSettings.update", 1, 15),
+    ("export", 1, 16),
+    ("settings.dictionary", 1, 3),
+    ("settings.separatorTokens", 1, 3),
+    ("settings.nonSeparatorTokens", 1, 3),
+    ("settings.proximityPrecision", 1, 6),
+    ("settings.embedders", 1, 6),
+    ("settings.searchCutoffMs", 1, 9),
+    ("settings.localizedAttributes", 1, 10),
+    ("settings.facetSearch", 1, 12),
+    ("settings.prefixSearch", 1, 12),
+    ("settings.chat", 1, 15),
+];
+
+/// Version-gated capabilities `version` supports, as recorded in `ServerStatus.capabilities`.
+pub fn supported(version: &Version) -> Vec<String> {
+    GATED
+        .iter()
+        .filter(|(_, major, minor)| *version >= Version::new(*major, *minor, 0))
+        .map(|(name, _, _)| name.to_string())
+        .collect()
+}
+
+/// The subset of `wanted` that `version` lacks. Nothing is filtered while the version is unknown.
+pub fn unsupported<'a>(
+    version: Option<&Version>,
+    wanted: impl IntoIterator<Item = &'a str>,
+) -> Vec<&'a str> {
+    let Some(version) = version else {
+        return Vec::new();
+    };
+    wanted
+        .into_iter()
+        .filter(|w| {
+            GATED
+                .iter()
+                .any(|(name, major, minor)| name == w && *version < Version::new(*major, *minor, 0))
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VersionView {
+    pkg_version: String,
+}
+
+/// Ask the instance for its version via `GET /version`.
+pub async fn detect(endpoint: &str, master_key: &str) -> Result<Version, ReconcileError> {
+    let view = meili_http::client()
+        .get(format!("{}/version", endpoint))
+        .bearer_auth(master_key)
+        .send_checked()
+        .await
+        .map_err(anyhow::Error::from)?
+        .json::<VersionView>()
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(Version::parse(view.pkg_version.trim_start_matches('v')).map_err(anyhow::Error::from)?)
+}
+
+/// Version the Server last reported in status, if it was detected.
+pub async fn server_version(client: &Client, ns: &str, server: &str) -> Option<Version> {
+    let server = Api::<Server>::namespaced(client.clone(), ns)
+        .get_opt(server)
+        .await
+        .ok()??;
+    Version::parse(server.status?.meilisearch_version.as_deref()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gates_by_version() {
+        let v1_5 = Version::new(1, 5, 2);
+        let caps = supported(&v1_5);
+        assert!(caps.contains(&"snapshots.create".to_string()));
+        assert!(!caps.contains(&"settings.searchCutoffMs".to_string()));
+        assert_eq!(
+            unsupported(
+                Some(&v1_5),
+                ["search", "network.get", "settings.dictionary"]
+            ),
+            vec!["network.get"]
+        );
+        assert!(unsupported(None, ["network.get"]).is_empty());
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+
+use futures::Stream;
+use k8s_openapi::NamespaceResourceScope;
+use k8s_openapi::api::{
+    apps::v1::StatefulSet,
+    core::v1::{Secret, Service},
+};
+use kube::{
+    Api, Client, Resource, ResourceExt,
+    api::{DeleteParams, ListParams, Patch, PatchParams},
+    core::PartialObjectMeta,
+    runtime::{
+        Controller, WatchStreamExt, metadata_watcher,
+        reflector::{self, ObjectRef, Store},
+        watcher,
+    },
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{crds::server::Server, error::ReconcileError};
+
+/// Field manager of every write the operator makes, so a GitOps tool sees one owner for the
+/// fields it shares with the operator.
+pub const FIELD_MANAGER: &str = "meilisearch-operator";
+
+/// Standard label on every StatefulSet, Service and Secret the operator generates, valued
+/// `FIELD_MANAGER`. The Server's child watches select on it.
+pub const MANAGED_BY_LABEL: &str = "app.kubernetes.io/managed-by";
+
+/// Standard label grouping the generated objects under the `meilisearch` application.
+pub const PART_OF_LABEL: &str = "app.kubernetes.io/part-of";
+
+/// Labels marking an object as generated by the operator, for `server` when it belongs to one.
+pub fn managed_labels(server: Option<&str>) -> BTreeMap<String, String> {
+    let mut labels = BTreeMap::from([
+        (MANAGED_BY_LABEL.to_string(), FIELD_MANAGER.to_string()),
+        (PART_OF_LABEL.to_string(), "meilisearch".to_string()),
+    ]);
+    if let Some(server) = server {
+        labels.insert(SERVER_LABEL.to_string(), server.to_string());
+    }
+    labels
+}
+
+/// Server-side apply `obj` as `FIELD_MANAGER`. Fields it leaves out stay with whoever set
+/// them. On a conflict, labels and annotations another manager set first are left to it and
+/// the operator takes over the remaining fields, so a GitOps tool's metadata survives while
+/// the workload still matches the CR.
+pub async fn apply<K>(api: &Api<K>, name: &str, obj: &K) -> Result<K, ReconcileError>
+where
+    K: Resource + Clone + DeserializeOwned + Serialize + Debug,
+{
+    let pp = PatchParams::apply(FIELD_MANAGER);
+    match api.patch(name, &pp, &Patch::Apply(obj)).await {
+        Err(kube::Error::Api(ae)) if ae.code == 409 => {
+            let fields = conflicting_fields(&ae.message);
+            tracing::info!(name, ?fields, "apply conflicts with another field manager");
+            let mut obj = serde_json::to_value(obj).map_err(anyhow::Error::from)?;
+            for field in &fields {
+                for map in ["labels", "annotations"] {
+                    if let Some(key) = field.strip_prefix(&format!(".metadata.{}.", map))
+                        && let Some(m) = obj["metadata"][map].as_object_mut()
+                    {
+                        m.remove(key);
+                    }
+                }
+            }
+            Ok(api.patch(name, &pp.force(), &Patch::Apply(&obj)).await?)
+        }
+        res => Ok(res?),
+    }
+}
+
+/// Field paths listed in the API server's apply conflict message.
+fn conflicting_fields(message: &str) -> Vec<String> {
+    message
+        .split_whitespace()
+        .filter(|w| w.starts_with('.'))
+        .map(String::from)
+        .collect()
+}
+
+/// Label the operator writes on Index and Key CRs so a Server's dependents can be listed
+/// with a selector instead of scanning the namespace.
+pub const SERVER_LABEL: &str = "meili.operator.dev/server";
+
+/// Namespace of the Server a Secret in the operator namespace belongs to, next to
+/// `SERVER_LABEL` with its name.
+pub const SERVER_NAMESPACE_LABEL: &str = "meili.operator.dev/server-namespace";
+
+/// Point `SERVER_LABEL` at `server`, patching only when it is missing or stale.
+pub async fn ensure_server_label<K>(
+    api: &Api<K>,
+    obj: &K,
+    server: &str,
+) -> Result<(), ReconcileError>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    if obj.labels().get(SERVER_LABEL).map(String::as_str) == Some(server) {
+        return Ok(());
+    }
+    // A merge patch, not an apply: applying only the label would drop the spec fields the
+    // operator applied to the Keys and Indexes it creates itself
+    let patch = serde_json::json!({"metadata": {"labels": {SERVER_LABEL: server}}});
+    let pp = PatchParams {
+        field_manager: Some(FIELD_MANAGER.into()),
+        ..Default::default()
+    };
+    let _ = api
+        .patch(&obj.name_any(), &pp, &Patch::Merge(&patch))
+        .await?;
+    Ok(())
+}
+
+/// Selector matching the Index and Key CRs of `server`.
+pub fn server_selector(server: &str) -> String {
+    format!("{}={}", SERVER_LABEL, server)
+}
+
+/// Label on CRs the operator creates on behalf of another CR (a SearchGateway's Keys, a
+/// Tenant's Indexes and Key), valued with the creator's `metadata.uid`. Unlike ownerReferences
+/// it works across namespaces, so the creator deletes them itself.
+pub const OWNER_LABEL: &str = "meili.operator.dev/owner-uid";
+
+/// Server-side apply `obj` into `ns`, labelled as created by the CR with uid `owner`.
+pub async fn apply_owned<K>(
+    client: &Client,
+    ns: &str,
+    owner: &str,
+    mut obj: K,
+) -> Result<K, ReconcileError>
+where
+    K: Resource<DynamicType = (), Scope = NamespaceResourceScope>
+        + Clone
+        + DeserializeOwned
+        + Serialize
+        + Debug,
+{
+    obj.labels_mut()
+        .insert(OWNER_LABEL.to_string(), owner.to_string());
+    apply(&Api::namespaced(client.clone(), ns), &obj.name_any(), &obj).await
+}
+
+/// Delete the CRs created for `owner` that are not in `keep` (namespace, name).
+pub async fn prune_owned<K>(
+    client: &Client,
+    owner: &str,
+    keep: &BTreeSet<(String, String)>,
+) -> Result<(), ReconcileError>
+where
+    K: Resource<DynamicType = (), Scope = NamespaceResourceScope>
+        + Clone
+        + DeserializeOwned
+        + Debug,
+{
+    let lp = ListParams::default().labels(&format!("{}={}", OWNER_LABEL, owner));
+    for obj in Api::<K>::all(client.clone()).list(&lp).await? {
+        let ns = obj.namespace().unwrap_or_default();
+        if keep.contains(&(ns.clone(), obj.name_any())) || obj.meta().deletion_timestamp.is_some() {
+            continue;
+        }
+        let _ = Api::<K>::namespaced(client.clone(), &ns)
+            .delete(&obj.name_any(), &DeleteParams::default())
+            .await?;
+    }
+    Ok(())
+}
+
+/// Delete the CR `name` in `ns` when `stale` says it can't be applied in place, e.g. because
+/// a field the CRD makes immutable changed. Returns true while the old CR or its finalizer is
+/// still around, so the caller waits before creating the replacement.
+pub async fn replace_stale<K>(
+    client: &Client,
+    ns: &str,
+    name: &str,
+    stale: impl Fn(&K) -> bool,
+) -> Result<bool, ReconcileError>
+where
+    K: Resource<DynamicType = (), Scope = NamespaceResourceScope>
+        + Clone
+        + DeserializeOwned
+        + Debug,
+{
+    let api = Api::<K>::namespaced(client.clone(), ns);
+    match api.get_opt(name).await? {
+        None => Ok(false),
+        Some(obj) if obj.meta().deletion_timestamp.is_some() => Ok(true),
+        Some(obj) if stale(&obj) => {
+            let _ = api.delete(name, &DeleteParams::default()).await?;
+            Ok(true)
+        }
+        Some(_) => Ok(false),
+    }
+}
+
+/// Metadata-only caches of the objects a Server owns, limited to those carrying
+/// `MANAGED_BY_LABEL`. Only existence and ownership matter here, so Secret payloads and pod
+/// templates never enter the operator's memory.
+#[derive(Clone)]
+pub struct ServerChildren {
+    secrets: Store<PartialObjectMeta<Secret>>,
+    services: Store<PartialObjectMeta<Service>>,
+    statefulsets: Store<PartialObjectMeta<StatefulSet>>,
+}
+
+impl ServerChildren {
+    /// Register the metadata watches as owned-object triggers on `controller`.
+    pub fn watch(client: &Client, controller: Controller<Server>) -> (Controller<Server>, Self) {
+        let (secrets, secret_events) = metadata_stream::<Secret>(client);
+        let (services, service_events) = metadata_stream::<Service>(client);
+        let (statefulsets, sts_events) = metadata_stream::<StatefulSet>(client);
+        let controller = controller
+            .owns_stream(secret_events)
+            .owns_stream(service_events)
+            .owns_stream(sts_events);
+        (
+            controller,
+            Self {
+                secrets,
+                services,
+                statefulsets,
+            },
+        )
+    }
+
+    /// Whether the master key Secret, Service and active StatefulSet of a Server all exist.
+    /// Returns false until the watches have synced.
+    pub fn present(&self, ns: &str, name: &str, active: &str) -> bool {
+        self.secrets
+            .get(&ObjectRef::new(&format!("{}-meili-master", name)).within(ns))
+            .is_some()
+            && self
+                .services
+                .get(&ObjectRef::new(name).within(ns))
+                .is_some()
+            && self
+                .statefulsets
+                .get(&ObjectRef::new(active).within(ns))
+                .is_some()
+    }
+}
+
+fn metadata_stream<K>(
+    client: &Client,
+) -> (
+    Store<PartialObjectMeta<K>>,
+    impl Stream<Item = Result<PartialObjectMeta<K>, watcher::Error>> + Send + 'static,
+)
+where
+    K: Resource<DynamicType = ()> + Clone + DeserializeOwned + Debug + Send + Sync + 'static,
+{
+    let (reader, writer) = reflector::store();
+    let config =
+        watcher::Config::default().labels(&format!("{}={}", MANAGED_BY_LABEL, FIELD_MANAGER));
+    let stream = metadata_watcher(Api::<K>::all(client.clone()), config)
+        .default_backoff()
+        .reflect(writer)
+        .touched_objects();
+    (reader, stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_fields_from_apply_conflicts() {
+        let message = "Apply failed with 2 conflicts: conflicts with \"argocd-controller\" using apps/v1:\n- .metadata.labels.team\n- .spec.template.spec.containers[name=\"meilisearch\"].image";
+        assert_eq!(
+            conflicting_fields(message),
+            vec![
+                ".metadata.labels.team",
+                ".spec.template.spec.containers[name=\"meilisearch\"].image"
+            ]
+        );
+    }
+}
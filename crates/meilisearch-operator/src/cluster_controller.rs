@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use kube::{
+    Api, Client, ResourceExt,
+    runtime::controller::{Action, Controller},
+};
+use tokio::time::Duration;
+use tracing::error;
+
+use crate::{
+    clusters::RemoteClusters,
+    config::OperatorConfig,
+    crds::{
+        cluster::{Cluster, ClusterStatus},
+        common::Phase,
+    },
+    error::ReconcileError,
+    resync, status, watch,
+};
+
+#[derive(Clone)]
+pub struct Ctx {
+    pub client: Client,
+    pub clusters: RemoteClusters,
+}
+
+pub fn controller(client: Client, config: &OperatorConfig) -> Controller<Cluster> {
+    watch::controller(Api::<Cluster>::all(client), config)
+}
+
+/// Kubeconfig Secret changes are picked up on the next check; unready Clusters retry every 30s.
+const RESYNC: Duration = Duration::from_secs(300);
+
+/// Verify the kubeconfig by asking the remote API server for its version.
+pub async fn reconcile(cluster: Arc<Cluster>, ctx: Arc<Ctx>) -> Result<Action, ReconcileError> {
+    let ns = cluster.namespace().unwrap();
+    let name = cluster.name_any();
+    let mut status = ClusterStatus {
+        observed_generation: cluster.metadata.generation,
+        last_synced: Some(resync::now()),
+        ..cluster.status.clone().unwrap_or_default()
+    };
+    let checked = async {
+        let client = ctx.clusters.client(&ctx.client, &ns, &cluster).await?;
+        Ok::<_, ReconcileError>(client.apiserver_version().await?)
+    }
+    .await;
+    match checked {
+        Ok(info) => {
+            status.phase = Phase::Ready;
+            status.ready = true;
+            status.message = None;
+            status.kubernetes_version = Some(info.git_version);
+        }
+        Err(e) => {
+            status.phase = Phase::Degraded;
+            status.ready = false;
+            status.message = Some(e.to_string());
+        }
+    }
+    let api: Api<Cluster> = Api::namespaced(ctx.client.clone(), &ns);
+    status::merge(&api, &name, &status).await?;
+    Ok(Action::requeue(if status.ready {
+        RESYNC
+    } else {
+        Duration::from_secs(30)
+    }))
+}
+
+pub fn error_policy(_cluster: Arc<Cluster>, err: &ReconcileError, _ctx: Arc<Ctx>) -> Action {
+    error!(error = ?err, "cluster reconcile failed");
+    Action::requeue(Duration::from_secs(30))
+}
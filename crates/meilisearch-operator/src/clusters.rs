@@ -0,0 +1,312 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use k8s_openapi::api::core::v1::{Secret, Service};
+use kube::{
+    Api, Client, Config, ResourceExt,
+    config::{KubeConfigOptions, Kubeconfig},
+};
+use tokio::sync::Mutex;
+
+use crate::{
+    config::OperatorConfig,
+    crds::{
+        cluster::Cluster,
+        server::{Server, ServerSpec},
+    },
+    error::ReconcileError,
+};
+
+/// Endpoint of a Server's Service in the operator's own cluster.
+pub const LOCAL_ENDPOINT_TEMPLATE: &str = "http://{name}.{namespace}.svc.cluster.local:{port}";
+
+/// Where a Server's workload runs: the operator's cluster or a `Cluster` CR.
+pub struct Target {
+    pub client: Client,
+    /// False when the workload lives in the operator's cluster (owner references apply)
+    pub remote: bool,
+    pub endpoint_template: String,
+}
+
+impl Target {
+    pub fn endpoint(&self, name: &str, ns: &str, port: u16) -> String {
+        endpoint(&self.endpoint_template, name, ns, port)
+    }
+}
+
+/// (namespace, kubeconfig Secret) -> (Secret resourceVersion, client)
+type ClientCache = HashMap<(String, String), (String, Client)>;
+
+/// Clients for remote clusters, rebuilt when the kubeconfig Secret changes.
+#[derive(Clone, Default)]
+pub struct RemoteClusters {
+    cache: Arc<Mutex<ClientCache>>,
+}
+
+impl RemoteClusters {
+    /// Client and endpoint template for `cluster_ref` in `ns`, or the local cluster when unset.
+    pub async fn target(
+        &self,
+        local: &Client,
+        ns: &str,
+        cluster_ref: Option<&str>,
+    ) -> Result<Target, ReconcileError> {
+        let Some(cluster_ref) = cluster_ref else {
+            return Ok(Target {
+                client: local.clone(),
+                remote: false,
+                endpoint_template: LOCAL_ENDPOINT_TEMPLATE.into(),
+            });
+        };
+        let cluster = Api::<Cluster>::namespaced(local.clone(), ns)
+            .get_opt(cluster_ref)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Cluster {}/{} not found", ns, cluster_ref))?;
+        Ok(Target {
+            client: self.client(local, ns, &cluster).await?,
+            remote: true,
+            endpoint_template: cluster.spec.endpoint_template.clone(),
+        })
+    }
+
+    /// Client for `cluster`, built from its kubeconfig Secret.
+    pub async fn client(
+        &self,
+        local: &Client,
+        ns: &str,
+        cluster: &Cluster,
+    ) -> Result<Client, ReconcileError> {
+        let secret_ref = &cluster.spec.kubeconfig_secret_ref;
+        let secret = Api::<Secret>::namespaced(local.clone(), ns)
+            .get_opt(&secret_ref.name)
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!("kubeconfig Secret {}/{} not found", ns, secret_ref.name)
+            })?;
+        let version = secret.metadata.resource_version.clone().unwrap_or_default();
+        let cache_key = (ns.to_string(), secret_ref.name.clone());
+        let mut cache = self.cache.lock().await;
+        if let Some((cached_version, client)) = cache.get(&cache_key)
+            && *cached_version == version
+        {
+            return Ok(client.clone());
+        }
+        let raw = secret
+            .data
+            .as_ref()
+            .and_then(|d| d.get(&secret_ref.key))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Secret {}/{} has no key {}",
+                    ns,
+                    secret_ref.name,
+                    secret_ref.key
+                )
+            })?;
+        let kubeconfig = Kubeconfig::from_yaml(&String::from_utf8(raw.0.clone())?)
+            .map_err(|e| anyhow::anyhow!("invalid kubeconfig: {}", e))?;
+        let config = Config::from_custom_kubeconfig(kubeconfig, &KubeConfigOptions::default())
+            .await
+            .map_err(|e| anyhow::anyhow!("invalid kubeconfig: {}", e))?;
+        let client = Client::try_from(config)?;
+        cache.insert(cache_key, (version, client.clone()));
+        Ok(client)
+    }
+}
+
+/// Expand `{name}`, `{namespace}` and `{port}` in an endpoint template.
+pub fn endpoint(template: &str, name: &str, ns: &str, port: u16) -> String {
+    template
+        .replace("{name}", name)
+        .replace("{namespace}", ns)
+        .replace("{port}", &port.to_string())
+}
+
+/// URL the operator calls for a Server whose Service answers at `internal`.
+pub fn operator_endpoint(spec: &ServerSpec, internal: &str) -> String {
+    spec.endpoint_override
+        .as_deref()
+        .map_or(internal, |e| e.trim_end_matches('/'))
+        .to_string()
+}
+
+/// URL for `server` when the operator runs outside the cluster: a `DEV_ENDPOINTS` entry,
+/// `endpoint_override`, the Service's load balancer, else a `kubectl port-forward` on localhost.
+pub async fn dev_endpoint(
+    client: &Client,
+    dev: &BTreeMap<String, String>,
+    server: &Server,
+) -> Result<String, ReconcileError> {
+    let ns = server.namespace().unwrap_or_default();
+    let name = server.name_any();
+    if server.spec.endpoint_override.is_some() && !dev.contains_key(&format!("{}/{}", ns, name)) {
+        return Ok(operator_endpoint(&server.spec, ""));
+    }
+    let port = server.spec.port;
+    Ok(dev_service_endpoint(client, dev, &ns, &name, port)
+        .await?
+        .unwrap_or_else(|| format!("http://localhost:{}", port)))
+}
+
+/// URL for the Service `ns/name` when the operator runs outside the cluster: its
+/// `DEV_ENDPOINTS` entry or its load balancer, if it has either.
+pub async fn dev_service_endpoint(
+    client: &Client,
+    dev: &BTreeMap<String, String>,
+    ns: &str,
+    name: &str,
+    port: u16,
+) -> Result<Option<String>, ReconcileError> {
+    if let Some(url) = dev.get(&format!("{}/{}", ns, name)) {
+        return Ok(Some(url.clone()));
+    }
+    let ingress = Api::<Service>::namespaced(client.clone(), ns)
+        .get_opt(name)
+        .await?
+        .and_then(|s| s.status?.load_balancer?.ingress?.into_iter().next());
+    Ok(ingress
+        .and_then(|i| i.hostname.or(i.ip))
+        .map(|host| format!("http://{}", host_port(&host, port))))
+}
+
+/// `host:port`, with IPv6 literals in brackets as URLs need them.
+pub fn host_port(host: &str, port: u16) -> String {
+    if host.contains(':') && !host.starts_with('[') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// Endpoint Index and Key controllers use for `server`: its `endpoint_override`, the one the
+/// Server published in status (which accounts for remote clusters), or its in-cluster Service.
+/// In dev mode, see [`dev_endpoint`].
+pub async fn server_endpoint(
+    client: &Client,
+    config: &OperatorConfig,
+    ns: &str,
+    server: &str,
+) -> Result<String, ReconcileError> {
+    let server_obj = Api::<Server>::namespaced(client.clone(), ns)
+        .get_opt(server)
+        .await?;
+    if let (Some(dev), Some(s)) = (&config.dev_endpoints, &server_obj) {
+        return dev_endpoint(client, dev, s).await;
+    }
+    let port = server_obj.as_ref().map_or(7700, |s| s.spec.port);
+    let local = endpoint(LOCAL_ENDPOINT_TEMPLATE, server, ns, port);
+    Ok(match server_obj {
+        Some(s) if s.spec.endpoint_override.is_some() => operator_endpoint(&s.spec, &local),
+        Some(s) => s.status.and_then(|s| s.endpoint).unwrap_or(local),
+        None => local,
+    })
+}
+
+/// Why Index and Key CRs of `ns/server` have to wait, or `None` once the Server is Ready.
+pub async fn server_not_ready(
+    client: &Client,
+    ns: &str,
+    server: &str,
+) -> Result<Option<String>, ReconcileError> {
+    let server_obj = Api::<Server>::namespaced(client.clone(), ns)
+        .get_opt(server)
+        .await?;
+    Ok(wait_reason(ns, server, server_obj.as_ref()))
+}
+
+/// Message naming the Server a `server_ref` points at when no such Server exists. Unlike a
+/// Server that is not Ready yet, this is usually a typo and is reported as Degraded.
+pub async fn missing_server(
+    client: &Client,
+    ns: &str,
+    server: &str,
+) -> Result<Option<String>, ReconcileError> {
+    let found = Api::<Server>::namespaced(client.clone(), ns)
+        .get_metadata_opt(server)
+        .await?;
+    Ok(found.is_none().then(|| missing_server_message(ns, server)))
+}
+
+pub fn missing_server_message(ns: &str, server: &str) -> String {
+    format!(
+        "server_ref names Server {}/{}, which does not exist",
+        ns, server
+    )
+}
+
+fn wait_reason(ns: &str, name: &str, server: Option<&Server>) -> Option<String> {
+    let Some(server) = server else {
+        return Some(format!("Server {}/{} not found", ns, name));
+    };
+    if server.metadata.deletion_timestamp.is_some() {
+        return Some(format!("Server {}/{} is being deleted", ns, name));
+    }
+    match server.status.as_ref() {
+        Some(s) if s.ready => None,
+        Some(s) => Some(match &s.message {
+            Some(msg) => format!("Server {}/{} is not Ready: {}", ns, name, msg),
+            None => format!("Server {}/{} is not Ready yet", ns, name),
+        }),
+        None => Some(format!("Server {}/{} is not Ready yet", ns, name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crds::server::ServerStatus;
+
+    #[test]
+    fn expands_endpoint_template() {
+        assert_eq!(
+            endpoint(LOCAL_ENDPOINT_TEMPLATE, "meili", "search", 7700),
+            "http://meili.search.svc.cluster.local:7700"
+        );
+        assert_eq!(
+            endpoint(
+                "https://{namespace}-{name}.eu.example.com",
+                "meili",
+                "search",
+                7700
+            ),
+            "https://search-meili.eu.example.com"
+        );
+        assert_eq!(host_port("203.0.113.7", 7700), "203.0.113.7:7700");
+        assert_eq!(host_port("2001:db8::7", 7700), "[2001:db8::7]:7700");
+        assert_eq!(host_port("lb.example.com", 7700), "lb.example.com:7700");
+    }
+
+    #[test]
+    fn prefers_endpoint_override() {
+        let internal = "http://meili.search.svc.cluster.local:7700";
+        let mut spec = ServerSpec::default();
+        assert_eq!(operator_endpoint(&spec, internal), internal);
+        spec.endpoint_override = Some("https://meili.mesh.internal/".into());
+        assert_eq!(
+            operator_endpoint(&spec, internal),
+            "https://meili.mesh.internal"
+        );
+    }
+
+    #[test]
+    fn waits_until_server_ready() {
+        assert_eq!(
+            wait_reason("search", "meili", None).as_deref(),
+            Some("Server search/meili not found")
+        );
+        let mut server = Server::new("meili", ServerSpec::default());
+        assert!(wait_reason("search", "meili", Some(&server)).is_some());
+        server.status = Some(ServerStatus {
+            message: Some("Meilisearch not healthy".into()),
+            ..Default::default()
+        });
+        assert_eq!(
+            wait_reason("search", "meili", Some(&server)).as_deref(),
+            Some("Server search/meili is not Ready: Meilisearch not healthy")
+        );
+        server.status.as_mut().unwrap().ready = true;
+        assert!(wait_reason("search", "meili", Some(&server)).is_none());
+    }
+}
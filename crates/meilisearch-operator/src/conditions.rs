@@ -0,0 +1,70 @@
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, Time};
+use k8s_openapi::chrono::Utc;
+
+pub const DEGRADED: &str = "Degraded";
+pub const WAITING_FOR_SERVER: &str = "WaitingForServer";
+pub const DELETION_BLOCKED: &str = "DeletionBlocked";
+pub const DRIFT_DETECTED: &str = "DriftDetected";
+pub const TIMED_OUT: &str = "TimedOut";
+pub const LAST_TASK_FAILED: &str = "LastTaskFailed";
+pub const FROZEN: &str = "Frozen";
+pub const PENDING_CHANGES: &str = "PendingChanges";
+pub const REPLICATION_UNSUPPORTED: &str = "ReplicationUnsupported";
+
+/// Insert or update a condition by type, keeping lastTransitionTime stable
+/// unless the status actually flips.
+pub fn set_condition(
+    conditions: &mut Vec<Condition>,
+    type_: &str,
+    status: bool,
+    reason: &str,
+    message: impl Into<String>,
+    observed_generation: Option<i64>,
+) {
+    let status = if status { "True" } else { "False" }.to_string();
+    let message = message.into();
+    if let Some(c) = conditions.iter_mut().find(|c| c.type_ == type_) {
+        if c.status != status {
+            c.last_transition_time = Time(Utc::now());
+        }
+        c.status = status;
+        c.reason = reason.to_string();
+        c.message = message;
+        c.observed_generation = observed_generation;
+        return;
+    }
+    conditions.push(Condition {
+        type_: type_.to_string(),
+        status,
+        reason: reason.to_string(),
+        message,
+        observed_generation,
+        last_transition_time: Time(Utc::now()),
+    });
+}
+
+/// True when the condition of the given type is present with status "True".
+pub fn is_true(conditions: &[Condition], type_: &str) -> bool {
+    conditions
+        .iter()
+        .any(|c| c.type_ == type_ && c.status == "True")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transition_time_only_moves_on_status_change() {
+        let mut conds = Vec::new();
+        set_condition(&mut conds, DEGRADED, true, "A", "first", Some(1));
+        let t0 = conds[0].last_transition_time.clone();
+        set_condition(&mut conds, DEGRADED, true, "B", "second", Some(2));
+        assert_eq!(conds.len(), 1);
+        assert_eq!(conds[0].last_transition_time, t0);
+        assert_eq!(conds[0].reason, "B");
+        assert!(is_true(&conds, DEGRADED));
+        set_condition(&mut conds, DEGRADED, false, "C", "", Some(3));
+        assert!(!is_true(&conds, DEGRADED));
+    }
+}
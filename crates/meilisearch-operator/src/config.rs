@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Image used when a Server sets neither `image` nor `version`.
+pub const DEFAULT_IMAGE: &str = "getmeili/meilisearch:v1.24.0";
+
+/// Image run by SearchGateway Deployments unless one sets `image`; it ships the
+/// `meilisearch-gateway` binary.
+pub const DEFAULT_GATEWAY_IMAGE: &str = "mbround18/meilisearch-operator:latest";
+
+/// Operator-wide settings, read once at startup from the environment.
+#[derive(Clone, Debug)]
+pub struct OperatorConfig {
+    /// `MEILI_DEFAULT_IMAGE`: fleet-wide default Meilisearch image
+    pub default_image: String,
+    /// `MEILI_DEFAULT_IMAGE_ROLLOUT`: move Servers already running to a changed default image;
+    /// otherwise only new Servers get it
+    pub default_image_rollout: bool,
+    /// `DRY_RUN`: report intended changes in status and Events instead of applying them
+    pub dry_run: bool,
+    /// `IMMUTABLE_SECRETS`: create generated Secrets with `immutable: true` unless a CR overrides it
+    pub immutable_secrets: bool,
+    /// `ALLOW_CROSS_NAMESPACE_REFS`: let Index CRs reference Servers in other namespaces
+    pub allow_cross_namespace_refs: bool,
+    /// `DEV_MODE`: the operator runs outside the cluster and reaches Servers through
+    /// `DEV_ENDPOINTS` ("namespace/name=url,..."), their load balancer or localhost port-forwards
+    pub dev_endpoints: Option<BTreeMap<String, String>>,
+    /// `RECONCILE_DEBOUNCE_SECONDS`: quiet period that collapses bursts of updates to one CR
+    /// into a single reconcile
+    pub reconcile_debounce: Duration,
+    /// `GATEWAY_IMAGE`: image for SearchGateway Deployments, normally the operator's own
+    pub gateway_image: String,
+    /// `RECONCILE_TIMEOUT_SECONDS`: bound on one Index or Key reconcile, so a hung Meilisearch
+    /// call frees its worker; 0 disables it
+    pub reconcile_timeout: Option<Duration>,
+    /// `SERVER_WORKERS`: Server reconciles running at once; 0 lifts the limit
+    pub server_workers: u16,
+    /// `INDEX_WORKERS`: Index reconciles running at once; 0 lifts the limit
+    pub index_workers: u16,
+    /// `KEY_WORKERS`: Key reconciles running at once; 0 lifts the limit
+    pub key_workers: u16,
+    /// `INSTALL_CRDS`: apply the operator's CRDs at startup instead of only checking them
+    pub install_crds: bool,
+    /// `CHANGE_FREEZE`: pause every change to the cluster and Meilisearch, as the
+    /// `meilisearch-operator-freeze` ConfigMap does while the operator runs (see [`crate::freeze`])
+    pub change_freeze: bool,
+}
+
+impl Default for OperatorConfig {
+    fn default() -> Self {
+        Self {
+            default_image: DEFAULT_IMAGE.into(),
+            default_image_rollout: false,
+            dry_run: false,
+            immutable_secrets: false,
+            allow_cross_namespace_refs: false,
+            dev_endpoints: None,
+            reconcile_debounce: Duration::from_secs(1),
+            gateway_image: DEFAULT_GATEWAY_IMAGE.into(),
+            reconcile_timeout: Some(Duration::from_secs(60)),
+            server_workers: 4,
+            index_workers: 8,
+            key_workers: 8,
+            install_crds: false,
+            change_freeze: false,
+        }
+    }
+}
+
+impl OperatorConfig {
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        Self {
+            default_image: var("MEILI_DEFAULT_IMAGE")
+                .filter(|v| !v.is_empty())
+                .unwrap_or(defaults.default_image),
+            default_image_rollout: var("MEILI_DEFAULT_IMAGE_ROLLOUT")
+                .map(|v| matches!(v.trim(), "1" | "true"))
+                .unwrap_or(defaults.default_image_rollout),
+            dry_run: var("DRY_RUN")
+                .map(|v| matches!(v.trim(), "1" | "true"))
+                .unwrap_or(defaults.dry_run),
+            immutable_secrets: var("IMMUTABLE_SECRETS")
+                .map(|v| matches!(v.trim(), "1" | "true"))
+                .unwrap_or(defaults.immutable_secrets),
+            allow_cross_namespace_refs: var("ALLOW_CROSS_NAMESPACE_REFS")
+                .map(|v| matches!(v.trim(), "1" | "true"))
+                .unwrap_or(defaults.allow_cross_namespace_refs),
+            dev_endpoints: var("DEV_MODE")
+                .is_some_and(|v| matches!(v.trim(), "1" | "true"))
+                .then(|| parse_dev_endpoints(&var("DEV_ENDPOINTS").unwrap_or_default())),
+            reconcile_debounce: var("RECONCILE_DEBOUNCE_SECONDS")
+                .and_then(|v| v.trim().parse().ok())
+                .map_or(defaults.reconcile_debounce, Duration::from_secs),
+            gateway_image: var("GATEWAY_IMAGE")
+                .filter(|v| !v.is_empty())
+                .unwrap_or(defaults.gateway_image),
+            reconcile_timeout: var("RECONCILE_TIMEOUT_SECONDS")
+                .and_then(|v| v.trim().parse().ok())
+                .map_or(defaults.reconcile_timeout, |s: u64| {
+                    (s > 0).then(|| Duration::from_secs(s))
+                }),
+            server_workers: var("SERVER_WORKERS")
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(defaults.server_workers),
+            index_workers: var("INDEX_WORKERS")
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(defaults.index_workers),
+            key_workers: var("KEY_WORKERS")
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(defaults.key_workers),
+            install_crds: var("INSTALL_CRDS")
+                .map(|v| matches!(v.trim(), "1" | "true"))
+                .unwrap_or(defaults.install_crds),
+            change_freeze: var("CHANGE_FREEZE")
+                .map(|v| matches!(v.trim(), "1" | "true"))
+                .unwrap_or(defaults.change_freeze),
+        }
+    }
+
+    /// Concurrent reconciles allowed for CRs of `kind`, 0 meaning unlimited. Kinds without
+    /// their own setting are few and cheap, so they are not limited.
+    pub fn workers(&self, kind: &str) -> u16 {
+        match kind {
+            "Server" => self.server_workers,
+            "Index" => self.index_workers,
+            "Key" => self.key_workers,
+            _ => 0,
+        }
+    }
+
+    /// The variables `from_env` reads back into this config, for rendering install manifests.
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("MEILI_DEFAULT_IMAGE", self.default_image.clone()),
+            (
+                "MEILI_DEFAULT_IMAGE_ROLLOUT",
+                self.default_image_rollout.to_string(),
+            ),
+            ("DRY_RUN", self.dry_run.to_string()),
+            ("IMMUTABLE_SECRETS", self.immutable_secrets.to_string()),
+            (
+                "ALLOW_CROSS_NAMESPACE_REFS",
+                self.allow_cross_namespace_refs.to_string(),
+            ),
+            (
+                "RECONCILE_DEBOUNCE_SECONDS",
+                self.reconcile_debounce.as_secs().to_string(),
+            ),
+            ("GATEWAY_IMAGE", self.gateway_image.clone()),
+            (
+                "RECONCILE_TIMEOUT_SECONDS",
+                self.reconcile_timeout
+                    .map_or(0, |t| t.as_secs())
+                    .to_string(),
+            ),
+            ("SERVER_WORKERS", self.server_workers.to_string()),
+            ("INDEX_WORKERS", self.index_workers.to_string()),
+            ("KEY_WORKERS", self.key_workers.to_string()),
+            ("INSTALL_CRDS", self.install_crds.to_string()),
+            ("CHANGE_FREEZE", self.change_freeze.to_string()),
+        ];
+        if let Some(dev) = &self.dev_endpoints {
+            let endpoints: Vec<String> = dev.iter().map(|(s, u)| format!("{}={}", s, u)).collect();
+            env.push(("DEV_MODE", "true".into()));
+            env.push(("DEV_ENDPOINTS", endpoints.join(",")));
+        }
+        env
+    }
+}
+
+/// Parse "search/meili=http://localhost:7701,other/meili=http://localhost:7702".
+fn parse_dev_endpoints(value: &str) -> BTreeMap<String, String> {
+    value
+        .split(',')
+        .filter_map(|entry| entry.trim().split_once('='))
+        .map(|(server, url)| {
+            (
+                server.trim().to_string(),
+                url.trim().trim_end_matches('/').to_string(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dev_endpoints() {
+        let map = parse_dev_endpoints("search/meili=http://localhost:7701/, bad ,b/c=http://x:1");
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["search/meili"], "http://localhost:7701");
+        assert_eq!(map["b/c"], "http://x:1");
+        assert!(parse_dev_endpoints("").is_empty());
+    }
+
+    #[test]
+    fn env_reads_back_into_the_same_config() {
+        let config = OperatorConfig {
+            dry_run: true,
+            dev_endpoints: Some(BTreeMap::from([("a/b".into(), "http://x:1".into())])),
+            reconcile_timeout: None,
+            key_workers: 32,
+            change_freeze: true,
+            default_image_rollout: true,
+            ..Default::default()
+        };
+        let env: BTreeMap<&str, String> = config.env().into_iter().collect();
+        let read = OperatorConfig::from_vars(|name| env.get(name).cloned());
+        assert_eq!(format!("{:?}", read), format!("{:?}", config));
+    }
+}
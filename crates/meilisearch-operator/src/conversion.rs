@@ -0,0 +1,182 @@
+use kube::core::{
+    Status,
+    conversion::{ConversionRequest, ConversionResponse, ConversionReview},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::crds::{
+    V1ALPHA1, V1BETA1,
+    common::ServerRef,
+    index::{DeletionPolicy, Index, IndexKeySpec, IndexSettings, IndexSpec, v1alpha1},
+};
+
+/// Holds v1beta1-only Index fields while an object is served as v1alpha1, so a round trip
+/// through the old version does not lose them.
+pub const PRESERVED_ANNOTATION: &str = "meili.operator.dev/v1beta1-fields";
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+struct Preserved {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    server_namespace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    settings: Option<IndexSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template_ref: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    keys: Vec<IndexKeySpec>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    frozen: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_documents: Option<u64>,
+}
+
+/// Answer an API server ConversionReview for Index objects.
+pub fn review(review: ConversionReview) -> ConversionReview {
+    let request = match ConversionRequest::from_review(review) {
+        Ok(r) => r,
+        Err(_) => {
+            return ConversionResponse::invalid(Status::failure(
+                "ConversionReview has no request",
+                "BadRequest",
+            ))
+            .into_review();
+        }
+    };
+    let desired = request.desired_api_version.clone();
+    let converted: Result<Vec<Value>, String> = request
+        .objects
+        .iter()
+        .map(|obj| convert_index(obj.clone(), &desired))
+        .collect();
+    let response = ConversionResponse::for_request(request);
+    match converted {
+        Ok(objects) => response.success(objects),
+        Err(msg) => response.failure(Status::failure(&msg, "ConversionFailed")),
+    }
+    .into_review()
+}
+
+/// Convert one Index object to `desired` ("meili.operator.dev/v1alpha1" or ".../v1beta1").
+pub fn convert_index(obj: Value, desired: &str) -> Result<Value, String> {
+    let from = obj["apiVersion"].as_str().unwrap_or_default().to_string();
+    if from == desired {
+        return Ok(obj);
+    }
+    let converted = match (from.rsplit('/').next(), desired.rsplit('/').next()) {
+        (Some(V1ALPHA1), Some(V1BETA1)) => {
+            let old: v1alpha1::Index = serde_json::from_value(obj).map_err(|e| e.to_string())?;
+            serde_json::to_value(up(old))
+        }
+        (Some(V1BETA1), Some(V1ALPHA1)) => {
+            let new: Index = serde_json::from_value(obj).map_err(|e| e.to_string())?;
+            serde_json::to_value(down(new))
+        }
+        _ => return Err(format!("cannot convert Index from {} to {}", from, desired)),
+    };
+    converted.map_err(|e| e.to_string())
+}
+
+fn up(old: v1alpha1::Index) -> Index {
+    let mut metadata = old.metadata;
+    let preserved: Preserved = metadata
+        .annotations
+        .as_mut()
+        .and_then(|a| a.remove(PRESERVED_ANNOTATION))
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default();
+    let spec = old.spec;
+    Index {
+        metadata,
+        spec: IndexSpec {
+            server_ref: ServerRef {
+                name: spec.server_ref,
+                namespace: preserved.server_namespace,
+            },
+            uid: spec.uid,
+            primary_key: spec.primary_key,
+            settings: preserved.settings,
+            template_ref: preserved.template_ref,
+            deletion_policy: if spec.delete_on_finalize {
+                DeletionPolicy::Delete
+            } else {
+                DeletionPolicy::Retain
+            },
+            admin_key: spec.admin_key,
+            keys: preserved.keys,
+            retention: spec.retention,
+            adoption_policy: spec.adoption_policy,
+            frozen: preserved.frozen,
+            min_documents: preserved.min_documents,
+        },
+        status: old.status,
+    }
+}
+
+fn down(new: Index) -> v1alpha1::Index {
+    let mut metadata = new.metadata;
+    let spec = new.spec;
+    let preserved = Preserved {
+        server_namespace: spec.server_ref.namespace,
+        settings: spec.settings,
+        template_ref: spec.template_ref,
+        keys: spec.keys,
+        frozen: spec.frozen,
+        min_documents: spec.min_documents,
+    };
+    if preserved != Preserved::default() {
+        metadata.annotations.get_or_insert_default().insert(
+            PRESERVED_ANNOTATION.into(),
+            serde_json::to_string(&preserved).unwrap_or_default(),
+        );
+    }
+    v1alpha1::Index {
+        metadata,
+        spec: v1alpha1::IndexSpec {
+            server_ref: spec.server_ref.name,
+            uid: spec.uid,
+            primary_key: spec.primary_key,
+            delete_on_finalize: spec.deletion_policy == DeletionPolicy::Delete,
+            admin_key: spec.admin_key,
+            retention: spec.retention,
+            adoption_policy: spec.adoption_policy,
+        },
+        status: new.status,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_v1alpha1() {
+        let alpha = serde_json::json!({
+            "apiVersion": "meili.operator.dev/v1alpha1",
+            "kind": "Index",
+            "metadata": {"name": "products", "namespace": "search"},
+            "spec": {"server_ref": "meili", "uid": "products", "delete_on_finalize": true},
+        });
+        let beta = convert_index(alpha, "meili.operator.dev/v1beta1").unwrap();
+        assert_eq!(beta["apiVersion"], "meili.operator.dev/v1beta1");
+        assert_eq!(beta["spec"]["server_ref"]["name"], "meili");
+        assert_eq!(beta["spec"]["deletion_policy"], "Delete");
+
+        let mut beta = beta;
+        beta["spec"]["settings"] = serde_json::json!({"stop_words": ["the"]});
+        let alpha = convert_index(beta.clone(), "meili.operator.dev/v1alpha1").unwrap();
+        assert_eq!(alpha["spec"]["server_ref"], "meili");
+        assert_eq!(alpha["spec"]["delete_on_finalize"], true);
+        let back = convert_index(alpha, "meili.operator.dev/v1beta1").unwrap();
+        assert_eq!(
+            back["spec"]["settings"]["stop_words"],
+            serde_json::json!(["the"])
+        );
+        assert_eq!(back["spec"]["deletion_policy"], "Delete");
+        assert!(
+            back["metadata"]["annotations"]
+                .get(PRESERVED_ANNOTATION)
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::{Context, bail};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::{
+    Api, Client, ResourceExt,
+    api::{Patch, PatchParams},
+    runtime::wait::{await_condition, conditions},
+};
+use tokio::time::Duration;
+use tracing::info;
+
+use crate::{children::FIELD_MANAGER, crds::index::ConversionWebhook, install};
+
+/// How long freshly applied CRDs get to become Established.
+const ESTABLISH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Make sure every CRD the operator watches is installed and serves the versions it reads,
+/// failing with the list of what is wrong instead of leaving the controllers to retry watches
+/// that can never succeed. With `install` the CRDs are applied first, the Index CRD converted
+/// by `conversion` when the operator serves its webhook.
+pub async fn ensure(
+    client: &Client,
+    conversion: Option<&ConversionWebhook>,
+    install: bool,
+) -> anyhow::Result<()> {
+    let api: Api<CustomResourceDefinition> = Api::all(client.clone());
+    let mut problems = Vec::new();
+    for expected in install::crds(conversion) {
+        let name = expected.name_any();
+        if install {
+            api.patch(
+                &name,
+                &PatchParams::apply(FIELD_MANAGER).force(),
+                &Patch::Apply(&expected),
+            )
+            .await
+            .with_context(|| format!("applying CRD {}", name))?;
+            tokio::time::timeout(
+                ESTABLISH_TIMEOUT,
+                await_condition(api.clone(), &name, conditions::is_crd_established()),
+            )
+            .await
+            .with_context(|| format!("CRD {} not established", name))??;
+            info!(crd = %name, "CRD applied");
+            continue;
+        }
+        let installed = api
+            .get_opt(&name)
+            .await
+            .with_context(|| format!("reading CRD {}", name))?;
+        problems.extend(mismatch(&expected, installed.as_ref()));
+    }
+    if !problems.is_empty() {
+        bail!(
+            "{}; apply manifests/crds.yaml or set INSTALL_CRDS=true",
+            problems.join("; ")
+        );
+    }
+    Ok(())
+}
+
+/// What keeps the operator from working with `installed` where it expects `expected`.
+fn mismatch(
+    expected: &CustomResourceDefinition,
+    installed: Option<&CustomResourceDefinition>,
+) -> Option<String> {
+    let name = expected.name_any();
+    let Some(installed) = installed else {
+        return Some(format!("CRD {} is not installed", name));
+    };
+    let served = |crd: &CustomResourceDefinition, storage: bool| -> Vec<String> {
+        crd.spec
+            .versions
+            .iter()
+            .filter(|v| v.served && (!storage || v.storage))
+            .map(|v| v.name.clone())
+            .collect()
+    };
+    let missing: Vec<String> = served(expected, false)
+        .into_iter()
+        .filter(|v| !served(installed, false).contains(v))
+        .collect();
+    if !missing.is_empty() {
+        return Some(format!(
+            "CRD {} does not serve {}",
+            name,
+            missing.join(", ")
+        ));
+    }
+    let storage = served(expected, true);
+    if served(installed, true) != storage {
+        return Some(format!(
+            "CRD {} stores {} instead of {}",
+            name,
+            served(installed, true).join(", "),
+            storage.join(", ")
+        ));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crds::index;
+
+    #[test]
+    fn reports_missing_and_outdated_crds() {
+        let expected = index::crd();
+        assert!(mismatch(&expected, None).unwrap().contains("not installed"));
+        assert_eq!(mismatch(&expected, Some(&expected)), None);
+
+        let mut old = expected.clone();
+        old.spec.versions.retain(|v| v.name == "v1alpha1");
+        old.spec.versions[0].storage = true;
+        let problem = mismatch(&expected, Some(&old)).unwrap();
+        assert!(problem.contains("does not serve v1beta1"), "{}", problem);
+
+        let mut stored = expected.clone();
+        for v in &mut stored.spec.versions {
+            v.storage = !v.storage;
+        }
+        let problem = mismatch(&expected, Some(&stored)).unwrap();
+        assert!(problem.contains("stores v1alpha1"), "{}", problem);
+    }
+}
@@ -0,0 +1,56 @@
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::common::Phase;
+
+/// A remote Kubernetes cluster Servers can be scheduled into with `cluster_ref`.
+#[derive(CustomResource, Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[kube(
+    group = "meili.operator.dev",
+    version = "v1alpha1",
+    kind = "Cluster",
+    plural = "clusters",
+    namespaced,
+    status = "ClusterStatus",
+    shortname = "mcl",
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
+)]
+pub struct ClusterSpec {
+    /// Secret in the Cluster's namespace holding a kubeconfig for the remote cluster
+    pub kubeconfig_secret_ref: KubeconfigSecretRef,
+    /// How the operator reaches a Server's Service in this cluster; `{name}`, `{namespace}`
+    /// and `{port}` are substituted
+    #[serde(default = "default_endpoint_template")]
+    pub endpoint_template: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct KubeconfigSecretRef {
+    pub name: String,
+    /// Data key holding the kubeconfig
+    #[serde(default = "default_kubeconfig_key")]
+    pub key: String,
+}
+
+fn default_endpoint_template() -> String {
+    "http://{name}.{namespace}.svc.clusterset.local:{port}".into()
+}
+
+fn default_kubeconfig_key() -> String {
+    "kubeconfig".into()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+pub struct ClusterStatus {
+    #[serde(default)]
+    pub phase: Phase,
+    pub ready: bool,
+    pub message: Option<String>,
+    /// Version reported by the remote API server
+    pub kubernetes_version: Option<String>,
+    pub observed_generation: Option<i64>,
+    /// RFC3339 time of the last connectivity check
+    pub last_synced: Option<String>,
+}
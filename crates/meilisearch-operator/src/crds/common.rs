@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Result of the last `meili.operator.dev/trigger` annotation handled by a controller.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct TriggerOutcome {
+    /// Requested action, e.g. "rotate-key"
+    pub action: String,
+    /// Succeeded or Failed
+    pub result: String,
+    pub message: Option<String>,
+    /// RFC3339 completion time
+    pub completed_at: String,
+}
+
+/// Object in an S3-compatible bucket.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ObjectStoreSpec {
+    #[serde(default)]
+    pub provider: ObjectStoreProvider,
+    /// Overrides the provider's endpoint, e.g. "http://minio.storage:9000"
+    pub endpoint: Option<String>,
+    pub bucket: String,
+    /// Object key; exports treat it as a prefix for their part files, imports read this one object
+    pub path: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    /// Secret in the CR's namespace with the access key pair (HMAC keys for GCS)
+    pub credentials_secret_ref: ObjectStoreCredentials,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum ObjectStoreProvider {
+    /// Amazon S3 or any S3-compatible store
+    #[default]
+    S3,
+    /// Google Cloud Storage through its S3-compatible XML API
+    Gcs,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ObjectStoreCredentials {
+    pub name: String,
+    #[serde(default = "default_access_key_id_key")]
+    pub access_key_id_key: String,
+    #[serde(default = "default_secret_access_key_key")]
+    pub secret_access_key_key: String,
+}
+
+fn default_region() -> String {
+    "us-east-1".into()
+}
+
+fn default_access_key_id_key() -> String {
+    "AWS_ACCESS_KEY_ID".into()
+}
+
+fn default_secret_access_key_key() -> String {
+    "AWS_SECRET_ACCESS_KEY".into()
+}
+
+/// One data key of a ConfigMap in the referencing resource's namespace.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ConfigMapKeyRef {
+    pub name: String,
+    pub key: String,
+}
+
+/// Secret holding a connection URL, credentials included.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ConnectionSecretRef {
+    pub name: String,
+    /// Data key holding the URL
+    #[serde(default = "default_url_key")]
+    pub key: String,
+}
+
+fn default_url_key() -> String {
+    "url".into()
+}
+
+/// Where a resource is in its lifecycle, shown by `kubectl get -w`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum Phase {
+    /// Not reconciled yet
+    #[default]
+    Pending,
+    /// First rollout in progress
+    Provisioning,
+    /// The referenced Server is missing or not Ready
+    WaitingForServer,
+    Ready,
+    /// Rejected or unhealthy; see `message` and conditions
+    Degraded,
+    /// Deletion in progress
+    Terminating,
+}
+
+/// Reference to a Server, in the referencing object's namespace unless `namespace` is set.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct ServerRef {
+    pub name: String,
+    /// Cross-namespace references require the operator's `ALLOW_CROSS_NAMESPACE_REFS`
+    pub namespace: Option<String>,
+}
+
+impl ServerRef {
+    /// Namespace of the Server, given the namespace of the referencing object.
+    pub fn namespace_or<'a>(&'a self, own: &'a str) -> &'a str {
+        self.namespace.as_deref().unwrap_or(own)
+    }
+}
+
+/// Shape of a generated Secret, to fit existing consumers.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct SecretTemplateSpec {
+    /// Data key holding the credential; defaults to `masterKey` for Servers and `key` otherwise
+    pub key_name: Option<String>,
+    /// Secret `type`; defaults to `Opaque`
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    /// Extra labels on the Secret
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// Extra annotations on the Secret, e.g. for replicators
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// Where generated credentials are written.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct SecretStoreSpec {
+    #[serde(default)]
+    pub kind: SecretStoreKind,
+    /// Required when kind is Vault
+    pub vault: Option<VaultStoreSpec>,
+    /// Required when kind is PushSecret
+    pub push_secret: Option<PushSecretSpec>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum SecretStoreKind {
+    /// Plain Kubernetes Secret
+    #[default]
+    Kubernetes,
+    /// HashiCorp Vault KV v2, authenticated with the operator's service account
+    Vault,
+    /// Kubernetes Secret plus an External Secrets `PushSecret` that copies it to an external store
+    PushSecret,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct VaultStoreSpec {
+    /// e.g. "https://vault.vault.svc:8200"
+    pub address: String,
+    /// KV v2 mount
+    #[serde(default = "default_vault_mount")]
+    pub mount: String,
+    /// Secret path under the mount, e.g. "meilisearch/search-key"
+    pub path: String,
+    /// Vault role bound to the operator's service account
+    pub role: String,
+    /// Kubernetes auth method mount
+    #[serde(default = "default_vault_auth_mount")]
+    pub auth_mount: String,
+}
+
+fn default_vault_mount() -> String {
+    "secret".into()
+}
+
+fn default_vault_auth_mount() -> String {
+    "kubernetes".into()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct PushSecretSpec {
+    /// SecretStore or ClusterSecretStore name
+    pub store_name: String,
+    /// SecretStore (default) or ClusterSecretStore
+    #[serde(default = "default_store_kind")]
+    pub store_kind: String,
+    /// Key in the external store
+    pub remote_key: String,
+    #[serde(default = "default_refresh_interval")]
+    pub refresh_interval: String,
+}
+
+fn default_store_kind() -> String {
+    "SecretStore".into()
+}
+
+fn default_refresh_interval() -> String {
+    "1h".into()
+}
@@ -1,38 +1,310 @@
-use kube::CustomResource;
+use std::collections::BTreeMap;
+
+use k8s_openapi::ByteString;
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+    CustomResourceConversion, CustomResourceDefinition, ServiceReference, WebhookClientConfig,
+    WebhookConversion,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Condition;
+use kube::{CustomResource, CustomResourceExt, core::crd::merge_crds};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use super::common::{ConfigMapKeyRef, Phase, SecretTemplateSpec, ServerRef, TriggerOutcome};
+
+pub mod v1alpha1;
+
+/// Index on a Meilisearch Server. v1alpha1 Indexes are converted by the operator's webhook
+/// Service, which the CRD only names when the webhook is installed; without it they are
+/// served with their v1alpha1 fields unconverted.
 #[derive(CustomResource, Debug, Serialize, Deserialize, Clone, JsonSchema)]
 #[kube(
     group = "meili.operator.dev",
-    version = "v1alpha1",
+    version = "v1beta1",
     kind = "Index",
     plural = "indexes",
     namespaced,
     status = "IndexStatus",
-    shortname = "midx"
+    shortname = "midx",
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
 )]
 pub struct IndexSpec {
-    /// Reference to Server name in same namespace
-    pub server_ref: String,
-    /// Index uid
+    /// Server hosting the index. Changing it moves the index, see `status.retarget`
+    pub server_ref: ServerRef,
+    /// Index uid; immutable
+    #[schemars(extend("x-kubernetes-validations" = [{"rule": "self == oldSelf", "message": "uid is immutable"}]))]
     pub uid: String,
     /// Optional primary key
     pub primary_key: Option<String>,
-    /// If true, delete index on CR deletion
+    /// Index settings the operator keeps applied; unset fields are left to Meilisearch
+    pub settings: Option<IndexSettings>,
+    /// IndexTemplate in the same namespace whose settings apply where `settings` leaves a
+    /// field unset
+    pub template_ref: Option<String>,
+    /// What happens to the Meilisearch index when the CR is deleted
     #[serde(default)]
-    pub delete_on_finalize: bool,
-    /// Optional: generate an admin key with actions ["*"] scoped to this index
+    pub deletion_policy: DeletionPolicy,
+    /// Optional: generate a key scoped to this index with the actions in `admin_key.actions`
+    /// (default ["*"])
     pub admin_key: Option<IndexAdminKeySpec>,
+    /// Further keys scoped to this index, e.g. a search key for the frontend and an ingest
+    /// key for the backend, each provisioned as a Key CR with its own Secret
+    #[serde(default)]
+    pub keys: Vec<IndexKeySpec>,
+    /// Optional: periodically delete documents older than a cutoff
+    pub retention: Option<IndexRetentionSpec>,
+    /// What to do when the index already exists on the server with a different shape
+    #[serde(default)]
+    pub adoption_policy: AdoptionPolicy,
+    /// Content freeze: the keys from `admin_key` and `keys` are replaced by read-only ones
+    /// until this is unset, while search keeps working. Other keys that can write to the
+    /// index are only reported in the `Frozen` condition
+    #[serde(default)]
+    pub frozen: bool,
+    /// Fewest documents the index should hold; a lower live count, e.g. after a bad purge,
+    /// marks the Index Degraded
+    pub min_documents: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum DeletionPolicy {
+    /// Leave the index and its documents on the server
+    #[default]
+    Retain,
+    /// Delete the index from the server
+    Delete,
+}
+
+/// Subset of Meilisearch index settings managed declaratively.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct IndexSettings {
+    pub searchable_attributes: Option<Vec<String>>,
+    pub displayed_attributes: Option<Vec<String>>,
+    pub filterable_attributes: Option<Vec<String>>,
+    pub sortable_attributes: Option<Vec<String>>,
+    /// e.g. ["words", "typo", "proximity", "attribute", "sort", "exactness"]
+    pub ranking_rules: Option<Vec<String>>,
+    pub distinct_attribute: Option<String>,
+    pub stop_words: Option<Vec<String>>,
+    pub synonyms: Option<BTreeMap<String, Vec<String>>>,
+    /// More synonyms from a ConfigMap, as a JSON object or one group per line ("tv, television"
+    /// for mutual synonyms, "nyc => new york" one-way); `synonyms` wins for words in both
+    pub synonyms_from: Option<ConfigMapKeyRef>,
+    /// More stop words from a ConfigMap, as a JSON array or one word per line
+    pub stop_words_from: Option<ConfigMapKeyRef>,
+    pub typo_tolerance: Option<TypoTolerance>,
+    /// ByAttribute trades proximity accuracy for faster indexing (Meilisearch 1.6+)
+    pub proximity_precision: Option<ProximityPrecision>,
+    /// Upper bound for a search, after which the hits found so far are returned (1.9+)
+    #[schemars(range(min = 1))]
+    pub search_cutoff_ms: Option<u64>,
+    /// Whether facet values can be searched (1.12+)
+    pub facet_search: Option<bool>,
+    /// Disabled skips the prefix databases, speeding up indexing (1.12+)
+    pub prefix_search: Option<PrefixSearch>,
+    /// Languages of attributes, first matching rule wins (1.10+)
+    pub localized_attributes: Option<Vec<LocalizedAttributes>>,
+    /// Multi-word terms kept as a single token, e.g. "J. R. R." (1.3+)
+    pub dictionary: Option<Vec<String>>,
+    /// Extra characters splitting words (1.3+)
+    pub separator_tokens: Option<Vec<String>>,
+    /// Characters that no longer split words, e.g. "@" or "#" (1.3+)
+    pub non_separator_tokens: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct LocalizedAttributes {
+    /// Attribute names, `*` matching any run of characters, e.g. "title_ja" or "*_ja"
+    #[schemars(length(min = 1))]
+    pub attribute_patterns: Vec<String>,
+    /// ISO 639-3 codes, e.g. "jpn" or "cmn"
+    #[schemars(length(min = 1))]
+    pub locales: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum ProximityPrecision {
+    ByWord,
+    ByAttribute,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum PrefixSearch {
+    IndexingTime,
+    Disabled,
+}
+
+/// Typo tolerance; fields left unset keep the server's value.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct TypoTolerance {
+    pub enabled: Option<bool>,
+    pub min_word_size_for_typos: Option<MinWordSizeForTypos>,
+    /// Words only matched exactly
+    pub disable_on_words: Option<Vec<String>>,
+    /// Attributes only matched exactly
+    pub disable_on_attributes: Option<Vec<String>>,
+}
+
+/// Word lengths from which typos are accepted.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+#[schemars(extend("x-kubernetes-validations" = [{
+    "rule": "!has(self.one_typo) || !has(self.two_typos) || self.one_typo <= self.two_typos",
+    "message": "one_typo must not exceed two_typos"
+}]))]
+pub struct MinWordSizeForTypos {
+    /// Meilisearch defaults to 5
+    pub one_typo: Option<u8>,
+    /// Meilisearch defaults to 9
+    pub two_typos: Option<u8>,
+}
+
+impl IndexSettings {
+    /// These settings with unset fields taken from `base`.
+    pub fn over(self, base: &IndexSettings) -> IndexSettings {
+        IndexSettings {
+            searchable_attributes: self
+                .searchable_attributes
+                .or_else(|| base.searchable_attributes.clone()),
+            displayed_attributes: self
+                .displayed_attributes
+                .or_else(|| base.displayed_attributes.clone()),
+            filterable_attributes: self
+                .filterable_attributes
+                .or_else(|| base.filterable_attributes.clone()),
+            sortable_attributes: self
+                .sortable_attributes
+                .or_else(|| base.sortable_attributes.clone()),
+            ranking_rules: self.ranking_rules.or_else(|| base.ranking_rules.clone()),
+            distinct_attribute: self
+                .distinct_attribute
+                .or_else(|| base.distinct_attribute.clone()),
+            stop_words: self.stop_words.or_else(|| base.stop_words.clone()),
+            synonyms: self.synonyms.or_else(|| base.synonyms.clone()),
+            synonyms_from: self.synonyms_from.or_else(|| base.synonyms_from.clone()),
+            stop_words_from: self
+                .stop_words_from
+                .or_else(|| base.stop_words_from.clone()),
+            typo_tolerance: self.typo_tolerance.or_else(|| base.typo_tolerance.clone()),
+            proximity_precision: self.proximity_precision.or(base.proximity_precision),
+            search_cutoff_ms: self.search_cutoff_ms.or(base.search_cutoff_ms),
+            facet_search: self.facet_search.or(base.facet_search),
+            prefix_search: self.prefix_search.or(base.prefix_search),
+            localized_attributes: self
+                .localized_attributes
+                .or_else(|| base.localized_attributes.clone()),
+            dictionary: self.dictionary.or_else(|| base.dictionary.clone()),
+            separator_tokens: self
+                .separator_tokens
+                .or_else(|| base.separator_tokens.clone()),
+            non_separator_tokens: self
+                .non_separator_tokens
+                .or_else(|| base.non_separator_tokens.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum AdoptionPolicy {
+    /// Take over an existing index with the same uid
+    #[default]
+    Adopt,
+    /// Mark the Index Degraded instead of taking over a conflicting index
+    Fail,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
 pub struct IndexStatus {
+    #[serde(default)]
+    pub phase: Phase,
     pub ready: bool,
     pub message: Option<String>,
+    #[serde(default)]
+    #[schemars(extend("x-kubernetes-list-type" = "map", "x-kubernetes-list-map-keys" = ["type"]))]
+    pub conditions: Vec<Condition>,
+    /// RFC3339 time of the last retention sweep
+    pub last_retention_run: Option<String>,
+    /// taskUid of the last retention document deletion
+    pub last_retention_task_uid: Option<u64>,
+    /// taskUid of the last settings update
+    pub last_settings_task_uid: Option<u64>,
+    /// numberOfDocuments at the last full reconcile
+    pub document_count: Option<u64>,
+    /// Outcome of the last `meili.operator.dev/trigger` annotation
+    pub last_trigger: Option<TriggerOutcome>,
+    /// Changes a reconcile would make, recorded instead of applied in dry-run mode
+    pub dry_run_plan: Option<Vec<String>>,
+    /// metadata.generation last acted on
+    pub observed_generation: Option<i64>,
+    /// RFC3339 time of the last full reconcile against Meilisearch
+    pub last_synced: Option<String>,
+    /// metadata.generation of the IndexTemplate applied by the last full reconcile
+    pub template_generation: Option<i64>,
+    /// Digest of the ConfigMap contents applied by the last full reconcile
+    pub sources_checksum: Option<String>,
+    /// "<namespace>/<name>" of the admin key Secret last written
+    pub admin_key_secret: Option<String>,
+    /// Uid of the admin key on the server
+    pub admin_key_uid: Option<String>,
+    /// Index creation or deletion enqueued on the server and not finished yet
+    pub pending_task: Option<PendingTask>,
+    /// "<namespace>/<name>" of the Server the index was last reconciled on
+    pub server: Option<String>,
+    /// Progress of the last move to another Server after `server_ref` changed
+    pub retarget: Option<IndexRetarget>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+/// A move of the index between Servers, recorded step by step.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct IndexRetarget {
+    /// "<namespace>/<name>" of the Server the index is moved away from
+    pub from: String,
+    /// "<namespace>/<name>" of the Server it is moved to
+    pub to: String,
+    pub step: RetargetStep,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum RetargetStep {
+    /// Waiting for the index deletion on the old Server (`deletion_policy: Delete`)
+    DeletingIndex,
+    /// Waiting for the Key CRs from `keys` to delete their keys on the old Server
+    DeletingKeys,
+    /// The old Server is cleaned up; the index is created on the new one
+    Completed,
+}
+
+/// A Meilisearch task the operator enqueued and checks on later reconciles.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct PendingTask {
+    /// taskUid on the server
+    pub uid: u64,
+    /// Meilisearch task type, "indexCreation" or "indexDeletion"
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct IndexRetentionSpec {
+    /// Filterable numeric attribute holding a Unix timestamp in seconds; a plain attribute
+    /// name, dots allowed for nested fields
+    #[schemars(pattern(r"^[A-Za-z0-9_][A-Za-z0-9_.-]*$"))]
+    pub older_than_field: String,
+    /// Documents whose `older_than_field` is older than this many days are deleted
+    #[schemars(range(min = 1))]
+    pub max_age_days: u32,
+    /// Extra filter ANDed with the age condition, e.g. "kind = 'log'"
+    pub filter: Option<String>,
+    /// How often to sweep
+    #[serde(default = "default_retention_interval")]
+    pub interval_seconds: i64,
+}
+
+fn default_retention_interval() -> i64 {
+    3600
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct IndexAdminKeySpec {
     /// Create an admin key scoped to this index
     #[serde(default)]
@@ -41,4 +313,88 @@ pub struct IndexAdminKeySpec {
     pub secret_namespace: Option<String>,
     /// Name for the Secret (defaults to "<uid>-admin-key" if None)
     pub secret_name: Option<String>,
+    /// Data key name, type, labels and annotations of the Secret
+    pub secret_template: Option<SecretTemplateSpec>,
+    /// Actions of the key, e.g. ["documents.add", "documents.get", "settings.update"]
+    #[serde(default = "default_admin_actions")]
+    #[schemars(length(min = 1))]
+    pub actions: Vec<String>,
+    /// Optional RFC3339 expiration
+    pub expires_at: Option<String>,
+}
+
+/// A key restricted to this index.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct IndexKeySpec {
+    /// Unique within the Index; names the Key CR "<index CR>-<name>"
+    #[schemars(pattern(r"^[a-z0-9]([-a-z0-9]*[a-z0-9])?$"))]
+    pub name: String,
+    /// Actions of the key, e.g. ["search"] or ["documents.add", "documents.delete"]
+    #[schemars(length(min = 1))]
+    pub actions: Vec<String>,
+    /// Optional RFC3339 expiration
+    pub expires_at: Option<String>,
+    /// Namespace to store the Secret (defaults to CR namespace if None)
+    pub secret_namespace: Option<String>,
+    /// Name for the Secret (defaults to "<index CR>-<name>-key" if None)
+    pub secret_name: Option<String>,
+    /// Data key name, type, labels and annotations of the Secret
+    pub secret_template: Option<SecretTemplateSpec>,
+}
+
+fn default_admin_actions() -> Vec<String> {
+    vec!["*".into()]
+}
+
+/// Webhook Service the API server calls to convert Index objects between versions.
+pub const CONVERSION_SERVICE: &str = "meilisearch-operator-webhook";
+
+/// Where the API server reaches the operator's conversion webhook.
+#[derive(Clone, Debug)]
+pub struct ConversionWebhook {
+    /// Namespace of the operator and its webhook Service
+    pub namespace: String,
+    /// PEM bundle the webhook certificate is checked against; `None` leaves it to
+    /// cert-manager's CA injector, from the webhook Certificate
+    pub ca_bundle: Option<Vec<u8>>,
+}
+
+/// Index CRD serving v1alpha1 and v1beta1 (stored), without a conversion webhook: the API
+/// server then only rewrites `apiVersion`, so installs that serve v1alpha1 clients add one
+/// with [`with_conversion`].
+pub fn crd() -> CustomResourceDefinition {
+    merge_crds(vec![v1alpha1::Index::crd(), Index::crd()], super::V1BETA1)
+        .expect("Index CRD versions share group and kind")
+}
+
+/// `crd` converted by the webhook Service in `webhook.namespace`. The Service and its
+/// certificate have to exist, or every read and write of an Index at the version it isn't
+/// stored at fails.
+pub fn with_conversion(
+    mut crd: CustomResourceDefinition,
+    webhook: &ConversionWebhook,
+) -> CustomResourceDefinition {
+    if webhook.ca_bundle.is_none() {
+        crd.metadata.annotations.get_or_insert_default().insert(
+            "cert-manager.io/inject-ca-from".to_string(),
+            format!("{}/{}", webhook.namespace, CONVERSION_SERVICE),
+        );
+    }
+    crd.spec.conversion = Some(CustomResourceConversion {
+        strategy: "Webhook".into(),
+        webhook: Some(WebhookConversion {
+            client_config: Some(WebhookClientConfig {
+                service: Some(ServiceReference {
+                    name: CONVERSION_SERVICE.into(),
+                    namespace: webhook.namespace.clone(),
+                    path: Some("/convert".into()),
+                    port: Some(443),
+                }),
+                ca_bundle: webhook.ca_bundle.clone().map(ByteString),
+                ..Default::default()
+            }),
+            conversion_review_versions: vec!["v1".into()],
+        }),
+    });
+    crd
 }
@@ -0,0 +1,40 @@
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{AdoptionPolicy, IndexAdminKeySpec, IndexRetentionSpec, IndexStatus};
+
+/// Original Index API, still served and converted to v1beta1 by the conversion webhook.
+#[derive(CustomResource, Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[kube(
+    group = "meili.operator.dev",
+    version = "v1alpha1",
+    kind = "Index",
+    plural = "indexes",
+    namespaced,
+    status = "IndexStatus",
+    shortname = "midx",
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
+)]
+pub struct IndexSpec {
+    /// Reference to Server name in same namespace; immutable
+    #[schemars(extend("x-kubernetes-validations" = [{"rule": "self == oldSelf", "message": "server_ref is immutable"}]))]
+    pub server_ref: String,
+    /// Index uid; immutable
+    #[schemars(extend("x-kubernetes-validations" = [{"rule": "self == oldSelf", "message": "uid is immutable"}]))]
+    pub uid: String,
+    /// Optional primary key
+    pub primary_key: Option<String>,
+    /// If true, delete index on CR deletion
+    #[serde(default)]
+    pub delete_on_finalize: bool,
+    /// Optional: generate a key scoped to this index with the actions in `admin_key.actions`
+    /// (default ["*"])
+    pub admin_key: Option<IndexAdminKeySpec>,
+    /// Optional: periodically delete documents older than a cutoff
+    pub retention: Option<IndexRetentionSpec>,
+    /// What to do when the index already exists on the server with a different shape
+    #[serde(default)]
+    pub adoption_policy: AdoptionPolicy,
+}
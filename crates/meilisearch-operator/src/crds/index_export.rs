@@ -0,0 +1,68 @@
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::common::{ObjectStoreSpec, Phase};
+
+/// One-off export of an Index's documents to object storage, lighter than a full dump.
+#[derive(CustomResource, Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[kube(
+    group = "meili.operator.dev",
+    version = "v1alpha1",
+    kind = "IndexExport",
+    plural = "indexexports",
+    namespaced,
+    status = "IndexExportStatus",
+    shortname = "mexp",
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Documents","type":"integer","jsonPath":".status.exported_documents"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
+)]
+pub struct IndexExportSpec {
+    /// Index CR in the same namespace
+    pub index_ref: String,
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Bucket and key prefix; documents land in `<path>/part-00000.<ext>`, one file per batch
+    pub destination: ObjectStoreSpec,
+    /// Documents per request and per file
+    #[serde(default = "default_batch_size")]
+    #[schemars(range(min = 1, max = 100000))]
+    pub batch_size: u32,
+    /// Fields to export (and CSV columns, in order); all displayed fields otherwise
+    pub fields: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum ExportFormat {
+    /// One JSON document per line
+    #[default]
+    Ndjson,
+    /// Header row plus one row per document; nested values are written as JSON
+    Csv,
+}
+
+fn default_batch_size() -> u32 {
+    1000
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+pub struct IndexExportStatus {
+    #[serde(default)]
+    pub phase: Phase,
+    pub message: Option<String>,
+    /// Documents offset the next batch starts at; an interrupted export resumes here
+    #[serde(default)]
+    pub next_offset: u64,
+    /// Part files written so far
+    #[serde(default)]
+    pub parts: u32,
+    #[serde(default)]
+    pub exported_documents: u64,
+    /// Document count Meilisearch reported when the last batch was read
+    pub total_documents: Option<u64>,
+    /// RFC3339 time the export finished
+    pub completed_at: Option<String>,
+    /// metadata.generation the progress belongs to; a spec change starts over
+    pub observed_generation: Option<i64>,
+}
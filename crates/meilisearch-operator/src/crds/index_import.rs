@@ -0,0 +1,103 @@
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::common::{ObjectStoreSpec, Phase};
+
+/// One-off load of documents from object storage or an HTTP URL into an Index.
+#[derive(CustomResource, Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[kube(
+    group = "meili.operator.dev",
+    version = "v1alpha1",
+    kind = "IndexImport",
+    plural = "indeximports",
+    namespaced,
+    status = "IndexImportStatus",
+    shortname = "mimp",
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Sent","type":"integer","jsonPath":".status.records_sent"}"#,
+    printcolumn = r#"{"name":"Indexed","type":"integer","jsonPath":".status.indexed_documents"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
+)]
+pub struct IndexImportSpec {
+    /// Index CR in the same namespace
+    pub index_ref: String,
+    pub source: ImportSource,
+    #[serde(default)]
+    pub format: ImportFormat,
+    /// Primary key passed with every batch, for indexes that don't have one yet
+    pub primary_key: Option<String>,
+    /// Documents per batch
+    #[serde(default = "default_batch_size")]
+    #[schemars(range(min = 1))]
+    pub batch_size: u32,
+    /// Upper bound on a batch's payload; lowered to 90% of the Server's
+    /// `http_payload_size_limit` when that is smaller
+    #[serde(default = "default_max_batch_bytes")]
+    #[schemars(range(min = 1024))]
+    pub max_batch_bytes: u64,
+    /// Batches Meilisearch may have queued but not yet processed; sending pauses at this many
+    /// so a small instance is not buried under a large load. Unset sends as fast as the
+    /// source is read.
+    #[schemars(range(min = 1))]
+    pub max_pending_tasks: Option<u32>,
+}
+
+/// Exactly one of `object_store` or `url`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ImportSource {
+    pub object_store: Option<ObjectStoreSpec>,
+    /// Plain HTTP(S) GET, e.g. a pre-signed URL
+    #[schemars(pattern(r"^https?://"))]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum ImportFormat {
+    /// One JSON document per line, streamed
+    #[default]
+    Ndjson,
+    /// Header row plus one row per document, streamed; the header is repeated in every batch
+    Csv,
+    /// A single JSON array, read into memory before batching
+    Json,
+}
+
+fn default_batch_size() -> u32 {
+    1000
+}
+
+fn default_max_batch_bytes() -> u64 {
+    10 << 20
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+pub struct IndexImportStatus {
+    #[serde(default)]
+    pub phase: Phase,
+    pub message: Option<String>,
+    /// Source records sent to Meilisearch; an interrupted import skips this many on restart
+    #[serde(default)]
+    pub records_sent: u64,
+    #[serde(default)]
+    pub batches: u32,
+    /// Whole source sent; only task progress is left
+    #[serde(default)]
+    pub all_sent: bool,
+    /// Ingestion tasks that have not finished yet
+    #[serde(default)]
+    pub pending_task_uids: Vec<u64>,
+    #[serde(default)]
+    pub succeeded_tasks: u32,
+    #[serde(default)]
+    pub failed_tasks: u32,
+    /// Documents finished tasks report as indexed
+    #[serde(default)]
+    pub indexed_documents: u64,
+    /// Error of the first failed task
+    pub first_error: Option<String>,
+    /// RFC3339 time the last task finished
+    pub completed_at: Option<String>,
+    /// metadata.generation the progress belongs to; a spec change starts over
+    pub observed_generation: Option<i64>,
+}
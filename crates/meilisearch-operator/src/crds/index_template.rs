@@ -0,0 +1,24 @@
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::index::IndexSettings;
+
+/// Reusable settings preset. Index CRs in the same namespace reference it with
+/// `template_ref`; edits are re-applied to all of them.
+#[derive(CustomResource, Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[kube(
+    group = "meili.operator.dev",
+    version = "v1alpha1",
+    kind = "IndexTemplate",
+    plural = "indextemplates",
+    namespaced,
+    shortname = "mitpl",
+    printcolumn = r#"{"name":"Description","type":"string","jsonPath":".spec.description"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
+)]
+pub struct IndexTemplateSpec {
+    pub description: Option<String>,
+    /// Settings for referencing indexes; fields an Index sets itself take precedence
+    pub settings: IndexSettings,
+}
@@ -1,7 +1,10 @@
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Condition;
 use kube::CustomResource;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use super::common::{Phase, SecretStoreSpec, SecretTemplateSpec, TriggerOutcome};
+
 #[derive(CustomResource, Debug, Serialize, Deserialize, Clone, JsonSchema)]
 #[kube(
     group = "meili.operator.dev",
@@ -10,10 +13,18 @@ use serde::{Deserialize, Serialize};
     plural = "keys",
     namespaced,
     status = "KeyStatus",
-    shortname = "mkey"
+    shortname = "mkey",
+    printcolumn = r#"{"name":"Key","type":"string","jsonPath":".status.key_name"}"#,
+    printcolumn = r#"{"name":"Secret","type":"string","jsonPath":".status.secret_ref"}"#,
+    printcolumn = r#"{"name":"Expires","type":"string","jsonPath":".status.expires_at"}"#,
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Created","type":"string","priority":1,"jsonPath":".status.created_at"}"#,
+    printcolumn = r#"{"name":"Synced","type":"string","priority":1,"jsonPath":".status.last_synced"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
 )]
 pub struct KeySpec {
-    /// Reference to Server name in same namespace
+    /// Reference to Server name in same namespace; immutable
+    #[schemars(extend("x-kubernetes-validations" = [{"rule": "self == oldSelf", "message": "server_ref is immutable"}]))]
     pub server_ref: String,
     /// Meilisearch key name
     pub name: Option<String>,
@@ -28,12 +39,49 @@ pub struct KeySpec {
     /// Where to store the created key secret
     pub secret_namespace: String,
     pub secret_name: String,
+    /// Output backend; defaults to a Kubernetes Secret at secret_namespace/secret_name
+    pub secret_store: Option<SecretStoreSpec>,
+    /// Create the Secret with `immutable: true` (recreated on rotation); defaults to the
+    /// operator's `IMMUTABLE_SECRETS`
+    pub immutable_secret: Option<bool>,
+    /// Data key name, type, labels and annotations of the generated Secret
+    pub secret_template: Option<SecretTemplateSpec>,
+    /// Keep a Secret in another namespace when the Key is deleted. Secrets in the Key's own
+    /// namespace are owned by it and always garbage collected.
+    #[serde(default)]
+    pub retain_secret: bool,
+    /// Reuse a key value already in the target Secret that this Key did not write, e.g. one
+    /// pasted by hand. It is only used when the server's key carries the requested actions,
+    /// indexes and expiry.
+    #[serde(default)]
+    pub adopt_existing_secret: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
 pub struct KeyStatus {
     /// UID of key on server
     pub uid: Option<String>,
+    /// Name of the key on the server
+    pub key_name: Option<String>,
+    /// RFC3339 creation time reported by the server
+    pub created_at: Option<String>,
+    /// RFC3339 expiry reported by the server; unset for keys that never expire
+    pub expires_at: Option<String>,
+    /// Where the key is written: "<namespace>/<name>" of the Secret, or "vault:<mount>/<path>"
+    pub secret_ref: Option<String>,
+    #[serde(default)]
+    pub phase: Phase,
     pub ready: bool,
     pub message: Option<String>,
+    #[serde(default)]
+    #[schemars(extend("x-kubernetes-list-type" = "map", "x-kubernetes-list-map-keys" = ["type"]))]
+    pub conditions: Vec<Condition>,
+    /// Outcome of the last `meili.operator.dev/trigger` annotation
+    pub last_trigger: Option<TriggerOutcome>,
+    /// Changes a reconcile would make, recorded instead of applied in dry-run mode
+    pub dry_run_plan: Option<Vec<String>>,
+    /// metadata.generation last acted on
+    pub observed_generation: Option<i64>,
+    /// RFC3339 time of the last full reconcile against Meilisearch
+    pub last_synced: Option<String>,
 }
@@ -1,3 +1,142 @@
+pub mod cluster;
+pub mod common;
+pub mod database_sync;
 pub mod index;
+pub mod index_export;
+pub mod index_import;
+pub mod index_template;
 pub mod key;
+pub mod search_gateway;
 pub mod server;
+pub mod settings_rollout;
+pub mod stream_sink;
+pub mod tenant;
+
+use k8s_openapi::{
+    apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
+    apimachinery::pkg::apis::meta::v1::OwnerReference,
+};
+use kube::{Resource, core::CustomResourceExt};
+
+/// API group of every CRD; the `#[kube(group = ...)]` attributes must spell the same string.
+pub const GROUP: &str = "meili.operator.dev";
+pub const V1ALPHA1: &str = "v1alpha1";
+pub const V1BETA1: &str = "v1beta1";
+/// Finalizer every controller adds to the CRs it cleans up after.
+pub const FINALIZER: &str = "meili.operator.dev/finalizer";
+
+/// Controller owner reference to `obj`, with apiVersion and kind taken from its type so they
+/// follow the version the operator is built against.
+pub fn owner_ref<K: Resource<DynamicType = ()>>(obj: &K) -> OwnerReference {
+    OwnerReference {
+        api_version: K::api_version(&()).into_owned(),
+        kind: K::kind(&()).into_owned(),
+        name: obj.meta().name.clone().unwrap_or_default(),
+        uid: obj.meta().uid.clone().unwrap_or_default(),
+        controller: Some(true),
+        block_owner_deletion: Some(true),
+    }
+}
+
+/// Every CRD the operator serves, in the order they are installed.
+pub fn all() -> Vec<CustomResourceDefinition> {
+    vec![
+        server::Server::crd(),
+        index::crd(),
+        key::Key::crd(),
+        cluster::Cluster::crd(),
+        index_export::IndexExport::crd(),
+        index_import::IndexImport::crd(),
+        stream_sink::StreamSink::crd(),
+        database_sync::DatabaseSync::crd(),
+        search_gateway::SearchGateway::crd(),
+        tenant::Tenant::crd(),
+        index_template::IndexTemplate::crd(),
+        settings_rollout::SettingsRollout::crd(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crds_share_the_group_constant() {
+        for crd in all() {
+            assert_eq!(crd.spec.group, GROUP, "{}", crd.spec.names.kind);
+        }
+        assert!(FINALIZER.starts_with(GROUP));
+        let server = server::Server::new("s", Default::default());
+        assert_eq!(
+            owner_ref(&server).api_version,
+            format!("{}/{}", GROUP, V1BETA1)
+        );
+        assert_eq!(
+            index::Index::api_version(&()),
+            format!("{}/{}", GROUP, V1BETA1)
+        );
+    }
+
+    #[test]
+    fn server_refs_and_index_uids_are_immutable() {
+        // Rule on `field` per version
+        let rules = |crd: CustomResourceDefinition, field: &str| -> Vec<(String, Option<String>)> {
+            crd.spec
+                .versions
+                .iter()
+                .map(|v| {
+                    let schema = v.schema.as_ref().unwrap().open_api_v3_schema.as_ref();
+                    let spec = &schema.unwrap().properties.as_ref().unwrap()["spec"];
+                    let prop = &spec.properties.as_ref().unwrap()[field];
+                    let rule = prop
+                        .x_kubernetes_validations
+                        .as_ref()
+                        .map(|r| r[0].rule.clone());
+                    (v.name.clone(), rule)
+                })
+                .collect()
+        };
+        for (_, rule) in rules(index::crd(), "uid")
+            .into_iter()
+            .chain(rules(key::Key::crd(), "server_ref"))
+        {
+            assert_eq!(rule.as_deref(), Some("self == oldSelf"));
+        }
+        // v1beta1 Indexes move to another Server instead
+        for (version, rule) in rules(index::crd(), "server_ref") {
+            assert_eq!(rule.is_some(), version == V1ALPHA1, "{}", version);
+        }
+    }
+
+    #[test]
+    fn status_conditions_are_maps_keyed_by_type() {
+        for crd in all() {
+            for version in &crd.spec.versions {
+                let schema = version.schema.as_ref().unwrap().open_api_v3_schema.as_ref();
+                let Some(conditions) = schema
+                    .unwrap()
+                    .properties
+                    .as_ref()
+                    .and_then(|p| p.get("status"))
+                    .and_then(|s| s.properties.as_ref())
+                    .and_then(|p| p.get("conditions"))
+                else {
+                    continue;
+                };
+                let kind = &crd.spec.names.kind;
+                assert_eq!(
+                    conditions.x_kubernetes_list_type.as_deref(),
+                    Some("map"),
+                    "{}",
+                    kind
+                );
+                assert_eq!(
+                    conditions.x_kubernetes_list_map_keys,
+                    Some(vec!["type".to_string()]),
+                    "{}",
+                    kind
+                );
+            }
+        }
+    }
+}
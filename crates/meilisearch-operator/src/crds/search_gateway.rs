@@ -0,0 +1,79 @@
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::common::{Phase, ServerRef};
+
+/// A Deployment serving one search endpoint across several Servers: `/multi-search` (federated
+/// or not) and `/indexes/{uid}/search` are routed to the Server owning each index.
+#[derive(CustomResource, Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[kube(
+    group = "meili.operator.dev",
+    version = "v1alpha1",
+    kind = "SearchGateway",
+    plural = "searchgateways",
+    namespaced,
+    status = "SearchGatewayStatus",
+    shortname = "mgw",
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Ready","type":"integer","jsonPath":".status.ready_replicas"}"#,
+    printcolumn = r#"{"name":"Endpoint","type":"string","jsonPath":".status.endpoint"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
+)]
+pub struct SearchGatewaySpec {
+    /// Servers and the index uids each one answers for; an index uid may appear only once
+    #[schemars(length(min = 1))]
+    pub backends: Vec<GatewayBackend>,
+    /// Callers allowed to search. Each gets a generated bearer token in the `<name>-tenants`
+    /// Secret. With no tenants the gateway accepts unauthenticated requests.
+    #[serde(default)]
+    pub tenants: Vec<GatewayTenant>,
+    #[serde(default = "default_replicas")]
+    #[schemars(range(min = 0))]
+    pub replicas: i32,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Gateway image; defaults to the operator's `GATEWAY_IMAGE`
+    pub image: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct GatewayBackend {
+    /// Short name, used in generated object names
+    pub name: String,
+    pub server_ref: ServerRef,
+    /// Index uids routed to this Server; the generated search key is limited to them
+    #[schemars(length(min = 1))]
+    pub indexes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct GatewayTenant {
+    /// Token Secret data key
+    pub name: String,
+    /// Filter ANDed into every query this tenant sends, e.g. `tenant_id = 42`
+    pub filter: Option<String>,
+    /// Index uids this tenant may search; all backend indexes when empty
+    #[serde(default)]
+    pub indexes: Vec<String>,
+}
+
+fn default_replicas() -> i32 {
+    1
+}
+
+fn default_port() -> u16 {
+    7700
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+pub struct SearchGatewayStatus {
+    #[serde(default)]
+    pub phase: Phase,
+    pub message: Option<String>,
+    /// In-cluster URL of the gateway Service
+    pub endpoint: Option<String>,
+    pub ready_replicas: Option<i32>,
+    /// metadata.generation last acted on
+    pub observed_generation: Option<i64>,
+}
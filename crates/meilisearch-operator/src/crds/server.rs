@@ -1,7 +1,11 @@
+use k8s_openapi::api::core::v1::{HostAlias, PodDNSConfig};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Condition;
 use kube::CustomResource;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use super::common::{Phase, SecretStoreSpec, SecretTemplateSpec};
+
 #[derive(CustomResource, Debug, Serialize, Deserialize, Clone, JsonSchema)]
 #[kube(
     group = "meili.operator.dev",
@@ -10,20 +14,459 @@ use serde::{Deserialize, Serialize};
     plural = "servers",
     namespaced,
     status = "ServerStatus",
-    shortname = "msrv"
+    shortname = "msrv",
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
 )]
 pub struct ServerSpec {
     pub image: Option<String>,
+    /// Semver constraint such as "~1.12" or "^1"; the operator resolves it against published
+    /// releases and upgrades automatically within it. Overrides the tag of `image`.
+    pub version: Option<String>,
+    /// Pin the image by digest, e.g. "sha256:<64 hex chars>"
+    #[schemars(pattern(r"^sha256:[a-f0-9]{64}$"))]
+    pub image_digest: Option<String>,
+    /// Always, IfNotPresent or Never (Kubernetes default when omitted)
+    pub image_pull_policy: Option<ImagePullPolicy>,
+    /// Number of pods. Meilisearch does not replicate data between instances,
+    /// so values above 1 yield independent copies and mark the Server Degraded.
     #[serde(default = "default_replicas")]
     pub replicas: i32,
     /// Storage size, e.g. "10Gi"
     pub storage: Option<String>,
+    /// Separate volume for dumps and snapshots, mounted at /meili_dumps. Like `storage`,
+    /// this becomes a volume claim template, which Kubernetes does not allow to change later.
+    pub dump_storage: Option<DumpStorageSpec>,
     /// Service type: ClusterIP, NodePort, LoadBalancer
     #[serde(default = "default_service_type")]
     pub service_type: String,
     /// Port for meilisearch HTTP, default 7700
     #[serde(default = "default_port")]
     pub port: u16,
+    /// IP families of the Service, the first being its primary, e.g. [IPv6] or [IPv4, IPv6]
+    /// on dual-stack clusters; the cluster default when empty. With IPv6 listed Meilisearch
+    /// listens on `[::]` instead of `0.0.0.0`.
+    #[serde(default)]
+    pub ip_families: Vec<IpFamily>,
+    /// Service `ipFamilyPolicy`; the cluster default (SingleStack) when unset
+    pub ip_family_policy: Option<IpFamilyPolicy>,
+    /// How image changes are rolled out: InPlace (default), BlueGreen or Canary; the latter two
+    /// require `storage`
+    #[serde(default)]
+    pub upgrade_strategy: UpgradeStrategy,
+    /// Traffic shifting settings for the Canary upgrade strategy
+    pub canary: Option<CanarySpec>,
+    /// Pod update strategy of the StatefulSet (RollingUpdate with optional partition, or OnDelete)
+    pub update_strategy: Option<StatefulSetUpdate>,
+    /// Pod terminationGracePeriodSeconds; raise it when `pre_stop_backup` is set on large instances
+    pub termination_grace_period_seconds: Option<i64>,
+    /// Trigger a snapshot or dump from a preStop hook and wait for it before shutdown
+    pub pre_stop_backup: Option<BackupKind>,
+    /// PriorityClass for the Meilisearch pods
+    pub priority_class_name: Option<String>,
+    /// Pod dnsPolicy, e.g. ClusterFirst or None (then `dns_config` is required)
+    pub dns_policy: Option<String>,
+    /// Custom resolvers/search domains, e.g. for reaching embedder APIs via split-horizon DNS
+    pub dns_config: Option<PodDNSConfig>,
+    /// Extra /etc/hosts entries for the Meilisearch pods
+    pub host_aliases: Option<Vec<HostAlias>>,
+    /// MEILI_HTTP_PAYLOAD_SIZE_LIMIT, e.g. "100MB" or "512Mi"
+    pub http_payload_size_limit: Option<String>,
+    /// MEILI_MAX_INDEXING_MEMORY, e.g. "2GiB"
+    pub max_indexing_memory: Option<String>,
+    /// MEILI_MAX_INDEXING_THREADS
+    #[schemars(range(min = 1))]
+    pub max_indexing_threads: Option<u32>,
+    /// MEILI_LOG_LEVEL
+    pub log_level: Option<LogLevel>,
+    /// Enable the experimental `/logs/stream` route
+    #[serde(default)]
+    pub experimental_logs_route: bool,
+    /// Send Meilisearch telemetry; off by default (`--no-analytics`)
+    #[serde(default)]
+    pub analytics: bool,
+    /// Periodically delete finished tasks so the task database doesn't grow unbounded
+    pub task_cleanup: Option<TaskCleanupSpec>,
+    /// Also write the master key to Vault or push it via External Secrets. The namespace
+    /// Secret is kept because the Meilisearch pod reads its key from it.
+    pub secret_store: Option<SecretStoreSpec>,
+    /// Create the master key Secret with `immutable: true`; defaults to the operator's
+    /// `IMMUTABLE_SECRETS`
+    pub immutable_secrets: Option<bool>,
+    /// Data key name, type, labels and annotations of the master key Secret
+    pub secret_template: Option<SecretTemplateSpec>,
+    /// Length and alphabet of the master key the operator generates
+    pub master_key: Option<MasterKeySpec>,
+    /// `Cluster` in this namespace to run the workload in; defaults to the operator's cluster
+    pub cluster_ref: Option<String>,
+    /// How long to wait for `/health` after rolling out; raise it for large dump imports
+    pub health_check: Option<HealthCheckSpec>,
+    /// URL the operator uses to reach Meilisearch instead of the Service DNS name, e.g. a
+    /// mesh virtual service or an external load balancer
+    #[schemars(pattern(r"^https?://"))]
+    pub endpoint_override: Option<String>,
+    /// What deleting the Server does to Index and Key CRs that reference it: Cascade
+    /// (default) deletes them along with it, Block keeps the Server until they are gone
+    #[serde(default)]
+    pub deletion_policy: ServerDeletionPolicy,
+    /// What to do when the StatefulSet was edited outside the operator: Correct (default)
+    /// reverts the edit, Warn leaves it in place. Both report it.
+    #[serde(default)]
+    pub drift_policy: DriftPolicy,
+    /// Service mesh the pods are injected into: sets the proxy startup annotations, names
+    /// the port `http` with appProtocol `http`, and makes the operator's health checks
+    /// tolerate the sidecar coming and going
+    pub mesh: Option<ServiceMesh>,
+    /// When image upgrades and StatefulSet changes may roll out; outside it they wait,
+    /// reported by the `PendingChanges` condition. Any time when unset
+    pub maintenance_window: Option<MaintenanceWindow>,
+}
+
+impl Default for ServerSpec {
+    fn default() -> Self {
+        Self {
+            image: None,
+            version: None,
+            image_digest: None,
+            image_pull_policy: None,
+            replicas: default_replicas(),
+            storage: None,
+            dump_storage: None,
+            service_type: default_service_type(),
+            port: default_port(),
+            ip_families: Vec::new(),
+            ip_family_policy: None,
+            upgrade_strategy: UpgradeStrategy::default(),
+            canary: None,
+            update_strategy: None,
+            termination_grace_period_seconds: None,
+            pre_stop_backup: None,
+            priority_class_name: None,
+            dns_policy: None,
+            dns_config: None,
+            host_aliases: None,
+            http_payload_size_limit: None,
+            max_indexing_memory: None,
+            max_indexing_threads: None,
+            log_level: None,
+            experimental_logs_route: false,
+            analytics: false,
+            task_cleanup: None,
+            secret_store: None,
+            immutable_secrets: None,
+            secret_template: None,
+            master_key: None,
+            cluster_ref: None,
+            health_check: None,
+            endpoint_override: None,
+            deletion_policy: ServerDeletionPolicy::default(),
+            drift_policy: DriftPolicy::default(),
+            mesh: None,
+            maintenance_window: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum ImagePullPolicy {
+    Always,
+    IfNotPresent,
+    Never,
+}
+
+impl ImagePullPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Always => "Always",
+            Self::IfNotPresent => "IfNotPresent",
+            Self::Never => "Never",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum ServerDeletionPolicy {
+    /// Delete dependent Index and Key CRs without calling Meilisearch, removing their finalizers
+    #[default]
+    Cascade,
+    /// Hold the finalizer and set `DeletionBlocked` while dependents exist
+    Block,
+}
+
+/// Meilisearch refuses master keys shorter than this many bytes.
+pub const MIN_MASTER_KEY_BYTES: usize = 16;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct MasterKeySpec {
+    /// Characters in the generated key
+    #[serde(default = "default_master_key_length")]
+    #[schemars(range(min = 16, max = 512))]
+    pub length: usize,
+    #[serde(default)]
+    pub charset: MasterKeyCharset,
+}
+
+impl Default for MasterKeySpec {
+    fn default() -> Self {
+        Self {
+            length: default_master_key_length(),
+            charset: MasterKeyCharset::default(),
+        }
+    }
+}
+
+fn default_master_key_length() -> usize {
+    64
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum MasterKeyCharset {
+    /// A-Z, a-z and 0-9
+    #[default]
+    Alphanumeric,
+    /// 0-9 and a-f
+    Hex,
+    /// URL-safe base64 alphabet: A-Z, a-z, 0-9, '-' and '_'
+    Base64Url,
+}
+
+impl MasterKeyCharset {
+    pub fn alphabet(&self) -> &'static [u8] {
+        match self {
+            Self::Alphanumeric => b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+            Self::Hex => b"0123456789abcdef",
+            Self::Base64Url => b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum IpFamily {
+    #[serde(rename = "IPv4")]
+    Ipv4,
+    #[serde(rename = "IPv6")]
+    Ipv6,
+}
+
+impl IpFamily {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ipv4 => "IPv4",
+            Self::Ipv6 => "IPv6",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum IpFamilyPolicy {
+    SingleStack,
+    /// Dual-stack where the cluster supports it, single-stack otherwise
+    PreferDualStack,
+    RequireDualStack,
+}
+
+impl IpFamilyPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::SingleStack => "SingleStack",
+            Self::PreferDualStack => "PreferDualStack",
+            Self::RequireDualStack => "RequireDualStack",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct MaintenanceWindow {
+    /// Five-field cron expression in UTC for when the window opens, e.g. "0 2 * * 6"
+    pub schedule: String,
+    /// How long it stays open, e.g. "4h" or "90m"
+    pub duration: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum ServiceMesh {
+    Istio,
+    Linkerd,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum DriftPolicy {
+    /// Report the edit with an Event and the `DriftDetected` condition, then re-apply the spec
+    #[default]
+    Correct,
+    /// Report the edit and stop updating the StatefulSet until the edit is undone
+    Warn,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum UpgradeStrategy {
+    /// Patch the image on the existing StatefulSet
+    #[default]
+    InPlace,
+    /// Dump, restore into a parallel StatefulSet, verify, then switch the Service over.
+    /// Requires `storage`.
+    BlueGreen,
+    /// Like BlueGreen, but first shifts a share of traffic through `canary.http_route`
+    /// and rolls back if probe searches against the new version start failing.
+    Canary,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct TaskCleanupSpec {
+    /// Minimum task age, e.g. "7d", "12h"
+    #[serde(default = "default_task_cleanup_older_than")]
+    pub older_than: String,
+    /// Task statuses to delete: any of succeeded, failed, canceled
+    #[serde(default = "default_task_cleanup_statuses")]
+    pub statuses: Vec<String>,
+    /// How often to run the cleanup
+    #[serde(default = "default_task_cleanup_interval")]
+    #[schemars(range(min = 1))]
+    pub interval_seconds: i64,
+}
+
+fn default_task_cleanup_older_than() -> String {
+    "7d".into()
+}
+fn default_task_cleanup_statuses() -> Vec<String> {
+    vec!["succeeded".into(), "failed".into(), "canceled".into()]
+}
+fn default_task_cleanup_interval() -> i64 {
+    3600
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct HealthCheckSpec {
+    /// Seconds between `/health` probes
+    #[serde(default = "default_health_interval")]
+    #[schemars(range(min = 1))]
+    pub interval_seconds: u64,
+    /// Probes before the instance counts as unhealthy
+    #[serde(default = "default_health_attempts")]
+    #[schemars(range(min = 1))]
+    pub max_attempts: u32,
+    /// Timeout of a single probe in seconds
+    #[serde(default = "default_health_timeout")]
+    #[schemars(range(min = 1))]
+    pub timeout_seconds: u64,
+    /// Error (default) fails the reconcile and retries with backoff; Degraded records the
+    /// Server as not ready and checks again on the next reconcile
+    #[serde(default)]
+    pub on_timeout: HealthTimeoutAction,
+}
+
+impl Default for HealthCheckSpec {
+    fn default() -> Self {
+        Self {
+            interval_seconds: default_health_interval(),
+            max_attempts: default_health_attempts(),
+            timeout_seconds: default_health_timeout(),
+            on_timeout: HealthTimeoutAction::default(),
+        }
+    }
+}
+
+fn default_health_interval() -> u64 {
+    2
+}
+fn default_health_attempts() -> u32 {
+    120
+}
+fn default_health_timeout() -> u64 {
+    1
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum HealthTimeoutAction {
+    #[default]
+    Error,
+    Degraded,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct DumpStorageSpec {
+    /// Size, e.g. "20Gi"
+    pub size: String,
+    /// StorageClass, e.g. a cheaper HDD-backed class
+    pub storage_class_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "OFF",
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+            Self::Trace => "TRACE",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum BackupKind {
+    /// POST /snapshots
+    Snapshot,
+    /// POST /dumps
+    Dump,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct StatefulSetUpdate {
+    #[serde(rename = "type", default)]
+    pub type_: StatefulSetUpdateType,
+    /// RollingUpdate only: pods with an ordinal below this keep the old template
+    #[schemars(range(min = 0))]
+    pub partition: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum StatefulSetUpdateType {
+    #[default]
+    RollingUpdate,
+    /// Pods only pick up a new template when deleted manually
+    OnDelete,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct CanarySpec {
+    /// Gateway API HTTPRoute (same namespace) whose backendRefs point at the Server Service
+    pub http_route: String,
+    /// Percentage of traffic sent to the new version while under analysis
+    #[serde(default = "default_canary_weight")]
+    #[schemars(range(min = 1, max = 99))]
+    pub weight: i32,
+    /// How long the canary must stay healthy before it is promoted; at least two analysis
+    /// passes
+    #[serde(default = "default_canary_analysis_seconds")]
+    #[schemars(range(min = 60))]
+    pub analysis_seconds: i64,
+    /// Probe failure ratio (0.0-1.0) that triggers a rollback
+    #[serde(default = "default_canary_max_error_rate")]
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub max_error_rate: f64,
+}
+
+fn default_canary_weight() -> i32 {
+    10
+}
+fn default_canary_analysis_seconds() -> i64 {
+    600
+}
+fn default_canary_max_error_rate() -> f64 {
+    0.05
 }
 
 fn default_replicas() -> i32 {
@@ -38,7 +481,95 @@ fn default_port() -> u16 {
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
 pub struct ServerStatus {
+    #[serde(default)]
+    pub phase: Phase,
     pub ready: bool,
+    /// URL the operator, Index and Key controllers use (`spec.endpoint_override` when set)
     pub endpoint: Option<String>,
+    /// In-cluster URL of the Server's Service
+    pub internal_endpoint: Option<String>,
+    pub message: Option<String>,
+    #[serde(default)]
+    #[schemars(extend("x-kubernetes-list-type" = "map", "x-kubernetes-list-map-keys" = ["type"]))]
+    pub conditions: Vec<Condition>,
+    /// Effective image serving traffic, after operator defaults, version resolution and digest pinning
+    pub current_image: Option<String>,
+    /// Release picked for `spec.version`
+    pub resolved_version: Option<String>,
+    /// Version the running instance reports on `/version`
+    pub meilisearch_version: Option<String>,
+    /// Version-gated key actions and settings fields (`settings.<field>`) the instance supports
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Log level the pods run with (Meilisearch defaults to INFO)
+    pub log_level: Option<String>,
+    /// URL of the experimental log streaming route, when enabled
+    pub logs_route: Option<String>,
+    /// RFC3339 time of the last task cleanup request
+    pub last_task_cleanup: Option<String>,
+    /// taskUid of the last task deletion
+    pub last_task_cleanup_task_uid: Option<u64>,
+    /// Newest failed taskUid already reported on its Index
+    pub last_failed_task_uid: Option<u64>,
+    /// StatefulSet currently selected by the Service (defaults to the Server name)
+    pub active_statefulset: Option<String>,
+    /// Ready pods of the active StatefulSet at the last full reconcile
+    pub ready_replicas: Option<i32>,
+    /// In-flight blue/green upgrade, if any
+    pub upgrade: Option<UpgradeStatus>,
+    /// Changes a reconcile would make, recorded instead of applied in dry-run mode
+    pub dry_run_plan: Option<Vec<String>>,
+    /// metadata.generation last acted on
+    pub observed_generation: Option<i64>,
+    /// RFC3339 time of the last full reconcile against Meilisearch
+    pub last_synced: Option<String>,
+    /// Indexes and keys on the instance that no Index or Key CR manages
+    pub unmanaged: Option<UnmanagedResources>,
+    /// Digest of the StatefulSet spec last applied, to tell changes held back by the
+    /// maintenance window
+    pub statefulset_checksum: Option<String>,
+}
+
+/// What an instance holds beyond the CRs referencing it, e.g. on a Server adopted live.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct UnmanagedResources {
+    /// Index uids
+    #[serde(default)]
+    pub indexes: Vec<String>,
+    #[serde(default)]
+    pub keys: Vec<UnmanagedKey>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct UnmanagedKey {
+    pub uid: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct UpgradeStatus {
+    pub phase: UpgradePhase,
+    pub target_image: String,
+    pub target_statefulset: String,
+    pub dump_task_uid: Option<u64>,
+    pub dump_uid: Option<String>,
+    #[serde(default)]
+    pub verify_attempts: u32,
+    /// RFC3339 time traffic started flowing to the canary
+    pub canary_started_at: Option<String>,
+    #[serde(default)]
+    pub probes: u32,
+    #[serde(default)]
+    pub probe_failures: u32,
     pub message: Option<String>,
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum UpgradePhase {
+    Dumping,
+    Importing,
+    Provisioning,
+    Verifying,
+    Canary,
+    Failed,
+}
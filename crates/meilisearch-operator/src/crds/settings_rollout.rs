@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{common::ServerRef, index::IndexSettings};
+
+/// Settings patch written into every Index CR in the namespace that matches `selector`, a few
+/// Indexes at a time. Each patched Index applies it through its own reconcile; the rollout
+/// moves on as they become Ready and stops at the first one that does not.
+#[derive(CustomResource, Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[kube(
+    group = "meili.operator.dev",
+    version = "v1alpha1",
+    kind = "SettingsRollout",
+    plural = "settingsrollouts",
+    namespaced,
+    status = "SettingsRolloutStatus",
+    shortname = "msr",
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Updated","type":"string","jsonPath":".status.progress"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
+)]
+pub struct SettingsRolloutSpec {
+    /// Labels an Index CR must carry; empty selects every Index in the namespace
+    #[serde(default)]
+    pub selector: BTreeMap<String, String>,
+    /// Only Indexes on these Servers; empty means any
+    #[serde(default)]
+    pub server_refs: Vec<ServerRef>,
+    /// Fields written into each Index's `settings`; unset fields are left alone
+    pub settings: IndexSettings,
+    /// Indexes patched but not yet Ready at any time
+    #[serde(default = "default_max_unavailable")]
+    #[schemars(range(min = 1))]
+    pub max_unavailable: u32,
+}
+
+fn default_max_unavailable() -> u32 {
+    1
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum RolloutPhase {
+    #[default]
+    Pending,
+    Progressing,
+    /// Every selected Index runs with the settings
+    Completed,
+    /// An Index failed to apply them; the rollout stopped
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum RolloutTargetState {
+    /// Not patched yet
+    Pending,
+    /// Patched; waiting for the Index to apply the settings
+    Applying,
+    Succeeded,
+    Failed,
+}
+
+/// One selected Index.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct RolloutTarget {
+    /// Index CR name
+    pub name: String,
+    pub state: RolloutTargetState,
+    /// metadata.generation the patch produced; the Index is done once it observed it
+    pub generation: Option<i64>,
+    /// taskUid of the Index's last settings update, when it reports one
+    pub task_uid: Option<u64>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+pub struct SettingsRolloutStatus {
+    #[serde(default)]
+    pub phase: RolloutPhase,
+    pub message: Option<String>,
+    /// "<succeeded>/<selected>"
+    pub progress: Option<String>,
+    #[serde(default)]
+    pub targets: Vec<RolloutTarget>,
+    /// metadata.generation the targets were computed for
+    pub observed_generation: Option<i64>,
+}
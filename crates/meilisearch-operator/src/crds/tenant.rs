@@ -0,0 +1,109 @@
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::common::{Phase, ServerRef};
+use super::index::{DeletionPolicy, IndexSettings};
+
+/// One tenant of a multi-tenant search setup: a set of indexes named after the tenant, a
+/// search key limited to them and a tenant token scoped to the tenant's documents.
+#[derive(CustomResource, Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[kube(
+    group = "meili.operator.dev",
+    version = "v1alpha1",
+    kind = "Tenant",
+    plural = "tenants",
+    namespaced,
+    status = "TenantStatus",
+    shortname = "mten",
+    printcolumn = r#"{"name":"Tenant","type":"string","jsonPath":".spec.tenant_id"}"#,
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Indexes","type":"string","jsonPath":".status.ready_indexes"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
+)]
+pub struct TenantSpec {
+    /// Server hosting the tenant's indexes
+    pub server_ref: ServerRef,
+    /// Tenant identifier; index uids are `<tenant_id>-<index name>`
+    #[schemars(pattern(r"^[A-Za-z0-9_-]+$"))]
+    pub tenant_id: String,
+    /// Indexes created for the tenant
+    #[schemars(length(min = 1))]
+    pub indexes: Vec<TenantIndexTemplate>,
+    #[serde(default)]
+    pub key: TenantKeyTemplate,
+    /// Also sign a tenant token from the key
+    pub token: Option<TenantTokenSpec>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct TenantIndexTemplate {
+    /// Suffix of the index uid and of the Index CR name
+    #[schemars(pattern(r"^[a-z0-9-]+$"))]
+    pub name: String,
+    pub primary_key: Option<String>,
+    pub settings: Option<IndexSettings>,
+    /// IndexTemplate in the Tenant's namespace filling in settings left unset
+    pub template_ref: Option<String>,
+    #[serde(default)]
+    pub deletion_policy: DeletionPolicy,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct TenantKeyTemplate {
+    /// Actions of the tenant's key, limited to the tenant's indexes
+    #[serde(default = "default_actions")]
+    pub actions: Vec<String>,
+    /// Optional ISO8601 expiration
+    pub expires_at: Option<String>,
+    /// Secret for the key; defaults to "<name>-key"
+    pub secret_name: Option<String>,
+}
+
+impl Default for TenantKeyTemplate {
+    fn default() -> Self {
+        Self {
+            actions: default_actions(),
+            expires_at: None,
+            secret_name: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct TenantTokenSpec {
+    /// Filter every search with the token is restricted to; `{tenant_id}` is replaced, e.g.
+    /// "tenant_id = {tenant_id}"
+    pub filter: Option<String>,
+    /// Token lifetime; it is re-signed once two thirds have passed. Never expires when unset.
+    #[schemars(range(min = 300))]
+    pub expires_in_seconds: Option<u64>,
+    /// Secret for the token; defaults to "<name>-token"
+    pub secret_name: Option<String>,
+}
+
+fn default_actions() -> Vec<String> {
+    vec!["search".into()]
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+pub struct TenantStatus {
+    #[serde(default)]
+    pub phase: Phase,
+    pub message: Option<String>,
+    /// Uids of the tenant's indexes
+    #[serde(default)]
+    pub indexes: Vec<String>,
+    /// Ready Index CRs out of all, e.g. "2/3"
+    pub ready_indexes: Option<String>,
+    #[serde(default)]
+    pub key_ready: bool,
+    /// Uid of the tenant's key on the server
+    pub key_uid: Option<String>,
+    /// RFC3339 expiry of the current tenant token
+    pub token_expires_at: Option<String>,
+    /// Digest of what the current tenant token was signed from
+    pub token_checksum: Option<String>,
+    /// metadata.generation last acted on
+    pub observed_generation: Option<i64>,
+}
@@ -0,0 +1,174 @@
+use k8s_openapi::chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+
+/// A five-field cron expression (minute hour day-of-month month day-of-week), evaluated in UTC.
+/// Supports `*`, lists, ranges, steps and the `@hourly`-style macros. As in Vixie cron, when
+/// both day fields are restricted a day matching either one fires.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = match expr.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            return Err(format!(
+                "expected 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        };
+        let mut weekdays = field(weekday, 0, 7)?;
+        // 7 is Sunday too
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+        Ok(Self {
+            minutes: field(minute, 0, 59)?,
+            hours: field(hour, 0, 23)?,
+            days: field(day, 1, 31)?,
+            months: field(month, 1, 12)?,
+            weekdays,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        })
+    }
+
+    /// First time strictly after `after` the schedule fires, searching up to five years ahead.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut day = Utc
+            .with_ymd_and_hms(start.year(), start.month(), start.day(), 0, 0, 0)
+            .single()?;
+        for _ in 0..366 * 5 {
+            if self.day_matches(day) {
+                for hour in 0..24 {
+                    if self.hours & (1 << hour) == 0 {
+                        continue;
+                    }
+                    for minute in 0..60 {
+                        if self.minutes & (1 << minute) == 0 {
+                            continue;
+                        }
+                        let t = day + Duration::hours(hour) + Duration::minutes(minute);
+                        if t >= start {
+                            return Some(t);
+                        }
+                    }
+                }
+            }
+            day += Duration::days(1);
+        }
+        None
+    }
+
+    fn day_matches(&self, day: DateTime<Utc>) -> bool {
+        if self.months & (1 << day.month()) == 0 {
+            return false;
+        }
+        let dom = self.days & (1 << day.day()) != 0;
+        let dow = self.weekdays & (1 << day.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => dom || dow,
+            (false, true) => dom,
+            (true, false) => dow,
+            (true, true) => true,
+        }
+    }
+}
+
+/// Bitmask of the values `spec` selects within `min..=max`.
+fn field(spec: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in spec.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, s)) => (
+                r,
+                s.parse::<u32>()
+                    .ok()
+                    .filter(|&s| s > 0)
+                    .ok_or_else(|| format!("invalid step in {:?}", part))?,
+            ),
+            None => (part, 1),
+        };
+        let num = |s: &str| {
+            s.parse::<u32>()
+                .ok()
+                .filter(|n| (min..=max).contains(n))
+                .ok_or_else(|| format!("{:?} is not in {}-{}", s, min, max))
+        };
+        let (lo, hi) = match range {
+            "*" => (min, max),
+            r => match r.split_once('-') {
+                Some((a, b)) => (num(a)?, num(b)?),
+                // "5/15" means 5 through the end, every 15
+                None if part.contains('/') => (num(r)?, max),
+                None => (num(r)?, num(r)?),
+            },
+        };
+        if lo > hi {
+            return Err(format!("empty range {:?}", range));
+        }
+        for v in (lo..=hi).step_by(step as usize) {
+            mask |= 1 << v;
+        }
+    }
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn finds_next_fire_time() {
+        let nightly = Schedule::parse("30 2 * * *").unwrap();
+        assert_eq!(
+            nightly.next_after(at("2024-06-01T02:30:00Z")),
+            Some(at("2024-06-02T02:30:00Z"))
+        );
+        assert_eq!(
+            nightly.next_after(at("2024-06-01T01:00:59Z")),
+            Some(at("2024-06-01T02:30:00Z"))
+        );
+        let quarter = Schedule::parse("*/15 9-17 * * 1-5").unwrap();
+        // Saturday evening -> Monday morning
+        assert_eq!(
+            quarter.next_after(at("2024-06-01T18:00:00Z")),
+            Some(at("2024-06-03T09:00:00Z"))
+        );
+        // Both day fields restricted: the 1st or any Sunday
+        let either = Schedule::parse("0 0 1 * 7").unwrap();
+        assert_eq!(
+            either.next_after(at("2024-06-01T00:00:00Z")),
+            Some(at("2024-06-02T00:00:00Z"))
+        );
+        assert_eq!(Schedule::parse("@daily"), Schedule::parse("0 0 * * *"));
+        assert!(Schedule::parse("0 0 * *").is_err());
+        assert!(Schedule::parse("60 * * * *").is_err());
+        assert!(Schedule::parse("*/0 * * * *").is_err());
+        assert_eq!(
+            Schedule::parse("0 0 30 2 *")
+                .unwrap()
+                .next_after(at("2024-01-01T00:00:00Z")),
+            None
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use k8s_openapi::api::core::v1::ObjectReference;
+use kube::{
+    Resource, ResourceExt,
+    runtime::events::{EventType, Recorder},
+};
+use tokio::time::Duration;
+use tracing::info;
+
+use crate::{config::OperatorConfig, events, freeze};
+
+/// `"true"` reports intended changes without applying them; `"false"` opts out of a
+/// fleet-wide `DRY_RUN`.
+pub const DRY_RUN_ANNOTATION: &str = "meili.operator.dev/dry-run";
+
+/// Whether to only report changes for `obj`: during a change freeze always, otherwise as
+/// the annotation or `DRY_RUN` say.
+pub fn enabled<K: Resource>(config: &OperatorConfig, obj: &K) -> bool {
+    if freeze::active(config) {
+        return true;
+    }
+    match obj.annotations().get(DRY_RUN_ANNOTATION).map(|v| v.trim()) {
+        Some("true") => true,
+        Some("false") => false,
+        _ => config.dry_run,
+    }
+}
+
+/// When to look at a dry-run object again: every `every`, or soon during a change freeze so
+/// its changes go out shortly after the freeze is lifted.
+pub fn recheck(config: &OperatorConfig, every: Duration) -> Duration {
+    if freeze::active(config) {
+        freeze::RETRY.min(every)
+    } else {
+        every
+    }
+}
+
+/// Log the plan and emit a `DryRun` Event when it differs from the one already recorded.
+pub async fn report(
+    recorder: &Recorder,
+    reference: &ObjectReference,
+    previous: Option<&Vec<String>>,
+    plan: &[String],
+) {
+    info!(
+        object = reference.name.as_deref().unwrap_or_default(),
+        ?plan,
+        "dry-run plan"
+    );
+    if previous.map(Vec::as_slice) == Some(plan) {
+        return;
+    }
+    let note = if plan.is_empty() {
+        "no changes".to_string()
+    } else {
+        plan.join("; ")
+    };
+    events::publish(
+        recorder,
+        reference,
+        EventType::Normal,
+        "DryRun",
+        "Reconcile",
+        note,
+    )
+    .await;
+}
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::meili_http::{self, ApiError};
+
 #[derive(Debug, Error)]
 pub enum ReconcileError {
     #[error(transparent)]
@@ -10,4 +12,26 @@ pub enum ReconcileError {
     Meili(#[from] meilisearch_sdk::errors::Error),
     #[error(transparent)]
     Utf8(#[from] std::string::FromUtf8Error),
+    #[error("reconcile did not finish within {}s", .0.as_secs())]
+    TimedOut(std::time::Duration),
+}
+
+impl ReconcileError {
+    /// The error Meilisearch answered with, when it rejected a request.
+    pub fn meili_api(&self) -> Option<ApiError> {
+        let sdk = |e: &meilisearch_sdk::errors::Error| match e {
+            meilisearch_sdk::errors::Error::Meilisearch(e) => Some(ApiError::from(e)),
+            _ => None,
+        };
+        match self {
+            Self::Meili(e) => sdk(e),
+            Self::Anyhow(e) => e.chain().find_map(|cause| {
+                if let Some(meili_http::Error::Api(api)) = cause.downcast_ref() {
+                    return Some(api.clone());
+                }
+                cause.downcast_ref().and_then(sdk)
+            }),
+            _ => None,
+        }
+    }
 }
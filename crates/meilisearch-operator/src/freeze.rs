@@ -0,0 +1,88 @@
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::{
+    Api, Client,
+    runtime::{WatchStreamExt, watcher},
+};
+use tokio::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::OperatorConfig;
+
+/// ConfigMap in the operator namespace whose `frozen: "true"` pauses all changes.
+pub const FREEZE_CONFIGMAP: &str = "meilisearch-operator-freeze";
+/// How often paused reconciles check whether the freeze was lifted.
+pub const RETRY: Duration = Duration::from_secs(30);
+
+static FROZEN: AtomicBool = AtomicBool::new(false);
+
+/// True during a change freeze, from `CHANGE_FREEZE` or the freeze ConfigMap. Server, Index
+/// and Key reconciles then run as in dry-run mode; the other controllers wait.
+pub fn active(config: &OperatorConfig) -> bool {
+    config.change_freeze || FROZEN.load(Ordering::SeqCst)
+}
+
+/// Follow the freeze ConfigMap in `namespace` for as long as the operator runs.
+pub async fn watch(client: Client, namespace: String) {
+    let api: Api<ConfigMap> = Api::namespaced(client, &namespace);
+    let config = watcher::Config::default().fields(&format!("metadata.name={}", FREEZE_CONFIGMAP));
+    let mut events = pin!(watcher(api, config).default_backoff());
+    // Whether the ConfigMap showed up in the relist under way
+    let mut listed = false;
+    while let Some(event) = events.next().await {
+        match event {
+            Ok(watcher::Event::Init) => listed = false,
+            Ok(watcher::Event::InitApply(cm)) => {
+                listed = true;
+                set(frozen(&cm));
+            }
+            Ok(watcher::Event::InitDone) if !listed => set(false),
+            Ok(watcher::Event::Apply(cm)) => set(frozen(&cm)),
+            Ok(watcher::Event::Delete(_)) => set(false),
+            Ok(_) => {}
+            Err(e) => warn!(error = ?e, "watching the change freeze ConfigMap failed"),
+        }
+    }
+}
+
+fn set(frozen: bool) {
+    if FROZEN.swap(frozen, Ordering::SeqCst) != frozen {
+        if frozen {
+            info!("change freeze on, pausing changes");
+        } else {
+            info!("change freeze lifted, resuming changes");
+        }
+    }
+}
+
+fn frozen(cm: &ConfigMap) -> bool {
+    cm.data
+        .as_ref()
+        .and_then(|d| d.get("frozen"))
+        .is_some_and(|v| matches!(v.trim(), "1" | "true"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn freezes_only_on_a_true_flag() {
+        let cm = |v: &str| ConfigMap {
+            data: Some(BTreeMap::from([("frozen".into(), v.into())])),
+            ..Default::default()
+        };
+        assert!(frozen(&cm("true")));
+        assert!(frozen(&cm(" 1\n")));
+        assert!(!frozen(&cm("false")));
+        assert!(!frozen(&ConfigMap::default()));
+        assert!(active(&OperatorConfig {
+            change_freeze: true,
+            ..Default::default()
+        }));
+    }
+}
@@ -0,0 +1,570 @@
+use std::{collections::HashMap, convert::Infallible, net::SocketAddr, sync::Arc};
+
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::{
+    Method, Request, Response, StatusCode,
+    body::{Bytes, Incoming},
+    service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use sha2::{Digest, Sha256};
+use tokio::net::TcpListener;
+use tracing::debug;
+
+use crate::meili_http::{self, MeiliSend};
+
+/// Largest request body the gateway reads.
+const MAX_BODY: usize = 10 * 1024 * 1024;
+
+/// Gateway configuration as rendered by the SearchGateway controller. Secrets are not part
+/// of it; they are read from the named environment variables.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {
+    pub backends: Vec<BackendConfig>,
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendConfig {
+    pub name: String,
+    pub url: String,
+    pub indexes: Vec<String>,
+    /// Variable holding the backend's search key
+    pub key_env: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TenantConfig {
+    pub name: String,
+    /// Variable holding the tenant's bearer token
+    pub token_env: String,
+    pub filter: Option<String>,
+    #[serde(default)]
+    pub indexes: Vec<String>,
+}
+
+/// Routing table with secrets resolved.
+pub struct Gateway {
+    backends: Vec<Backend>,
+    /// Index uid to position in `backends`
+    routes: HashMap<String, usize>,
+    tenants: Vec<Tenant>,
+}
+
+struct Backend {
+    url: String,
+    key: String,
+}
+
+struct Tenant {
+    name: String,
+    token_hash: [u8; 32],
+    filter: Option<String>,
+    indexes: Vec<String>,
+}
+
+impl Gateway {
+    /// Resolve `config`, looking up keys and tokens with `env`.
+    pub fn new(config: Config, env: impl Fn(&str) -> Option<String>) -> anyhow::Result<Self> {
+        let var = |name: &str| {
+            env(name)
+                .filter(|v| !v.is_empty())
+                .ok_or_else(|| anyhow::anyhow!("environment variable {} is not set", name))
+        };
+        let mut routes = HashMap::new();
+        let mut backends = Vec::new();
+        for (i, b) in config.backends.into_iter().enumerate() {
+            for uid in b.indexes {
+                if routes.insert(uid.clone(), i).is_some() {
+                    anyhow::bail!("index {} is listed under more than one backend", uid);
+                }
+            }
+            backends.push(Backend {
+                url: b.url.trim_end_matches('/').to_string(),
+                key: var(&b.key_env)?,
+            });
+        }
+        let tenants = config
+            .tenants
+            .into_iter()
+            .map(|t| {
+                Ok(Tenant {
+                    token_hash: Sha256::digest(var(&t.token_env)?).into(),
+                    name: t.name,
+                    filter: t.filter.filter(|f| !f.trim().is_empty()),
+                    indexes: t.indexes,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            backends,
+            routes,
+            tenants,
+        })
+    }
+
+    /// The tenant presenting `authorization`, `Ok(None)` when no tenants are configured.
+    fn authenticate(&self, authorization: Option<&str>) -> Result<Option<&Tenant>, Reject> {
+        if self.tenants.is_empty() {
+            return Ok(None);
+        }
+        let token = authorization
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .ok_or_else(|| {
+                Reject::new(
+                    StatusCode::UNAUTHORIZED,
+                    "missing_authorization_header",
+                    "The Authorization header is missing. It must use the bearer authorization method.",
+                )
+            })?;
+        // Comparing digests keeps the comparison time independent of the token
+        let hash: [u8; 32] = Sha256::digest(token.trim()).into();
+        self.tenants
+            .iter()
+            .find(|t| t.token_hash == hash)
+            .map(Some)
+            .ok_or_else(|| {
+                Reject::new(
+                    StatusCode::FORBIDDEN,
+                    "invalid_api_key",
+                    "The provided API key is invalid.",
+                )
+            })
+    }
+
+    /// Backend serving `uid` for `tenant`, after checking the tenant may search it.
+    fn route(&self, tenant: Option<&Tenant>, uid: &str) -> Result<usize, Reject> {
+        if let Some(t) = tenant
+            && !t.indexes.is_empty()
+            && !t.indexes.iter().any(|i| i == uid)
+        {
+            return Err(Reject::new(
+                StatusCode::FORBIDDEN,
+                "invalid_api_key",
+                &format!("Tenant `{}` is not allowed to search `{}`.", t.name, uid),
+            ));
+        }
+        self.routes.get(uid).copied().ok_or_else(|| {
+            Reject::new(
+                StatusCode::NOT_FOUND,
+                "index_not_found",
+                &format!("Index `{}` not found.", uid),
+            )
+        })
+    }
+}
+
+/// An error answered in Meilisearch's error format.
+#[derive(Debug)]
+struct Reject {
+    status: StatusCode,
+    body: Value,
+}
+
+impl Reject {
+    fn new(status: StatusCode, code: &str, message: &str) -> Self {
+        let kind = match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => "auth",
+            s if s.is_server_error() => "system",
+            _ => "invalid_request",
+        };
+        Self {
+            status,
+            body: json!({ "message": message, "code": code, "type": kind }),
+        }
+    }
+
+    fn bad_request(message: &str) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+}
+
+/// Serve the gateway on `addr` until the process exits.
+pub async fn serve(addr: SocketAddr, gateway: Arc<Gateway>) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let gateway = gateway.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |req| handle(gateway.clone(), req));
+            if let Err(e) = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                debug!(error = ?e, %peer, "gateway connection closed");
+            }
+        });
+    }
+}
+
+async fn handle(
+    gateway: Arc<Gateway>,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let result = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/health") => Ok((StatusCode::OK, json!({ "status": "available" }))),
+        (&Method::POST, "/multi-search") => multi_search(&gateway, req).await,
+        (&Method::POST, path) => match path
+            .strip_prefix("/indexes/")
+            .and_then(|p| p.strip_suffix("/search"))
+            .filter(|uid| !uid.is_empty() && !uid.contains('/'))
+        {
+            Some(uid) => {
+                let uid = uid.to_string();
+                search(&gateway, &uid, req).await
+            }
+            None => Err(not_found()),
+        },
+        _ => Err(not_found()),
+    };
+    let (status, body) = result.unwrap_or_else(|r| (r.status, r.body));
+    let mut resp = Response::new(Full::new(Bytes::from(
+        serde_json::to_vec(&body).unwrap_or_default(),
+    )));
+    *resp.status_mut() = status;
+    resp.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    Ok(resp)
+}
+
+fn not_found() -> Reject {
+    Reject::new(StatusCode::NOT_FOUND, "not_found", "Not found.")
+}
+
+/// Authenticate the caller and read the JSON body.
+async fn read(
+    gateway: &Gateway,
+    req: Request<Incoming>,
+) -> Result<(Option<&Tenant>, Value), Reject> {
+    let authorization = req
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let tenant = gateway.authenticate(authorization.as_deref())?;
+    let body = Limited::new(req.into_body(), MAX_BODY)
+        .collect()
+        .await
+        .map_err(|e| {
+            Reject::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "payload_too_large",
+                &e.to_string(),
+            )
+        })?
+        .to_bytes();
+    let body = serde_json::from_slice(&body)
+        .map_err(|e| Reject::bad_request(&format!("The request body is not valid JSON: {}", e)))?;
+    Ok((tenant, body))
+}
+
+async fn search(
+    gateway: &Gateway,
+    uid: &str,
+    req: Request<Incoming>,
+) -> Result<(StatusCode, Value), Reject> {
+    let (tenant, mut body) = read(gateway, req).await?;
+    let backend = gateway.route(tenant, uid)?;
+    scope(&mut body, tenant)?;
+    forward(gateway, backend, &format!("indexes/{}/search", uid), &body).await
+}
+
+async fn multi_search(
+    gateway: &Gateway,
+    req: Request<Incoming>,
+) -> Result<(StatusCode, Value), Reject> {
+    let (tenant, mut body) = read(gateway, req).await?;
+    let Some(queries) = body.get_mut("queries").and_then(Value::as_array_mut) else {
+        return Err(Reject::bad_request("Missing field `queries`."));
+    };
+    // Backend of every query, in request order
+    let mut owners = Vec::with_capacity(queries.len());
+    for query in queries.iter_mut() {
+        let uid = query
+            .get("indexUid")
+            .and_then(Value::as_str)
+            .ok_or_else(|| Reject::bad_request("Every query needs an `indexUid`."))?;
+        owners.push(gateway.route(tenant, uid)?);
+        scope(query, tenant)?;
+    }
+    let mut involved = owners.clone();
+    involved.sort_unstable();
+    involved.dedup();
+    // One Server answers everything: nothing to split or merge
+    if let [only] = involved.as_slice() {
+        return forward(gateway, *only, "multi-search", &body).await;
+    }
+
+    let federation = body.get("federation").filter(|f| !f.is_null()).cloned();
+    let (offset, limit) = match &federation {
+        Some(f) => {
+            if f.get("page").is_some() || f.get("hitsPerPage").is_some() {
+                return Err(Reject::bad_request(
+                    "Federated searches across several Servers use `offset` and `limit`, not `page` and `hitsPerPage`.",
+                ));
+            }
+            if f.get("mergeFacets").is_some_and(|m| !m.is_null()) {
+                return Err(Reject::bad_request(
+                    "`mergeFacets` is not supported for federated searches across several Servers; use `facetsByIndex`.",
+                ));
+            }
+            let num = |k: &str, d: u64| f.get(k).and_then(Value::as_u64).unwrap_or(d) as usize;
+            (num("offset", 0), num("limit", 20))
+        }
+        None => (0, 0),
+    };
+    let queries = body["queries"].as_array().cloned().unwrap_or_default();
+    let requests = involved.iter().map(|&backend| {
+        let positions: Vec<usize> = (0..owners.len())
+            .filter(|&i| owners[i] == backend)
+            .collect();
+        let mut part = Map::new();
+        part.insert(
+            "queries".into(),
+            positions.iter().map(|&i| queries[i].clone()).collect(),
+        );
+        if let Some(f) = &federation {
+            // Each Server returns enough hits to cover the requested page once merged
+            let mut f = f.as_object().cloned().unwrap_or_default();
+            f.insert("offset".into(), 0.into());
+            f.insert("limit".into(), (offset + limit).into());
+            if let Some(Value::Object(facets)) = f.get_mut("facetsByIndex") {
+                facets.retain(|uid, _| gateway.routes.get(uid) == Some(&backend));
+            }
+            part.insert("federation".into(), Value::Object(f));
+        }
+        async move {
+            let (status, resp) =
+                forward(gateway, backend, "multi-search", &Value::Object(part)).await?;
+            Ok::<_, Reject>((positions, status, resp))
+        }
+    });
+    let mut parts = Vec::new();
+    for result in futures::future::join_all(requests).await {
+        let (positions, status, resp) = result?;
+        if !status.is_success() {
+            return Ok((status, resp));
+        }
+        parts.push((positions, resp));
+    }
+    Ok(match federation {
+        Some(_) => (StatusCode::OK, merge_federated(parts, offset, limit)),
+        None => (StatusCode::OK, merge_results(owners.len(), parts)),
+    })
+}
+
+/// AND the tenant's filter into a search query.
+fn scope(query: &mut Value, tenant: Option<&Tenant>) -> Result<(), Reject> {
+    let Some(filter) = tenant.and_then(|t| t.filter.as_deref()) else {
+        return Ok(());
+    };
+    let Some(query) = query.as_object_mut() else {
+        return Err(Reject::bad_request("A search query must be a JSON object."));
+    };
+    let scoped = match query.remove("filter") {
+        None | Some(Value::Null) => Value::String(filter.into()),
+        Some(Value::String(s)) if s.trim().is_empty() => Value::String(filter.into()),
+        Some(Value::String(s)) => Value::String(format!("({}) AND ({})", s, filter)),
+        // Top-level array elements are ANDed
+        Some(Value::Array(mut all)) => {
+            all.push(Value::String(filter.into()));
+            Value::Array(all)
+        }
+        Some(_) => {
+            return Err(Reject::bad_request(
+                "`filter` must be a string or an array.",
+            ));
+        }
+    };
+    query.insert("filter".into(), scoped);
+    Ok(())
+}
+
+async fn forward(
+    gateway: &Gateway,
+    backend: usize,
+    path: &str,
+    body: &Value,
+) -> Result<(StatusCode, Value), Reject> {
+    let backend = &gateway.backends[backend];
+    let unavailable =
+        |e: String| Reject::new(StatusCode::BAD_GATEWAY, "remote_could_not_send_request", &e);
+    let resp = meili_http::client()
+        .post(format!("{}/{}", backend.url, path))
+        .bearer_auth(&backend.key)
+        .json(body)
+        .send_meili()
+        .await
+        .map_err(|e| unavailable(format!("{}: {}", backend.url, e)))?;
+    let status = StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    let body = resp
+        .json()
+        .await
+        .map_err(|e| unavailable(format!("{}: {}", backend.url, e)))?;
+    Ok((status, body))
+}
+
+/// Put each Server's `results` back at the positions of the queries it answered.
+fn merge_results(len: usize, parts: Vec<(Vec<usize>, Value)>) -> Value {
+    let mut results = vec![Value::Null; len];
+    for (positions, resp) in parts {
+        let answers = match resp {
+            Value::Object(mut o) => o.remove("results"),
+            _ => None,
+        };
+        if let Some(Value::Array(answers)) = answers {
+            for (pos, answer) in positions.into_iter().zip(answers) {
+                results[pos] = answer;
+            }
+        }
+    }
+    json!({ "results": results })
+}
+
+/// Merge federated results from several Servers: hits are ordered by weighted ranking score
+/// and `_federation.queriesPosition` is mapped back to the caller's query order.
+fn merge_federated(parts: Vec<(Vec<usize>, Value)>, offset: usize, limit: usize) -> Value {
+    let mut hits = Vec::new();
+    let mut total = 0;
+    let mut took = 0;
+    let mut facets = Map::new();
+    for (positions, mut resp) in parts {
+        total += resp["estimatedTotalHits"].as_u64().unwrap_or(0);
+        took = took.max(resp["processingTimeMs"].as_u64().unwrap_or(0));
+        if let Some(Value::Object(f)) = resp.get_mut("facetsByIndex").map(Value::take) {
+            facets.extend(f);
+        }
+        if let Some(Value::Array(part)) = resp.get_mut("hits").map(Value::take) {
+            for mut hit in part {
+                let fed = &mut hit["_federation"];
+                if let Some(p) = fed["queriesPosition"].as_u64()
+                    && let Some(&orig) = positions.get(p as usize)
+                {
+                    fed["queriesPosition"] = orig.into();
+                }
+                hits.push(hit);
+            }
+        }
+    }
+    let score = |hit: &Value| {
+        hit["_federation"]["weightedRankingScore"]
+            .as_f64()
+            .unwrap_or(0.0)
+    };
+    hits.sort_by(|a, b| score(b).total_cmp(&score(a)));
+    let hits: Vec<Value> = hits.into_iter().skip(offset).take(limit).collect();
+    let mut out = json!({
+        "hits": hits,
+        "processingTimeMs": took,
+        "limit": limit,
+        "offset": offset,
+        "estimatedTotalHits": total,
+    });
+    if !facets.is_empty() {
+        out["facetsByIndex"] = Value::Object(facets);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tenant(filter: Option<&str>) -> Tenant {
+        Tenant {
+            name: "acme".into(),
+            token_hash: Sha256::digest("secret").into(),
+            filter: filter.map(Into::into),
+            indexes: vec!["movies".into()],
+        }
+    }
+
+    #[test]
+    fn scopes_queries_to_tenant() {
+        let t = tenant(Some("tenant = acme"));
+        let mut q = json!({ "q": "x" });
+        scope(&mut q, Some(&t)).unwrap();
+        assert_eq!(q["filter"], "tenant = acme");
+        let mut q = json!({ "filter": "genre = horror OR genre = drama" });
+        scope(&mut q, Some(&t)).unwrap();
+        assert_eq!(
+            q["filter"],
+            "(genre = horror OR genre = drama) AND (tenant = acme)"
+        );
+        let mut q = json!({ "filter": [["genre = horror", "genre = drama"]] });
+        scope(&mut q, Some(&t)).unwrap();
+        assert_eq!(
+            q["filter"],
+            json!([["genre = horror", "genre = drama"], "tenant = acme"])
+        );
+        assert!(scope(&mut json!({ "filter": 1 }), Some(&t)).is_err());
+        let mut q = json!({ "filter": "a = 1" });
+        scope(&mut q, Some(&tenant(None))).unwrap();
+        assert_eq!(q["filter"], "a = 1");
+
+        let gw = Gateway {
+            backends: vec![],
+            routes: HashMap::from([("movies".into(), 0), ("books".into(), 0)]),
+            tenants: vec![t],
+        };
+        assert!(gw.authenticate(None).is_err());
+        assert!(gw.authenticate(Some("Bearer wrong")).is_err());
+        let t = gw.authenticate(Some("Bearer secret")).unwrap();
+        assert_eq!(gw.route(t, "movies").unwrap(), 0);
+        assert_eq!(
+            gw.route(t, "books").unwrap_err().status,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[test]
+    fn merges_federated_results() {
+        let hit = |id: u64, pos: u64, score: f64| json!({ "id": id, "_federation": { "queriesPosition": pos, "weightedRankingScore": score } });
+        // Queries 0 and 2 went to one Server, query 1 to the other
+        let a = json!({
+            "hits": [hit(1, 0, 0.9), hit(2, 1, 0.5)],
+            "processingTimeMs": 3,
+            "estimatedTotalHits": 10,
+            "facetsByIndex": { "movies": {} },
+        });
+        let b = json!({
+            "hits": [hit(3, 0, 0.7)],
+            "processingTimeMs": 8,
+            "estimatedTotalHits": 4,
+            "facetsByIndex": { "books": {} },
+        });
+        let merged = merge_federated(vec![(vec![0, 2], a), (vec![1], b)], 1, 2);
+        let ids: Vec<u64> = merged["hits"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|h| h["id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![3, 2]);
+        assert_eq!(merged["hits"][0]["_federation"]["queriesPosition"], 1);
+        assert_eq!(merged["hits"][1]["_federation"]["queriesPosition"], 2);
+        assert_eq!(merged["estimatedTotalHits"], 14);
+        assert_eq!(merged["processingTimeMs"], 8);
+        assert_eq!(merged["facetsByIndex"].as_object().unwrap().len(), 2);
+
+        let results = merge_results(
+            3,
+            vec![
+                (vec![0, 2], json!({ "results": [{ "q": 0 }, { "q": 2 }] })),
+                (vec![1], json!({ "results": [{ "q": 1 }] })),
+            ],
+        );
+        assert_eq!(
+            results,
+            json!({ "results": [{ "q": 0 }, { "q": 1 }, { "q": 2 }] })
+        );
+    }
+}
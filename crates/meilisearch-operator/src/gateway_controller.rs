@@ -0,0 +1,542 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use k8s_openapi::{
+    ByteString,
+    api::{
+        apps::v1::{Deployment, DeploymentSpec},
+        core::v1::{
+            ConfigMap, ConfigMapVolumeSource, Container, ContainerPort, EnvVar, EnvVarSource,
+            HTTPGetAction, PodSpec, PodTemplateSpec, Probe, Secret, SecretKeySelector, Volume,
+            VolumeMount,
+        },
+    },
+    apimachinery::pkg::{
+        apis::meta::v1::{LabelSelector, OwnerReference},
+        util::intstr::IntOrString,
+    },
+};
+use kube::{
+    Api, Client, Resource, ResourceExt,
+    api::{Patch, PatchParams},
+    core::ObjectMeta,
+    runtime::{
+        controller::{Action, Controller},
+        events::Recorder,
+    },
+};
+use rand::{Rng, distr::Alphanumeric};
+use sha2::{Digest, Sha256};
+use tokio::time::Duration;
+use tracing::error;
+
+use crate::{
+    children, clusters,
+    config::OperatorConfig,
+    crds::{
+        FINALIZER,
+        common::Phase,
+        key::{Key, KeySpec},
+        search_gateway::{SearchGateway, SearchGatewaySpec},
+        server::Server,
+    },
+    error::ReconcileError,
+    freeze,
+    gateway::{BackendConfig, Config, TenantConfig},
+    secret_cache::SecretCache,
+    server_controller, status, watch,
+};
+
+#[derive(Clone)]
+pub struct Ctx {
+    pub client: Client,
+    pub secrets: SecretCache,
+    pub recorder: Recorder,
+    pub config: Arc<OperatorConfig>,
+}
+
+pub fn controller(client: Client, config: &OperatorConfig) -> Controller<SearchGateway> {
+    watch::controller(Api::<SearchGateway>::all(client), config)
+}
+
+/// Where the gateway container reads its configuration.
+const CONFIG_DIR: &str = "/etc/meilisearch-gateway";
+/// Retry interval while Servers or search keys are not ready, or the rollout is in progress.
+const WAIT: Duration = Duration::from_secs(15);
+/// Resync interval once the gateway is Ready.
+const RESYNC: Duration = Duration::from_secs(300);
+
+/// Issue a search key per backend Server, then run the gateway Deployment and Service with
+/// those keys and the tenants' tokens.
+pub async fn reconcile(gw: Arc<SearchGateway>, ctx: Arc<Ctx>) -> Result<Action, ReconcileError> {
+    if freeze::active(&ctx.config) {
+        return Ok(Action::requeue(freeze::RETRY));
+    }
+    let ns = gw.namespace().unwrap();
+    let name = gw.name_any();
+    let api: Api<SearchGateway> = Api::namespaced(ctx.client.clone(), &ns);
+    let owner = gw.uid().unwrap_or_default();
+
+    // Search keys live on the backends' Servers, possibly in other namespaces, so they are
+    // removed explicitly rather than by garbage collection.
+    if gw.metadata.deletion_timestamp.is_some() {
+        children::prune_owned::<Key>(&ctx.client, &owner, &BTreeSet::new()).await?;
+        let pp = PatchParams::default();
+        let patch = serde_json::json!({"metadata": {"finalizers": null}});
+        let _ = api.patch(&name, &pp, &Patch::Merge(&patch)).await?;
+        return Ok(Action::await_change());
+    }
+    if !gw.finalizers().iter().any(|f| f == FINALIZER) {
+        let mut finals = gw.finalizers().to_vec();
+        finals.push(FINALIZER.to_string());
+        let patch = serde_json::json!({"metadata": {"finalizers": finals}});
+        let _ = api
+            .patch(&name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await?;
+    }
+
+    let mut status = gw.status.clone().unwrap_or_default();
+    status.observed_generation = gw.metadata.generation;
+    if let Err(msg) = validate(&gw.spec, &ns, ctx.config.allow_cross_namespace_refs) {
+        status.phase = Phase::Degraded;
+        status.message = Some(msg);
+        status::merge(&api, &name, &status).await?;
+        return Ok(Action::await_change());
+    }
+
+    // One search-only key per backend, written as a Secret next to the gateway
+    let mut keys = BTreeSet::new();
+    let mut backends = Vec::new();
+    let mut waiting = Vec::new();
+    for (i, backend) in gw.spec.backends.iter().enumerate() {
+        let server_ns = backend.server_ref.namespace_or(&ns).to_string();
+        let server = &backend.server_ref.name;
+        let key_name = key_name(&ns, &name, &server_ns, &backend.name);
+        keys.insert((server_ns.clone(), key_name.clone()));
+        if let Some(msg) = clusters::server_not_ready(&ctx.client, &server_ns, server).await? {
+            waiting.push(msg);
+            continue;
+        }
+        // Key.server_ref is immutable, so a backend moved to another Server gets a new Key
+        // CR once the old one removed its key
+        let stale = |old: &Key| &old.spec.server_ref != server;
+        if children::replace_stale(&ctx.client, &server_ns, &key_name, stale).await? {
+            waiting.push(format!(
+                "search key {}/{} is being replaced",
+                server_ns, key_name
+            ));
+            continue;
+        }
+        let secret_name = format!("{}-{}-search-key", name, backend.name);
+        let key = children::apply_owned(
+            &ctx.client,
+            &server_ns,
+            &owner,
+            Key::new(
+                &key_name,
+                KeySpec {
+                    server_ref: server.clone(),
+                    name: Some(format!("search-gateway {}/{} {}", ns, name, backend.name)),
+                    description: Some(format!(
+                        "Search key for SearchGateway {}/{} (backend {})",
+                        ns, name, backend.name
+                    )),
+                    actions: vec!["search".into()],
+                    indexes: backend.indexes.clone(),
+                    expires_at: None,
+                    secret_namespace: ns.clone(),
+                    secret_name: secret_name.clone(),
+                    secret_store: None,
+                    immutable_secret: None,
+                    secret_template: None,
+                    retain_secret: false,
+                    adopt_existing_secret: false,
+                },
+            ),
+        )
+        .await?;
+        let secret = Api::<Secret>::namespaced(ctx.client.clone(), &ns)
+            .get_opt(&secret_name)
+            .await?;
+        let ready = key.status.as_ref().is_some_and(|s| s.ready);
+        let Some(secret) = secret.filter(|_| ready) else {
+            waiting.push(format!(
+                "search key {}/{} is not ready",
+                server_ns, key_name
+            ));
+            continue;
+        };
+        backends.push((
+            BackendConfig {
+                name: backend.name.clone(),
+                url: backend_url(&ctx.client, &server_ns, server).await?,
+                indexes: backend.indexes.clone(),
+                key_env: format!("BACKEND_{}_KEY", i),
+            },
+            secret_name,
+            secret.resource_version().unwrap_or_default(),
+        ));
+    }
+    // Keys of backends dropped from the spec
+    children::prune_owned::<Key>(&ctx.client, &owner, &keys).await?;
+    if !waiting.is_empty() {
+        status.phase = Phase::WaitingForServer;
+        status.message = Some(waiting.join("; "));
+        status::merge(&api, &name, &status).await?;
+        return Ok(Action::requeue(WAIT));
+    }
+
+    let owner_ref = gw.controller_owner_ref(&()).unwrap();
+    let tokens_secret = format!("{}-tenants", name);
+    let tokens = ensure_tokens(&ctx.client, &ns, &tokens_secret, &gw.spec, &owner_ref).await?;
+    let config = Config {
+        backends: backends.iter().map(|(b, _, _)| b.clone()).collect(),
+        tenants: gw
+            .spec
+            .tenants
+            .iter()
+            .enumerate()
+            .map(|(i, t)| TenantConfig {
+                name: t.name.clone(),
+                token_env: format!("TENANT_{}_TOKEN", i),
+                filter: t.filter.clone(),
+                indexes: t.indexes.clone(),
+            })
+            .collect(),
+    };
+    let rendered = serde_json::to_string_pretty(&config).unwrap_or_default();
+    // Keys and tokens reach the pods as environment variables, so a change to any of them
+    // has to roll the Deployment
+    let mut hash = Sha256::new();
+    hash.update(&rendered);
+    for (_, _, version) in &backends {
+        hash.update(version);
+    }
+    hash.update(&tokens);
+    let checksum = crate::object_store::hex(&hash.finalize());
+
+    let workload = format!("{}-gateway", name);
+    let cm = ConfigMap {
+        metadata: ObjectMeta {
+            name: Some(workload.clone()),
+            owner_references: Some(vec![owner_ref.clone()]),
+            ..Default::default()
+        },
+        data: Some(BTreeMap::from([("config.json".to_string(), rendered)])),
+        ..Default::default()
+    };
+    let _ = children::apply(
+        &Api::<ConfigMap>::namespaced(ctx.client.clone(), &ns),
+        &workload,
+        &cm,
+    )
+    .await?;
+
+    let mut env: Vec<EnvVar> = backends
+        .iter()
+        .map(|(b, secret, _)| secret_env(&b.key_env, secret, "key"))
+        .chain(
+            config
+                .tenants
+                .iter()
+                .map(|t| secret_env(&t.token_env, &tokens_secret, &t.name)),
+        )
+        .collect();
+    env.push(EnvVar {
+        name: "PORT".into(),
+        value: Some(gw.spec.port.to_string()),
+        ..Default::default()
+    });
+    let image = gw
+        .spec
+        .image
+        .clone()
+        .unwrap_or_else(|| ctx.config.gateway_image.clone());
+    let deploy = build_deployment(&workload, &image, &gw.spec, env, &checksum, &owner_ref);
+    let deployment = children::apply(
+        &Api::<Deployment>::namespaced(ctx.client.clone(), &ns),
+        &workload,
+        &deploy,
+    )
+    .await?;
+    server_controller::ensure_service(
+        &ctx.client,
+        &ns,
+        &workload,
+        &workload,
+        None,
+        server_controller::ServiceNetwork::port(gw.spec.port),
+        Some(&owner_ref),
+    )
+    .await?;
+
+    let ready = deployment
+        .status
+        .as_ref()
+        .and_then(|s| s.ready_replicas)
+        .unwrap_or(0);
+    status.ready_replicas = Some(ready);
+    status.endpoint = Some(clusters::endpoint(
+        clusters::LOCAL_ENDPOINT_TEMPLATE,
+        &workload,
+        &ns,
+        gw.spec.port,
+    ));
+    let done = ready >= gw.spec.replicas;
+    if done {
+        status.phase = Phase::Ready;
+        status.message = Some(format!(
+            "routing {} indexes across {} Servers",
+            config
+                .backends
+                .iter()
+                .map(|b| b.indexes.len())
+                .sum::<usize>(),
+            config.backends.len()
+        ));
+    } else {
+        status.phase = Phase::Pending;
+        status.message = Some(format!("{}/{} replicas ready", ready, gw.spec.replicas));
+    }
+    status::merge(&api, &name, &status).await?;
+    Ok(Action::requeue(if done { RESYNC } else { WAIT }))
+}
+
+pub fn error_policy(_gw: Arc<SearchGateway>, err: &ReconcileError, _ctx: Arc<Ctx>) -> Action {
+    error!(error = ?err, "search gateway reconcile failed");
+    Action::requeue(Duration::from_secs(60))
+}
+
+/// Spec problems the API server's schema can't catch.
+fn validate(spec: &SearchGatewaySpec, ns: &str, cross_ns: bool) -> Result<(), String> {
+    let mut backends = BTreeSet::new();
+    let mut indexes = BTreeSet::new();
+    for b in &spec.backends {
+        if !backends.insert(&b.name) {
+            return Err(format!("backend {} is listed twice", b.name));
+        }
+        let server_ns = b.server_ref.namespace_or(ns);
+        if server_ns != ns && !cross_ns {
+            return Err(format!(
+                "backend {} points at namespace {}; cross-namespace references are disabled",
+                b.name, server_ns
+            ));
+        }
+        for uid in &b.indexes {
+            if !indexes.insert(uid) {
+                return Err(format!("index {} is routed to more than one backend", uid));
+            }
+        }
+    }
+    let mut tenants = BTreeSet::new();
+    for t in &spec.tenants {
+        if !tenants.insert(&t.name) {
+            return Err(format!("tenant {} is listed twice", t.name));
+        }
+        if let Some(uid) = t.indexes.iter().find(|uid| !indexes.contains(uid)) {
+            return Err(format!(
+                "tenant {} lists index {}, which no backend serves",
+                t.name, uid
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Name of a gateway's Key CR for one backend; prefixed with the gateway's namespace when it
+/// lives in the Server's namespace instead.
+fn key_name(ns: &str, gw: &str, server_ns: &str, backend: &str) -> String {
+    if ns == server_ns {
+        format!("{}-{}-gateway", gw, backend)
+    } else {
+        format!("{}-{}-{}-gateway", ns, gw, backend)
+    }
+}
+
+/// URL the gateway pods use for a Server: the endpoint it published, or its Service.
+async fn backend_url(client: &Client, ns: &str, server: &str) -> Result<String, ReconcileError> {
+    let server_obj = Api::<Server>::namespaced(client.clone(), ns)
+        .get_opt(server)
+        .await?;
+    let port = server_obj.as_ref().map_or(7700, |s| s.spec.port);
+    Ok(server_obj
+        .and_then(|s| s.status)
+        .and_then(|s| s.endpoint)
+        .unwrap_or_else(|| clusters::endpoint(clusters::LOCAL_ENDPOINT_TEMPLATE, server, ns, port)))
+}
+
+/// Make sure every tenant has a token, keeping existing ones. Returns a digest of the tokens.
+async fn ensure_tokens(
+    client: &Client,
+    ns: &str,
+    name: &str,
+    spec: &SearchGatewaySpec,
+    owner: &OwnerReference,
+) -> Result<Vec<u8>, ReconcileError> {
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), ns);
+    let existing = secrets
+        .get_opt(name)
+        .await?
+        .and_then(|s| s.data)
+        .unwrap_or_default();
+    let data: BTreeMap<String, ByteString> = spec
+        .tenants
+        .iter()
+        .map(|t| {
+            let token = existing.get(&t.name).cloned().unwrap_or_else(|| {
+                ByteString(
+                    rand::rng()
+                        .sample_iter(&Alphanumeric)
+                        .take(48)
+                        .collect::<Vec<u8>>(),
+                )
+            });
+            (t.name.clone(), token)
+        })
+        .collect();
+    let mut digest = Sha256::new();
+    for (tenant, token) in &data {
+        digest.update(tenant);
+        digest.update(&token.0);
+    }
+    let sec = Secret {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            owner_references: Some(vec![owner.clone()]),
+            labels: Some(children::managed_labels(None)),
+            ..Default::default()
+        },
+        data: Some(data),
+        ..Default::default()
+    };
+    let _ = children::apply(&secrets, name, &sec).await?;
+    Ok(digest.finalize().to_vec())
+}
+
+fn secret_env(var: &str, secret: &str, key: &str) -> EnvVar {
+    EnvVar {
+        name: var.into(),
+        value_from: Some(EnvVarSource {
+            secret_key_ref: Some(SecretKeySelector {
+                name: secret.into(),
+                key: key.into(),
+                optional: Some(false),
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+fn build_deployment(
+    name: &str,
+    image: &str,
+    spec: &SearchGatewaySpec,
+    env: Vec<EnvVar>,
+    checksum: &str,
+    owner: &OwnerReference,
+) -> Deployment {
+    let labels = BTreeMap::from([(String::from("app"), name.to_string())]);
+    let port = spec.port as i32;
+    Deployment {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            owner_references: Some(vec![owner.clone()]),
+            ..Default::default()
+        },
+        spec: Some(DeploymentSpec {
+            replicas: Some(spec.replicas),
+            selector: LabelSelector {
+                match_labels: Some(labels.clone()),
+                ..Default::default()
+            },
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(labels),
+                    annotations: Some(BTreeMap::from([(
+                        "meili.operator.dev/config-checksum".to_string(),
+                        checksum.to_string(),
+                    )])),
+                    ..Default::default()
+                }),
+                spec: Some(PodSpec {
+                    containers: vec![Container {
+                        name: "gateway".into(),
+                        image: Some(image.into()),
+                        command: Some(vec!["/usr/local/bin/meilisearch-gateway".into()]),
+                        env: Some(env),
+                        ports: Some(vec![ContainerPort {
+                            name: Some("http".into()),
+                            container_port: port,
+                            ..Default::default()
+                        }]),
+                        readiness_probe: Some(Probe {
+                            http_get: Some(HTTPGetAction {
+                                path: Some("/health".into()),
+                                port: IntOrString::Int(port),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        }),
+                        volume_mounts: Some(vec![VolumeMount {
+                            name: "config".into(),
+                            mount_path: CONFIG_DIR.into(),
+                            read_only: Some(true),
+                            ..Default::default()
+                        }]),
+                        ..Default::default()
+                    }],
+                    volumes: Some(vec![Volume {
+                        name: "config".into(),
+                        config_map: Some(ConfigMapVolumeSource {
+                            name: name.into(),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }]),
+                    ..Default::default()
+                }),
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_ambiguous_routes() {
+        let spec = |backends: serde_json::Value, tenants: serde_json::Value| -> SearchGatewaySpec {
+            serde_json::from_value(serde_json::json!({ "backends": backends, "tenants": tenants }))
+                .unwrap()
+        };
+        let a = serde_json::json!({ "name": "a", "server_ref": { "name": "eu" }, "indexes": ["movies"] });
+        let b = serde_json::json!({ "name": "b", "server_ref": { "name": "us", "namespace": "us" }, "indexes": ["books"] });
+        let ok = spec(
+            serde_json::json!([a, b]),
+            serde_json::json!([{ "name": "acme", "indexes": ["books"] }]),
+        );
+        assert!(validate(&ok, "search", true).is_ok());
+        assert!(validate(&ok, "search", false).is_err());
+        let mut dup = b.clone();
+        dup["indexes"] = serde_json::json!(["movies"]);
+        assert!(
+            validate(
+                &spec(serde_json::json!([a, dup]), serde_json::json!([])),
+                "search",
+                true
+            )
+            .is_err()
+        );
+        let unknown = spec(
+            serde_json::json!([a]),
+            serde_json::json!([{ "name": "acme", "indexes": ["books"] }]),
+        );
+        assert!(validate(&unknown, "search", true).is_err());
+        assert_eq!(key_name("search", "gw", "search", "a"), "gw-a-gateway");
+        assert_eq!(key_name("search", "gw", "us", "b"), "search-gw-b-gateway");
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::BTreeSet;
+
+use anyhow::Context;
+use k8s_openapi::api::core::v1::Secret;
+use kube::{Api, Client, ResourceExt};
+use serde::Deserialize;
+
+use crate::{
+    crds::{
+        common::ServerRef,
+        index::{AdoptionPolicy, DeletionPolicy, Index, IndexSpec},
+        key::{Key, KeySpec},
+        server::{Server, UnmanagedKey, UnmanagedResources},
+    },
+    error::ReconcileError,
+    key_controller::{self, KeyItem},
+    key_registry,
+    meili_http::{self, MeiliSend},
+    secret_template,
+};
+
+/// An index as `GET /indexes` lists it.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RemoteIndex {
+    pub uid: String,
+    #[serde(rename = "primaryKey")]
+    pub primary_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexesPage {
+    results: Vec<RemoteIndex>,
+    total: usize,
+}
+
+/// Indexes and keys on Server `ns`/`server` that none of `indexes` or `keys` manages. Keys
+/// count as managed when registered to a CR or recorded in a Key's status; the keys
+/// Meilisearch creates itself are left out.
+pub(crate) async fn scan(
+    client: &Client,
+    endpoint: &str,
+    master_key: &str,
+    (ns, server): (&str, &str),
+    indexes: &[Index],
+    keys: &[Key],
+) -> Result<(Vec<RemoteIndex>, Vec<KeyItem>), ReconcileError> {
+    let managed_indexes: BTreeSet<&str> = indexes.iter().map(|i| i.spec.uid.as_str()).collect();
+    let registry = key_registry::load(client, ns, server).await?;
+    let managed_keys: BTreeSet<&str> = registry
+        .keys()
+        .map(String::as_str)
+        .chain(
+            keys.iter()
+                .filter_map(|k| k.status.as_ref()?.uid.as_deref()),
+        )
+        .collect();
+    let remote_indexes = list_indexes_http(endpoint, master_key)
+        .await?
+        .into_iter()
+        .filter(|i| !managed_indexes.contains(i.uid.as_str()))
+        .collect();
+    let remote_keys = key_controller::list_all_keys_http(endpoint, master_key)
+        .await?
+        .into_iter()
+        .filter(|k| !managed_keys.contains(k.uid.as_str()) && !builtin(k.name.as_deref()))
+        .collect();
+    Ok((remote_indexes, remote_keys))
+}
+
+/// The `status.unmanaged` summary of a [`scan`], `None` when everything is managed.
+pub(crate) fn summary(indexes: &[RemoteIndex], keys: &[KeyItem]) -> Option<UnmanagedResources> {
+    (!indexes.is_empty() || !keys.is_empty()).then(|| UnmanagedResources {
+        indexes: indexes.iter().map(|i| i.uid.clone()).collect(),
+        keys: keys
+            .iter()
+            .map(|k| UnmanagedKey {
+                uid: k.uid.clone(),
+                name: k.name.clone(),
+            })
+            .collect(),
+    })
+}
+
+/// Keys Meilisearch generates from the master key ("Default Search API Key", ...).
+fn builtin(name: Option<&str>) -> bool {
+    name.is_some_and(|n| n.starts_with("Default ") && n.ends_with(" API Key"))
+}
+
+/// Index CR taking over `remote` as is: no settings, and the index stays when the CR goes.
+pub fn index_cr(ns: &str, server: &str, remote: &RemoteIndex) -> Index {
+    let mut idx = Index::new(
+        &cr_name(&remote.uid),
+        IndexSpec {
+            server_ref: ServerRef {
+                name: server.into(),
+                namespace: None,
+            },
+            uid: remote.uid.clone(),
+            primary_key: remote.primary_key.clone(),
+            settings: None,
+            template_ref: None,
+            deletion_policy: DeletionPolicy::Retain,
+            admin_key: None,
+            keys: Vec::new(),
+            retention: None,
+            adoption_policy: AdoptionPolicy::Adopt,
+            frozen: false,
+            min_documents: None,
+        },
+    );
+    idx.metadata.namespace = Some(ns.into());
+    idx
+}
+
+/// Key CR matching `item`, which the Key controller adopts instead of creating a new key,
+/// writing it to the Secret `<server>-<name>-key`.
+pub(crate) fn key_cr(ns: &str, server: &str, item: &KeyItem) -> Key {
+    let name = cr_name(&format!(
+        "{}-{}",
+        server,
+        item.name.as_deref().unwrap_or(&item.uid)
+    ));
+    let mut key = Key::new(
+        &name,
+        KeySpec {
+            server_ref: server.into(),
+            name: item.name.clone(),
+            description: item.description.clone(),
+            actions: item.actions.clone(),
+            indexes: item.indexes.clone(),
+            expires_at: item.expires_at.clone(),
+            secret_namespace: ns.into(),
+            secret_name: format!("{}-key", name),
+            secret_store: None,
+            immutable_secret: None,
+            secret_template: None,
+            retain_secret: false,
+            adopt_existing_secret: false,
+        },
+    );
+    key.metadata.namespace = Some(ns.into());
+    key
+}
+
+/// `value` as a DNS-1123 name: lowercase alphanumerics and '-', at most 63 characters.
+fn cr_name(value: &str) -> String {
+    let mut name: String = value
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    name.truncate(63);
+    let name = name.trim_matches('-');
+    if name.is_empty() {
+        "imported".into()
+    } else {
+        name.to_string()
+    }
+}
+
+async fn list_indexes_http(
+    endpoint: &str,
+    master_key: &str,
+) -> Result<Vec<RemoteIndex>, ReconcileError> {
+    let mut out = Vec::new();
+    loop {
+        let page = meili_http::client()
+            .get(format!("{}/indexes", endpoint))
+            .bearer_auth(master_key)
+            .query(&[("offset", out.len()), ("limit", 1000)])
+            .send_checked()
+            .await
+            .map_err(anyhow::Error::from)?
+            .json::<IndexesPage>()
+            .await
+            .map_err(anyhow::Error::from)?;
+        let done = page.results.is_empty();
+        out.extend(page.results);
+        if done || out.len() >= page.total {
+            return Ok(out);
+        }
+    }
+}
+
+/// Index and Key CRs for everything on Server `ns`/`server` that no CR manages yet, reached
+/// at `endpoint` or else the endpoint in its status.
+pub async fn generate(
+    client: &Client,
+    ns: &str,
+    server: &str,
+    endpoint: Option<&str>,
+) -> anyhow::Result<Vec<serde_json::Value>> {
+    let srv = Api::<Server>::namespaced(client.clone(), ns)
+        .get(server)
+        .await
+        .with_context(|| format!("reading Server {}/{}", ns, server))?;
+    let endpoint = match endpoint {
+        Some(e) => e.trim_end_matches('/').to_string(),
+        None => srv
+            .status
+            .and_then(|s| s.endpoint)
+            .with_context(|| format!("Server {}/{} has no endpoint yet", ns, server))?,
+    };
+    let secret = format!("{}-meili-master", server);
+    let master_key = Api::<Secret>::namespaced(client.clone(), ns)
+        .get(&secret)
+        .await
+        .ok()
+        .and_then(|s| secret_template::read(&s, "masterKey"))
+        .with_context(|| format!("no master key in Secret {}/{}", ns, secret))?;
+    let indexes: Vec<Index> = Api::<Index>::all(client.clone())
+        .list(&Default::default())
+        .await?
+        .items
+        .into_iter()
+        .filter(|i| {
+            let own = i.namespace().unwrap_or_default();
+            i.spec.server_ref.name == server && i.spec.server_ref.namespace_or(&own) == ns
+        })
+        .collect();
+    let keys: Vec<Key> = Api::<Key>::namespaced(client.clone(), ns)
+        .list(&Default::default())
+        .await?
+        .items
+        .into_iter()
+        .filter(|k| k.spec.server_ref == server)
+        .collect();
+    let (remote_indexes, remote_keys) = scan(
+        client,
+        &endpoint,
+        &master_key,
+        (ns, server),
+        &indexes,
+        &keys,
+    )
+    .await?;
+    let mut docs = Vec::new();
+    for remote in &remote_indexes {
+        docs.push(serde_json::to_value(index_cr(ns, server, remote))?);
+    }
+    for item in &remote_keys {
+        docs.push(serde_json::to_value(key_cr(ns, server, item))?);
+    }
+    Ok(docs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_crs_adopt_what_is_there() {
+        let idx = index_cr(
+            "search",
+            "meili",
+            &RemoteIndex {
+                uid: "Movies_2024".into(),
+                primary_key: Some("id".into()),
+            },
+        );
+        assert_eq!(idx.name_any(), "movies-2024");
+        assert_eq!(idx.spec.uid, "Movies_2024");
+        assert_eq!(idx.spec.deletion_policy, DeletionPolicy::Retain);
+        assert!(idx.spec.settings.is_none());
+
+        assert!(builtin(Some("Default Search API Key")));
+        assert!(!builtin(Some("frontend")));
+        assert!(!builtin(None));
+        assert_eq!(cr_name("__"), "imported");
+        assert!(summary(&[], &[]).is_none());
+    }
+}
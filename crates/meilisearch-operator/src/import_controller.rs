@@ -0,0 +1,596 @@
+use std::collections::VecDeque;
+
+use kube::{
+    Api, Client, Resource, ResourceExt,
+    runtime::{
+        controller::{Action, Controller},
+        events::{EventType, Recorder},
+    },
+};
+use reqwest::{Response, StatusCode};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::error;
+
+use crate::{
+    clusters,
+    config::OperatorConfig,
+    crds::{
+        common::Phase,
+        index::Index,
+        index_import::{ImportFormat, IndexImport, IndexImportStatus},
+        server::Server,
+    },
+    error::ReconcileError,
+    events, freeze, index_controller,
+    meili_http::{self, MeiliSend},
+    object_store::{self, Bucket},
+    resync,
+    secret_cache::SecretCache,
+    server_controller::parse_byte_size,
+    shutdown, status, watch,
+};
+
+#[derive(Clone)]
+pub struct Ctx {
+    pub client: Client,
+    pub secrets: SecretCache,
+    pub recorder: Recorder,
+    pub config: Arc<OperatorConfig>,
+}
+
+pub fn controller(client: Client, config: &OperatorConfig) -> Controller<IndexImport> {
+    watch::controller(Api::<IndexImport>::all(client), config)
+}
+
+/// Retry interval while the Index or its Server is missing or not Ready.
+const WAIT: Duration = Duration::from_secs(60);
+/// Poll interval while ingestion tasks are still running.
+const TASK_POLL: Duration = Duration::from_secs(10);
+/// Poll interval while sending waits for `max_pending_tasks` to drain.
+const PENDING_POLL: Duration = Duration::from_secs(2);
+/// Meilisearch's own default for `http_payload_size_limit`.
+const DEFAULT_PAYLOAD_LIMIT: u64 = 100_000_000;
+
+/// Stream the source into the index batch by batch, recording progress after every batch so an
+/// interrupted import skips what was already sent, then follow the enqueued tasks to the end.
+pub async fn reconcile(imp: Arc<IndexImport>, ctx: Arc<Ctx>) -> Result<Action, ReconcileError> {
+    if freeze::active(&ctx.config) {
+        return Ok(Action::requeue(freeze::RETRY));
+    }
+    let ns = imp.namespace().unwrap();
+    let name = imp.name_any();
+    let api: Api<IndexImport> = Api::namespaced(ctx.client.clone(), &ns);
+    let mut status = imp.status.clone().unwrap_or_default();
+    if status.observed_generation != imp.metadata.generation {
+        // New or edited import: start over
+        status = IndexImportStatus {
+            observed_generation: imp.metadata.generation,
+            ..Default::default()
+        };
+    }
+    if status.completed_at.is_some() {
+        return Ok(Action::await_change());
+    }
+    let source = &imp.spec.source;
+    if source.object_store.is_some() == source.url.is_some() {
+        status.phase = Phase::Degraded;
+        status.message = Some("source needs exactly one of object_store or url".into());
+        status::merge(&api, &name, &status).await?;
+        return Ok(Action::await_change());
+    }
+
+    let Some(idx) = Api::<Index>::namespaced(ctx.client.clone(), &ns)
+        .get_opt(&imp.spec.index_ref)
+        .await?
+    else {
+        status.phase = Phase::Pending;
+        status.message = Some(format!("Index {}/{} not found", ns, imp.spec.index_ref));
+        status::merge(&api, &name, &status).await?;
+        return Ok(Action::requeue(WAIT));
+    };
+    let server_ns = idx.spec.server_ref.namespace_or(&ns).to_string();
+    let server = &idx.spec.server_ref.name;
+    if let Some(msg) = clusters::server_not_ready(&ctx.client, &server_ns, server).await? {
+        status.phase = Phase::WaitingForServer;
+        status.message = Some(msg);
+        status::merge(&api, &name, &status).await?;
+        return Ok(Action::requeue(WAIT));
+    }
+    let endpoint = clusters::server_endpoint(&ctx.client, &ctx.config, &server_ns, server).await?;
+    let master_key = index_controller::get_master_key(&ctx.secrets, &server_ns, server).await?;
+    let target = Target {
+        endpoint: &endpoint,
+        master_key: &master_key,
+        uid: &idx.spec.uid,
+        primary_key: imp.spec.primary_key.as_deref(),
+        format: imp.spec.format,
+        max_pending_tasks: imp.spec.max_pending_tasks.map(|n| n as usize),
+    };
+
+    status.phase = Phase::Provisioning;
+    status.message = None;
+    if !status.all_sent {
+        let payload_limit = Api::<Server>::namespaced(ctx.client.clone(), &server_ns)
+            .get_opt(server)
+            .await?
+            .and_then(|s| s.spec.http_payload_size_limit)
+            .and_then(|v| parse_byte_size(&v))
+            .unwrap_or(DEFAULT_PAYLOAD_LIMIT);
+        // Leave headroom for the CSV header and Meilisearch's own accounting
+        let mut max_bytes = imp.spec.max_batch_bytes.min(payload_limit / 10 * 9);
+        let batch_size = u64::from(imp.spec.batch_size);
+
+        let resp = match (&source.object_store, &source.url) {
+            (Some(store), _) => {
+                Bucket::connect(&ctx.secrets, &ns, store)
+                    .await?
+                    .get(&store.path)
+                    .await?
+            }
+            (None, Some(url)) => object_store::get_url(url).await?,
+            (None, None) => unreachable!("checked above"),
+        };
+        let mut reader = Reader::open(resp, imp.spec.format).await?;
+        let mut batch = Batch::default();
+        let mut read = 0u64;
+        while let Some(record) = reader.next().await? {
+            read += 1;
+            if read <= status.records_sent {
+                continue;
+            }
+            let header = reader.header.as_deref().unwrap_or_default();
+            if header.len() + record.len() + 1 > max_bytes as usize {
+                return Err(anyhow::anyhow!(
+                    "record {} is larger than the {} byte batch limit",
+                    read,
+                    max_bytes
+                )
+                .into());
+            }
+            if batch.len(header) + record.len() + 1 > max_bytes as usize {
+                batch
+                    .send(&target, header, &mut max_bytes, &mut status)
+                    .await?;
+                status::merge(&api, &name, &status).await?;
+            }
+            batch.push(&record);
+            if batch.records == batch_size {
+                batch
+                    .send(&target, header, &mut max_bytes, &mut status)
+                    .await?;
+                status::merge(&api, &name, &status).await?;
+            }
+            if batch.records == 0 && shutdown::requested() {
+                // Progress is saved; the next operator instance resumes
+                return Ok(Action::requeue(Duration::from_secs(5)));
+            }
+        }
+        let header = reader.header.as_deref().unwrap_or_default();
+        batch
+            .send(&target, header, &mut max_bytes, &mut status)
+            .await?;
+        status.all_sent = true;
+        status::merge(&api, &name, &status).await?;
+    }
+
+    track_tasks(&target, &mut status).await?;
+    if !status.pending_task_uids.is_empty() {
+        status.message = Some(format!(
+            "{} record(s) sent in {} batch(es), {} task(s) still processing",
+            status.records_sent,
+            status.batches,
+            status.pending_task_uids.len()
+        ));
+        status::merge(&api, &name, &status).await?;
+        return Ok(Action::requeue(TASK_POLL));
+    }
+
+    let failed = status.failed_tasks > 0;
+    let msg = match &status.first_error {
+        Some(err) if failed => format!(
+            "{} of {} batch(es) failed, {} documents indexed; first error: {}",
+            status.failed_tasks, status.batches, status.indexed_documents, err
+        ),
+        _ => format!(
+            "indexed {} documents from {} record(s) in {} batch(es)",
+            status.indexed_documents, status.records_sent, status.batches
+        ),
+    };
+    events::publish(
+        &ctx.recorder,
+        &imp.object_ref(&()),
+        if failed {
+            EventType::Warning
+        } else {
+            EventType::Normal
+        },
+        "ImportCompleted",
+        "Import",
+        &msg,
+    )
+    .await;
+    status.phase = if failed {
+        Phase::Degraded
+    } else {
+        Phase::Ready
+    };
+    status.message = Some(msg);
+    status.completed_at = Some(resync::now());
+    status::merge(&api, &name, &status).await?;
+    Ok(Action::await_change())
+}
+
+pub fn error_policy(_imp: Arc<IndexImport>, err: &ReconcileError, _ctx: Arc<Ctx>) -> Action {
+    error!(error = ?err, "index import failed");
+    Action::requeue(Duration::from_secs(60))
+}
+
+struct Target<'a> {
+    endpoint: &'a str,
+    master_key: &'a str,
+    uid: &'a str,
+    primary_key: Option<&'a str>,
+    format: ImportFormat,
+    max_pending_tasks: Option<usize>,
+}
+
+/// Source records, one document each: NDJSON lines, CSV rows (the header kept aside) or the
+/// elements of a JSON array re-encoded as NDJSON lines.
+struct Reader {
+    resp: Response,
+    splitter: Splitter,
+    queue: VecDeque<Vec<u8>>,
+    done: bool,
+    header: Option<Vec<u8>>,
+}
+
+impl Reader {
+    async fn open(mut resp: Response, format: ImportFormat) -> Result<Self, ReconcileError> {
+        let mut queue = VecDeque::new();
+        let mut done = false;
+        if format == ImportFormat::Json {
+            let mut body = Vec::new();
+            while let Some(chunk) = resp.chunk().await.map_err(anyhow::Error::from)? {
+                body.extend_from_slice(&chunk);
+            }
+            let docs: Vec<Value> = serde_json::from_slice(&body)
+                .map_err(|e| anyhow::anyhow!("source is not a JSON array: {}", e))?;
+            queue.extend(docs.iter().map(|d| d.to_string().into_bytes()));
+            done = true;
+        }
+        let mut reader = Self {
+            resp,
+            splitter: Splitter::new(format == ImportFormat::Csv),
+            queue,
+            done,
+            header: None,
+        };
+        if format == ImportFormat::Csv {
+            reader.header = reader.next().await?;
+        }
+        Ok(reader)
+    }
+
+    async fn next(&mut self) -> Result<Option<Vec<u8>>, ReconcileError> {
+        loop {
+            if let Some(record) = self.queue.pop_front() {
+                return Ok(Some(record));
+            }
+            if self.done {
+                return Ok(None);
+            }
+            match self.resp.chunk().await.map_err(anyhow::Error::from)? {
+                Some(chunk) => self.queue.extend(self.splitter.push(&chunk)),
+                None => {
+                    self.queue.extend(self.splitter.finish());
+                    self.done = true;
+                }
+            }
+        }
+    }
+}
+
+/// Splits a byte stream into non-blank lines. In CSV mode newlines inside quoted cells stay
+/// part of the row.
+struct Splitter {
+    csv: bool,
+    in_quotes: bool,
+    buf: Vec<u8>,
+}
+
+impl Splitter {
+    fn new(csv: bool) -> Self {
+        Self {
+            csv,
+            in_quotes: false,
+            buf: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, chunk: &[u8]) -> Vec<Vec<u8>> {
+        let mut records = Vec::new();
+        for &b in chunk {
+            match b {
+                b'"' if self.csv => self.in_quotes = !self.in_quotes,
+                b'\n' if !self.in_quotes => {
+                    records.extend(self.take());
+                    continue;
+                }
+                _ => {}
+            }
+            self.buf.push(b);
+        }
+        records
+    }
+
+    fn finish(&mut self) -> Option<Vec<u8>> {
+        self.take()
+    }
+
+    fn take(&mut self) -> Option<Vec<u8>> {
+        let mut record = std::mem::take(&mut self.buf);
+        if record.last() == Some(&b'\r') {
+            record.pop();
+        }
+        (!record.iter().all(u8::is_ascii_whitespace)).then_some(record)
+    }
+}
+
+#[derive(Default)]
+struct Batch {
+    body: Vec<u8>,
+    /// End offset of each record in `body`
+    ends: Vec<usize>,
+    records: u64,
+}
+
+impl Batch {
+    /// Payload size with `header` in front.
+    fn len(&self, header: &[u8]) -> usize {
+        if header.is_empty() {
+            self.body.len()
+        } else {
+            header.len() + 1 + self.body.len()
+        }
+    }
+
+    fn push(&mut self, record: &[u8]) {
+        self.body.extend_from_slice(record);
+        self.body.push(b'\n');
+        self.ends.push(self.body.len());
+        self.records += 1;
+    }
+
+    /// The first and second half of the records.
+    fn split(self) -> (Self, Self) {
+        let mid = self.ends.len() / 2;
+        let mut first = Self::default();
+        let mut second = Self::default();
+        let mut start = 0;
+        for (i, &end) in self.ends.iter().enumerate() {
+            let record = &self.body[start..end - 1];
+            if i < mid {
+                first.push(record);
+            } else {
+                second.push(record);
+            }
+            start = end;
+        }
+        (first, second)
+    }
+
+    fn payload(&self, header: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.len(header));
+        if !header.is_empty() {
+            out.extend_from_slice(header);
+            out.push(b'\n');
+        }
+        out.extend_from_slice(&self.body);
+        out
+    }
+
+    /// Enqueue the batch as documents tasks and account for them in `status`. A batch
+    /// Meilisearch turns away as too large is split in half, and `max_bytes` lowered for the
+    /// batches after it. With `max_pending_tasks`, waits for queued tasks to finish first.
+    async fn send(
+        &mut self,
+        target: &Target<'_>,
+        header: &[u8],
+        max_bytes: &mut u64,
+        status: &mut IndexImportStatus,
+    ) -> Result<(), ReconcileError> {
+        if self.records == 0 {
+            return Ok(());
+        }
+        let mut parts = vec![std::mem::take(self)];
+        while let Some(part) = parts.pop() {
+            if let Some(limit) = target.max_pending_tasks {
+                track_tasks(target, status).await?;
+                while status.pending_task_uids.len() >= limit {
+                    if shutdown::requested() {
+                        return Err(shutdown::interrupted("queued import tasks").into());
+                    }
+                    tokio::time::sleep(PENDING_POLL).await;
+                    track_tasks(target, status).await?;
+                }
+            }
+            match part.enqueue(target, header).await? {
+                Some(uid) => {
+                    status.pending_task_uids.push(uid);
+                    status.records_sent += part.records;
+                    status.batches += 1;
+                }
+                None if part.records > 1 => {
+                    *max_bytes = (*max_bytes).min(part.len(header) as u64 / 2);
+                    let (first, second) = part.split();
+                    parts.push(second);
+                    parts.push(first);
+                }
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Meilisearch rejected a single {} byte record as too large; raise the Server's http_payload_size_limit",
+                        part.len(header)
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Post the batch as one documents task, or `None` when Meilisearch answers 413.
+    async fn enqueue(
+        &self,
+        target: &Target<'_>,
+        header: &[u8],
+    ) -> Result<Option<u64>, ReconcileError> {
+        let content_type = match target.format {
+            ImportFormat::Csv => "text/csv",
+            ImportFormat::Ndjson | ImportFormat::Json => "application/x-ndjson",
+        };
+        let mut req = meili_http::client()
+            .post(format!(
+                "{}/indexes/{}/documents",
+                target.endpoint, target.uid
+            ))
+            .bearer_auth(target.master_key)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            // Large batches take a while to upload; don't hold them to the default timeout
+            .timeout(Duration::from_secs(300))
+            .body(self.payload(header));
+        if let Some(pk) = target.primary_key {
+            req = req.query(&[("primaryKey", pk)]);
+        }
+        let resp = req.send_meili().await.map_err(anyhow::Error::from)?;
+        if resp.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            return Ok(None);
+        }
+        let task: Value = meili_http::checked(resp)
+            .await
+            .map_err(anyhow::Error::from)?
+            .json()
+            .await
+            .map_err(anyhow::Error::from)?;
+        let uid = task["taskUid"]
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("response has no taskUid"))?;
+        Ok(Some(uid))
+    }
+}
+
+#[derive(Deserialize)]
+struct TaskList {
+    results: Vec<Task>,
+}
+
+#[derive(Deserialize)]
+struct Task {
+    uid: u64,
+    status: String,
+    details: Option<TaskDetails>,
+    error: Option<TaskError>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskDetails {
+    indexed_documents: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TaskError {
+    message: String,
+}
+
+/// Fold finished tasks into the counters and drop them from `pending_task_uids`.
+async fn track_tasks(
+    target: &Target<'_>,
+    status: &mut IndexImportStatus,
+) -> Result<(), ReconcileError> {
+    let pending = status.pending_task_uids.clone();
+    for uids in pending.chunks(100) {
+        let list: TaskList = meili_http::client()
+            .get(format!("{}/tasks", target.endpoint))
+            .query(&[
+                (
+                    "uids",
+                    uids.iter()
+                        .map(u64::to_string)
+                        .collect::<Vec<_>>()
+                        .join(","),
+                ),
+                ("limit", uids.len().to_string()),
+            ])
+            .bearer_auth(target.master_key)
+            .send_checked()
+            .await
+            .map_err(anyhow::Error::from)?
+            .json()
+            .await
+            .map_err(anyhow::Error::from)?;
+        for task in list.results {
+            match task.status.as_str() {
+                "succeeded" => status.succeeded_tasks += 1,
+                "failed" | "canceled" => {
+                    status.failed_tasks += 1;
+                    if status.first_error.is_none() {
+                        status.first_error = Some(
+                            task.error
+                                .map(|e| e.message)
+                                .unwrap_or_else(|| format!("task {} {}", task.uid, task.status)),
+                        );
+                    }
+                }
+                _ => continue,
+            }
+            status.indexed_documents += task
+                .details
+                .and_then(|d| d.indexed_documents)
+                .unwrap_or_default();
+            status.pending_task_uids.retain(|&u| u != task.uid);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_records_across_chunks() {
+        let mut csv = Splitter::new(true);
+        let mut rows = csv.push(b"id,title\r\n1,\"Dune\nPart");
+        rows.extend(csv.push(b" 1\"\n\n2,Emma"));
+        rows.extend(csv.finish());
+        assert_eq!(
+            rows,
+            vec![
+                b"id,title".to_vec(),
+                b"1,\"Dune\nPart 1\"".to_vec(),
+                b"2,Emma".to_vec()
+            ]
+        );
+
+        // Quotes only matter for CSV
+        let mut ndjson = Splitter::new(false);
+        let lines = ndjson.push(b"{\"t\":\"a\\\"b\"}\n  \n{\"t\":2}\n");
+        assert_eq!(lines.len(), 2);
+        assert_eq!(ndjson.finish(), None);
+
+        let mut batch = Batch::default();
+        batch.push(b"1,Dune");
+        batch.push(b"2,Emma");
+        assert_eq!(batch.payload(b"id,title"), b"id,title\n1,Dune\n2,Emma\n");
+        assert_eq!(batch.len(b"id,title"), 23);
+        assert_eq!(batch.len(b""), 14);
+        batch.push(b"3,\"Ulysses\nPart 1\"");
+        let (first, second) = batch.split();
+        assert_eq!(first.payload(b""), b"1,Dune\n");
+        assert_eq!(second.records, 2);
+        assert_eq!(second.payload(b""), b"2,Emma\n3,\"Ulysses\nPart 1\"\n");
+    }
+}
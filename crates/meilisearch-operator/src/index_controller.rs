@@ -1,300 +1,2001 @@
+use k8s_openapi::api::core::v1::ConfigMap;
 use kube::{
-    Api, Client, ResourceExt,
-    runtime::controller::{Action, Controller},
+    Api, Client, Resource, ResourceExt,
+    runtime::{
+        WatchStreamExt,
+        controller::{Action, Controller},
+        events::{EventType, Recorder},
+        metadata_watcher, predicates,
+        reflector::ObjectRef,
+        watcher,
+    },
 };
-use meilisearch_sdk::{
-    client::Client as MeiliClient,
-    key::{Action as MeiliAction, KeyBuilder},
-};
-use std::sync::Arc;
+use meilisearch_sdk::key::KeyBuilder;
+use semver::Version;
+use std::{collections::BTreeSet, sync::Arc};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
 use tokio::time::Duration;
 use tracing::error;
 
 use crate::{
-    crds::index::{Index, IndexStatus},
+    audit::Audit,
+    capabilities,
+    children::{self, ensure_server_label},
+    clusters,
+    conditions::{self, DEGRADED, DRIFT_DETECTED, FROZEN, TIMED_OUT, WAITING_FOR_SERVER},
+    config::OperatorConfig,
+    crds::{
+        self, FINALIZER,
+        common::Phase,
+        index::{
+            AdoptionPolicy, DeletionPolicy, Index, IndexAdminKeySpec, IndexKeySpec, IndexRetarget,
+            IndexRetentionSpec, IndexSettings, IndexSpec, IndexStatus, PendingTask, PrefixSearch,
+            ProximityPrecision, RetargetStep,
+        },
+        index_template::IndexTemplate,
+        key::{Key, KeySpec},
+    },
+    dry_run,
     error::ReconcileError,
+    events, key_controller, key_registry,
+    meili_http::{self, MeiliSend},
+    phase, resync,
+    secret_cache::SecretCache,
+    secret_template, settings_lint, settings_sources, shutdown, status, task_watch,
+    triggers::{self, Trigger},
+    watch,
 };
 
 #[derive(Clone)]
 pub struct Ctx {
     pub client: Client,
+    pub secrets: SecretCache,
+    pub recorder: Recorder,
+    pub config: Arc<OperatorConfig>,
 }
 
-pub fn controller(client: Client) -> Controller<Index> {
-    let api: Api<Index> = Api::all(client.clone());
-    Controller::new(api, Default::default()).shutdown_on_signal()
+pub fn controller(client: Client, config: &OperatorConfig) -> Controller<Index> {
+    let controller = watch::controller(Api::<Index>::all(client.clone()), config);
+    let controller = watch::follow_servers(controller, client.clone(), |idx| {
+        let ns = idx.namespace().unwrap_or_default();
+        let server = &idx.spec.server_ref;
+        (server.namespace_or(&ns).to_string(), server.name.clone())
+    });
+    // Re-apply settings to every Index referencing a template that was created or edited
+    let indexes = controller.store();
+    let templates = watcher(
+        Api::<IndexTemplate>::all(client.clone()),
+        watcher::Config::default(),
+    )
+    .default_backoff()
+    .touched_objects()
+    .predicate_filter(predicates::generation);
+    let controller = controller.watches_stream(templates, move |template| {
+        let ns = template.namespace();
+        let name = template.name_any();
+        indexes
+            .state()
+            .into_iter()
+            .filter(|idx| idx.namespace() == ns && idx.spec.template_ref.as_ref() == Some(&name))
+            .map(|idx| ObjectRef::from_obj(idx.as_ref()))
+            .collect::<Vec<_>>()
+    });
+    // Hot-reload synonyms and stop words. Indexes using a template may take sources from it, so
+    // those are checked too; an unchanged checksum makes that cheap.
+    let indexes = controller.store();
+    let config_maps = metadata_watcher(Api::<ConfigMap>::all(client), watcher::Config::default())
+        .default_backoff()
+        .touched_objects();
+    controller.watches_stream(config_maps, move |cm| {
+        let ns = cm.namespace();
+        let name = cm.name_any();
+        indexes
+            .state()
+            .into_iter()
+            .filter(|idx| {
+                idx.namespace() == ns
+                    && (idx.spec.template_ref.is_some()
+                        || idx.spec.settings.as_ref().is_some_and(|s| {
+                            [&s.synonyms_from, &s.stop_words_from]
+                                .into_iter()
+                                .flatten()
+                                .any(|r| r.name == name)
+                        }))
+            })
+            .map(|idx| ObjectRef::from_obj(idx.as_ref()))
+            .collect::<Vec<_>>()
+    })
 }
 
-const FINALIZER: &str = "meili.operator.dev/finalizer";
+const RESYNC: Duration = Duration::from_secs(600);
+/// Fallback while waiting for the Server; its watch normally requeues first.
+const SERVER_WAIT: Duration = Duration::from_secs(120);
 
 pub async fn reconcile(idx: Arc<Index>, ctx: Arc<Ctx>) -> Result<Action, ReconcileError> {
+    let res = watch::bounded(
+        ctx.config.reconcile_timeout,
+        reconcile_index(idx.clone(), ctx.clone()),
+    )
+    .await;
+    // Show what Meilisearch objected to instead of leaving it to the operator log
+    if let Err(e) = &res
+        && let Some(api) = e.meili_api()
+        && idx.metadata.deletion_timestamp.is_none()
+    {
+        reject(&idx, &ctx, &api.reason(), api.to_string()).await?;
+    }
+    if let Err(e @ ReconcileError::TimedOut(_)) = &res {
+        let mut status = idx.status.clone().unwrap_or_default();
+        conditions::set_condition(
+            &mut status.conditions,
+            TIMED_OUT,
+            true,
+            "ReconcileTimedOut",
+            e.to_string(),
+            idx.metadata.generation,
+        );
+        let api: Api<Index> = Api::namespaced(ctx.client.clone(), &idx.namespace().unwrap());
+        if let Some(cond) = status.conditions.iter().find(|c| c.type_ == TIMED_OUT) {
+            status::apply_condition(&api, &idx.name_any(), status::TIMEOUT_MANAGER, cond).await?;
+        }
+    }
+    res
+}
+
+async fn reconcile_index(idx: Arc<Index>, ctx: Arc<Ctx>) -> Result<Action, ReconcileError> {
     let ns = idx.namespace().unwrap();
     let name = idx.name_any();
-    let server = &idx.spec.server_ref;
+
+    // Fill in settings from the referenced IndexTemplate and ConfigMaps; everything below
+    // sees the result
+    let mut template_generation = None;
+    let mut sources_checksum = None;
+    let idx = if idx.metadata.deletion_timestamp.is_none() {
+        let mut merged = (*idx).clone();
+        if let Some(template) = &idx.spec.template_ref {
+            let Some(t) = Api::<IndexTemplate>::namespaced(ctx.client.clone(), &ns)
+                .get_opt(template)
+                .await?
+            else {
+                let msg = format!("IndexTemplate {}/{} not found", ns, template);
+                return reject(&idx, &ctx, "TemplateNotFound", msg).await;
+            };
+            template_generation = t.metadata.generation;
+            merged.spec.settings = Some(
+                merged
+                    .spec
+                    .settings
+                    .take()
+                    .unwrap_or_default()
+                    .over(&t.spec.settings),
+            );
+        }
+        if let Some(settings) = merged.spec.settings.as_mut() {
+            match settings_sources::resolve(&ctx.client, &ns, settings).await? {
+                Ok(checksum) => sources_checksum = checksum,
+                Err(msg) => return reject(&idx, &ctx, "SettingsSourceInvalid", msg).await,
+            }
+        }
+        // The keys below are reconciled to their read-only form, which replaces them
+        if merged.spec.frozen {
+            if let Some(ak) = merged.spec.admin_key.as_mut() {
+                ak.actions = read_only_actions(&ak.actions);
+            }
+            for k in &mut merged.spec.keys {
+                k.actions = read_only_actions(&k.actions);
+            }
+        }
+        Arc::new(merged)
+    } else {
+        idx
+    };
+
+    let server = idx.spec.server_ref.name.as_str();
+    let server_ns = idx.spec.server_ref.namespace_or(&ns).to_string();
     let mut status_message: Option<String> = None;
+    let audit = Audit::new(&ctx.recorder, idx.as_ref(), server);
+
+    if dry_run::enabled(&ctx.config, idx.as_ref()) {
+        return dry_run_index(&idx, &ctx).await;
+    }
+
+    if server_ns != ns && !ctx.config.allow_cross_namespace_refs {
+        if idx.metadata.deletion_timestamp.is_some() {
+            // Nothing was ever created on the other namespace's Server
+            remove_finalizer(&ctx.client, &ns, &name).await?;
+            return Ok(Action::await_change());
+        }
+        let msg = format!(
+            "server_ref points at namespace {}; cross-namespace references are disabled",
+            server_ns
+        );
+        return reject(&idx, &ctx, "CrossNamespaceRef", msg).await;
+    }
 
     // Handle deletion via finalizer
     if idx.metadata.deletion_timestamp.is_some() {
+        // Mid-move the index still lives on the Server it was reconciled on
+        let (server_ns, server) =
+            bound_server(&idx).unwrap_or_else(|| (server_ns.clone(), server.to_string()));
+        let server = server.as_str();
+        let audit = Audit::new(&ctx.recorder, idx.as_ref(), server);
         // If the referenced Server is being deleted, skip Meilisearch calls and just remove our finalizer.
-        if !server_is_deleting(&ctx.client, &ns, server).await?
-            && idx.spec.delete_on_finalize
+        if !server_is_deleting(&ctx.client, &server_ns, server).await? {
+            phase::set(
+                &Api::<Index>::namespaced(ctx.client.clone(), &ns),
+                &name,
+                idx.status.as_ref().map(|s| s.phase),
+                Phase::Terminating,
+            )
+            .await?;
+            let endpoint =
+                clusters::server_endpoint(&ctx.client, &ctx.config, &server_ns, server).await?;
+            let master_key = get_master_key(&ctx.secrets, &server_ns, server).await?;
+            if idx.spec.deletion_policy == DeletionPolicy::Delete {
+                match pending_task(&idx, "indexDeletion") {
+                    Some(uid) => match task_state(&endpoint, &master_key, uid).await? {
+                        TaskState::Pending => return Ok(Action::requeue(TASK_POLL)),
+                        TaskState::Failed(api) if api.code != "index_not_found" => {
+                            record_pending_task(&ctx.client, &idx, None).await?;
+                            return Err(anyhow::Error::from(meili_http::Error::Api(api)).into());
+                        }
+                        _ => {}
+                    },
+                    None => {
+                        let client = meili_http::sdk(&endpoint, &master_key);
+                        let res = client.delete_index(&idx.spec.uid).await;
+                        audit.record("indexes.delete", &idx.spec.uid, &res).await;
+                        let task = PendingTask {
+                            uid: res?.task_uid as u64,
+                            type_: "indexDeletion".into(),
+                        };
+                        record_pending_task(&ctx.client, &idx, Some(task)).await?;
+                        return Ok(Action::requeue(TASK_POLL));
+                    }
+                }
+            }
+            // Admin keys go with the CR even when the index itself is retained
+            delete_admin_keys(
+                &ctx.client,
+                &audit,
+                &idx,
+                (&server_ns, server),
+                &endpoint,
+                &master_key,
+            )
+            .await?;
+        }
+        // Same-namespace Secrets are garbage collected through their ownerReference
+        if let Some((secret_ns, secret_name)) = recorded_admin_secret(&idx)
+            && secret_ns != ns
         {
-            let endpoint = format!("http://{}.{}.svc.cluster.local:7700", server, ns);
-            let master_key = get_master_key(&ctx.client, &ns, server).await?;
-            let client = MeiliClient::new(&endpoint, Some(&master_key))?;
-            let task = client.delete_index(&idx.spec.uid).await?;
-            let _ = task.wait_for_completion(&client, None, None).await?;
+            delete_secret(&ctx.client, &secret_ns, &secret_name).await?;
         }
+        // Key CRs from `keys` may live in the Server's namespace, out of reach of ownerReferences
+        children::prune_owned::<Key>(
+            &ctx.client,
+            &idx.uid().unwrap_or_default(),
+            &BTreeSet::new(),
+        )
+        .await?;
         remove_finalizer(&ctx.client, &ns, &name).await?;
         return Ok(Action::await_change());
     }
 
+    if let Some(dup) = duplicate_key_name(&idx.spec.keys) {
+        let msg = format!("keys lists {:?} more than once", dup);
+        return reject(&idx, &ctx, "DuplicateKeyName", msg).await;
+    }
+
     ensure_finalizer(&ctx.client, &ns, &name, &idx).await?;
+    ensure_server_label(
+        &Api::<Index>::namespaced(ctx.client.clone(), &ns),
+        idx.as_ref(),
+        server,
+    )
+    .await?;
+
+    // Nothing changed since the last full reconcile; wait for the periodic resync
+    if let Some(prev) = idx.status.as_ref()
+        && prev.ready
+        && prev.dry_run_plan.is_none()
+        && prev.template_generation == template_generation
+        && prev.sources_checksum == sources_checksum
+        && triggers::requested(idx.as_ref()).is_none()
+        && idx
+            .spec
+            .retention
+            .as_ref()
+            .is_none_or(|r| !retention_due(r, prev, OffsetDateTime::now_utc()))
+        && let Some(wait) = resync::remaining(
+            idx.metadata.generation,
+            prev.observed_generation,
+            prev.last_synced.as_deref(),
+            RESYNC,
+        )
+    {
+        return Ok(Action::requeue(wait));
+    }
+
+    if let Some(msg) = clusters::missing_server(&ctx.client, &server_ns, server).await? {
+        return reject(&idx, &ctx, "ServerNotFound", msg).await;
+    }
+    if let Some(msg) = clusters::server_not_ready(&ctx.client, &server_ns, server).await? {
+        return wait_for_server(&idx, &ctx, msg).await;
+    }
+
+    // server_ref changed: clean up the old Server before creating the index on the new one
+    if let Some(from) = bound_server(&idx)
+        && from != (server_ns.clone(), server.to_string())
+    {
+        return retarget(&idx, &ctx, from, format!("{}/{}", server_ns, server)).await;
+    }
+
+    if idx
+        .status
+        .as_ref()
+        .is_none_or(|s| s.phase == Phase::Pending)
+    {
+        phase::set(
+            &Api::<Index>::namespaced(ctx.client.clone(), &ns),
+            &name,
+            None,
+            Phase::Provisioning,
+        )
+        .await?;
+    }
+
+    let endpoint = clusters::server_endpoint(&ctx.client, &ctx.config, &server_ns, server).await?;
+    let master_key = get_master_key(&ctx.secrets, &server_ns, server).await?;
+    let client = meili_http::sdk(&endpoint, &master_key);
+
+    let existing = get_index_http(&endpoint, &master_key, &idx.spec.uid).await?;
+
+    // Refuse to take over an index created elsewhere with a different shape
+    if idx.spec.adoption_policy == AdoptionPolicy::Fail
+        && let Some(conflict) = existing.as_ref().and_then(|e| {
+            primary_key_conflict(idx.spec.primary_key.as_deref(), e["primaryKey"].as_str())
+        })
+    {
+        return reject(&idx, &ctx, "AdoptionConflict", conflict).await;
+    }
 
-    let endpoint = format!("http://{}.{}.svc.cluster.local:7700", server, ns);
-    let master_key = get_master_key(&ctx.client, &ns, server).await?;
-    let client = MeiliClient::new(&endpoint, Some(&master_key))?;
+    // Ensure index exists. Creation is enqueued and checked on later passes rather than
+    // waited for, so the worker is free meanwhile.
+    if existing.is_none() {
+        if let Some(uid) = pending_task(&idx, "indexCreation") {
+            match task_state(&endpoint, &master_key, uid).await? {
+                TaskState::Pending => return Ok(Action::requeue(TASK_POLL)),
+                TaskState::Failed(api) => {
+                    record_pending_task(&ctx.client, &idx, None).await?;
+                    return Err(anyhow::Error::from(meili_http::Error::Api(api)).into());
+                }
+                // Deleted again since; create it anew
+                TaskState::Succeeded => {}
+            }
+        }
+        let res = client
+            .create_index(&idx.spec.uid, idx.spec.primary_key.as_deref())
+            .await;
+        audit.record("indexes.create", &idx.spec.uid, &res).await;
+        let task = PendingTask {
+            uid: res?.task_uid as u64,
+            type_: "indexCreation".into(),
+        };
+        record_pending_task(&ctx.client, &idx, Some(task)).await?;
+        return Ok(Action::requeue(TASK_POLL));
+    }
 
-    // Ensure index exists
-    let task = client
-        .create_index(&idx.spec.uid, idx.spec.primary_key.as_deref())
+    let mut ranking_drift = None;
+    let mut settings_task = None;
+    if let Some(settings) = &idx.spec.settings {
+        let version = capabilities::server_version(&ctx.client, &server_ns, server).await;
+        let skipped = unsupported_settings(settings, version.as_ref());
+        if let (Some(v), false) = (&version, skipped.is_empty()) {
+            events::publish(
+                &ctx.recorder,
+                &idx.object_ref(&()),
+                EventType::Warning,
+                "UnsupportedSettings",
+                "Reconcile",
+                format!("Meilisearch {} does not support {}", v, skipped.join(", ")),
+            )
+            .await;
+        }
+        let findings = settings_lint::lint(settings);
+        if !findings.is_empty() {
+            events::publish(
+                &ctx.recorder,
+                &idx.object_ref(&()),
+                EventType::Warning,
+                "SettingsLint",
+                "Reconcile",
+                findings.join("; "),
+            )
+            .await;
+        }
+        let current = get_settings_http(&endpoint, &master_key, &idx.spec.uid).await?;
+        // Only a spec already applied can drift; otherwise the difference is our pending change
+        let applied = idx.status.as_ref().is_some_and(|prev| {
+            prev.ready
+                && prev.observed_generation == idx.metadata.generation
+                && prev.template_generation == template_generation
+                && prev.sources_checksum == sources_checksum
+        });
+        if applied && let Some(msg) = ranking_rules_drift(settings, &current) {
+            events::publish(
+                &ctx.recorder,
+                &idx.object_ref(&()),
+                EventType::Warning,
+                "RankingRulesDrift",
+                "Reconcile",
+                format!("{}; re-applying", msg),
+            )
+            .await;
+            ranking_drift = Some(msg);
+        }
+        settings_task = apply_settings(
+            &audit,
+            &endpoint,
+            &master_key,
+            &idx.spec.uid,
+            settings,
+            &current,
+            false,
+            version.as_ref(),
+        )
         .await?;
-    let _ = task.wait_for_completion(&client, None, None).await?;
+    }
+
+    // One-shot actions requested via annotation
+    let mut last_trigger = None;
+    // The status write below carries the resourceVersion clearing the trigger produced
+    let mut cleared = None;
+    if let Some(requested) = triggers::requested(idx.as_ref()) {
+        let (action, result) = match requested {
+            Ok(t) => (
+                t.as_str().to_string(),
+                run_trigger(t, &ctx, &audit, &ns, &name, &idx, &endpoint, &master_key).await,
+            ),
+            Err(other) => (
+                other.clone(),
+                Err(anyhow::anyhow!("unknown trigger {:?}", other).into()),
+            ),
+        };
+        last_trigger = Some(triggers::outcome(&action, &result));
+        let api: Api<Index> = Api::namespaced(ctx.client.clone(), &ns);
+        cleared = Some(triggers::clear(&api, &name).await?);
+    }
 
     // Optionally create an admin key scoped to this index and store it in a Secret
+    let mut admin_key_uid = None;
     if let Some(ak) = &idx.spec.admin_key
         && ak.create
+        && let Some((target_ns, secret_name)) = admin_secret_location(&idx, &ns)
     {
-        // First, try to adopt an existing matching key to avoid duplicates
-        if let Some(existing) =
-            find_matching_admin_key_http(&endpoint, &master_key, &idx.spec.uid).await?
-        {
-            let target_ns = ak.secret_namespace.clone().unwrap_or_else(|| ns.clone());
-            let secret_name = ak
-                .secret_name
-                .clone()
-                .unwrap_or_else(|| format!("{}-admin-key", idx.spec.uid));
-            store_index_key_secret(
-                &ctx.client,
-                &ns,
-                &name,
-                &target_ns,
-                &secret_name,
-                &existing.key,
-                &idx,
+        // Reuse the key created earlier, found by uid, unless its actions or expiry changed
+        let owner = key_registry::owner_id("Index", &ns, &name);
+        match find_admin_key(&ctx.client, &idx, &endpoint, &master_key).await? {
+            Some(existing) if admin_key_current(&existing, ak) => {
+                admin_key_uid = Some(existing.uid.clone());
+                store_index_key_secret(
+                    &ctx.client,
+                    &ns,
+                    &target_ns,
+                    &secret_name,
+                    &existing.key,
+                    &idx,
+                    ctx.config.immutable_secrets,
+                )
+                .await?;
+            }
+            stale => {
+                let version = capabilities::server_version(&ctx.client, &server_ns, server).await;
+                let res = admin_key_builder(&idx.spec.uid, ak, version.as_ref())
+                    .execute(&client)
+                    .await;
+                audit
+                    .record("keys.create", &format!("{}-admin", idx.spec.uid), &res)
+                    .await;
+                let created = res?;
+                key_registry::record(&ctx.client, &server_ns, server, &created.uid, &owner).await?;
+                admin_key_uid = Some(created.uid.clone());
+                if stale.is_some() {
+                    // The Secret may be immutable, so it is replaced rather than updated
+                    delete_secret(&ctx.client, &target_ns, &secret_name).await?;
+                }
+                store_index_key_secret(
+                    &ctx.client,
+                    &ns,
+                    &target_ns,
+                    &secret_name,
+                    &created.key,
+                    &idx,
+                    ctx.config.immutable_secrets,
+                )
+                .await?;
+                if let Some(old) = stale {
+                    let res = delete_key_http(&endpoint, &master_key, &old.uid).await;
+                    audit.record("keys.delete", &old.uid, &res).await;
+                    res.map_err(anyhow::Error::from)?;
+                    key_registry::forget(&ctx.client, &server_ns, server, &old.uid).await?;
+                    status_message =
+                        Some("admin key replaced after its actions or expiry changed".into());
+                }
+            }
+        }
+    } else {
+        // admin_key was removed or disabled
+        delete_admin_keys(
+            &ctx.client,
+            &audit,
+            &idx,
+            (&server_ns, server),
+            &endpoint,
+            &master_key,
+        )
+        .await?;
+    }
+    // Drop the Secret when it moved or the admin key is gone
+    let admin_key_secret = admin_secret_location(&idx, &ns);
+    if let Some(old) = recorded_admin_secret(&idx)
+        && Some(&old) != admin_key_secret.as_ref()
+    {
+        delete_secret(&ctx.client, &old.0, &old.1).await?;
+    }
+
+    // Keys from `keys` are Key CRs; the Key controller creates them and writes their Secrets
+    ensure_index_keys(&ctx.client, &idx, &ns, &server_ns).await?;
+
+    // Update status
+    let mut status = idx.status.clone().unwrap_or_default();
+    status.admin_key_uid = admin_key_uid;
+    status.pending_task = None;
+    if settings_task.is_some() {
+        status.last_settings_task_uid = settings_task;
+    }
+    status.admin_key_secret =
+        admin_key_secret.map(|(secret_ns, secret_name)| format!("{}/{}", secret_ns, secret_name));
+    status.phase = Phase::Ready;
+    status.ready = true;
+    status.message = status_message;
+    status.dry_run_plan = None;
+    status.observed_generation = idx.metadata.generation;
+    status.template_generation = template_generation;
+    status.sources_checksum = sources_checksum;
+    status.last_synced = Some(resync::now());
+    status.server = Some(format!("{}/{}", server_ns, server));
+    conditions::set_condition(
+        &mut status.conditions,
+        DEGRADED,
+        false,
+        "AsExpected",
+        "",
+        idx.metadata.generation,
+    );
+    conditions::set_condition(
+        &mut status.conditions,
+        WAITING_FOR_SERVER,
+        false,
+        "ServerReady",
+        "",
+        idx.metadata.generation,
+    );
+    conditions::set_condition(
+        &mut status.conditions,
+        TIMED_OUT,
+        false,
+        "Completed",
+        "",
+        idx.metadata.generation,
+    );
+    // Keys the index doesn't own are left alone, so the freeze only reports those that can
+    // still write to it
+    let frozen_message = if idx.spec.frozen {
+        let keys = key_controller::list_all_keys_http(&endpoint, &master_key).await?;
+        let writable = writable_keys(&keys, &idx.spec);
+        let msg = if writable.is_empty() {
+            "keys of this index are read-only".to_string()
+        } else {
+            format!(
+                "keys of this index are read-only; still writable: {}",
+                writable.join(", ")
             )
-            .await?;
-            status_message = Some("adopted existing admin key".into());
+        };
+        let previous = status.conditions.iter().find(|c| c.type_ == FROZEN);
+        let previous = previous.map(|c| c.message.as_str());
+        if !writable.is_empty() && previous != Some(msg.as_str()) {
+            events::publish(
+                &ctx.recorder,
+                &idx.object_ref(&()),
+                EventType::Warning,
+                "WritableKeys",
+                "Reconcile",
+                format!(
+                    "index {} is frozen but these keys can still write to it: {}",
+                    idx.spec.uid,
+                    writable.join(", ")
+                ),
+            )
+            .await;
+        }
+        msg
+    } else {
+        String::new()
+    };
+    conditions::set_condition(
+        &mut status.conditions,
+        FROZEN,
+        idx.spec.frozen,
+        if idx.spec.frozen {
+            "ContentFreeze"
         } else {
-            let mut kb = KeyBuilder::new();
-            kb.with_actions(vec![MeiliAction::All]);
-            kb.with_indexes(vec![idx.spec.uid.clone()]);
-            kb.with_name(format!("{}-admin", idx.spec.uid));
-            kb.with_description(format!("Admin key for index {}", idx.spec.uid));
-            let created = kb.execute(&client).await?;
-
-            let target_ns = ak.secret_namespace.clone().unwrap_or_else(|| ns.clone());
-            let secret_name = ak
-                .secret_name
-                .clone()
-                .unwrap_or_else(|| format!("{}-admin-key", idx.spec.uid));
+            "Writable"
+        },
+        frozen_message,
+        idx.metadata.generation,
+    );
+    match ranking_drift {
+        Some(msg) => conditions::set_condition(
+            &mut status.conditions,
+            DRIFT_DETECTED,
+            true,
+            "RankingRulesChanged",
+            msg,
+            idx.metadata.generation,
+        ),
+        None => conditions::set_condition(
+            &mut status.conditions,
+            DRIFT_DETECTED,
+            false,
+            "InSync",
+            "",
+            idx.metadata.generation,
+        ),
+    }
+    if last_trigger.is_some() {
+        status.last_trigger = last_trigger;
+    }
+    if let Some(retention) = &idx.spec.retention
+        && let Err(e) = apply_retention(
+            &audit,
+            &endpoint,
+            &master_key,
+            &idx.spec.uid,
+            retention,
+            &mut status,
+        )
+        .await
+    {
+        status.message = Some(format!("retention sweep failed: {}", e));
+    }
+    // Data-loss tripwire: too few documents left marks the Index Degraded
+    let was_below = status
+        .document_count
+        .is_some_and(|count| documents_below_minimum(idx.spec.min_documents, count).is_some());
+    status.document_count = None;
+    if let Some(min) = idx.spec.min_documents {
+        let count = get_document_count_http(&endpoint, &master_key, &idx.spec.uid).await?;
+        status.document_count = Some(count);
+        if let Some(msg) = documents_below_minimum(Some(min), count) {
+            if !was_below {
+                events::publish(
+                    &ctx.recorder,
+                    &idx.object_ref(&()),
+                    EventType::Warning,
+                    "DocumentsBelowMinimum",
+                    "Reconcile",
+                    &msg,
+                )
+                .await;
+            }
+            status.phase = Phase::Degraded;
+            conditions::set_condition(
+                &mut status.conditions,
+                DEGRADED,
+                true,
+                "DocumentsBelowMinimum",
+                &msg,
+                idx.metadata.generation,
+            );
+            status.message = Some(msg);
+        }
+    }
+    let api: Api<Index> = Api::namespaced(ctx.client.clone(), &ns);
+    status::apply(&api, cleared.as_ref().unwrap_or(&idx), &status).await?;
+
+    Ok(Action::requeue(RESYNC))
+}
+
+pub fn error_policy(_idx: Arc<Index>, err: &ReconcileError, _ctx: Arc<Ctx>) -> Action {
+    error!(error = ?err, "index reconcile failed");
+    Action::requeue(Duration::from_secs(60))
+}
+
+/// Mark the Index Degraded without touching the index on the server.
+async fn reject(
+    idx: &Index,
+    ctx: &Ctx,
+    reason: &str,
+    msg: String,
+) -> Result<Action, ReconcileError> {
+    let ns = idx.namespace().unwrap();
+    let mut status = idx.status.clone().unwrap_or_default();
+    if !conditions::is_true(&status.conditions, DEGRADED) || status.message.as_ref() != Some(&msg) {
+        events::publish(
+            &ctx.recorder,
+            &idx.object_ref(&()),
+            EventType::Warning,
+            reason,
+            "Reconcile",
+            &msg,
+        )
+        .await;
+    }
+    status.phase = Phase::Degraded;
+    status.ready = false;
+    conditions::set_condition(
+        &mut status.conditions,
+        DEGRADED,
+        true,
+        reason,
+        &msg,
+        idx.metadata.generation,
+    );
+    status.message = Some(msg);
+    status.observed_generation = idx.metadata.generation;
+    let api: Api<Index> = Api::namespaced(ctx.client.clone(), &ns);
+    status::apply(&api, idx, &status).await?;
+    Ok(Action::requeue(Duration::from_secs(600)))
+}
+
+/// Record that the Server is missing or not Ready and look again later; the Server watch
+/// requeues the Index as soon as it turns Ready.
+async fn wait_for_server(idx: &Index, ctx: &Ctx, msg: String) -> Result<Action, ReconcileError> {
+    let ns = idx.namespace().unwrap();
+    let mut status = idx.status.clone().unwrap_or_default();
+    status.phase = Phase::WaitingForServer;
+    status.ready = false;
+    conditions::set_condition(
+        &mut status.conditions,
+        WAITING_FOR_SERVER,
+        true,
+        "ServerNotReady",
+        &msg,
+        idx.metadata.generation,
+    );
+    status.message = Some(msg);
+    let api: Api<Index> = Api::namespaced(ctx.client.clone(), &ns);
+    status::apply(&api, idx, &status).await?;
+    Ok(Action::requeue(SERVER_WAIT))
+}
+
+/// A conflict exists only when both sides name a primary key and they differ.
+fn primary_key_conflict(desired: Option<&str>, existing: Option<&str>) -> Option<String> {
+    match (desired, existing) {
+        (Some(d), Some(e)) if d != e => Some(format!(
+            "index already exists with primary key {:?}, spec wants {:?}",
+            e, d
+        )),
+        _ => None,
+    }
+}
+
+async fn get_index_http(
+    endpoint: &str,
+    master_key: &str,
+    uid: &str,
+) -> Result<Option<serde_json::Value>, ReconcileError> {
+    let resp = meili_http::client()
+        .get(format!("{}/indexes/{}", endpoint, uid))
+        .bearer_auth(master_key)
+        .send_meili()
+        .await
+        .map_err(anyhow::Error::from)?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(
+        meili_http::checked(resp)
+            .await
+            .map_err(anyhow::Error::from)?
+            .json()
+            .await
+            .map_err(anyhow::Error::from)?,
+    ))
+}
+
+/// Report what a reconcile would change without touching the cluster or Meilisearch.
+async fn dry_run_index(idx: &Index, ctx: &Ctx) -> Result<Action, ReconcileError> {
+    let ns = idx.namespace().unwrap();
+    let server = idx.spec.server_ref.name.as_str();
+    let server_ns = idx.spec.server_ref.namespace_or(&ns).to_string();
+    let uid = &idx.spec.uid;
+    let mut status = idx.status.clone().unwrap_or_default();
+    let mut plan = Vec::new();
+    if idx.metadata.deletion_timestamp.is_some() {
+        if idx.spec.deletion_policy == DeletionPolicy::Delete
+            && !server_is_deleting(&ctx.client, &server_ns, server).await?
+        {
+            plan.push(format!("delete index {}", uid));
+        }
+        plan.push("remove finalizer".into());
+    } else {
+        if !idx.finalizers().iter().any(|f| f == FINALIZER) {
+            plan.push("add finalizer".into());
+        }
+        let endpoint =
+            clusters::server_endpoint(&ctx.client, &ctx.config, &server_ns, server).await?;
+        match get_master_key(&ctx.secrets, &server_ns, server).await {
+            Ok(master_key) => {
+                let existing = get_index_http(&endpoint, &master_key, uid).await?;
+                if let (Some(settings), Some(_)) = (&idx.spec.settings, &existing) {
+                    let current = get_settings_http(&endpoint, &master_key, uid).await?;
+                    let patch = settings_patch(settings, &current, false);
+                    if !patch.is_empty() {
+                        let keys: Vec<&str> = patch.keys().map(String::as_str).collect();
+                        plan.push(format!("update settings {}", keys.join(", ")));
+                    }
+                }
+                match existing {
+                    None => plan.push(format!("create index {}", uid)),
+                    Some(existing) if idx.spec.adoption_policy == AdoptionPolicy::Fail => {
+                        if let Some(conflict) = primary_key_conflict(
+                            idx.spec.primary_key.as_deref(),
+                            existing["primaryKey"].as_str(),
+                        ) {
+                            plan.push(format!("mark Degraded: {}", conflict));
+                        }
+                    }
+                    Some(_) => {}
+                }
+                match triggers::requested(idx) {
+                    Some(Ok(t)) => plan.push(format!("run trigger {}", t.as_str())),
+                    Some(Err(other)) => plan.push(format!("reject unknown trigger {:?}", other)),
+                    None => {}
+                }
+                if let Some((target_ns, secret_name)) = admin_secret_location(idx, &ns) {
+                    if find_admin_key(&ctx.client, idx, &endpoint, &master_key)
+                        .await?
+                        .is_none()
+                    {
+                        plan.push(format!("create admin key {}-admin", uid));
+                    }
+                    use k8s_openapi::api::core::v1::Secret;
+                    let secrets: Api<Secret> = Api::namespaced(ctx.client.clone(), &target_ns);
+                    if secrets.get_opt(&secret_name).await?.is_none() {
+                        plan.push(format!("create Secret {}/{}", target_ns, secret_name));
+                    }
+                }
+                if let Some(retention) = &idx.spec.retention
+                    && retention_due(retention, &status, OffsetDateTime::now_utc())
+                {
+                    plan.push(format!(
+                        "delete documents older than {} days from {}",
+                        retention.max_age_days, uid
+                    ));
+                }
+            }
+            Err(e) => plan.push(format!("wait for Server {} master key: {}", server, e)),
+        }
+    }
+    dry_run::report(
+        &ctx.recorder,
+        &idx.object_ref(&()),
+        status.dry_run_plan.as_ref(),
+        &plan,
+    )
+    .await;
+    status.dry_run_plan = Some(plan);
+    let api: Api<Index> = Api::namespaced(ctx.client.clone(), &ns);
+    status::apply(&api, idx, &status).await?;
+    Ok(Action::requeue(dry_run::recheck(
+        &ctx.config,
+        Duration::from_secs(600),
+    )))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_trigger(
+    trigger: Trigger,
+    ctx: &Ctx,
+    audit: &Audit<'_>,
+    ns: &str,
+    name: &str,
+    idx: &Index,
+    endpoint: &str,
+    master_key: &str,
+) -> Result<String, ReconcileError> {
+    let client = &ctx.client;
+    let uid = &idx.spec.uid;
+    let owner = key_registry::owner_id("Index", ns, name);
+    let server = idx.spec.server_ref.name.as_str();
+    let server_ns = idx.spec.server_ref.namespace_or(ns);
+    match trigger {
+        Trigger::Reindex => {
+            let res = reindex(endpoint, master_key, uid).await;
+            audit.record("indexes.reindex", uid, &res).await;
+            res
+        }
+        Trigger::ResyncSettings => {
+            if let Some(pk) = &idx.spec.primary_key {
+                let http = meili_http::client();
+                let res = async {
+                    http.patch(format!("{}/indexes/{}", endpoint, uid))
+                        .bearer_auth(master_key)
+                        .json(&serde_json::json!({ "primaryKey": pk }))
+                        .send_checked()
+                        .await?
+                        .json::<serde_json::Value>()
+                        .await
+                        .map_err(meili_http::Error::from)
+                }
+                .await;
+                audit.record("indexes.update", uid, &res).await;
+                let task = res.map_err(anyhow::Error::from)?;
+                // Fails harmlessly when documents already fixed the primary key
+                let _ = wait_task(http, endpoint, master_key, &task).await;
+            }
+            if let Some(settings) = &idx.spec.settings {
+                let version = capabilities::server_version(client, server_ns, server).await;
+                // Forced, so what the server has does not matter
+                apply_settings(
+                    audit,
+                    endpoint,
+                    master_key,
+                    uid,
+                    settings,
+                    &serde_json::Value::Null,
+                    true,
+                    version.as_ref(),
+                )
+                .await?;
+            }
+            if let Some((target_ns, secret_name)) = admin_secret_location(idx, ns)
+                && let Some(existing) = find_admin_key(client, idx, endpoint, master_key).await?
+            {
+                delete_secret(client, &target_ns, &secret_name).await?;
+                store_index_key_secret(
+                    client,
+                    ns,
+                    &target_ns,
+                    &secret_name,
+                    &existing.key,
+                    idx,
+                    ctx.config.immutable_secrets,
+                )
+                .await?;
+            }
+            Ok("index settings and admin key Secret re-applied".into())
+        }
+        Trigger::RotateKey => {
+            let (target_ns, secret_name) = admin_secret_location(idx, ns)
+                .ok_or_else(|| anyhow::anyhow!("rotate-key requires admin_key.create"))?;
+            let ak = idx
+                .spec
+                .admin_key
+                .as_ref()
+                .expect("admin_secret_location requires admin_key");
+            let old = find_admin_key(client, idx, endpoint, master_key).await?;
+            let meili = meili_http::sdk(endpoint, master_key);
+            let version = capabilities::server_version(client, server_ns, server).await;
+            let res = admin_key_builder(uid, ak, version.as_ref())
+                .execute(&meili)
+                .await;
+            audit
+                .record("keys.create", &format!("{}-admin", uid), &res)
+                .await;
+            let created = res?;
+            key_registry::record(client, server_ns, server, &created.uid, &owner).await?;
+            delete_secret(client, &target_ns, &secret_name).await?;
             store_index_key_secret(
-                &ctx.client,
-                &ns,
-                &name,
+                client,
+                ns,
                 &target_ns,
                 &secret_name,
                 &created.key,
-                &idx,
+                idx,
+                ctx.config.immutable_secrets,
             )
             .await?;
+            if let Some(old) = old {
+                let res = delete_key_http(endpoint, master_key, &old.uid).await;
+                audit.record("keys.delete", &old.uid, &res).await;
+                res.map_err(anyhow::Error::from)?;
+                key_registry::forget(client, server_ns, server, &old.uid).await?;
+            }
+            Ok(format!("admin key rotated to {}", created.uid))
         }
     }
+}
 
-    // Update status
-    let status = IndexStatus {
-        ready: true,
-        message: status_message,
-    };
-    let pp = kube::api::PatchParams::apply("meilisearch-operator");
+/// PATCH the settings that differ from `current`, the server's (all set ones when `force`),
+/// returning the taskUid of the update, if one was sent. Fields `version` does not support
+/// are left out.
+#[allow(clippy::too_many_arguments)]
+async fn apply_settings(
+    audit: &Audit<'_>,
+    endpoint: &str,
+    master_key: &str,
+    uid: &str,
+    settings: &IndexSettings,
+    current: &serde_json::Value,
+    force: bool,
+    version: Option<&Version>,
+) -> Result<Option<u64>, ReconcileError> {
+    let mut patch = settings_patch(settings, current, force);
+    let skipped = unsupported_settings(settings, version);
+    patch.retain(|field, _| !skipped.contains(&format!("settings.{}", field)));
+    if patch.is_empty() {
+        return Ok(None);
+    }
+    let http = meili_http::client();
+    let res = async {
+        http.patch(format!("{}/indexes/{}/settings", endpoint, uid))
+            .bearer_auth(master_key)
+            .json(&patch)
+            .send_checked()
+            .await?
+            .json::<serde_json::Value>()
+            .await
+            .map_err(meili_http::Error::from)
+    }
+    .await;
+    audit.record("settings.update", uid, &res).await;
+    let task = res.map_err(anyhow::Error::from)?;
+    wait_task(http, endpoint, master_key, &task).await?;
+    Ok(task["taskUid"].as_u64())
+}
+
+async fn get_settings_http(
+    endpoint: &str,
+    master_key: &str,
+    uid: &str,
+) -> Result<serde_json::Value, ReconcileError> {
+    Ok(meili_http::client()
+        .get(format!("{}/indexes/{}/settings", endpoint, uid))
+        .bearer_auth(master_key)
+        .send_checked()
+        .await
+        .map_err(anyhow::Error::from)?
+        .json()
+        .await
+        .map_err(anyhow::Error::from)?)
+}
+
+/// numberOfDocuments of index `uid`.
+async fn get_document_count_http(
+    endpoint: &str,
+    master_key: &str,
+    uid: &str,
+) -> Result<u64, ReconcileError> {
+    let stats: serde_json::Value = meili_http::client()
+        .get(format!("{}/indexes/{}/stats", endpoint, uid))
+        .bearer_auth(master_key)
+        .send_checked()
+        .await
+        .map_err(anyhow::Error::from)?
+        .json()
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(stats["numberOfDocuments"].as_u64().unwrap_or_default())
+}
+
+/// Why `count` documents are too few for `min_documents`, if they are.
+fn documents_below_minimum(min_documents: Option<u64>, count: u64) -> Option<String> {
+    let min = min_documents?;
+    (count < min).then(|| {
+        format!(
+            "index holds {} documents, below min_documents {}",
+            count, min
+        )
+    })
+}
+
+/// Describe how the live ranking rules in `current` differ from `settings`, if they do.
+fn ranking_rules_drift(settings: &IndexSettings, current: &serde_json::Value) -> Option<String> {
+    let want = settings.ranking_rules.as_ref()?;
+    let live: Vec<String> = serde_json::from_value(current["rankingRules"].clone()).ok()?;
+    (live != *want).then(|| {
+        format!(
+            "live ranking rules [{}] differ from spec [{}]",
+            live.join(", "),
+            want.join(", ")
+        )
+    })
+}
+
+/// Set fields of `settings` that `version` lacks, named `settings.<field>` like capabilities.
+fn unsupported_settings(settings: &IndexSettings, version: Option<&Version>) -> Vec<String> {
+    let fields: Vec<String> = settings_patch(settings, &serde_json::Value::Null, true)
+        .keys()
+        .map(|field| format!("settings.{}", field))
+        .collect();
+    capabilities::unsupported(version, fields.iter().map(String::as_str))
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Settings body with the set fields of `desired` that differ from `current`. Attribute sets
+/// and token lists are compared ignoring order, since Meilisearch returns them sorted; typo
+/// tolerance only sends and compares the fields that are set.
+fn settings_patch(
+    desired: &IndexSettings,
+    current: &serde_json::Value,
+    force: bool,
+) -> serde_json::Map<String, serde_json::Value> {
+    use Compare::*;
+    use serde_json::json;
+    let typo_tolerance = desired.typo_tolerance.as_ref().map(|t| {
+        let sizes = t
+            .min_word_size_for_typos
+            .as_ref()
+            .map(|m| without_nulls(json!({ "oneTypo": m.one_typo, "twoTypos": m.two_typos })));
+        without_nulls(json!({
+            "enabled": t.enabled,
+            "minWordSizeForTypos": sizes,
+            "disableOnWords": t.disable_on_words,
+            "disableOnAttributes": t.disable_on_attributes,
+        }))
+    });
+    let fields = [
+        (
+            "searchableAttributes",
+            json!(desired.searchable_attributes),
+            Exact,
+        ),
+        (
+            "displayedAttributes",
+            json!(desired.displayed_attributes),
+            Exact,
+        ),
+        (
+            "filterableAttributes",
+            json!(desired.filterable_attributes),
+            Unordered,
+        ),
+        (
+            "sortableAttributes",
+            json!(desired.sortable_attributes),
+            Unordered,
+        ),
+        ("rankingRules", json!(desired.ranking_rules), Exact),
+        (
+            "distinctAttribute",
+            json!(desired.distinct_attribute),
+            Exact,
+        ),
+        ("stopWords", json!(desired.stop_words), Unordered),
+        ("synonyms", json!(desired.synonyms), Exact),
+        ("typoTolerance", json!(typo_tolerance), Partial),
+        (
+            "proximityPrecision",
+            json!(desired.proximity_precision.map(|p| match p {
+                ProximityPrecision::ByWord => "byWord",
+                ProximityPrecision::ByAttribute => "byAttribute",
+            })),
+            Exact,
+        ),
+        ("searchCutoffMs", json!(desired.search_cutoff_ms), Exact),
+        ("facetSearch", json!(desired.facet_search), Exact),
+        (
+            "prefixSearch",
+            json!(desired.prefix_search.map(|p| match p {
+                PrefixSearch::IndexingTime => "indexingTime",
+                PrefixSearch::Disabled => "disabled",
+            })),
+            Exact,
+        ),
+        (
+            "localizedAttributes",
+            json!(desired.localized_attributes.as_ref().map(|rules| {
+                rules
+                    .iter()
+                    .map(|r| json!({ "attributePatterns": r.attribute_patterns, "locales": r.locales }))
+                    .collect::<Vec<_>>()
+            })),
+            Exact,
+        ),
+        ("dictionary", json!(desired.dictionary), Unordered),
+        ("separatorTokens", json!(desired.separator_tokens), Unordered),
+        (
+            "nonSeparatorTokens",
+            json!(desired.non_separator_tokens),
+            Unordered,
+        ),
+    ];
+    fields
+        .into_iter()
+        .filter(|(_, want, _)| !want.is_null())
+        .filter(|(key, want, compare)| {
+            force
+                || match compare {
+                    Exact => *want != current[*key],
+                    Unordered => sorted(want) != sorted(&current[*key]),
+                    Partial => !covered(want, &current[*key]),
+                }
+        })
+        .map(|(key, want, _)| (key.to_string(), want))
+        .collect()
+}
+
+/// How a settings field is compared with the server's value.
+enum Compare {
+    Exact,
+    /// Sets Meilisearch returns sorted
+    Unordered,
+    /// Objects Meilisearch merges into its current value; only the keys sent count
+    Partial,
+}
+
+fn sorted(v: &serde_json::Value) -> Vec<String> {
+    let mut items: Vec<String> = v
+        .as_array()
+        .map(|a| a.iter().map(|i| i.to_string()).collect())
+        .unwrap_or_default();
+    items.sort();
+    items
+}
+
+/// Whether `current` already holds every key of `want`, comparing arrays as sets.
+fn covered(want: &serde_json::Value, current: &serde_json::Value) -> bool {
+    match want {
+        serde_json::Value::Object(fields) => fields.iter().all(|(k, v)| covered(v, &current[k])),
+        serde_json::Value::Array(_) => current.is_array() && sorted(want) == sorted(current),
+        _ => want == current,
+    }
+}
+
+fn without_nulls(mut v: serde_json::Value) -> serde_json::Value {
+    if let Some(fields) = v.as_object_mut() {
+        fields.retain(|_, f| !f.is_null());
+    }
+    v
+}
+
+/// Names (or uids) of the keys in `keys` that can still write to a frozen index: those whose
+/// `indexes` cover its uid, exactly, through `*` or a `prefix*` pattern, and that hold a write
+/// action. The admin key and `keys` of the index itself are narrowed by the operator and left
+/// out.
+fn writable_keys(keys: &[key_controller::KeyItem], spec: &IndexSpec) -> Vec<String> {
+    let uid = spec.uid.as_str();
+    let own: BTreeSet<String> = std::iter::once(format!("{}-admin", uid))
+        .chain(spec.keys.iter().map(|k| format!("{}-{}", uid, k.name)))
+        .collect();
+    keys.iter()
+        .filter(|k| !k.name.as_ref().is_some_and(|n| own.contains(n)))
+        .filter(|k| {
+            k.indexes
+                .iter()
+                .any(|i| i == uid || i.strip_suffix('*').is_some_and(|p| uid.starts_with(p)))
+        })
+        .filter(|k| {
+            k.actions
+                .iter()
+                .any(|a| read_only_actions(std::slice::from_ref(a)) != [a.as_str()])
+        })
+        .map(|k| k.name.clone().unwrap_or_else(|| k.uid.clone()))
+        .collect()
+}
+
+/// `actions` without those that write: wildcards narrow to their read action, and a key
+/// left with nothing keeps only `version`, since Meilisearch keys need at least one action.
+fn read_only_actions(actions: &[String]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for action in actions {
+        let read: &[&str] = match action.as_str() {
+            "*" => &[
+                "search",
+                "documents.get",
+                "indexes.get",
+                "settings.get",
+                "stats.get",
+                "tasks.get",
+            ],
+            "documents.*" => &["documents.get"],
+            "indexes.*" => &["indexes.get"],
+            "settings.*" => &["settings.get"],
+            "tasks.*" => &["tasks.get"],
+            "stats.*" => &["stats.get"],
+            a if a == "search" || a == "version" || a.ends_with(".get") => &[a],
+            _ => &[],
+        };
+        for a in read {
+            if !out.iter().any(|o| o == a) {
+                out.push(a.to_string());
+            }
+        }
+    }
+    if out.is_empty() {
+        out.push("version".into());
+    }
+    out
+}
+
+/// Namespace and name of the Server the index was last reconciled on.
+fn bound_server(idx: &Index) -> Option<(String, String)> {
+    let recorded = idx.status.as_ref()?.server.as_deref()?;
+    let (ns, name) = recorded.split_once('/')?;
+    Some((ns.to_string(), name.to_string()))
+}
+
+/// Move the index off Server `from` after `server_ref` changed to `to`: delete it there when
+/// `deletion_policy` is Delete, then its admin keys, its `keys` Key CRs and the admin key
+/// Secret, one step per pass recorded in `status.retarget`. The regular reconcile then
+/// creates it on the new Server. A Server that is gone or being deleted is skipped.
+async fn retarget(
+    idx: &Index,
+    ctx: &Ctx,
+    (old_ns, old): (String, String),
+    to: String,
+) -> Result<Action, ReconcileError> {
+    let ns = idx.namespace().unwrap();
     let api: Api<Index> = Api::namespaced(ctx.client.clone(), &ns);
+    let from = format!("{}/{}", old_ns, old);
+    let mut status = idx.status.clone().unwrap_or_default();
+    let mut record = |step| {
+        status.retarget = Some(IndexRetarget {
+            from: from.clone(),
+            to: to.clone(),
+            step,
+        });
+        status.phase = Phase::Provisioning;
+        status.ready = false;
+        status.message = Some(format!("moving from Server {} to {}", from, to));
+        status.clone()
+    };
+
+    if !server_is_deleting(&ctx.client, &old_ns, &old).await? {
+        let audit = Audit::new(&ctx.recorder, idx, &old);
+        let endpoint = clusters::server_endpoint(&ctx.client, &ctx.config, &old_ns, &old).await?;
+        let master_key = get_master_key(&ctx.secrets, &old_ns, &old).await?;
+        if idx.spec.deletion_policy == DeletionPolicy::Delete {
+            match pending_task(idx, "indexDeletion") {
+                Some(uid) => match task_state(&endpoint, &master_key, uid).await? {
+                    TaskState::Pending => return Ok(Action::requeue(TASK_POLL)),
+                    TaskState::Failed(api) if api.code != "index_not_found" => {
+                        record_pending_task(&ctx.client, idx, None).await?;
+                        return Err(anyhow::Error::from(meili_http::Error::Api(api)).into());
+                    }
+                    _ => {}
+                },
+                None => {
+                    let client = meili_http::sdk(&endpoint, &master_key);
+                    let res = client.delete_index(&idx.spec.uid).await;
+                    audit.record("indexes.delete", &idx.spec.uid, &res).await;
+                    let mut status = record(RetargetStep::DeletingIndex);
+                    status.pending_task = Some(PendingTask {
+                        uid: res?.task_uid as u64,
+                        type_: "indexDeletion".into(),
+                    });
+                    status::apply(&api, idx, &status).await?;
+                    return Ok(Action::requeue(TASK_POLL));
+                }
+            }
+        }
+        delete_admin_keys(
+            &ctx.client,
+            &audit,
+            idx,
+            (&old_ns, &old),
+            &endpoint,
+            &master_key,
+        )
+        .await?;
+    }
+
+    // The Key CRs name the old Server, and Key.server_ref is immutable; they are recreated
+    // for the new one once their finalizers removed the keys
+    let owner = idx.uid().unwrap_or_default();
+    children::prune_owned::<Key>(&ctx.client, &owner, &BTreeSet::new()).await?;
+    let lp =
+        kube::api::ListParams::default().labels(&format!("{}={}", children::OWNER_LABEL, owner));
+    if !Api::<Key>::all(ctx.client.clone())
+        .list_metadata(&lp)
+        .await?
+        .items
+        .is_empty()
+    {
+        status::apply(&api, idx, &record(RetargetStep::DeletingKeys)).await?;
+        return Ok(Action::requeue(TASK_POLL));
+    }
+
+    if let Some((secret_ns, secret_name)) = recorded_admin_secret(idx) {
+        delete_secret(&ctx.client, &secret_ns, &secret_name).await?;
+    }
+    let mut status = record(RetargetStep::Completed);
+    status.pending_task = None;
+    status.admin_key_secret = None;
+    status.admin_key_uid = None;
+    status.server = Some(to.clone());
+    if status::apply(&api, idx, &status).await? {
+        events::publish(
+            &ctx.recorder,
+            &idx.object_ref(&()),
+            EventType::Normal,
+            "Retargeted",
+            "Reconcile",
+            format!(
+                "index {} removed from Server {}; creating it on {}",
+                idx.spec.uid, from, to
+            ),
+        )
+        .await;
+    }
+    Ok(Action::requeue(TASK_POLL))
+}
+
+/// Namespace and name of the admin key Secret recorded in the status.
+fn recorded_admin_secret(idx: &Index) -> Option<(String, String)> {
+    let recorded = idx.status.as_ref()?.admin_key_secret.as_deref()?;
+    let (ns, name) = recorded.split_once('/')?;
+    Some((ns.to_string(), name.to_string()))
+}
+
+/// Delete the keys registered to this Index on Server `server_ns`/`server`, i.e. its admin keys.
+async fn delete_admin_keys(
+    client: &Client,
+    audit: &Audit<'_>,
+    idx: &Index,
+    (server_ns, server): (&str, &str),
+    endpoint: &str,
+    master_key: &str,
+) -> Result<(), ReconcileError> {
+    let ns = idx.namespace().unwrap_or_default();
+    let owner = key_registry::owner_id("Index", &ns, &idx.name_any());
+    let registry = key_registry::load(client, server_ns, server).await?;
+    for (uid, _) in registry.iter().filter(|(_, o)| **o == owner) {
+        let res = delete_key_http(endpoint, master_key, uid).await;
+        audit.record("keys.delete", uid, &res).await;
+        res.map_err(anyhow::Error::from)?;
+        key_registry::forget(client, server_ns, server, uid).await?;
+    }
+    Ok(())
+}
+
+/// `DELETE /keys/{uid}`, treating a key that is already gone as deleted.
+async fn delete_key_http(
+    endpoint: &str,
+    master_key: &str,
+    uid: &str,
+) -> Result<(), meili_http::Error> {
+    let resp = meili_http::client()
+        .delete(format!("{}/keys/{}", endpoint, uid))
+        .bearer_auth(master_key)
+        .send_meili()
+        .await?;
+    if resp.status() != reqwest::StatusCode::NOT_FOUND {
+        meili_http::checked(resp).await?;
+    }
+    Ok(())
+}
+
+fn duplicate_key_name(keys: &[IndexKeySpec]) -> Option<&str> {
+    let mut seen = BTreeSet::new();
+    keys.iter()
+        .map(|k| k.name.as_str())
+        .find(|n| !seen.insert(*n))
+}
+
+/// Name of the Key CR for `keys` entry `key`, prefixed with the Index's namespace when it
+/// lives in the Server's namespace.
+fn key_cr_name(ns: &str, server_ns: &str, index: &str, key: &str) -> String {
+    if server_ns == ns {
+        format!("{}-{}", index, key)
+    } else {
+        format!("{}-{}-{}", ns, index, key)
+    }
+}
+
+/// Apply one Key CR per `keys` entry, labelled with the Index uid, and delete those no
+/// longer listed.
+async fn ensure_index_keys(
+    client: &Client,
+    idx: &Index,
+    ns: &str,
+    server_ns: &str,
+) -> Result<(), ReconcileError> {
+    let name = idx.name_any();
+    let owner = idx.uid().unwrap_or_default();
+    let mut keep = BTreeSet::new();
+    for k in &idx.spec.keys {
+        let cr = key_cr_name(ns, server_ns, &name, &k.name);
+        let mut key = Key::new(
+            &cr,
+            KeySpec {
+                server_ref: idx.spec.server_ref.name.clone(),
+                name: Some(format!("{}-{}", idx.spec.uid, k.name)),
+                description: Some(format!("Key {} of Index {}/{}", k.name, ns, name)),
+                actions: k.actions.clone(),
+                indexes: vec![idx.spec.uid.clone()],
+                expires_at: k.expires_at.clone(),
+                secret_namespace: k.secret_namespace.clone().unwrap_or_else(|| ns.to_string()),
+                secret_name: k
+                    .secret_name
+                    .clone()
+                    .unwrap_or_else(|| format!("{}-{}-key", name, k.name)),
+                secret_store: None,
+                immutable_secret: None,
+                secret_template: k.secret_template.clone(),
+                retain_secret: false,
+                adopt_existing_secret: false,
+            },
+        );
+        if server_ns == ns {
+            key.metadata.owner_references = idx.controller_owner_ref(&()).map(|r| vec![r]);
+        }
+        children::apply_owned(client, server_ns, &owner, key).await?;
+        keep.insert((server_ns.to_string(), cr));
+    }
+    // Keys dropped from the spec, or left in the old namespace after server_ref moved
+    children::prune_owned::<Key>(client, &owner, &keep).await
+}
+
+/// Builder for an Index's admin key from `admin_key.actions` and `expires_at`, without the
+/// actions `version` lacks.
+fn admin_key_builder(
+    index_uid: &str,
+    ak: &IndexAdminKeySpec,
+    version: Option<&Version>,
+) -> KeyBuilder {
+    let skipped = capabilities::unsupported(version, ak.actions.iter().map(String::as_str));
+    let mut kb = KeyBuilder::new();
+    kb.with_actions(
+        ak.actions
+            .iter()
+            .filter(|a| !skipped.contains(&a.as_str()))
+            .map(|a| key_controller::meili_action(a)),
+    );
+    kb.with_indexes(vec![index_uid.to_string()]);
+    kb.with_name(format!("{}-admin", index_uid));
+    kb.with_description(format!("Admin key for index {}", index_uid));
+    if let Some(dt) = ak
+        .expires_at
+        .as_deref()
+        .and_then(|e| OffsetDateTime::parse(e, &Rfc3339).ok())
+    {
+        kb.with_expires_at(dt);
+    }
+    kb
+}
+
+/// Whether the server's key still has the actions and expiry `ak` asks for.
+fn admin_key_current(item: &KeyItem, ak: &IndexAdminKeySpec) -> bool {
+    let parse = |t: Option<&str>| t.and_then(|t| OffsetDateTime::parse(t, &Rfc3339).ok());
+    let mut have = item.actions.clone();
+    let mut want = ak.actions.clone();
+    have.sort();
+    want.sort();
+    have == want && parse(item.expires_at.as_deref()) == parse(ak.expires_at.as_deref())
+}
+
+fn admin_secret_location(idx: &Index, ns: &str) -> Option<(String, String)> {
+    let ak = idx.spec.admin_key.as_ref().filter(|ak| ak.create)?;
+    Some((
+        ak.secret_namespace
+            .clone()
+            .unwrap_or_else(|| ns.to_string()),
+        ak.secret_name
+            .clone()
+            .unwrap_or_else(|| format!("{}-admin-key", idx.spec.uid)),
+    ))
+}
+
+/// Rebuild `uid` by copying its settings and documents into a scratch index and swapping it in.
+async fn reindex(endpoint: &str, master_key: &str, uid: &str) -> Result<String, ReconcileError> {
+    let http = meili_http::client();
+    let scratch = format!("{}-reindex", uid);
+    let call = |req: reqwest::RequestBuilder| async {
+        req.bearer_auth(master_key)
+            .timeout(Duration::from_secs(60))
+            .send_checked()
+            .await
+            .map_err(anyhow::Error::from)?
+            .json::<serde_json::Value>()
+            .await
+            .map_err(|e| ReconcileError::from(anyhow::Error::from(e)))
+    };
+
+    // Leftover from an interrupted run; the task fails harmlessly if absent
+    let task = call(http.delete(format!("{}/indexes/{}", endpoint, scratch))).await?;
+    let _ = wait_task(http, endpoint, master_key, &task).await;
+
+    let source = call(http.get(format!("{}/indexes/{}", endpoint, uid))).await?;
+    let task = call(
+        http.post(format!("{}/indexes", endpoint))
+            .json(&serde_json::json!({ "uid": scratch, "primaryKey": source["primaryKey"] })),
+    )
+    .await?;
+    wait_task(http, endpoint, master_key, &task).await?;
+
+    let settings = call(http.get(format!("{}/indexes/{}/settings", endpoint, uid))).await?;
+    let task = call(
+        http.patch(format!("{}/indexes/{}/settings", endpoint, scratch))
+            .json(&settings),
+    )
+    .await?;
+    wait_task(http, endpoint, master_key, &task).await?;
+
+    let mut offset = 0u64;
+    loop {
+        let page = call(http.get(format!(
+            "{}/indexes/{}/documents?offset={}&limit=1000",
+            endpoint, uid, offset
+        )))
+        .await?;
+        let docs = page["results"].as_array().cloned().unwrap_or_default();
+        if docs.is_empty() {
+            break;
+        }
+        offset += docs.len() as u64;
+        let task = call(
+            http.post(format!("{}/indexes/{}/documents", endpoint, scratch))
+                .json(&docs),
+        )
+        .await?;
+        wait_task(http, endpoint, master_key, &task).await?;
+        if offset >= page["total"].as_u64().unwrap_or(0) {
+            break;
+        }
+    }
+
+    let task = call(
+        http.post(format!("{}/swap-indexes", endpoint))
+            .json(&serde_json::json!([{ "indexes": [uid, scratch] }])),
+    )
+    .await?;
+    wait_task(http, endpoint, master_key, &task).await?;
+    let task = call(http.delete(format!("{}/indexes/{}", endpoint, scratch))).await?;
+    wait_task(http, endpoint, master_key, &task).await?;
+    Ok(format!("reindexed {} documents", offset))
+}
+
+/// How soon a reconcile waiting on an enqueued task looks again.
+const TASK_POLL: Duration = Duration::from_secs(3);
+
+enum TaskState {
+    Pending,
+    Succeeded,
+    Failed(meili_http::ApiError),
+}
+
+/// taskUid of the Index's pending task of `type_`, if any.
+fn pending_task(idx: &Index, type_: &str) -> Option<u64> {
+    idx.status
+        .as_ref()?
+        .pending_task
+        .as_ref()
+        .filter(|t| t.type_ == type_)
+        .map(|t| t.uid)
+}
+
+/// Record (or clear) the task a later reconcile checks on, with a message while it runs.
+async fn record_pending_task(
+    client: &Client,
+    idx: &Index,
+    task: Option<PendingTask>,
+) -> Result<(), ReconcileError> {
+    let message = task
+        .as_ref()
+        .map(|t| format!("waiting for {} task {}", t.type_, t.uid));
+    let api: Api<Index> = Api::namespaced(client.clone(), &idx.namespace().unwrap_or_default());
+    let patch = serde_json::json!({"status": {"pending_task": task, "message": message}});
     let _ = api
         .patch_status(
-            &name,
-            &pp,
-            &kube::api::Patch::Merge(serde_json::json!({"status": status })),
+            &idx.name_any(),
+            &kube::api::PatchParams::default(),
+            &kube::api::Patch::Merge(&patch),
         )
         .await?;
+    Ok(())
+}
 
-    Ok(Action::requeue(Duration::from_secs(600)))
+/// Where task `uid` stands, with Meilisearch's error for failed or canceled tasks.
+async fn task_state(
+    endpoint: &str,
+    master_key: &str,
+    uid: u64,
+) -> Result<TaskState, ReconcileError> {
+    let task = meili_http::client()
+        .get(format!("{}/tasks/{}", endpoint, uid))
+        .bearer_auth(master_key)
+        .send_checked()
+        .await
+        .map_err(anyhow::Error::from)?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(task_state_of(&task, uid))
 }
 
-pub fn error_policy(_idx: Arc<Index>, err: &ReconcileError, _ctx: Arc<Ctx>) -> Action {
-    error!(error = ?err, "index reconcile failed");
-    Action::requeue(Duration::from_secs(60))
+fn task_state_of(task: &serde_json::Value, uid: u64) -> TaskState {
+    match task["status"].as_str() {
+        Some("succeeded") => TaskState::Succeeded,
+        Some("failed") => TaskState::Failed(task_watch::task_error(task, uid)),
+        Some("canceled") => TaskState::Failed(meili_http::ApiError {
+            message: format!("task {} was canceled", uid),
+            code: "task_canceled".into(),
+            kind: "invalid_request".into(),
+            link: String::new(),
+        }),
+        _ => TaskState::Pending,
+    }
+}
+
+/// Poll an enqueued task until it finishes; failed or canceled tasks are errors.
+pub(crate) async fn wait_task(
+    http: &reqwest::Client,
+    endpoint: &str,
+    master_key: &str,
+    enqueued: &serde_json::Value,
+) -> Result<(), ReconcileError> {
+    let uid = enqueued["taskUid"]
+        .as_u64()
+        .ok_or_else(|| anyhow::anyhow!("response has no taskUid"))?;
+    for _ in 0..600 {
+        if shutdown::requested() {
+            // The task keeps running in Meilisearch; the next reconcile picks up its result
+            return Err(shutdown::interrupted(&format!("task {}", uid)).into());
+        }
+        let task = http
+            .get(format!("{}/tasks/{}", endpoint, uid))
+            .bearer_auth(master_key)
+            .send_checked()
+            .await
+            .map_err(anyhow::Error::from)?
+            .json::<serde_json::Value>()
+            .await
+            .map_err(anyhow::Error::from)?;
+        match task["status"].as_str() {
+            Some("succeeded") => return Ok(()),
+            Some("failed") | Some("canceled") => {
+                return Err(anyhow::anyhow!(
+                    "task {} {}: {}",
+                    uid,
+                    task["status"].as_str().unwrap_or_default(),
+                    task["error"]["message"]
+                        .as_str()
+                        .unwrap_or("no error message")
+                )
+                .into());
+            }
+            _ => tokio::time::sleep(Duration::from_millis(500)).await,
+        }
+    }
+    Err(anyhow::anyhow!("timed out waiting for task {}", uid).into())
+}
+
+async fn delete_secret(client: &Client, ns: &str, name: &str) -> Result<(), ReconcileError> {
+    use k8s_openapi::api::core::v1::Secret;
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), ns);
+    match secrets.delete(name, &Default::default()).await {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(ae)) if ae.code == 404 => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Delete documents whose age field is past the cutoff, at most once per interval.
+async fn apply_retention(
+    audit: &Audit<'_>,
+    endpoint: &str,
+    master_key: &str,
+    uid: &str,
+    retention: &IndexRetentionSpec,
+    status: &mut IndexStatus,
+) -> Result<(), ReconcileError> {
+    let now = OffsetDateTime::now_utc();
+    if !retention_due(retention, status, now) {
+        return Ok(());
+    }
+    // The schema checks this too, but objects stored before it did are not revalidated
+    if !is_attribute_name(&retention.older_than_field) {
+        return Err(anyhow::anyhow!(
+            "retention.older_than_field {:?} is not a plain attribute name",
+            retention.older_than_field
+        )
+        .into());
+    }
+    let cutoff = now.unix_timestamp() - i64::from(retention.max_age_days) * 86_400;
+    let filter = retention_filter(retention, cutoff);
+    let client = meili_http::client();
+    let res = async {
+        client
+            .post(format!("{}/indexes/{}/documents/delete", endpoint, uid))
+            .bearer_auth(master_key)
+            .json(&serde_json::json!({ "filter": filter }))
+            .send_checked()
+            .await?
+            .json::<serde_json::Value>()
+            .await
+            .map_err(meili_http::Error::from)
+    }
+    .await;
+    audit
+        .record(
+            "documents.delete",
+            &format!("{} where {}", uid, filter),
+            &res,
+        )
+        .await;
+    let task = res.map_err(anyhow::Error::from)?;
+    status.last_retention_run = Some(now.format(&Rfc3339).map_err(anyhow::Error::from)?);
+    status.last_retention_task_uid = task["taskUid"].as_u64();
+    Ok(())
+}
+
+fn retention_due(
+    retention: &IndexRetentionSpec,
+    status: &IndexStatus,
+    now: OffsetDateTime,
+) -> bool {
+    status
+        .last_retention_run
+        .as_deref()
+        .and_then(|t| OffsetDateTime::parse(t, &Rfc3339).ok())
+        .is_none_or(|last| (now - last).whole_seconds() >= retention.interval_seconds)
+}
+
+/// Whether `field` can go into a filter unquoted: letters, digits, `_`, `-` and `.` for
+/// nested fields, not starting with `-` or `.`.
+fn is_attribute_name(field: &str) -> bool {
+    field
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        && field
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+fn retention_filter(retention: &IndexRetentionSpec, cutoff: i64) -> String {
+    let age = format!("{} < {}", retention.older_than_field, cutoff);
+    match retention.filter.as_deref() {
+        Some(extra) if !extra.trim().is_empty() => format!("({}) AND ({})", age, extra),
+        _ => age,
+    }
 }
 
 async fn server_is_deleting(client: &Client, ns: &str, name: &str) -> Result<bool, ReconcileError> {
-    use crate::crds::server::Server;
+    use crate::crds::server::{Server, ServerDeletionPolicy};
     let api: Api<Server> = Api::namespaced(client.clone(), ns);
     if let Some(srv) = api.get_opt(name).await? {
-        Ok(srv.metadata.deletion_timestamp.is_some())
+        // A blocked Server keeps running until its dependents have cleaned up
+        Ok(srv.metadata.deletion_timestamp.is_some()
+            && srv.spec.deletion_policy == ServerDeletionPolicy::Cascade)
     } else {
         // Treat missing as deleted/going away
         Ok(true)
     }
 }
 
-async fn get_master_key(client: &Client, ns: &str, server: &str) -> Result<String, ReconcileError> {
-    use k8s_openapi::api::core::v1::Secret;
-    let secrets: Api<Secret> = Api::namespaced(client.clone(), ns);
+pub(crate) async fn get_master_key(
+    secrets: &SecretCache,
+    ns: &str,
+    server: &str,
+) -> Result<String, ReconcileError> {
     let name = format!("{}-meili-master", server);
-    let sec = secrets.get(&name).await?;
-    let data = sec
-        .data
-        .ok_or_else(|| anyhow::anyhow!("secret data missing"))?;
-    let val = data
-        .get("masterKey")
-        .ok_or_else(|| anyhow::anyhow!("missing key"))?;
-    Ok(String::from_utf8(val.0.clone())?)
+    let sec = secrets
+        .get(ns, &name)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("master key Secret {}/{} not found", ns, name))?;
+    secret_template::read(&sec, "masterKey")
+        .ok_or_else(|| anyhow::anyhow!("master key Secret {}/{} has no key", ns, name).into())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn store_index_key_secret(
     client: &Client,
     owner_ns: &str,
-    owner_name: &str,
     target_ns: &str,
     name: &str,
     key: &str,
     idx: &Index,
+    immutable: bool,
 ) -> Result<(), ReconcileError> {
     use k8s_openapi::api::core::v1::Secret;
     let secrets: Api<Secret> = Api::namespaced(client.clone(), target_ns);
     let owner_ref = if owner_ns == target_ns {
-        Some(vec![
-            k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference {
-                api_version: "meili.operator.dev/v1alpha1".into(),
-                kind: "Index".into(),
-                name: owner_name.to_string(),
-                uid: idx.metadata.uid.clone().unwrap_or_default(),
-                controller: Some(true),
-                block_owner_deletion: Some(true),
-            },
-        ])
+        Some(vec![crds::owner_ref(idx)])
     } else {
         None
     };
-    let sec = Secret {
-        metadata: kube::core::ObjectMeta {
-            name: Some(name.to_string()),
-            owner_references: owner_ref,
-            ..Default::default()
-        },
-        string_data: Some(std::collections::BTreeMap::from([(
-            String::from("key"),
-            key.to_string(),
-        )])),
-        ..Default::default()
-    };
+    let template = idx
+        .spec
+        .admin_key
+        .as_ref()
+        .and_then(|a| a.secret_template.as_ref());
+    let mut sec = secret_template::build(name, template, "key", key, owner_ref, immutable);
+    sec.labels_mut().insert(
+        children::SERVER_LABEL.into(),
+        idx.spec.server_ref.name.clone(),
+    );
     let pp = kube::api::PostParams::default();
-    let _ = secrets.create(&pp, &sec).await.or_else(|e| match e {
-        kube::Error::Api(ae) if ae.code == 409 => Ok(Secret::default()),
-        _ => Err(e),
-    })?;
+    match secrets.create(&pp, &sec).await {
+        Ok(_) => {}
+        Err(kube::Error::Api(ae)) if ae.code == 409 => {
+            // Rewrite a Secret whose value was edited since it was written
+            if let Some(existing) = secrets.get_opt(name).await?
+                && !secret_template::intact(&existing, "key")
+            {
+                tracing::warn!(
+                    secret = name,
+                    namespace = target_ns,
+                    "admin key Secret failed its checksum, rewriting"
+                );
+                let _ = secrets.delete(name, &Default::default()).await?;
+                let _ = secrets.create(&pp, &sec).await?;
+            }
+        }
+        Err(e) => return Err(e.into()),
+    }
     Ok(())
 }
 
-// -------- Matching existing admin key via HTTP API --------
+// -------- Looking up the admin key via HTTP API --------
 
 #[derive(Debug, serde::Deserialize)]
 struct KeyItem {
-    name: Option<String>,
-    description: Option<String>,
     key: String,
-    #[allow(dead_code)]
     uid: String,
     actions: Vec<String>,
-    indexes: Vec<String>,
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct KeysPage {
-    results: Vec<KeyItem>,
-    #[allow(dead_code)]
-    offset: usize,
-    #[allow(dead_code)]
-    limit: usize,
-    total: usize,
+    #[serde(rename = "expiresAt")]
+    expires_at: Option<String>,
 }
 
-async fn list_all_keys_http(
+/// The Index's admin key: the one in `status.admin_key_uid`, else one registered to the Index
+/// on its Server (created before the uid was recorded, or by a rotation).
+async fn find_admin_key(
+    client: &Client,
+    idx: &Index,
     endpoint: &str,
     master_key: &str,
-) -> Result<Vec<KeyItem>, ReconcileError> {
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(5))
-        .build()
-        .map_err(anyhow::Error::from)?;
-    let mut out = Vec::new();
-    let mut offset = 0usize;
-    let limit = 1000usize;
-    loop {
-        let url = format!("{}/keys?offset={}&limit={}", endpoint, offset, limit);
-        let resp = client
-            .get(url)
-            .header(
-                reqwest::header::AUTHORIZATION,
-                format!("Bearer {}", master_key),
-            )
-            .send()
-            .await
-            .map_err(anyhow::Error::from)?
-            .error_for_status()
-            .map_err(anyhow::Error::from)?
-            .json::<KeysPage>()
-            .await
-            .map_err(anyhow::Error::from)?;
-        offset += resp.results.len();
-        out.extend(resp.results);
-        if offset >= resp.total {
-            break;
+) -> Result<Option<KeyItem>, ReconcileError> {
+    let ns = idx.namespace().unwrap_or_default();
+    let owner = key_registry::owner_id("Index", &ns, &idx.name_any());
+    let registry = key_registry::load(
+        client,
+        idx.spec.server_ref.namespace_or(&ns),
+        &idx.spec.server_ref.name,
+    )
+    .await?;
+    let recorded = idx.status.as_ref().and_then(|s| s.admin_key_uid.clone());
+    let registered = registry
+        .into_iter()
+        .filter(|(_, o)| *o == owner)
+        .map(|(uid, _)| uid);
+    for uid in recorded.into_iter().chain(registered) {
+        if let Some(key) = get_key_http(endpoint, master_key, &uid).await? {
+            return Ok(Some(key));
         }
     }
-    Ok(out)
-}
-
-fn eq_unordered<T: Eq + std::hash::Hash + Clone>(a: &[T], b: &[T]) -> bool {
-    use std::collections::HashSet;
-    let sa: HashSet<T> = a.iter().cloned().collect();
-    let sb: HashSet<T> = b.iter().cloned().collect();
-    sa == sb
-}
-
-fn matches_admin(index_uid: &str, item: &KeyItem) -> bool {
-    let expected_name = format!("{}-admin", index_uid);
-    let expected_desc = format!("Admin key for index {}", index_uid);
-    let actions_ok = item.actions.iter().any(|a| a == "*");
-    let indexes_ok = eq_unordered(&[index_uid.to_string()], &item.indexes);
-    let name_ok = item
-        .name
-        .as_ref()
-        .map(|s| s == &expected_name)
-        .unwrap_or(false);
-    let desc_ok = item
-        .description
-        .as_ref()
-        .map(|s| s == &expected_desc)
-        .unwrap_or(false);
-    actions_ok && indexes_ok && name_ok && desc_ok
+    Ok(None)
 }
 
-async fn find_matching_admin_key_http(
+/// `GET /keys/{uid}`; `None` when the server has no such key.
+async fn get_key_http(
     endpoint: &str,
     master_key: &str,
-    index_uid: &str,
+    uid: &str,
 ) -> Result<Option<KeyItem>, ReconcileError> {
-    let all = list_all_keys_http(endpoint, master_key).await?;
-    Ok(all.into_iter().find(|k| matches_admin(index_uid, k)))
+    let resp = meili_http::client()
+        .get(format!("{}/keys/{}", endpoint, uid))
+        .bearer_auth(master_key)
+        .send_meili()
+        .await
+        .map_err(anyhow::Error::from)?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(
+        meili_http::checked(resp)
+            .await
+            .map_err(anyhow::Error::from)?
+            .json()
+            .await
+            .map_err(anyhow::Error::from)?,
+    ))
 }
 
 async fn ensure_finalizer(
@@ -326,3 +2027,298 @@ async fn remove_finalizer(client: &Client, ns: &str, name: &str) -> Result<(), R
         .await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests_index_controller {
+    use super::*;
+    use crate::crds::index::{LocalizedAttributes, MinWordSizeForTypos, TypoTolerance};
+
+    #[test]
+    fn settings_patch_only_sends_drift() {
+        let desired = IndexSettings {
+            filterable_attributes: Some(vec!["genre".into(), "year".into()]),
+            ranking_rules: Some(vec!["words".into(), "typo".into()]),
+            ..Default::default()
+        };
+        let current = serde_json::json!({
+            "filterableAttributes": ["year", "genre"],
+            "rankingRules": ["typo", "words"],
+            "stopWords": ["the"],
+        });
+        let patch = settings_patch(&desired, &current, false);
+        assert_eq!(patch.keys().collect::<Vec<_>>(), vec!["rankingRules"]);
+        assert_eq!(settings_patch(&desired, &current, true).len(), 2);
+    }
+
+    #[test]
+    fn typo_tolerance_compares_only_set_fields() {
+        let desired = IndexSettings {
+            typo_tolerance: Some(TypoTolerance {
+                min_word_size_for_typos: Some(MinWordSizeForTypos {
+                    one_typo: Some(4),
+                    two_typos: None,
+                }),
+                disable_on_words: Some(vec!["sku".into(), "isbn".into()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut current = serde_json::json!({ "typoTolerance": {
+            "enabled": true,
+            "minWordSizeForTypos": { "oneTypo": 4, "twoTypos": 9 },
+            "disableOnWords": ["isbn", "sku"],
+            "disableOnAttributes": [],
+        }});
+        assert!(settings_patch(&desired, &current, false).is_empty());
+        current["typoTolerance"]["minWordSizeForTypos"]["oneTypo"] = 5.into();
+        assert_eq!(
+            settings_patch(&desired, &current, false)["typoTolerance"],
+            serde_json::json!({
+                "minWordSizeForTypos": { "oneTypo": 4 },
+                "disableOnWords": ["sku", "isbn"],
+            })
+        );
+    }
+
+    #[test]
+    fn tuning_settings_use_meilisearch_names() {
+        let desired = IndexSettings {
+            proximity_precision: Some(ProximityPrecision::ByAttribute),
+            search_cutoff_ms: Some(150),
+            prefix_search: Some(PrefixSearch::Disabled),
+            ..Default::default()
+        };
+        let current = serde_json::json!({
+            "proximityPrecision": "byWord",
+            "searchCutoffMs": 150,
+            "facetSearch": true,
+            "prefixSearch": "indexingTime",
+        });
+        let patch = settings_patch(&desired, &current, false);
+        assert_eq!(patch["proximityPrecision"], "byAttribute");
+        assert_eq!(patch["prefixSearch"], "disabled");
+        assert_eq!(patch.len(), 2);
+        let skipped = unsupported_settings(&desired, Some(&Version::new(1, 8, 0)));
+        assert_eq!(
+            skipped,
+            vec!["settings.prefixSearch", "settings.searchCutoffMs"]
+        );
+    }
+
+    #[test]
+    fn localized_attributes_use_meilisearch_names() {
+        let desired = IndexSettings {
+            localized_attributes: Some(vec![LocalizedAttributes {
+                attribute_patterns: vec!["*_ja".into()],
+                locales: vec!["jpn".into()],
+            }]),
+            non_separator_tokens: Some(vec!["@".into(), "#".into()]),
+            ..Default::default()
+        };
+        let current = serde_json::json!({
+            "localizedAttributes": null,
+            "nonSeparatorTokens": ["#", "@"],
+        });
+        let patch = settings_patch(&desired, &current, false);
+        assert_eq!(
+            patch["localizedAttributes"],
+            serde_json::json!([{ "attributePatterns": ["*_ja"], "locales": ["jpn"] }])
+        );
+        assert_eq!(patch.len(), 1);
+    }
+
+    #[test]
+    fn detects_ranking_rules_drift() {
+        let settings = IndexSettings {
+            ranking_rules: Some(vec!["words".into(), "typo".into()]),
+            ..Default::default()
+        };
+        let live = serde_json::json!({ "rankingRules": ["words", "typo"] });
+        assert_eq!(ranking_rules_drift(&settings, &live), None);
+        let changed = serde_json::json!({ "rankingRules": ["typo", "words"] });
+        assert_eq!(
+            ranking_rules_drift(&settings, &changed).as_deref(),
+            Some("live ranking rules [typo, words] differ from spec [words, typo]")
+        );
+        assert_eq!(
+            ranking_rules_drift(&IndexSettings::default(), &changed),
+            None
+        );
+    }
+
+    #[test]
+    fn admin_key_replaced_when_scope_changes() {
+        let ak: IndexAdminKeySpec = serde_json::from_value(serde_json::json!({
+            "create": true,
+            "actions": ["documents.add", "settings.update"],
+            "expires_at": "2030-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        let mut item = KeyItem {
+            key: "k".into(),
+            uid: "u".into(),
+            actions: vec!["settings.update".into(), "documents.add".into()],
+            expires_at: Some("2030-01-01T00:00:00.000Z".into()),
+        };
+        assert!(admin_key_current(&item, &ak));
+        item.actions = vec!["*".into()];
+        assert!(!admin_key_current(&item, &ak));
+        let default: IndexAdminKeySpec =
+            serde_json::from_value(serde_json::json!({ "create": true })).unwrap();
+        item.expires_at = None;
+        assert!(admin_key_current(&item, &default));
+    }
+
+    #[test]
+    fn task_state_carries_meilisearch_error() {
+        let failed = serde_json::json!({"status": "failed", "error": {
+            "message": "`my index` is not a valid index uid.",
+            "code": "invalid_index_uid",
+            "type": "invalid_request",
+            "link": "https://docs.meilisearch.com/errors#invalid_index_uid"
+        }});
+        match task_state_of(&failed, 7) {
+            TaskState::Failed(api) => assert_eq!(api.reason(), "InvalidIndexUid"),
+            _ => panic!("expected failure"),
+        }
+        let running = serde_json::json!({"status": "processing"});
+        assert!(matches!(task_state_of(&running, 7), TaskState::Pending));
+        let done = serde_json::json!({"status": "succeeded"});
+        assert!(matches!(task_state_of(&done, 7), TaskState::Succeeded));
+    }
+
+    #[test]
+    fn index_keys_get_distinct_key_crs() {
+        assert_eq!(
+            key_cr_name("shop", "shop", "products", "search"),
+            "products-search"
+        );
+        assert_eq!(
+            key_cr_name("shop", "meili", "products", "search"),
+            "shop-products-search"
+        );
+        let key = |name: &str| IndexKeySpec {
+            name: name.into(),
+            actions: vec!["search".into()],
+            expires_at: None,
+            secret_namespace: None,
+            secret_name: None,
+            secret_template: None,
+        };
+        assert_eq!(duplicate_key_name(&[key("search"), key("ingest")]), None);
+        assert_eq!(
+            duplicate_key_name(&[key("search"), key("ingest"), key("search")]),
+            Some("search")
+        );
+    }
+
+    #[test]
+    fn index_settings_override_template() {
+        let template = IndexSettings {
+            ranking_rules: Some(vec!["words".into(), "typo".into()]),
+            stop_words: Some(vec!["the".into()]),
+            ..Default::default()
+        };
+        let own = IndexSettings {
+            stop_words: Some(vec![]),
+            sortable_attributes: Some(vec!["year".into()]),
+            ..Default::default()
+        };
+        let merged = own.over(&template);
+        assert_eq!(merged.ranking_rules, template.ranking_rules);
+        assert_eq!(merged.stop_words, Some(vec![]));
+        assert_eq!(merged.sortable_attributes, Some(vec!["year".into()]));
+    }
+
+    #[test]
+    fn degrades_below_min_documents() {
+        assert_eq!(documents_below_minimum(None, 0), None);
+        assert_eq!(documents_below_minimum(Some(100), 100), None);
+        assert_eq!(
+            documents_below_minimum(Some(100), 12).as_deref(),
+            Some("index holds 12 documents, below min_documents 100")
+        );
+    }
+
+    #[test]
+    fn primary_key_conflicts_only_when_both_set_and_different() {
+        assert!(primary_key_conflict(Some("id"), Some("sku")).is_some());
+        assert!(primary_key_conflict(Some("id"), Some("id")).is_none());
+        assert!(primary_key_conflict(None, Some("sku")).is_none());
+        assert!(primary_key_conflict(Some("id"), None).is_none());
+    }
+
+    #[test]
+    fn retention_filter_combines_age_and_extra_filter() {
+        let mut retention = IndexRetentionSpec {
+            older_than_field: "created_at".into(),
+            max_age_days: 30,
+            filter: None,
+            interval_seconds: 3600,
+        };
+        assert_eq!(retention_filter(&retention, 1000), "created_at < 1000");
+        retention.filter = Some("kind = 'log'".into());
+        assert_eq!(
+            retention_filter(&retention, 1000),
+            "(created_at < 1000) AND (kind = 'log')"
+        );
+        assert!(is_attribute_name("meta.created_at"));
+        assert!(!is_attribute_name("x < 0 OR id"));
+        assert!(!is_attribute_name(""));
+        assert!(!is_attribute_name(".hidden"));
+    }
+
+    #[test]
+    fn frozen_keys_keep_only_read_actions() {
+        let actions = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            read_only_actions(&actions(&["search", "documents.add", "documents.get"])),
+            actions(&["search", "documents.get"])
+        );
+        assert_eq!(
+            read_only_actions(&actions(&["documents.*", "settings.update"])),
+            actions(&["documents.get"])
+        );
+        assert!(read_only_actions(&actions(&["*"])).contains(&"search".to_string()));
+        assert_eq!(
+            read_only_actions(&actions(&["documents.add", "documents.delete"])),
+            actions(&["version"])
+        );
+    }
+
+    #[test]
+    fn frozen_index_reports_other_writable_keys() {
+        let spec: IndexSpec = serde_json::from_value(serde_json::json!({
+            "server_ref": { "name": "meili" },
+            "uid": "movies",
+            "keys": [{ "name": "writer", "actions": ["documents.add"] }],
+        }))
+        .unwrap();
+        let key = |name: &str, actions: &[&str], indexes: &[&str]| {
+            serde_json::from_value::<key_controller::KeyItem>(serde_json::json!({
+                "name": name,
+                "description": null,
+                "key": "k",
+                "uid": format!("{}-uid", name),
+                "actions": actions,
+                "indexes": indexes,
+                "expiresAt": null,
+                "createdAt": null,
+            }))
+            .unwrap()
+        };
+        let keys = [
+            key("movies-admin", &["*"], &["movies"]),
+            key("movies-writer", &["documents.add"], &["movies"]),
+            key("ingest", &["documents.add"], &["movies"]),
+            key("all", &["settings.*"], &["*"]),
+            key("prefixed", &["documents.delete"], &["mov*"]),
+            key("search", &["search"], &["*"]),
+            key("books", &["documents.add"], &["books"]),
+        ];
+        assert_eq!(
+            writable_keys(&keys, &spec),
+            vec!["ingest", "all", "prefixed"]
+        );
+    }
+}
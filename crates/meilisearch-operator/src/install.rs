@@ -0,0 +1,441 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::{
+    admissionregistration::v1::{
+        RuleWithOperations, ServiceReference, ValidatingWebhook, ValidatingWebhookConfiguration,
+        WebhookClientConfig,
+    },
+    apps::v1::{Deployment, DeploymentSpec},
+    core::v1::{
+        Container, ContainerPort, EnvVar, EnvVarSource, Namespace, ObjectFieldSelector, PodSpec,
+        PodTemplateSpec, ResourceRequirements, SecretVolumeSource, Service, ServiceAccount,
+        ServicePort, ServiceSpec, Volume, VolumeMount,
+    },
+    rbac::v1::{ClusterRole, ClusterRoleBinding, PolicyRule, RoleRef, Subject},
+};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use k8s_openapi::apimachinery::pkg::{
+    api::resource::Quantity, apis::meta::v1::LabelSelector, util::intstr::IntOrString,
+};
+use kube::{CustomResourceExt, ResourceExt, core::ObjectMeta};
+use serde_json::{Value, json};
+
+use crate::{
+    admission,
+    config::OperatorConfig,
+    crds::{self, index},
+    meili_http::Limits,
+    webhook,
+};
+
+const NAME: &str = "meilisearch-operator";
+const WEBHOOK_PORT: i32 = 9443;
+const WEBHOOK_TLS_SECRET: &str = "meilisearch-operator-webhook-tls";
+
+/// What the install bundle is rendered for.
+#[derive(Clone, Debug)]
+pub struct InstallOptions {
+    pub namespace: String,
+    /// Operator image; also run by SearchGateway Deployments unless `config` says otherwise
+    pub image: String,
+    /// Conversion and `server_ref` validation webhooks, their Service and the cert-manager
+    /// Issuer and Certificate
+    pub webhook: bool,
+    /// Written into the Deployment as the environment `OperatorConfig::from_env` reads
+    pub config: OperatorConfig,
+    pub http: Limits,
+}
+
+/// CRDs, namespace, RBAC, webhook objects and the operator Deployment, in apply order.
+pub fn bundle(opts: &InstallOptions) -> Vec<Value> {
+    // cert-manager injects the CA of the Certificate in `webhook_objects`
+    let conversion = opts.webhook.then(|| index::ConversionWebhook {
+        namespace: opts.namespace.clone(),
+        ca_bundle: None,
+    });
+    let mut docs: Vec<Value> = crds(conversion.as_ref()).iter().map(to_value).collect();
+    docs.push(to_value(&Namespace {
+        metadata: meta(&opts.namespace, None),
+        ..Default::default()
+    }));
+    docs.push(to_value(&ServiceAccount {
+        metadata: meta(NAME, Some(&opts.namespace)),
+        ..Default::default()
+    }));
+    docs.push(to_value(&cluster_role(opts.config.install_crds)));
+    docs.push(to_value(&ClusterRoleBinding {
+        metadata: meta(NAME, None),
+        role_ref: RoleRef {
+            api_group: "rbac.authorization.k8s.io".into(),
+            kind: "ClusterRole".into(),
+            name: NAME.into(),
+        },
+        subjects: Some(vec![Subject {
+            kind: "ServiceAccount".into(),
+            name: NAME.into(),
+            namespace: Some(opts.namespace.clone()),
+            ..Default::default()
+        }]),
+    }));
+    if opts.webhook {
+        docs.extend(webhook_objects(&opts.namespace));
+    }
+    docs.push(to_value(&deployment(opts)));
+    docs
+}
+
+/// Every CRD; with `conversion`, the Index CRD is converted by that webhook.
+pub fn crds(conversion: Option<&index::ConversionWebhook>) -> Vec<CustomResourceDefinition> {
+    crds::all()
+        .into_iter()
+        .map(|crd| match conversion {
+            Some(webhook) if crd.name_any() == index::Index::crd_name() => {
+                index::with_conversion(crd, webhook)
+            }
+            _ => crd,
+        })
+        .collect()
+}
+
+/// `docs` as one multi-document YAML stream.
+pub fn render(docs: &[Value]) -> String {
+    docs.iter()
+        .map(|d| serde_yaml::to_string(d).expect("serialize manifest"))
+        .collect::<Vec<_>>()
+        .join("---\n")
+}
+
+fn to_value<T: serde::Serialize>(obj: &T) -> Value {
+    serde_json::to_value(obj).expect("serialize manifest")
+}
+
+fn meta(name: &str, namespace: Option<&str>) -> ObjectMeta {
+    ObjectMeta {
+        name: Some(name.into()),
+        namespace: namespace.map(String::from),
+        ..Default::default()
+    }
+}
+
+fn rule(groups: &[&str], resources: Vec<String>, verbs: &[&str]) -> PolicyRule {
+    PolicyRule {
+        api_groups: Some(groups.iter().map(|g| g.to_string()).collect()),
+        resources: Some(resources),
+        verbs: verbs.iter().map(|v| v.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+fn cluster_role(install_crds: bool) -> ClusterRole {
+    let all = [
+        "get", "list", "watch", "create", "update", "patch", "delete",
+    ];
+    let names = |list: &[&str]| list.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+    // Every CRD the operator serves, with its status subresource
+    let custom = crds::all()
+        .into_iter()
+        .flat_map(|crd| {
+            let plural = crd.spec.names.plural;
+            [format!("{}/status", plural), plural]
+        })
+        .collect();
+    ClusterRole {
+        metadata: meta(NAME, None),
+        rules: Some(vec![
+            rule(
+                &[""],
+                names(&[
+                    "pods",
+                    "services",
+                    "endpoints",
+                    "events",
+                    "secrets",
+                    "configmaps",
+                    "persistentvolumeclaims",
+                ]),
+                &all,
+            ),
+            rule(&["events.k8s.io"], names(&["events"]), &["create", "patch"]),
+            rule(
+                &["gateway.networking.k8s.io"],
+                names(&["httproutes"]),
+                &["get", "patch"],
+            ),
+            rule(
+                &["external-secrets.io"],
+                names(&["pushsecrets"]),
+                &["get", "create", "patch", "delete"],
+            ),
+            rule(&["apps"], names(&["statefulsets", "deployments"]), &all),
+            rule(&[crds::GROUP], custom, &all),
+            rule(&["batch"], names(&["jobs"]), &all),
+            rule(
+                &["apiextensions.k8s.io"],
+                names(&["customresourcedefinitions"]),
+                if install_crds {
+                    &["get", "list", "watch", "create", "patch"]
+                } else {
+                    &["get", "list", "watch"]
+                },
+            ),
+        ]),
+        ..Default::default()
+    }
+}
+
+fn webhook_objects(ns: &str) -> Vec<Value> {
+    let service = Service {
+        metadata: meta(index::CONVERSION_SERVICE, Some(ns)),
+        spec: Some(ServiceSpec {
+            selector: Some(BTreeMap::from([("app".into(), NAME.into())])),
+            ports: Some(vec![ServicePort {
+                name: Some("webhook".into()),
+                port: 443,
+                target_port: Some(IntOrString::String("webhook".into())),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let issuer = format!("{}-selfsigned", NAME);
+    let validation = ValidatingWebhookConfiguration {
+        metadata: ObjectMeta {
+            name: Some(format!("{}-server-ref", NAME)),
+            annotations: Some(BTreeMap::from([(
+                "cert-manager.io/inject-ca-from".to_string(),
+                format!("{}/{}", ns, index::CONVERSION_SERVICE),
+            )])),
+            ..Default::default()
+        },
+        webhooks: Some(vec![ValidatingWebhook {
+            name: format!("server-ref.{}", crds::GROUP),
+            admission_review_versions: vec!["v1".into()],
+            side_effects: "None".into(),
+            // An unreachable operator does not block writes; the controllers still report it
+            failure_policy: Some("Ignore".into()),
+            client_config: WebhookClientConfig {
+                service: Some(ServiceReference {
+                    name: index::CONVERSION_SERVICE.into(),
+                    namespace: ns.into(),
+                    path: Some("/validate".into()),
+                    port: Some(443),
+                }),
+                ..Default::default()
+            },
+            rules: Some(vec![RuleWithOperations {
+                api_groups: Some(vec![crds::GROUP.into()]),
+                api_versions: Some(vec!["*".into()]),
+                operations: Some(vec!["CREATE".into(), "UPDATE".into()]),
+                resources: Some(
+                    admission::VALIDATED_RESOURCES
+                        .iter()
+                        .map(|r| r.to_string())
+                        .collect(),
+                ),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }]),
+    };
+    vec![
+        to_value(&service),
+        json!({
+            "apiVersion": "cert-manager.io/v1",
+            "kind": "Issuer",
+            "metadata": {"name": issuer, "namespace": ns},
+            "spec": {"selfSigned": {}},
+        }),
+        json!({
+            "apiVersion": "cert-manager.io/v1",
+            "kind": "Certificate",
+            "metadata": {"name": index::CONVERSION_SERVICE, "namespace": ns},
+            "spec": {
+                "secretName": WEBHOOK_TLS_SECRET,
+                "dnsNames": [
+                    format!("{}.{}.svc", index::CONVERSION_SERVICE, ns),
+                    format!("{}.{}.svc.cluster.local", index::CONVERSION_SERVICE, ns),
+                ],
+                "issuerRef": {"kind": "Issuer", "name": issuer},
+            },
+        }),
+        to_value(&validation),
+    ]
+}
+
+fn deployment(opts: &InstallOptions) -> Deployment {
+    let labels = BTreeMap::from([("app".to_string(), NAME.to_string())]);
+    let field = |path: &str| EnvVarSource {
+        field_ref: Some(ObjectFieldSelector {
+            field_path: path.into(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut env = vec![
+        EnvVar {
+            name: "RUST_LOG".into(),
+            value: Some("info".into()),
+            ..Default::default()
+        },
+        EnvVar {
+            name: "OPERATOR_NAMESPACE".into(),
+            value_from: Some(field("metadata.namespace")),
+            ..Default::default()
+        },
+        EnvVar {
+            name: "POD_NAME".into(),
+            value_from: Some(field("metadata.name")),
+            ..Default::default()
+        },
+    ];
+    let mut settings = opts.config.env();
+    settings.extend(opts.http.env());
+    if opts.webhook {
+        settings.push(("WEBHOOK_PORT", WEBHOOK_PORT.to_string()));
+    }
+    env.extend(settings.into_iter().map(|(name, value)| EnvVar {
+        name: name.into(),
+        value: Some(value),
+        ..Default::default()
+    }));
+    let quantities = |cpu: &str, memory: &str| {
+        BTreeMap::from([
+            ("cpu".to_string(), Quantity(cpu.into())),
+            ("memory".to_string(), Quantity(memory.into())),
+        ])
+    };
+    let container = Container {
+        name: "operator".into(),
+        image: Some(opts.image.clone()),
+        image_pull_policy: Some("IfNotPresent".into()),
+        env: Some(env),
+        ports: opts.webhook.then(|| {
+            vec![ContainerPort {
+                name: Some("webhook".into()),
+                container_port: WEBHOOK_PORT,
+                ..Default::default()
+            }]
+        }),
+        volume_mounts: opts.webhook.then(|| {
+            vec![VolumeMount {
+                name: "webhook-certs".into(),
+                mount_path: webhook::DEFAULT_CERT_DIR.into(),
+                read_only: Some(true),
+                ..Default::default()
+            }]
+        }),
+        resources: Some(ResourceRequirements {
+            requests: Some(quantities("100m", "128Mi")),
+            limits: Some(quantities("500m", "512Mi")),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    Deployment {
+        metadata: meta(NAME, Some(&opts.namespace)),
+        spec: Some(DeploymentSpec {
+            replicas: Some(1),
+            selector: LabelSelector {
+                match_labels: Some(labels.clone()),
+                ..Default::default()
+            },
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(labels),
+                    ..Default::default()
+                }),
+                spec: Some(PodSpec {
+                    service_account_name: Some(NAME.into()),
+                    termination_grace_period_seconds: Some(30),
+                    containers: vec![container],
+                    volumes: opts.webhook.then(|| {
+                        vec![Volume {
+                            name: "webhook-certs".into(),
+                            secret: Some(SecretVolumeSource {
+                                secret_name: Some(WEBHOOK_TLS_SECRET.into()),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        }]
+                    }),
+                    ..Default::default()
+                }),
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundle_follows_namespace_and_config() {
+        let opts = InstallOptions {
+            namespace: "search-system".into(),
+            image: "example/operator:1.0".into(),
+            webhook: true,
+            config: OperatorConfig::default(),
+            http: Limits::default(),
+        };
+        let docs = bundle(&opts);
+        let kind = |k: &str| docs.iter().find(|d| d["kind"] == k).unwrap();
+
+        let index_crd = docs
+            .iter()
+            .find(|d| d["metadata"]["name"] == "indexes.meili.operator.dev")
+            .unwrap();
+        assert_eq!(
+            index_crd["spec"]["conversion"]["webhook"]["clientConfig"]["service"]["namespace"],
+            "search-system"
+        );
+        assert_eq!(
+            index_crd["metadata"]["annotations"]["cert-manager.io/inject-ca-from"],
+            "search-system/meilisearch-operator-webhook"
+        );
+        let resources = &kind("ClusterRole")["rules"][5]["resources"];
+        assert!(
+            resources
+                .as_array()
+                .unwrap()
+                .contains(&json!("tenants/status"))
+        );
+        let env = &kind("Deployment")["spec"]["template"]["spec"]["containers"][0]["env"];
+        assert!(
+            env.as_array()
+                .unwrap()
+                .contains(&json!({"name": "RECONCILE_TIMEOUT_SECONDS", "value": "60"}))
+        );
+        assert_eq!(
+            kind("Certificate")["metadata"]["namespace"],
+            "search-system"
+        );
+        assert_eq!(
+            kind("ValidatingWebhookConfiguration")["webhooks"][0]["clientConfig"]["service"]["namespace"],
+            "search-system"
+        );
+        assert_eq!(render(&docs).matches("\n---\n").count(), docs.len() - 1);
+    }
+
+    #[test]
+    fn bundle_without_webhook_leaves_out_conversion() {
+        let opts = InstallOptions {
+            namespace: "search-system".into(),
+            image: "example/operator:1.0".into(),
+            webhook: false,
+            config: OperatorConfig::default(),
+            http: Limits::default(),
+        };
+        let docs = bundle(&opts);
+        let index_crd = docs
+            .iter()
+            .find(|d| d["metadata"]["name"] == "indexes.meili.operator.dev")
+            .unwrap();
+        assert!(index_crd["spec"]["conversion"].is_null());
+        assert!(index_crd["metadata"]["annotations"].is_null());
+        assert!(!docs.iter().any(|d| d["kind"] == "Service"));
+    }
+}
@@ -1,41 +1,188 @@
+use k8s_openapi::api::core::v1::Secret;
 use kube::{
-    Api, Client, ResourceExt,
-    runtime::controller::{Action, Controller},
-};
-use meilisearch_sdk::{
-    client::Client as MeiliClient,
-    key::{Action as MeiliAction, KeyBuilder},
+    Api, Client, Resource, ResourceExt,
+    runtime::{
+        WatchStreamExt,
+        controller::{Action, Controller},
+        events::{EventType, Recorder},
+        metadata_watcher,
+        reflector::ObjectRef,
+        watcher,
+    },
 };
+use meilisearch_sdk::key::{Action as MeiliAction, KeyBuilder};
+use semver::Version;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
-use time::OffsetDateTime;
-use tokio::time::Duration;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use tokio::time::{Duration, Instant};
 use tracing::error;
 
 use crate::{
-    crds::key::{Key, KeyStatus},
+    audit::Audit,
+    capabilities,
+    children::{OWNER_LABEL, SERVER_LABEL, ensure_server_label},
+    clusters,
+    conditions::{self, DEGRADED, TIMED_OUT, WAITING_FOR_SERVER},
+    config::OperatorConfig,
+    crds::{
+        FINALIZER,
+        common::{Phase, SecretStoreKind, SecretTemplateSpec},
+        key::{Key, KeyStatus},
+    },
+    dry_run,
     error::ReconcileError,
+    events, key_registry,
+    meili_http::{self, MeiliSend},
+    phase, resync,
+    secret_cache::SecretCache,
+    secret_store, secret_template, status,
+    triggers::{self, Trigger},
+    watch,
 };
 
 #[derive(Clone)]
 pub struct Ctx {
     pub client: Client,
+    pub secrets: SecretCache,
+    pub recorder: Recorder,
+    pub config: Arc<OperatorConfig>,
+    pub key_lists: KeyListCache,
 }
 
-pub fn controller(client: Client) -> Controller<Key> {
-    let api: Api<Key> = Api::all(client.clone());
-    Controller::new(api, Default::default()).shutdown_on_signal()
+pub fn controller(client: Client, config: &OperatorConfig) -> Controller<Key> {
+    let controller = watch::controller(Api::<Key>::all(client.clone()), config);
+    let controller = watch::follow_servers(controller, client.clone(), |key| {
+        (
+            key.namespace().unwrap_or_default(),
+            key.spec.server_ref.clone(),
+        )
+    });
+    // Recreate a key's Secret within seconds of it being deleted, in any namespace
+    let keys = controller.store();
+    let secrets = metadata_watcher(
+        Api::<Secret>::all(client),
+        watcher::Config::default().labels(OWNER_LABEL),
+    )
+    .default_backoff()
+    .touched_objects();
+    controller.watches_stream(secrets, move |secret| {
+        let owner = secret.labels().get(OWNER_LABEL).cloned();
+        keys.state()
+            .into_iter()
+            .filter(|key| key.metadata.uid.is_some() && key.metadata.uid == owner)
+            .map(|key| ObjectRef::from_obj(key.as_ref()))
+            .collect::<Vec<_>>()
+    })
 }
 
-const FINALIZER: &str = "meili.operator.dev/finalizer";
+const RESYNC: Duration = Duration::from_secs(1200);
+/// Fallback while waiting for the Server; its watch normally requeues first.
+const SERVER_WAIT: Duration = Duration::from_secs(120);
 
 pub async fn reconcile(key: Arc<Key>, ctx: Arc<Ctx>) -> Result<Action, ReconcileError> {
+    let res = watch::bounded(
+        ctx.config.reconcile_timeout,
+        reconcile_key(key.clone(), ctx.clone()),
+    )
+    .await;
+    // Show what Meilisearch objected to, e.g. invalid actions, instead of leaving it to the
+    // operator log
+    if let Err(e) = &res
+        && let Some(api) = e.meili_api()
+        && key.metadata.deletion_timestamp.is_none()
+    {
+        degrade(&key, &ctx, &api.reason(), api.to_string()).await?;
+    }
+    if let Err(e @ ReconcileError::TimedOut(_)) = &res {
+        let mut status = key.status.clone().unwrap_or_default();
+        conditions::set_condition(
+            &mut status.conditions,
+            TIMED_OUT,
+            true,
+            "ReconcileTimedOut",
+            e.to_string(),
+            key.metadata.generation,
+        );
+        let api: Api<Key> = Api::namespaced(ctx.client.clone(), &key.namespace().unwrap());
+        if let Some(cond) = status.conditions.iter().find(|c| c.type_ == TIMED_OUT) {
+            status::apply_condition(&api, &key.name_any(), status::TIMEOUT_MANAGER, cond).await?;
+        }
+    }
+    res
+}
+
+async fn reconcile_key(key: Arc<Key>, ctx: Arc<Ctx>) -> Result<Action, ReconcileError> {
+    if dry_run::enabled(&ctx.config, key.as_ref()) {
+        return dry_run_key(&key, &ctx).await;
+    }
+
+    // Nothing changed since the last full reconcile and the Secret is still there; wait for
+    // the periodic resync
+    if key.metadata.deletion_timestamp.is_none()
+        && triggers::requested(key.as_ref()).is_none()
+        && let Some(prev) = key.status.as_ref()
+        && prev.ready
+        && prev.dry_run_plan.is_none()
+        && output_present(&ctx.secrets, &key).await?
+        && let Some(wait) = resync::remaining(
+            key.metadata.generation,
+            prev.observed_generation,
+            prev.last_synced.as_deref(),
+            RESYNC,
+        )
+    {
+        return Ok(Action::requeue(wait));
+    }
+
     let ns = key.namespace().unwrap();
     let name = key.name_any();
     let server = &key.spec.server_ref;
-    let endpoint = format!("http://{}.{}.svc.cluster.local:7700", server, ns);
-    let master_key = get_master_key(&ctx.client, &ns, server).await?;
-    let client = MeiliClient::new(&endpoint, Some(&master_key))?;
+    if key.metadata.deletion_timestamp.is_none() {
+        if let Some(msg) = clusters::missing_server(&ctx.client, &ns, server).await? {
+            degrade(&key, &ctx, "ServerNotFound", msg).await?;
+            return Ok(Action::requeue(SERVER_WAIT));
+        }
+        if let Some(msg) = clusters::server_not_ready(&ctx.client, &ns, server).await? {
+            return wait_for_server(&key, &ctx, msg).await;
+        }
+    }
+    let endpoint = clusters::server_endpoint(&ctx.client, &ctx.config, &ns, server).await?;
+    let master_key = get_master_key(&ctx.secrets, &ns, server).await?;
+    let client = meili_http::sdk(&endpoint, &master_key);
     let mut status_message: Option<String> = None;
+    let mut prev = KeyStatus {
+        phase: Phase::Ready,
+        dry_run_plan: None,
+        observed_generation: key.metadata.generation,
+        last_synced: Some(resync::now()),
+        secret_ref: Some(secret_ref(&key)),
+        ..key.status.clone().unwrap_or_default()
+    };
+    conditions::set_condition(
+        &mut prev.conditions,
+        WAITING_FOR_SERVER,
+        false,
+        "ServerReady",
+        "",
+        key.metadata.generation,
+    );
+    conditions::set_condition(
+        &mut prev.conditions,
+        DEGRADED,
+        false,
+        "AsExpected",
+        "",
+        key.metadata.generation,
+    );
+    conditions::set_condition(
+        &mut prev.conditions,
+        TIMED_OUT,
+        false,
+        "Completed",
+        "",
+        key.metadata.generation,
+    );
 
     // Finalizer deletion path
     if key.metadata.deletion_timestamp.is_some() {
@@ -43,142 +190,357 @@ pub async fn reconcile(key: Arc<Key>, ctx: Arc<Ctx>) -> Result<Action, Reconcile
         if !server_is_deleting(&ctx.client, &ns, server).await?
             && let Some(uid) = key.status.as_ref().and_then(|s| s.uid.as_ref())
         {
-            client.delete_key(uid).await?;
+            phase::set(
+                &Api::<Key>::namespaced(ctx.client.clone(), &ns),
+                &name,
+                key.status.as_ref().map(|s| s.phase),
+                Phase::Terminating,
+            )
+            .await?;
+            let res = client.delete_key(uid).await;
+            Audit::new(&ctx.recorder, key.as_ref(), server)
+                .record("keys.delete", uid, &res)
+                .await;
+            res?;
+            ctx.key_lists.invalidate(&endpoint).await;
+            key_registry::forget(&ctx.client, &ns, server, uid).await?;
+        }
+        if key.spec.secret_namespace != ns && !key.spec.retain_secret {
+            delete_foreign_secret(&ctx.client, &key).await?;
         }
         remove_finalizer(&ctx.client, &ns, &name).await?;
         return Ok(Action::await_change());
     }
 
     ensure_finalizer(&ctx.client, &ns, &name, &key).await?;
+    ensure_server_label(
+        &Api::<Key>::namespaced(ctx.client.clone(), &ns),
+        key.as_ref(),
+        server,
+    )
+    .await?;
+
+    // One-shot actions requested via annotation
+    if let Some(requested) = triggers::requested(key.as_ref()) {
+        let mut status = key.status.clone().unwrap_or_default();
+        let (action, result) = match requested {
+            Ok(t) => (
+                t.as_str().to_string(),
+                run_trigger(
+                    t,
+                    &ctx,
+                    &Audit::new(&ctx.recorder, key.as_ref(), server),
+                    &key,
+                    &endpoint,
+                    &master_key,
+                    &mut status,
+                )
+                .await,
+            ),
+            Err(other) => (
+                other.clone(),
+                Err(anyhow::anyhow!("unknown trigger {:?}", other).into()),
+            ),
+        };
+        status.last_trigger = Some(triggers::outcome(&action, &result));
+        let api: Api<Key> = Api::namespaced(ctx.client.clone(), &ns);
+        let cleared = triggers::clear(&api, &name).await?;
+        status::apply(&api, &cleared, &status).await?;
+        return Ok(Action::requeue(RESYNC));
+    }
+
+    // Keys the operator created are recorded per Server; adoption skips other CRs' keys
+    let owner = key_registry::owner_id("Key", &ns, &name);
+    let registry = key_registry::load(&ctx.client, &ns, server).await?;
+    let owned_uid = |uid: &str| {
+        (registry.get(uid).map(String::as_str) == Some(owner.as_str())).then(|| uid.to_string())
+    };
 
     // Prefer adopting an existing Secret's key if present and valid
-    if let Some(secret_key) = existing_secret_key(&ctx.client, &key).await?
-        && key_exists_by_value_http(&endpoint, &master_key, &secret_key).await?
+    if let Some(secret_key) = existing_secret_key(&ctx.secrets, &key).await?
+        && let Some(existing) =
+            find_key_by_value_http(&ctx.key_lists, &endpoint, &master_key, &secret_key).await?
+        && matches_spec_relaxed(&existing, &key)
     {
-        store_key_secret(
-            &ctx.client,
-            &ns,
-            &name,
-            &key.spec.secret_namespace,
-            &key.spec.secret_name,
-            &secret_key,
-        )
-        .await?;
+        store_key_output(&ctx, &key, &secret_key).await?;
         let status = KeyStatus {
-            uid: None,
+            uid: owned_uid(&existing.uid),
+            key_name: existing.name.clone(),
+            created_at: existing.created_at.clone(),
+            expires_at: existing.expires_at.clone(),
             ready: true,
             message: Some("using key from existing Secret".into()),
+            ..prev.clone()
         };
-        let pp = kube::api::PatchParams::apply("meilisearch-operator");
         let api: Api<Key> = Api::namespaced(ctx.client.clone(), &ns);
-        let _ = api
-            .patch_status(
-                &name,
-                &pp,
-                &kube::api::Patch::Merge(serde_json::json!({"status": status })),
-            )
-            .await?;
-        return Ok(Action::requeue(Duration::from_secs(1200)));
+        status::apply(&api, key.as_ref(), &status).await?;
+        return Ok(Action::requeue(RESYNC));
     }
 
     // Try to find an existing key that matches our spec to avoid duplicates (exact, then relaxed)
-    if let Some(existing) = find_matching_key_http(&endpoint, &master_key, &key).await? {
+    if let Some(existing) = find_matching_key_http(
+        &ctx.key_lists,
+        &endpoint,
+        &master_key,
+        &key,
+        &registry,
+        &owner,
+    )
+    .await?
+    {
         // Adopt existing exact match
-        store_key_secret(
-            &ctx.client,
-            &ns,
-            &name,
-            &key.spec.secret_namespace,
-            &key.spec.secret_name,
-            &existing.key,
-        )
-        .await?;
+        store_key_output(&ctx, &key, &existing.key).await?;
         status_message = Some("adopted existing key".into());
         let status = KeyStatus {
-            uid: None,
+            uid: owned_uid(&existing.uid),
+            key_name: existing.name.clone(),
+            created_at: existing.created_at.clone(),
+            expires_at: existing.expires_at.clone(),
             ready: true,
             message: status_message.clone(),
+            ..prev.clone()
         };
-        let pp = kube::api::PatchParams::apply("meilisearch-operator");
         let api: Api<Key> = Api::namespaced(ctx.client.clone(), &ns);
-        let _ = api
-            .patch_status(
-                &name,
-                &pp,
-                &kube::api::Patch::Merge(serde_json::json!({"status": status })),
-            )
-            .await?;
-        return Ok(Action::requeue(Duration::from_secs(1200)));
-    } else if let Some(existing) =
-        find_relaxed_matching_key_http(&endpoint, &master_key, &key).await?
+        status::apply(&api, key.as_ref(), &status).await?;
+        return Ok(Action::requeue(RESYNC));
+    } else if let Some(existing) = find_relaxed_matching_key_http(
+        &ctx.key_lists,
+        &endpoint,
+        &master_key,
+        &key,
+        &registry,
+        &owner,
+    )
+    .await?
     {
         // Adopt relaxed match (ignore name/description differences)
-        store_key_secret(
-            &ctx.client,
-            &ns,
-            &name,
-            &key.spec.secret_namespace,
-            &key.spec.secret_name,
-            &existing.key,
-        )
-        .await?;
+        store_key_output(&ctx, &key, &existing.key).await?;
         status_message = Some("adopted similar existing key".into());
         let status = KeyStatus {
-            uid: None,
+            uid: owned_uid(&existing.uid),
+            key_name: existing.name.clone(),
+            created_at: existing.created_at.clone(),
+            expires_at: existing.expires_at.clone(),
             ready: true,
             message: status_message.clone(),
+            ..prev.clone()
         };
-        let pp = kube::api::PatchParams::apply("meilisearch-operator");
         let api: Api<Key> = Api::namespaced(ctx.client.clone(), &ns);
-        let _ = api
-            .patch_status(
-                &name,
-                &pp,
-                &kube::api::Patch::Merge(serde_json::json!({"status": status })),
-            )
-            .await?;
-        return Ok(Action::requeue(Duration::from_secs(1200)));
+        status::apply(&api, key.as_ref(), &status).await?;
+        return Ok(Action::requeue(RESYNC));
+    }
+
+    if key
+        .status
+        .as_ref()
+        .is_none_or(|s| s.phase == Phase::Pending)
+    {
+        phase::set(
+            &Api::<Key>::namespaced(ctx.client.clone(), &ns),
+            &name,
+            None,
+            Phase::Provisioning,
+        )
+        .await?;
+    }
+    let version = capabilities::server_version(&ctx.client, &ns, server).await;
+    let skipped = capabilities::unsupported(
+        version.as_ref(),
+        key.spec.actions.iter().map(String::as_str),
+    );
+    if let (Some(v), false) = (&version, skipped.is_empty()) {
+        events::publish(
+            &ctx.recorder,
+            &key.object_ref(&()),
+            EventType::Warning,
+            "UnsupportedActions",
+            "Reconcile",
+            format!(
+                "Meilisearch {} does not support {}; creating the key without them",
+                v,
+                skipped.join(", ")
+            ),
+        )
+        .await;
+    }
+    let res = key_builder(&key, &name, version.as_ref())
+        .execute(&client)
+        .await;
+    Audit::new(&ctx.recorder, key.as_ref(), server)
+        .record(
+            "keys.create",
+            key.spec.name.as_deref().unwrap_or(&name),
+            &res,
+        )
+        .await;
+    let created = res?;
+    ctx.key_lists.invalidate(&endpoint).await;
+    key_registry::record(&ctx.client, &ns, server, &created.uid, &owner).await?;
+
+    // Store in target secret
+    store_key_output(&ctx, &key, &created.key).await?;
+
+    // Meilisearch cannot change a key's actions, indexes or expiry, so the key created for
+    // the previous spec is replaced
+    if let Some(old_uid) = key
+        .status
+        .as_ref()
+        .and_then(|s| s.uid.as_ref())
+        .filter(|uid| **uid != created.uid && owned_uid(uid).is_some())
+    {
+        let res = client.delete_key(old_uid).await;
+        Audit::new(&ctx.recorder, key.as_ref(), server)
+            .record("keys.delete", old_uid, &res)
+            .await;
+        res?;
+        ctx.key_lists.invalidate(&endpoint).await;
+        key_registry::forget(&ctx.client, &ns, server, old_uid).await?;
+        status_message = Some(format!("replaced key {} after the spec changed", old_uid));
+    }
+
+    // Update status
+    let status = KeyStatus {
+        uid: Some(created.uid.clone()),
+        key_name: created.name.clone(),
+        created_at: created.created_at.format(&Rfc3339).ok(),
+        expires_at: created.expires_at.and_then(|t| t.format(&Rfc3339).ok()),
+        ready: true,
+        message: status_message,
+        ..prev
+    };
+    let api: Api<Key> = Api::namespaced(ctx.client.clone(), &ns);
+    status::apply(&api, key.as_ref(), &status).await?;
+
+    Ok(Action::requeue(RESYNC))
+}
+
+/// Record that the Server is missing or not Ready and look again later; the Server watch
+/// requeues the Key as soon as it turns Ready.
+async fn wait_for_server(key: &Key, ctx: &Ctx, msg: String) -> Result<Action, ReconcileError> {
+    let ns = key.namespace().unwrap();
+    let mut status = key.status.clone().unwrap_or_default();
+    status.phase = Phase::WaitingForServer;
+    status.ready = false;
+    conditions::set_condition(
+        &mut status.conditions,
+        WAITING_FOR_SERVER,
+        true,
+        "ServerNotReady",
+        &msg,
+        key.metadata.generation,
+    );
+    status.message = Some(msg);
+    let api: Api<Key> = Api::namespaced(ctx.client.clone(), &ns);
+    status::apply(&api, key, &status).await?;
+    Ok(Action::requeue(SERVER_WAIT))
+}
+
+/// Report what a reconcile would change without touching the cluster or Meilisearch.
+async fn dry_run_key(key: &Key, ctx: &Ctx) -> Result<Action, ReconcileError> {
+    let ns = key.namespace().unwrap();
+    let name = key.name_any();
+    let server = &key.spec.server_ref;
+    let mut status = key.status.clone().unwrap_or_default();
+    let mut plan = Vec::new();
+    if key.metadata.deletion_timestamp.is_some() {
+        if !server_is_deleting(&ctx.client, &ns, server).await?
+            && let Some(uid) = status.uid.as_ref()
+        {
+            plan.push(format!("delete key {}", uid));
+        }
+        plan.push("remove finalizer".into());
+    } else {
+        if !key.finalizers().iter().any(|f| f == FINALIZER) {
+            plan.push("add finalizer".into());
+        }
+        let endpoint = clusters::server_endpoint(&ctx.client, &ctx.config, &ns, server).await?;
+        let secret = format!("{}/{}", key.spec.secret_namespace, key.spec.secret_name);
+        match get_master_key(&ctx.secrets, &ns, server).await {
+            Ok(master_key) => {
+                match triggers::requested(key) {
+                    Some(Ok(t)) => plan.push(format!("run trigger {}", t.as_str())),
+                    Some(Err(other)) => plan.push(format!("reject unknown trigger {:?}", other)),
+                    None => {}
+                }
+                let adopt_secret = match existing_secret_key(&ctx.secrets, key).await? {
+                    Some(v) => find_key_by_value_http(&ctx.key_lists, &endpoint, &master_key, &v)
+                        .await?
+                        .is_some_and(|k| matches_spec_relaxed(&k, key)),
+                    None => false,
+                };
+                let owner = key_registry::owner_id("Key", &ns, &name);
+                let registry = key_registry::load(&ctx.client, &ns, server).await?;
+                if adopt_secret {
+                    // Already in sync
+                } else if let Some(existing) = match find_matching_key_http(
+                    &ctx.key_lists,
+                    &endpoint,
+                    &master_key,
+                    key,
+                    &registry,
+                    &owner,
+                )
+                .await?
+                {
+                    Some(k) => Some(k),
+                    None => {
+                        find_relaxed_matching_key_http(
+                            &ctx.key_lists,
+                            &endpoint,
+                            &master_key,
+                            key,
+                            &registry,
+                            &owner,
+                        )
+                        .await?
+                    }
+                } {
+                    plan.push(format!("adopt key {} into Secret {}", existing.uid, secret));
+                } else {
+                    plan.push(format!(
+                        "create key {}",
+                        key.spec.name.as_deref().unwrap_or(&name)
+                    ));
+                    plan.push(format!("create Secret {}", secret));
+                }
+            }
+            Err(e) => plan.push(format!("wait for Server {} master key: {}", server, e)),
+        }
     }
+    dry_run::report(
+        &ctx.recorder,
+        &key.object_ref(&()),
+        status.dry_run_plan.as_ref(),
+        &plan,
+    )
+    .await;
+    status.dry_run_plan = Some(plan);
+    let api: Api<Key> = Api::namespaced(ctx.client.clone(), &ns);
+    status::apply(&api, key, &status).await?;
+    Ok(Action::requeue(dry_run::recheck(&ctx.config, RESYNC)))
+}
 
+/// Builder for `key`, leaving out actions `version` does not support.
+fn key_builder(key: &Key, name: &str, version: Option<&Version>) -> KeyBuilder {
     let mut kb = KeyBuilder::new();
     if let Some(n) = &key.spec.name {
         kb.with_name(n);
     } else {
         // Default the Meilisearch key name to the CR name when not specified
-        kb.with_name(&name);
+        kb.with_name(name);
     }
     if let Some(d) = &key.spec.description {
         kb.with_description(d);
     }
     kb.with_indexes(&key.spec.indexes);
     // Map action strings to enum, fallback to Unknown variant
+    let skipped = capabilities::unsupported(version, key.spec.actions.iter().map(String::as_str));
     let actions: Vec<MeiliAction> = key
         .spec
         .actions
         .iter()
-        .map(|s| match s.as_str() {
-            "*" => MeiliAction::All,
-            "search" => MeiliAction::Search,
-            "documents.add" => MeiliAction::DocumentsAdd,
-            "documents.get" => MeiliAction::DocumentsGet,
-            "documents.delete" => MeiliAction::DocumentsDelete,
-            "indexes.create" => MeiliAction::IndexesCreate,
-            "indexes.get" => MeiliAction::IndexesGet,
-            "indexes.update" => MeiliAction::IndexesUpdate,
-            "indexes.delete" => MeiliAction::IndexesDelete,
-            "tasks.get" => MeiliAction::TasksGet,
-            "settings.get" => MeiliAction::SettingsGet,
-            "settings.update" => MeiliAction::SettingsUpdate,
-            "stats.get" => MeiliAction::StatsGet,
-            "dumps.create" => MeiliAction::DumpsCreate,
-            "dumps.get" => MeiliAction::DumpsGet,
-            "version" => MeiliAction::Version,
-            "keys.get" => MeiliAction::KeyGet,
-            "keys.create" => MeiliAction::KeyCreate,
-            "keys.update" => MeiliAction::KeyUpdate,
-            "keys.delete" => MeiliAction::KeyDelete,
-            other => MeiliAction::Unknown(other.to_string()),
-        })
+        .filter(|a| !skipped.contains(&a.as_str()))
+        .map(|s| meili_action(s))
         .collect();
     kb.with_actions(actions);
     if let Some(exp) = &key.spec.expires_at
@@ -186,37 +548,177 @@ pub async fn reconcile(key: Arc<Key>, ctx: Arc<Ctx>) -> Result<Action, Reconcile
     {
         kb.with_expires_at(dt);
     }
+    kb
+}
 
-    let created = kb.execute(&client).await?;
+/// Map an action string to the SDK's enum, keeping unknown ones verbatim.
+pub(crate) fn meili_action(action: &str) -> MeiliAction {
+    match action {
+        "*" => MeiliAction::All,
+        "search" => MeiliAction::Search,
+        "documents.add" => MeiliAction::DocumentsAdd,
+        "documents.get" => MeiliAction::DocumentsGet,
+        "documents.delete" => MeiliAction::DocumentsDelete,
+        "indexes.create" => MeiliAction::IndexesCreate,
+        "indexes.get" => MeiliAction::IndexesGet,
+        "indexes.update" => MeiliAction::IndexesUpdate,
+        "indexes.delete" => MeiliAction::IndexesDelete,
+        "tasks.get" => MeiliAction::TasksGet,
+        "settings.get" => MeiliAction::SettingsGet,
+        "settings.update" => MeiliAction::SettingsUpdate,
+        "stats.get" => MeiliAction::StatsGet,
+        "dumps.create" => MeiliAction::DumpsCreate,
+        "dumps.get" => MeiliAction::DumpsGet,
+        "version" => MeiliAction::Version,
+        "keys.get" => MeiliAction::KeyGet,
+        "keys.create" => MeiliAction::KeyCreate,
+        "keys.update" => MeiliAction::KeyUpdate,
+        "keys.delete" => MeiliAction::KeyDelete,
+        other => MeiliAction::Unknown(other.to_string()),
+    }
+}
 
-    // Store in target secret
-    store_key_secret(
-        &ctx.client,
-        &ns,
-        &name,
-        &key.spec.secret_namespace,
-        &key.spec.secret_name,
-        &created.key,
-    )
-    .await?;
+async fn run_trigger(
+    trigger: Trigger,
+    ctx: &Ctx,
+    audit: &Audit<'_>,
+    key: &Key,
+    endpoint: &str,
+    master_key: &str,
+    status: &mut KeyStatus,
+) -> Result<String, ReconcileError> {
+    let client = &ctx.client;
+    let ns = key.namespace().unwrap_or_default();
+    let name = key.name_any();
+    let current = match existing_secret_key(&ctx.secrets, key).await? {
+        Some(value) => ctx
+            .key_lists
+            .list(endpoint, master_key)
+            .await?
+            .iter()
+            .find(|k| k.key == value)
+            .cloned(),
+        None => None,
+    };
+    match trigger {
+        Trigger::RotateKey => {
+            let meili = meili_http::sdk(endpoint, master_key);
+            let version = capabilities::server_version(client, &ns, &key.spec.server_ref).await;
+            let res = key_builder(key, &name, version.as_ref())
+                .execute(&meili)
+                .await;
+            audit
+                .record(
+                    "keys.create",
+                    key.spec.name.as_deref().unwrap_or(&name),
+                    &res,
+                )
+                .await;
+            let created = res?;
+            ctx.key_lists.invalidate(endpoint).await;
+            let owner = key_registry::owner_id("Key", &ns, &name);
+            key_registry::record(client, &ns, &key.spec.server_ref, &created.uid, &owner).await?;
+            delete_key_secret(client, key).await?;
+            store_key_output(ctx, key, &created.key).await?;
+            let old_uid = current.map(|k| k.uid).or_else(|| status.uid.clone());
+            if let Some(old_uid) = old_uid {
+                let res = meili.delete_key(&old_uid).await;
+                audit.record("keys.delete", &old_uid, &res).await;
+                res?;
+                ctx.key_lists.invalidate(endpoint).await;
+                key_registry::forget(client, &ns, &key.spec.server_ref, &old_uid).await?;
+            }
+            status.uid = Some(created.uid.clone());
+            status.key_name = created.name.clone();
+            status.created_at = created.created_at.format(&Rfc3339).ok();
+            status.expires_at = created.expires_at.and_then(|t| t.format(&Rfc3339).ok());
+            status.ready = true;
+            Ok(format!("key rotated to {}", created.uid))
+        }
+        Trigger::ResyncSettings => {
+            let current = current.ok_or_else(|| {
+                anyhow::anyhow!("Secret key is not present on the server; nothing to resync")
+            })?;
+            delete_key_secret(client, key).await?;
+            store_key_output(ctx, key, &current.key).await?;
+            Ok("Secret rewritten from server key".into())
+        }
+        Trigger::Reindex => Err(anyhow::anyhow!("reindex is only supported on Index").into()),
+    }
+}
 
-    // Update status
-    let status = KeyStatus {
-        uid: Some(created.uid.clone()),
-        ready: true,
-        message: status_message,
+async fn delete_key_secret(client: &Client, key: &Key) -> Result<(), ReconcileError> {
+    // Vault keeps history; the next write simply becomes the current version
+    if key
+        .spec
+        .secret_store
+        .as_ref()
+        .is_some_and(|s| s.kind == SecretStoreKind::Vault)
+    {
+        return Ok(());
+    }
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), &key.spec.secret_namespace);
+    match secrets
+        .delete(&key.spec.secret_name, &Default::default())
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(ae)) if ae.code == 404 => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Delete the key's Secret (and PushSecret) in another namespace, where no ownerReference
+/// can clean it up. Secrets not labelled with this Key's uid were not written by it and stay.
+async fn delete_foreign_secret(client: &Client, key: &Key) -> Result<(), ReconcileError> {
+    let target_ns = &key.spec.secret_namespace;
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), target_ns);
+    let Some(secret) = secrets.get_opt(&key.spec.secret_name).await? else {
+        return Ok(());
     };
-    let pp = kube::api::PatchParams::apply("meilisearch-operator");
-    let api: Api<Key> = Api::namespaced(ctx.client.clone(), &ns);
-    let _ = api
-        .patch_status(
-            &name,
-            &pp,
-            &kube::api::Patch::Merge(serde_json::json!({"status": status })),
-        )
-        .await?;
+    if secret.labels().get(OWNER_LABEL) != key.metadata.uid.as_ref() {
+        return Ok(());
+    }
+    if key
+        .spec
+        .secret_store
+        .as_ref()
+        .is_some_and(|s| s.kind == SecretStoreKind::PushSecret)
+    {
+        secret_store::delete_push_secret(client, target_ns, &key.spec.secret_name).await?;
+    }
+    delete_key_secret(client, key).await
+}
 
-    Ok(Action::requeue(Duration::from_secs(1200)))
+/// Mark the Key Degraded with `msg`, publishing a Warning Event when the message is new.
+async fn degrade(key: &Key, ctx: &Ctx, reason: &str, msg: String) -> Result<(), ReconcileError> {
+    let mut status = key.status.clone().unwrap_or_default();
+    if !conditions::is_true(&status.conditions, DEGRADED) || status.message.as_ref() != Some(&msg) {
+        events::publish(
+            &ctx.recorder,
+            &key.object_ref(&()),
+            EventType::Warning,
+            reason,
+            "Reconcile",
+            &msg,
+        )
+        .await;
+    }
+    status.phase = Phase::Degraded;
+    status.ready = false;
+    conditions::set_condition(
+        &mut status.conditions,
+        DEGRADED,
+        true,
+        reason,
+        &msg,
+        key.metadata.generation,
+    );
+    status.message = Some(msg);
+    status.observed_generation = key.metadata.generation;
+    let api: Api<Key> = Api::namespaced(ctx.client.clone(), &key.namespace().unwrap_or_default());
+    status::apply(&api, key, &status).await?;
+    Ok(())
 }
 
 pub fn error_policy(_key: Arc<Key>, err: &ReconcileError, _ctx: Arc<Ctx>) -> Action {
@@ -224,79 +726,156 @@ pub fn error_policy(_key: Arc<Key>, err: &ReconcileError, _ctx: Arc<Ctx>) -> Act
     Action::requeue(Duration::from_secs(60))
 }
 
-async fn get_master_key(client: &Client, ns: &str, server: &str) -> Result<String, ReconcileError> {
-    use k8s_openapi::api::core::v1::Secret;
-    let secrets: Api<Secret> = Api::namespaced(client.clone(), ns);
+async fn get_master_key(
+    secrets: &SecretCache,
+    ns: &str,
+    server: &str,
+) -> Result<String, ReconcileError> {
     let name = format!("{}-meili-master", server);
-    let sec = secrets.get(&name).await?;
-    let data = sec
-        .data
-        .ok_or_else(|| anyhow::anyhow!("secret data missing"))?;
-    let val = data
-        .get("masterKey")
-        .ok_or_else(|| anyhow::anyhow!("missing key"))?;
-    Ok(String::from_utf8(val.0.clone())?)
+    let sec = secrets
+        .get(ns, &name)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("master key Secret {}/{} not found", ns, name))?;
+    secret_template::read(&sec, "masterKey")
+        .ok_or_else(|| anyhow::anyhow!("master key Secret {}/{} has no key", ns, name).into())
 }
 
+/// Write the key value to the configured backend: Vault, or the target Secret (plus a
+/// PushSecret when requested).
+async fn store_key_output(ctx: &Ctx, key: &Key, value: &str) -> Result<(), ReconcileError> {
+    let client = &ctx.client;
+    let ns = key.namespace().unwrap_or_default();
+    let store = key.spec.secret_store.clone().unwrap_or_default();
+    if store.kind == SecretStoreKind::Vault {
+        let vault = store
+            .vault
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("secret_store.vault is required for kind Vault"))?;
+        let data = BTreeMap::from([("key".to_string(), value.to_string())]);
+        return secret_store::vault_write(vault, &data).await;
+    }
+    let replaced = store_key_secret(
+        client,
+        &ctx.secrets,
+        key,
+        &key.spec.secret_namespace,
+        &key.spec.secret_name,
+        value,
+        key.spec.secret_template.as_ref(),
+        key.spec
+            .immutable_secret
+            .unwrap_or(ctx.config.immutable_secrets),
+    )
+    .await?;
+    if let Some(reason) = replaced {
+        events::publish(
+            &ctx.recorder,
+            &key.object_ref(&()),
+            EventType::Warning,
+            reason,
+            "Reconcile",
+            format!("Secret {} was rewritten with the key", secret_ref(key)),
+        )
+        .await;
+    }
+    if store.kind == SecretStoreKind::PushSecret {
+        let push = store.push_secret.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("secret_store.push_secret is required for kind PushSecret")
+        })?;
+        let owner = if ns == key.spec.secret_namespace {
+            key.controller_owner_ref(&())
+        } else {
+            None
+        };
+        secret_store::ensure_push_secret(
+            client,
+            &key.spec.secret_namespace,
+            &key.spec.secret_name,
+            secret_template::key_name(key.spec.secret_template.as_ref(), "key"),
+            push,
+            owner,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Create the Secret for `owner`'s key, labelled with the Key's uid so a deleted Secret
+/// requeues the Key. Secrets created before the label existed get it added. One whose value
+/// fails its checksum or is another key is replaced; the reason is returned.
+#[allow(clippy::too_many_arguments)]
 async fn store_key_secret(
     client: &Client,
-    owner_ns: &str,
-    owner_name: &str,
+    cache: &SecretCache,
+    owner: &Key,
     target_ns: &str,
     name: &str,
     key: &str,
-) -> Result<(), ReconcileError> {
-    use k8s_openapi::api::core::v1::Secret;
+    template: Option<&SecretTemplateSpec>,
+    immutable: bool,
+) -> Result<Option<&'static str>, ReconcileError> {
     let secrets: Api<Secret> = Api::namespaced(client.clone(), target_ns);
-    let owner_ref = if owner_ns == target_ns {
-        Some(vec![
-            k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference {
-                api_version: "meili.operator.dev/v1alpha1".into(),
-                kind: "Key".into(),
-                name: owner_name.to_string(),
-                uid: key_uid(client, owner_ns, owner_name)
-                    .await
-                    .unwrap_or_default(),
-                controller: Some(true),
-                block_owner_deletion: Some(true),
-            },
-        ])
+    let owner_uid = owner.metadata.uid.clone().unwrap_or_default();
+    let owner_ref = if owner.namespace().as_deref() == Some(target_ns) {
+        owner.controller_owner_ref(&()).map(|r| vec![r])
     } else {
         None
     };
-    let sec = Secret {
-        metadata: kube::core::ObjectMeta {
-            name: Some(name.to_string()),
-            owner_references: owner_ref,
-            ..Default::default()
-        },
-        string_data: Some(std::collections::BTreeMap::from([(
-            String::from("key"),
-            key.to_string(),
-        )])),
-        ..Default::default()
-    };
+    let mut sec = secret_template::build(name, template, "key", key, owner_ref, immutable);
+    sec.labels_mut()
+        .insert(OWNER_LABEL.to_string(), owner_uid.clone());
+    sec.labels_mut()
+        .insert(SERVER_LABEL.to_string(), owner.spec.server_ref.clone());
     let pp = kube::api::PostParams::default();
-    let _ = secrets.create(&pp, &sec).await.or_else(|e| match e {
-        kube::Error::Api(ae) if ae.code == 409 => Ok(Secret::default()),
-        _ => Err(e),
-    })?;
-    Ok(())
+    match secrets.create(&pp, &sec).await {
+        Ok(_) => {}
+        Err(kube::Error::Api(ae)) if ae.code == 409 => {
+            let existing = cache.get(target_ns, name).await?;
+            let replace = match &existing {
+                Some(s) if !secret_template::intact(s, "key") => Some("SecretChecksumMismatch"),
+                Some(s) if secret_template::read(s, "key").as_deref() != Some(key) => {
+                    Some("SecretKeyReplaced")
+                }
+                _ => None,
+            };
+            if replace.is_some() {
+                // Possibly immutable, so replaced rather than patched
+                let _ = secrets.delete(name, &Default::default()).await?;
+                let _ = secrets.create(&pp, &sec).await?;
+                return Ok(replace);
+            }
+            let labelled =
+                existing.is_some_and(|s| s.labels().get(OWNER_LABEL) == Some(&owner_uid));
+            if !labelled {
+                let patch = serde_json::json!({"metadata": {"labels": {OWNER_LABEL: owner_uid}}});
+                let _ = secrets
+                    .patch(
+                        name,
+                        &kube::api::PatchParams::default(),
+                        &kube::api::Patch::Merge(&patch),
+                    )
+                    .await?;
+            }
+        }
+        Err(e) => return Err(e.into()),
+    }
+    Ok(None)
 }
 
 // -------- Matching existing keys via HTTP API --------
 
-#[derive(Debug, serde::Deserialize)]
-struct KeyItem {
-    name: Option<String>,
-    description: Option<String>,
+#[derive(Clone, Debug, serde::Deserialize)]
+pub(crate) struct KeyItem {
+    pub(crate) name: Option<String>,
+    pub(crate) description: Option<String>,
     key: String,
-    #[allow(dead_code)]
-    uid: String,
-    actions: Vec<String>,
-    indexes: Vec<String>,
+    pub(crate) uid: String,
+    pub(crate) actions: Vec<String>,
+    pub(crate) indexes: Vec<String>,
     #[serde(rename = "expiresAt")]
-    expires_at: Option<String>,
+    pub(crate) expires_at: Option<String>,
+    #[serde(rename = "createdAt")]
+    created_at: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -309,14 +888,57 @@ struct KeysPage {
     total: usize,
 }
 
-async fn list_all_keys_http(
+/// How long a `/keys` listing is reused by the Key reconciles of the same Server.
+const KEY_LIST_TTL: Duration = Duration::from_secs(60);
+
+type KeyListEntry = Arc<tokio::sync::Mutex<Option<(Instant, Arc<Vec<KeyItem>>)>>>;
+
+/// `/keys` listings shared by every Key CR of a Server, so a resync of hundreds of Keys pages
+/// through the keys once per `KEY_LIST_TTL` instead of once per CR. Concurrent reconciles
+/// for the same Server wait for a single fetch.
+#[derive(Clone, Default)]
+pub struct KeyListCache {
+    entries: Arc<std::sync::Mutex<HashMap<String, KeyListEntry>>>,
+}
+
+impl KeyListCache {
+    fn entry(&self, endpoint: &str) -> KeyListEntry {
+        self.entries
+            .lock()
+            .expect("key list cache lock")
+            .entry(endpoint.to_string())
+            .or_default()
+            .clone()
+    }
+
+    async fn list(
+        &self,
+        endpoint: &str,
+        master_key: &str,
+    ) -> Result<Arc<Vec<KeyItem>>, ReconcileError> {
+        let entry = self.entry(endpoint);
+        let mut cached = entry.lock().await;
+        if let Some((fetched, keys)) = cached.as_ref()
+            && fetched.elapsed() < KEY_LIST_TTL
+        {
+            return Ok(keys.clone());
+        }
+        let keys = Arc::new(list_all_keys_http(endpoint, master_key).await?);
+        *cached = Some((Instant::now(), keys.clone()));
+        Ok(keys)
+    }
+
+    /// Drop the listing after creating or deleting a key on `endpoint`.
+    async fn invalidate(&self, endpoint: &str) {
+        *self.entry(endpoint).lock().await = None;
+    }
+}
+
+pub(crate) async fn list_all_keys_http(
     endpoint: &str,
     master_key: &str,
 ) -> Result<Vec<KeyItem>, ReconcileError> {
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(5))
-        .build()
-        .map_err(anyhow::Error::from)?;
+    let client = meili_http::client();
     let mut out = Vec::new();
     let mut offset = 0usize;
     let limit = 1000usize;
@@ -328,11 +950,9 @@ async fn list_all_keys_http(
                 reqwest::header::AUTHORIZATION,
                 format!("Bearer {}", master_key),
             )
-            .send()
+            .send_checked()
             .await
             .map_err(anyhow::Error::from)?
-            .error_for_status()
-            .map_err(anyhow::Error::from)?
             .json::<KeysPage>()
             .await
             .map_err(anyhow::Error::from)?;
@@ -394,12 +1014,18 @@ fn matches_spec(item: &KeyItem, key: &Key) -> bool {
 }
 
 async fn find_matching_key_http(
+    key_lists: &KeyListCache,
     endpoint: &str,
     master_key: &str,
     key: &Key,
+    registry: &BTreeMap<String, String>,
+    owner: &str,
 ) -> Result<Option<KeyItem>, ReconcileError> {
-    let all = list_all_keys_http(endpoint, master_key).await?;
-    Ok(all.into_iter().find(|k| matches_spec(k, key)))
+    let all = key_lists.list(endpoint, master_key).await?;
+    Ok(all
+        .iter()
+        .find(|k| matches_spec(k, key) && key_registry::adoptable(registry, &k.uid, owner))
+        .cloned())
 }
 
 // Relaxed matching: ignore name/description differences, match on actions/indexes/expiry only
@@ -420,45 +1046,85 @@ fn matches_spec_relaxed(item: &KeyItem, key: &Key) -> bool {
 }
 
 async fn find_relaxed_matching_key_http(
+    key_lists: &KeyListCache,
     endpoint: &str,
     master_key: &str,
     key: &Key,
+    registry: &BTreeMap<String, String>,
+    owner: &str,
 ) -> Result<Option<KeyItem>, ReconcileError> {
-    let all = list_all_keys_http(endpoint, master_key).await?;
-    Ok(all.into_iter().find(|k| matches_spec_relaxed(k, key)))
+    let all = key_lists.list(endpoint, master_key).await?;
+    Ok(all
+        .iter()
+        .find(|k| matches_spec_relaxed(k, key) && key_registry::adoptable(registry, &k.uid, owner))
+        .cloned())
+}
+
+/// Where the key is written, for `status.secret_ref`.
+fn secret_ref(key: &Key) -> String {
+    match key.spec.secret_store.as_ref().and_then(|s| {
+        (s.kind == SecretStoreKind::Vault)
+            .then_some(s.vault.as_ref())
+            .flatten()
+    }) {
+        Some(vault) => format!("vault:{}/{}", vault.mount, vault.path),
+        None => format!("{}/{}", key.spec.secret_namespace, key.spec.secret_name),
+    }
+}
+
+/// Whether the Kubernetes Secret the key is written to exists and matches its checksum;
+/// other stores are not checked.
+async fn output_present(secrets: &SecretCache, key: &Key) -> Result<bool, ReconcileError> {
+    if key
+        .spec
+        .secret_store
+        .as_ref()
+        .is_some_and(|s| s.kind == SecretStoreKind::Vault)
+    {
+        return Ok(true);
+    }
+    Ok(secrets
+        .get(&key.spec.secret_namespace, &key.spec.secret_name)
+        .await?
+        .is_some_and(|s| secret_template::intact(&s, "key")))
 }
 
 // If a Secret already exists at the target location, try to reuse that key value
-async fn existing_secret_key(client: &Client, key: &Key) -> Result<Option<String>, ReconcileError> {
-    use k8s_openapi::api::core::v1::Secret;
-    let secrets: Api<Secret> = Api::namespaced(client.clone(), &key.spec.secret_namespace);
-    match secrets.get(&key.spec.secret_name).await {
-        Ok(sec) => {
-            if let Some(sd) = sec.string_data.as_ref()
-                && let Some(v) = sd.get("key")
-            {
-                return Ok(Some(v.clone()));
-            }
-            if let Some(data) = sec.data.as_ref()
-                && let Some(v) = data.get("key")
-            {
-                return Ok(String::from_utf8(v.0.clone()).ok());
-            }
-            Ok(None)
-        }
-        Err(kube::Error::Api(ae)) if ae.code == 404 => Ok(None),
-        Err(e) => Err(e.into()),
+/// Key value in the target Secret (or Vault path) that reconcile may reuse.
+async fn existing_secret_key(
+    secrets: &SecretCache,
+    key: &Key,
+) -> Result<Option<String>, ReconcileError> {
+    if let Some(store) = key.spec.secret_store.as_ref()
+        && store.kind == SecretStoreKind::Vault
+        && let Some(vault) = store.vault.as_ref()
+    {
+        return secret_store::vault_read(vault, "key").await;
     }
+    let Some(sec) = secrets
+        .get(&key.spec.secret_namespace, &key.spec.secret_name)
+        .await?
+    else {
+        return Ok(None);
+    };
+    // Values this Key did not write are only used when asked to, edited ones never; the
+    // Secret is then rewritten from the server's key
+    let written_here = sec.labels().get(OWNER_LABEL) == key.metadata.uid.as_ref();
+    if !(written_here || key.spec.adopt_existing_secret) || !secret_template::intact(&sec, "key") {
+        return Ok(None);
+    }
+    Ok(secret_template::read(&sec, "key"))
 }
 
-// Verify if a key string exists on the Meilisearch server by listing all keys
-async fn key_exists_by_value_http(
+// Look up a key string on the Meilisearch server by listing all keys
+async fn find_key_by_value_http(
+    key_lists: &KeyListCache,
     endpoint: &str,
     master_key: &str,
     key_value: &str,
-) -> Result<bool, ReconcileError> {
-    let all = list_all_keys_http(endpoint, master_key).await?;
-    Ok(all.iter().any(|k| k.key == key_value))
+) -> Result<Option<KeyItem>, ReconcileError> {
+    let all = key_lists.list(endpoint, master_key).await?;
+    Ok(all.iter().find(|k| k.key == key_value).cloned())
 }
 
 async fn ensure_finalizer(
@@ -491,17 +1157,13 @@ async fn remove_finalizer(client: &Client, ns: &str, name: &str) -> Result<(), R
     Ok(())
 }
 
-async fn key_uid(client: &Client, ns: &str, name: &str) -> Result<String, ReconcileError> {
-    let api: Api<Key> = Api::namespaced(client.clone(), ns);
-    let k = api.get(name).await?;
-    Ok(k.metadata.uid.unwrap_or_default())
-}
-
 async fn server_is_deleting(client: &Client, ns: &str, name: &str) -> Result<bool, ReconcileError> {
-    use crate::crds::server::Server;
+    use crate::crds::server::{Server, ServerDeletionPolicy};
     let api: Api<Server> = Api::namespaced(client.clone(), ns);
     if let Some(srv) = api.get_opt(name).await? {
-        Ok(srv.metadata.deletion_timestamp.is_some())
+        // A blocked Server keeps running until its dependents have cleaned up
+        Ok(srv.metadata.deletion_timestamp.is_some()
+            && srv.spec.deletion_policy == ServerDeletionPolicy::Cascade)
     } else {
         // Treat missing as deleted/going away
         Ok(true)
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::{Api, Client, Resource};
+
+use crate::{crds::server::Server, error::ReconcileError};
+
+/// Meilisearch keys carry no labels, so keys the operator creates are recorded in a
+/// per-Server ConfigMap `<server>-meili-keys` mapping key uid to its owning CR
+/// (`Key/<ns>/<name>` or `Index/<ns>/<name>`). The ConfigMap is owned by the Server.
+pub fn registry_name(server: &str) -> String {
+    format!("{}-meili-keys", server)
+}
+
+/// Owner string recorded for a CR.
+pub fn owner_id(kind: &str, ns: &str, name: &str) -> String {
+    format!("{}/{}/{}", kind, ns, name)
+}
+
+pub async fn load(
+    client: &Client,
+    ns: &str,
+    server: &str,
+) -> Result<BTreeMap<String, String>, ReconcileError> {
+    let api: Api<ConfigMap> = Api::namespaced(client.clone(), ns);
+    Ok(api
+        .get_opt(&registry_name(server))
+        .await?
+        .and_then(|cm| cm.data)
+        .unwrap_or_default())
+}
+
+pub async fn record(
+    client: &Client,
+    ns: &str,
+    server: &str,
+    uid: &str,
+    owner: &str,
+) -> Result<(), ReconcileError> {
+    let api: Api<ConfigMap> = Api::namespaced(client.clone(), ns);
+    let name = registry_name(server);
+    let patch = serde_json::json!({"data": {uid: owner}});
+    match api
+        .patch(
+            &name,
+            &kube::api::PatchParams::default(),
+            &kube::api::Patch::Merge(&patch),
+        )
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(ae)) if ae.code == 404 => {
+            let servers: Api<Server> = Api::namespaced(client.clone(), ns);
+            let owner_refs = servers
+                .get_opt(server)
+                .await?
+                .and_then(|s| s.controller_owner_ref(&()))
+                .map(|r| vec![r]);
+            let cm = ConfigMap {
+                metadata: kube::core::ObjectMeta {
+                    name: Some(name),
+                    owner_references: owner_refs,
+                    ..Default::default()
+                },
+                data: Some(BTreeMap::from([(uid.to_string(), owner.to_string())])),
+                ..Default::default()
+            };
+            let _ = api.create(&Default::default(), &cm).await?;
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn forget(
+    client: &Client,
+    ns: &str,
+    server: &str,
+    uid: &str,
+) -> Result<(), ReconcileError> {
+    let api: Api<ConfigMap> = Api::namespaced(client.clone(), ns);
+    let patch = serde_json::json!({"data": {uid: null}});
+    match api
+        .patch(
+            &registry_name(server),
+            &kube::api::PatchParams::default(),
+            &kube::api::Patch::Merge(&patch),
+        )
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(ae)) if ae.code == 404 => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Whether `uid` may be adopted by `owner`: unregistered keys and keys already recorded for
+/// `owner` are fine, keys managed for another CR are not.
+pub fn adoptable(registry: &BTreeMap<String, String>, uid: &str, owner: &str) -> bool {
+    registry.get(uid).is_none_or(|o| o == owner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_of_other_owners_are_not_adoptable() {
+        let registry = BTreeMap::from([("u1".to_string(), "Key/ns/a".to_string())]);
+        assert!(adoptable(&registry, "u1", "Key/ns/a"));
+        assert!(!adoptable(&registry, "u1", "Key/ns/b"));
+        assert!(adoptable(&registry, "u2", "Key/ns/b"));
+    }
+}
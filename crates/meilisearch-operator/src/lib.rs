@@ -1,5 +1,51 @@
+pub mod admission;
+pub mod audit;
+pub mod capabilities;
+pub mod children;
+pub mod cluster_controller;
+pub mod clusters;
+pub mod conditions;
+pub mod config;
+pub mod conversion;
+pub mod crd_check;
 pub mod crds;
+pub mod cron;
+pub mod dry_run;
 pub mod error;
+pub mod events;
+pub mod export_controller;
+pub mod freeze;
+pub mod gateway;
+pub mod gateway_controller;
+pub mod import;
+pub mod import_controller;
 pub mod index_controller;
+pub mod install;
 pub mod key_controller;
+pub mod key_registry;
+pub mod meili_http;
+pub mod nats;
+pub mod object_store;
+pub mod phase;
+pub mod postgres;
+pub mod resync;
+pub mod secret_cache;
+pub mod secret_store;
+pub mod secret_template;
 pub mod server_controller;
+pub mod settings_lint;
+pub mod settings_rollout_controller;
+pub mod settings_sources;
+pub mod shutdown;
+pub mod status;
+pub mod stream_controller;
+pub mod sync_controller;
+pub mod task_cleanup;
+pub mod task_watch;
+pub mod tenant_controller;
+pub mod tenant_token;
+pub mod triggers;
+pub mod upgrade;
+pub mod version_resolver;
+pub mod watch;
+pub mod webhook;
@@ -1,7 +1,12 @@
 use futures::StreamExt;
 use kube::Client;
 use meilisearch_operator::{
-    index_controller as idx, key_controller as keyc, server_controller as srv,
+    cluster_controller as clc, clusters::RemoteClusters, config::OperatorConfig, crd_check,
+    crds::index::ConversionWebhook, events, export_controller as exp, freeze,
+    gateway_controller as gwc, import_controller as imp, index_controller as idx,
+    key_controller as keyc, secret_cache::SecretCache, server_controller as srv,
+    settings_rollout_controller as sro, shutdown, stream_controller as sink,
+    sync_controller as dbs, tenant_controller as tnc, version_resolver::VersionResolver, webhook,
 };
 use std::sync::Arc;
 use tracing::info;
@@ -21,13 +26,58 @@ async fn main() -> anyhow::Result<()> {
     let client = Client::try_default().await?;
     let operator_namespace =
         std::env::var("OPERATOR_NAMESPACE").unwrap_or_else(|_| "meilisearch-operator".into());
+    let config = Arc::new(OperatorConfig::from_env());
+    info!(
+        default_image = %config.default_image,
+        dry_run = config.dry_run,
+        dev_mode = config.dev_endpoints.is_some(),
+        "operator config loaded"
+    );
+    let cert_dir = std::path::PathBuf::from(
+        std::env::var("WEBHOOK_CERT_DIR").unwrap_or_else(|_| webhook::DEFAULT_CERT_DIR.into()),
+    );
+    let webhook_enabled = cert_dir.join("tls.crt").exists();
+    // Applied CRDs name the CA of the serving certificate when it comes with one, and leave
+    // it to cert-manager otherwise
+    let conversion = webhook_enabled.then(|| ConversionWebhook {
+        namespace: operator_namespace.clone(),
+        ca_bundle: std::fs::read(cert_dir.join("ca.crt")).ok(),
+    });
+    crd_check::ensure(&client, conversion.as_ref(), config.install_crds).await?;
+
+    // Webhooks first: listing Indexes stored as v1alpha1 goes through the conversion webhook
+    if webhook_enabled {
+        let port: u16 = std::env::var("WEBHOOK_PORT")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(9443);
+        let webhook_client = client.clone();
+        tokio::spawn(async move {
+            let addr = ([0, 0, 0, 0], port).into();
+            if let Err(e) = webhook::serve(addr, cert_dir, webhook_client).await {
+                tracing::error!(error = ?e, "webhook server stopped");
+            }
+        });
+    } else {
+        info!(cert_dir = %cert_dir.display(), "no webhook certificate, webhooks disabled");
+    }
+
+    // Clients for remote clusters, shared by the Cluster and Server controllers
+    let clusters = RemoteClusters::default();
 
     // Server controller
+    let (srv_controller, children) =
+        srv::controller(client.clone(), operator_namespace.clone(), &config);
     let srv_ctx = Arc::new(srv::Ctx {
         client: client.clone(),
         operator_namespace: operator_namespace.clone(),
+        children,
+        recorder: events::recorder(client.clone()),
+        versions: Arc::new(VersionResolver::from_env()),
+        config: config.clone(),
+        clusters: clusters.clone(),
     });
-    let srv_controller = srv::controller(client.clone(), operator_namespace.clone())
+    let srv_controller = srv_controller
         .run(srv::reconcile, srv::error_policy, srv_ctx)
         .for_each(|res| async move {
             if let Err(e) = res {
@@ -35,11 +85,26 @@ async fn main() -> anyhow::Result<()> {
             }
         });
 
+    // Change freeze switch, read while running
+    tokio::spawn(freeze::watch(client.clone(), operator_namespace.clone()));
+    // Master key copies left behind by Servers deleted while the operator was down
+    tokio::spawn(srv::run_copy_sweep(
+        client.clone(),
+        operator_namespace.clone(),
+        config.clone(),
+    ));
+
+    // Shared Secret cache for master keys and Key target Secrets
+    let secrets = SecretCache::spawn(client.clone());
+
     // Index controller
     let idx_ctx = Arc::new(idx::Ctx {
         client: client.clone(),
+        secrets: secrets.clone(),
+        recorder: events::recorder(client.clone()),
+        config: config.clone(),
     });
-    let idx_controller = idx::controller(client.clone())
+    let idx_controller = idx::controller(client.clone(), &config)
         .run(idx::reconcile, idx::error_policy, idx_ctx)
         .for_each(|res| async move {
             if let Err(e) = res {
@@ -50,8 +115,12 @@ async fn main() -> anyhow::Result<()> {
     // Key controller
     let key_ctx = Arc::new(keyc::Ctx {
         client: client.clone(),
+        secrets: secrets.clone(),
+        recorder: events::recorder(client.clone()),
+        config: config.clone(),
+        key_lists: keyc::KeyListCache::default(),
     });
-    let key_controller = keyc::controller(client.clone())
+    let key_controller = keyc::controller(client.clone(), &config)
         .run(keyc::reconcile, keyc::error_policy, key_ctx)
         .for_each(|res| async move {
             if let Err(e) = res {
@@ -59,11 +128,153 @@ async fn main() -> anyhow::Result<()> {
             }
         });
 
+    // Cluster controller
+    let cl_ctx = Arc::new(clc::Ctx {
+        client: client.clone(),
+        clusters: clusters.clone(),
+    });
+    let cl_controller = clc::controller(client.clone(), &config)
+        .run(clc::reconcile, clc::error_policy, cl_ctx)
+        .for_each(|res| async move {
+            if let Err(e) = res {
+                tracing::warn!(error=?e, "cluster reconcile error");
+            }
+        });
+
+    // IndexExport controller
+    let exp_ctx = Arc::new(exp::Ctx {
+        client: client.clone(),
+        secrets: secrets.clone(),
+        recorder: events::recorder(client.clone()),
+        config: config.clone(),
+    });
+    let exp_controller = exp::controller(client.clone(), &config)
+        .run(exp::reconcile, exp::error_policy, exp_ctx)
+        .for_each(|res| async move {
+            if let Err(e) = res {
+                tracing::warn!(error=?e, "index export reconcile error");
+            }
+        });
+
+    // IndexImport controller
+    let imp_ctx = Arc::new(imp::Ctx {
+        client: client.clone(),
+        secrets: secrets.clone(),
+        recorder: events::recorder(client.clone()),
+        config: config.clone(),
+    });
+    let imp_controller = imp::controller(client.clone(), &config)
+        .run(imp::reconcile, imp::error_policy, imp_ctx)
+        .for_each(|res| async move {
+            if let Err(e) = res {
+                tracing::warn!(error=?e, "index import reconcile error");
+            }
+        });
+
+    // StreamSink controller; consumers run as their own tasks
+    let sink_ctx = Arc::new(sink::Ctx {
+        client: client.clone(),
+        secrets: secrets.clone(),
+        recorder: events::recorder(client.clone()),
+        config: config.clone(),
+        consumers: sink::Consumers::default(),
+    });
+    let sink_controller = sink::controller(client.clone(), &config)
+        .run(sink::reconcile, sink::error_policy, sink_ctx)
+        .for_each(|res| async move {
+            if let Err(e) = res {
+                tracing::warn!(error=?e, "stream sink reconcile error");
+            }
+        });
+
+    // DatabaseSync controller
+    let dbs_ctx = Arc::new(dbs::Ctx {
+        client: client.clone(),
+        secrets: secrets.clone(),
+        recorder: events::recorder(client.clone()),
+        config: config.clone(),
+    });
+    let dbs_controller = dbs::controller(client.clone(), &config)
+        .run(dbs::reconcile, dbs::error_policy, dbs_ctx)
+        .for_each(|res| async move {
+            if let Err(e) = res {
+                tracing::warn!(error=?e, "database sync reconcile error");
+            }
+        });
+
+    let gw_ctx = Arc::new(gwc::Ctx {
+        client: client.clone(),
+        secrets: secrets.clone(),
+        recorder: events::recorder(client.clone()),
+        config: config.clone(),
+    });
+    let gw_controller = gwc::controller(client.clone(), &config)
+        .run(gwc::reconcile, gwc::error_policy, gw_ctx)
+        .for_each(|res| async move {
+            if let Err(e) = res {
+                tracing::warn!(error=?e, "search gateway reconcile error");
+            }
+        });
+
+    let tn_ctx = Arc::new(tnc::Ctx {
+        client: client.clone(),
+        secrets: secrets.clone(),
+        recorder: events::recorder(client.clone()),
+        config: config.clone(),
+    });
+    let tn_controller = tnc::controller(client.clone(), &config)
+        .run(tnc::reconcile, tnc::error_policy, tn_ctx)
+        .for_each(|res| async move {
+            if let Err(e) = res {
+                tracing::warn!(error=?e, "tenant reconcile error");
+            }
+        });
+
+    let sro_ctx = Arc::new(sro::Ctx {
+        client: client.clone(),
+        secrets: secrets.clone(),
+        recorder: events::recorder(client.clone()),
+        config: config.clone(),
+    });
+    let sro_controller = sro::controller(client.clone(), &config)
+        .run(sro::reconcile, sro::error_policy, sro_ctx)
+        .for_each(|res| async move {
+            if let Err(e) = res {
+                tracing::warn!(error=?e, "settings rollout reconcile error");
+            }
+        });
+
+    // Each controller runs as its own task, so a backlog of Key reconciles is scheduled
+    // alongside Server reconciles rather than in front of them. Each stops taking new work on
+    // SIGTERM (shutdown_on_signal) and finishes once its in-flight reconciles complete; give
+    // them a bounded window before exiting.
+    let controllers = async {
+        tokio::join!(
+            tokio::spawn(srv_controller),
+            tokio::spawn(idx_controller),
+            tokio::spawn(key_controller),
+            tokio::spawn(cl_controller),
+            tokio::spawn(exp_controller),
+            tokio::spawn(imp_controller),
+            tokio::spawn(sink_controller),
+            tokio::spawn(dbs_controller),
+            tokio::spawn(gw_controller),
+            tokio::spawn(tn_controller),
+            tokio::spawn(sro_controller),
+        )
+    };
+    tokio::pin!(controllers);
     tokio::select! {
-        _ = srv_controller => {},
-        _ = idx_controller => {},
-        _ = key_controller => {},
-        _ = tokio::signal::ctrl_c() => { info!("shutdown signal received"); }
+        _ = &mut controllers => {},
+        _ = shutdown::signal() => {
+            let timeout = shutdown::drain_timeout();
+            info!(?timeout, "shutdown signal received, draining in-flight reconciles");
+            if tokio::time::timeout(timeout, &mut controllers).await.is_err() {
+                tracing::warn!("drain timeout reached, exiting with reconciles in flight");
+            } else {
+                info!("all controllers drained");
+            }
+        }
     }
     Ok(())
 }
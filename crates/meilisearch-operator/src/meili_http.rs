@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use futures::AsyncReadExt;
+use meilisearch_sdk::{
+    client::Client as MeiliClient,
+    errors::Error as SdkError,
+    request::{HttpClient, Method, parse_response},
+};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::sync::Semaphore;
+use tokio::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::shutdown;
+
+/// Limits applied to every Meilisearch server, keyed by scheme, host and port.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Limits {
+    /// `MEILI_HTTP_TIMEOUT_SECONDS`: timeout for requests that don't set their own
+    pub timeout: Duration,
+    /// `MEILI_HTTP_MAX_CONCURRENCY`: requests in flight per server
+    pub max_concurrency: usize,
+    /// `MEILI_HTTP_RETRIES`: extra attempts after a connection error, timeout, 429 or 5xx
+    pub retries: u32,
+    /// `MEILI_HTTP_BREAKER_THRESHOLD`: consecutive failures that open the circuit
+    pub breaker_threshold: u32,
+    /// `MEILI_HTTP_BREAKER_COOLDOWN_SECONDS`: how long an open circuit rejects requests
+    pub breaker_cooldown: Duration,
+    /// `MEILI_HTTP_POOL_IDLE_TIMEOUT_SECONDS`: how long an idle connection is kept for reuse
+    pub pool_idle_timeout: Duration,
+    /// `MEILI_HTTP_TCP_KEEPALIVE_SECONDS`: TCP keep-alive interval of pooled connections
+    pub tcp_keepalive: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            max_concurrency: 8,
+            retries: 3,
+            breaker_threshold: 5,
+            breaker_cooldown: Duration::from_secs(30),
+            pool_idle_timeout: Duration::from_secs(90),
+            tcp_keepalive: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Limits {
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| var(name).and_then(|v| v.trim().parse().ok());
+        Self {
+            timeout: var("MEILI_HTTP_TIMEOUT_SECONDS")
+                .map_or(defaults.timeout, Duration::from_secs),
+            max_concurrency: var("MEILI_HTTP_MAX_CONCURRENCY")
+                .map_or(defaults.max_concurrency, |n: u64| n.max(1) as usize),
+            retries: var("MEILI_HTTP_RETRIES").map_or(defaults.retries, |n: u64| n as u32),
+            breaker_threshold: var("MEILI_HTTP_BREAKER_THRESHOLD")
+                .map_or(defaults.breaker_threshold, |n: u64| n.max(1) as u32),
+            breaker_cooldown: var("MEILI_HTTP_BREAKER_COOLDOWN_SECONDS")
+                .map_or(defaults.breaker_cooldown, Duration::from_secs),
+            pool_idle_timeout: var("MEILI_HTTP_POOL_IDLE_TIMEOUT_SECONDS")
+                .map_or(defaults.pool_idle_timeout, Duration::from_secs),
+            tcp_keepalive: var("MEILI_HTTP_TCP_KEEPALIVE_SECONDS")
+                .map_or(defaults.tcp_keepalive, |s: u64| {
+                    Duration::from_secs(s.max(1))
+                }),
+        }
+    }
+
+    /// The variables `from_env` reads back into these limits.
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                "MEILI_HTTP_TIMEOUT_SECONDS",
+                self.timeout.as_secs().to_string(),
+            ),
+            (
+                "MEILI_HTTP_MAX_CONCURRENCY",
+                self.max_concurrency.to_string(),
+            ),
+            ("MEILI_HTTP_RETRIES", self.retries.to_string()),
+            (
+                "MEILI_HTTP_BREAKER_THRESHOLD",
+                self.breaker_threshold.to_string(),
+            ),
+            (
+                "MEILI_HTTP_BREAKER_COOLDOWN_SECONDS",
+                self.breaker_cooldown.as_secs().to_string(),
+            ),
+            (
+                "MEILI_HTTP_POOL_IDLE_TIMEOUT_SECONDS",
+                self.pool_idle_timeout.as_secs().to_string(),
+            ),
+            (
+                "MEILI_HTTP_TCP_KEEPALIVE_SECONDS",
+                self.tcp_keepalive.as_secs().to_string(),
+            ),
+        ]
+    }
+
+    /// Client for one server: keeps up to `max_concurrency` idle connections alive for reuse,
+    /// matching the requests that can be in flight to it.
+    fn build_client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .timeout(self.timeout)
+            .pool_max_idle_per_host(self.max_concurrency)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .tcp_keepalive(self.tcp_keepalive)
+            .build()
+            .expect("build http client")
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("circuit open for {0} after repeated failures; retrying later")]
+    CircuitOpen(String),
+    #[error("{0}")]
+    Api(ApiError),
+}
+
+/// Error body Meilisearch sends with a rejected request.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize)]
+pub struct ApiError {
+    pub message: String,
+    /// e.g. `invalid_api_key_actions` or `invalid_index_uid`
+    pub code: String,
+    /// `invalid_request`, `auth`, `internal` or `system`
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub link: String,
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Meilisearch {}: {}", self.code, self.message)?;
+        if !self.link.is_empty() {
+            write!(f, " ({})", self.link)?;
+        }
+        Ok(())
+    }
+}
+
+impl ApiError {
+    /// `code` as a condition reason, e.g. `InvalidApiKeyActions`.
+    pub fn reason(&self) -> String {
+        self.code
+            .split('_')
+            .filter(|w| !w.is_empty())
+            .map(|w| {
+                let mut c = w.chars();
+                c.next()
+                    .map(|f| f.to_ascii_uppercase().to_string() + c.as_str())
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+}
+
+impl From<&meilisearch_sdk::errors::MeilisearchError> for ApiError {
+    fn from(e: &meilisearch_sdk::errors::MeilisearchError) -> Self {
+        let name = |v: serde_json::Result<serde_json::Value>| {
+            v.ok()
+                .and_then(|v| v.as_str().map(String::from))
+                .unwrap_or_default()
+        };
+        Self {
+            message: e.error_message.clone(),
+            code: name(serde_json::to_value(&e.error_code)),
+            kind: name(serde_json::to_value(e.error_type)),
+            link: e.error_link.clone(),
+        }
+    }
+}
+
+/// `resp`, or its error: [`Error::Api`] when Meilisearch explained the failure in its JSON
+/// error body, the plain HTTP status error otherwise.
+pub async fn checked(resp: Response) -> Result<Response, Error> {
+    let Err(status) = resp.error_for_status_ref().map(|_| ()) else {
+        return Ok(resp);
+    };
+    let body = resp.bytes().await.unwrap_or_default();
+    match serde_json::from_slice::<ApiError>(&body) {
+        Ok(api) => Err(Error::Api(api)),
+        Err(_) => Err(Error::Http(status)),
+    }
+}
+
+struct Layer {
+    client: reqwest::Client,
+    limits: Limits,
+    servers: Mutex<HashMap<String, Arc<Server>>>,
+}
+
+struct Server {
+    /// Reused across reconciles so connections to the server stay open between them
+    client: reqwest::Client,
+    permits: Semaphore,
+    breaker: Mutex<Breaker>,
+}
+
+static LAYER: OnceLock<Layer> = OnceLock::new();
+
+fn layer() -> &'static Layer {
+    LAYER.get_or_init(|| {
+        let limits = Limits::from_env();
+        Layer {
+            client: limits.build_client(),
+            limits,
+            servers: Mutex::new(HashMap::new()),
+        }
+    })
+}
+
+impl Layer {
+    fn server(&self, origin: &str) -> Arc<Server> {
+        let mut servers = self.servers.lock().expect("server map lock");
+        servers
+            .entry(origin.to_string())
+            .or_insert_with(|| {
+                Arc::new(Server {
+                    client: self.limits.build_client(),
+                    permits: Semaphore::new(self.limits.max_concurrency),
+                    breaker: Mutex::new(Breaker::default()),
+                })
+            })
+            .clone()
+    }
+}
+
+/// Process-wide client for building Meilisearch requests; send them with [`send`], which
+/// runs them on the client of the server they go to.
+pub fn client() -> &'static reqwest::Client {
+    &layer().client
+}
+
+/// The pooled client of the server at `endpoint`, for requests that bypass [`send`].
+pub fn server_client(endpoint: &str) -> reqwest::Client {
+    let layer = layer();
+    match reqwest::Url::parse(endpoint) {
+        Ok(url) => layer
+            .server(&url.origin().ascii_serialization())
+            .client
+            .clone(),
+        Err(_) => layer.client.clone(),
+    }
+}
+
+/// `.send_meili()` for request chains; see [`send`].
+pub trait MeiliSend {
+    fn send_meili(self) -> impl Future<Output = Result<Response, Error>> + Send;
+    /// `send_meili` that also fails on error statuses, see [`checked`].
+    fn send_checked(self) -> impl Future<Output = Result<Response, Error>> + Send;
+}
+
+impl MeiliSend for RequestBuilder {
+    fn send_meili(self) -> impl Future<Output = Result<Response, Error>> + Send {
+        send(self)
+    }
+
+    async fn send_checked(self) -> Result<Response, Error> {
+        checked(send(self).await?).await
+    }
+}
+
+/// Send a request through the per-server concurrency limit and circuit breaker.
+///
+/// Connection errors are retried for any method; timeouts and 5xx responses only for
+/// idempotent ones, so a POST that may have reached Meilisearch never enqueues twice.
+/// The final response is returned as-is, leaving status handling to the caller.
+pub async fn send(req: RequestBuilder) -> Result<Response, Error> {
+    let (_, request) = req.build_split();
+    let request = request?;
+    let layer = layer();
+    let origin = request.url().origin().ascii_serialization();
+    let server = layer.server(&origin);
+    if !server
+        .breaker
+        .lock()
+        .expect("breaker lock")
+        .allows(Instant::now())
+    {
+        return Err(Error::CircuitOpen(origin));
+    }
+    let _permit = server
+        .permits
+        .acquire()
+        .await
+        .expect("server semaphore is never closed");
+
+    let mut attempt = 0;
+    loop {
+        // Streaming bodies can't be replayed; send those once
+        let Some(this) = request.try_clone() else {
+            let result = server.client.execute(request).await;
+            server.breaker.lock().expect("breaker lock").record(
+                Outcome::of(&result),
+                &layer.limits,
+                Instant::now(),
+            );
+            return Ok(result?);
+        };
+        let result = server.client.execute(this).await;
+        let outcome = Outcome::of(&result);
+        let retry_after = result.as_ref().ok().and_then(retry_after);
+        if !outcome.retryable(request.method())
+            || attempt >= layer.limits.retries
+            || shutdown::requested()
+        {
+            server.breaker.lock().expect("breaker lock").record(
+                outcome,
+                &layer.limits,
+                Instant::now(),
+            );
+            return Ok(result?);
+        }
+        attempt += 1;
+        warn!(
+            %origin,
+            url = %request.url(),
+            ?outcome,
+            attempt,
+            "meilisearch request failed; retrying"
+        );
+        tokio::time::sleep(retry_after.unwrap_or_else(|| backoff(attempt))).await;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Outcome {
+    Ok,
+    Connect,
+    Timeout,
+    ServerError,
+    /// 429: Meilisearch or a proxy in front of it turned the request away before handling it
+    RateLimited,
+}
+
+impl Outcome {
+    fn of(result: &Result<Response, reqwest::Error>) -> Self {
+        match result {
+            Ok(resp) => Self::from_status(resp.status()),
+            Err(e) if e.is_connect() => Self::Connect,
+            Err(e) if e.is_timeout() => Self::Timeout,
+            // Body or decoding errors aren't the server struggling
+            Err(_) => Self::Ok,
+        }
+    }
+
+    fn from_status(status: StatusCode) -> Self {
+        if status.is_server_error() {
+            Self::ServerError
+        } else if status == StatusCode::TOO_MANY_REQUESTS {
+            Self::RateLimited
+        } else {
+            Self::Ok
+        }
+    }
+
+    fn retryable(self, method: &reqwest::Method) -> bool {
+        let idempotent = matches!(
+            *method,
+            reqwest::Method::GET
+                | reqwest::Method::HEAD
+                | reqwest::Method::PUT
+                | reqwest::Method::DELETE
+        );
+        match self {
+            Self::Ok => false,
+            Self::Connect | Self::RateLimited => true,
+            Self::Timeout | Self::ServerError => idempotent,
+        }
+    }
+}
+
+/// `Retry-After` in seconds, capped at 30s.
+fn retry_after(resp: &Response) -> Option<Duration> {
+    resp.headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(|s: u64| Duration::from_secs(s.min(30)))
+}
+
+/// 250ms doubling per attempt, capped at 4s, with up to 50% jitter.
+fn backoff(attempt: u32) -> Duration {
+    let base = Duration::from_millis(250) * 2u32.pow(attempt.saturating_sub(1).min(4));
+    base + base.mul_f64(rand::random_range(0.0..0.5))
+}
+
+/// Opens after `breaker_threshold` consecutive failures and rejects requests until the
+/// cooldown passes; the next request is a probe that closes it or reopens it.
+#[derive(Debug, Default)]
+struct Breaker {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl Breaker {
+    fn allows(&self, now: Instant) -> bool {
+        self.open_until.is_none_or(|until| now >= until)
+    }
+
+    fn record(&mut self, outcome: Outcome, limits: &Limits, now: Instant) {
+        if outcome == Outcome::Ok {
+            *self = Self::default();
+            return;
+        }
+        // A server shedding load is answering; that is no reason to stop talking to it
+        if outcome == Outcome::RateLimited {
+            return;
+        }
+        self.failures += 1;
+        if self.failures >= limits.breaker_threshold {
+            self.open_until = Some(now + limits.breaker_cooldown);
+        }
+    }
+}
+
+/// SDK client whose requests go through [`send`].
+pub fn sdk(endpoint: &str, api_key: &str) -> MeiliClient<SdkHttp> {
+    MeiliClient::new_with_client(
+        endpoint,
+        Some(api_key),
+        SdkHttp {
+            api_key: api_key.to_string(),
+        },
+    )
+}
+
+#[derive(Clone, Debug)]
+pub struct SdkHttp {
+    api_key: String,
+}
+
+#[async_trait::async_trait]
+impl HttpClient for SdkHttp {
+    async fn stream_request<
+        Query: Serialize + Send + Sync,
+        Body: futures::AsyncRead + Send + Sync + 'static,
+        Output: DeserializeOwned + 'static,
+    >(
+        &self,
+        url: &str,
+        method: Method<Query, Body>,
+        content_type: &str,
+        expected_status_code: u16,
+    ) -> Result<Output, SdkError> {
+        let url = format!("{}{}", url, yaup::to_string(method.query())?);
+        let verb = match &method {
+            Method::Get { .. } => reqwest::Method::GET,
+            Method::Delete { .. } => reqwest::Method::DELETE,
+            Method::Post { .. } => reqwest::Method::POST,
+            Method::Put { .. } => reqwest::Method::PUT,
+            Method::Patch { .. } => reqwest::Method::PATCH,
+        };
+        let mut req = client().request(verb, &url).bearer_auth(&self.api_key);
+        if let Some(body) = method.into_body() {
+            // Buffered so the request can be retried
+            let mut buf = Vec::new();
+            std::pin::pin!(body)
+                .read_to_end(&mut buf)
+                .await
+                .map_err(|e| SdkError::Other(Box::new(e)))?;
+            req = req
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+                .body(buf);
+        }
+        let resp = send(req).await.map_err(|e| match e {
+            Error::Http(e) => SdkError::HttpError(e),
+            other => SdkError::Other(Box::new(other)),
+        })?;
+        let status = resp.status().as_u16();
+        let mut body = resp.text().await?;
+        if body.is_empty() {
+            body = "null".to_string();
+        }
+        parse_response(status, expected_status_code, &body, url)
+    }
+
+    fn is_tokio(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_errors_name_their_code() {
+        let api: ApiError = serde_json::from_str(
+            r#"{"message": "Unknown value `serch` at `.actions[0]`", "code": "invalid_api_key_actions", "type": "invalid_request", "link": "https://docs.meilisearch.com/errors#invalid_api_key_actions"}"#,
+        )
+        .unwrap();
+        assert_eq!(api.reason(), "InvalidApiKeyActions");
+        assert!(
+            api.to_string()
+                .starts_with("Meilisearch invalid_api_key_actions: Unknown value")
+        );
+        let err = crate::error::ReconcileError::from(anyhow::Error::from(Error::Api(api.clone())));
+        assert_eq!(err.meili_api(), Some(api));
+    }
+
+    #[test]
+    fn retries_only_what_is_safe_to_replay() {
+        let post = reqwest::Method::POST;
+        let get = reqwest::Method::GET;
+        assert!(Outcome::Connect.retryable(&post));
+        assert!(!Outcome::Timeout.retryable(&post));
+        assert!(!Outcome::ServerError.retryable(&post));
+        assert!(Outcome::ServerError.retryable(&get));
+        assert!(!Outcome::Ok.retryable(&get));
+        assert!(Outcome::RateLimited.retryable(&post));
+        assert_eq!(
+            Outcome::from_status(StatusCode::TOO_MANY_REQUESTS),
+            Outcome::RateLimited
+        );
+        assert_eq!(
+            Outcome::from_status(StatusCode::BAD_GATEWAY),
+            Outcome::ServerError
+        );
+        assert_eq!(Outcome::from_status(StatusCode::NOT_FOUND), Outcome::Ok);
+    }
+
+    #[test]
+    fn limits_read_back_from_their_env() {
+        let limits = Limits {
+            pool_idle_timeout: Duration::from_secs(30),
+            tcp_keepalive: Duration::from_secs(15),
+            ..Limits::default()
+        };
+        let env: HashMap<_, _> = limits.env().into_iter().collect();
+        assert_eq!(Limits::from_vars(|name| env.get(name).cloned()), limits);
+        let unset = Limits::from_vars(|_| None);
+        assert_eq!(unset.pool_idle_timeout, Duration::from_secs(90));
+    }
+
+    #[test]
+    fn breaker_opens_and_recovers() {
+        let limits = Limits {
+            breaker_threshold: 2,
+            ..Limits::default()
+        };
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+        breaker.record(Outcome::Connect, &limits, now);
+        assert!(breaker.allows(now));
+        breaker.record(Outcome::ServerError, &limits, now);
+        assert!(!breaker.allows(now));
+        let later = now + limits.breaker_cooldown;
+        assert!(breaker.allows(later));
+        // A failed probe reopens right away
+        breaker.record(Outcome::Timeout, &limits, later);
+        assert!(!breaker.allows(later));
+        breaker.record(Outcome::Ok, &limits, later);
+        assert!(breaker.allows(later));
+    }
+}
@@ -0,0 +1,43 @@
+use std::fmt::Debug;
+
+use kube::{
+    Api, Resource,
+    api::{Patch, PatchParams},
+};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::{children::FIELD_MANAGER, crds::common::Phase, error::ReconcileError};
+
+/// Patch only `status.phase`, skipping the write when it already reads `phase`. Used to
+/// surface progress before a long step; full status patches carry the phase themselves.
+pub async fn set<K>(
+    api: &Api<K>,
+    name: &str,
+    current: Option<Phase>,
+    phase: Phase,
+) -> Result<(), ReconcileError>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    if current == Some(phase) {
+        return Ok(());
+    }
+    api.patch_status(
+        name,
+        &PatchParams::apply(FIELD_MANAGER),
+        &Patch::Merge(json!({ "status": { "phase": phase } })),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Phase of a resource whose reconcile finished: Degraded while the Degraded condition
+/// holds, Ready otherwise.
+pub fn settled(degraded: bool) -> Phase {
+    if degraded {
+        Phase::Degraded
+    } else {
+        Phase::Ready
+    }
+}
@@ -0,0 +1,40 @@
+use k8s_openapi::chrono::{DateTime, SecondsFormat, Utc};
+use tokio::time::Duration;
+
+/// Time left until the periodic resync when the current generation was already reconciled
+/// successfully, or `None` when a full reconcile is due.
+pub fn remaining(
+    generation: Option<i64>,
+    observed_generation: Option<i64>,
+    last_synced: Option<&str>,
+    every: Duration,
+) -> Option<Duration> {
+    if generation.is_none() || generation != observed_generation {
+        return None;
+    }
+    let last = DateTime::parse_from_rfc3339(last_synced?).ok()?;
+    let elapsed = (Utc::now() - last.with_timezone(&Utc)).to_std().ok()?;
+    every.checked_sub(elapsed).filter(|d| !d.is_zero())
+}
+
+/// RFC3339 timestamp for `last_synced`.
+pub fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_only_observed_generation_within_interval() {
+        let every = Duration::from_secs(300);
+        let recent = now();
+        assert!(remaining(Some(2), Some(2), Some(&recent), every).is_some());
+        assert!(remaining(Some(3), Some(2), Some(&recent), every).is_none());
+        assert!(remaining(Some(2), Some(2), None, every).is_none());
+        let stale = (Utc::now() - k8s_openapi::chrono::Duration::seconds(600))
+            .to_rfc3339_opts(SecondsFormat::Secs, true);
+        assert!(remaining(Some(2), Some(2), Some(&stale), every).is_none());
+    }
+}
@@ -0,0 +1,78 @@
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Secret;
+use kube::{
+    Api, Client,
+    runtime::{
+        WatchStreamExt,
+        reflector::{self, ObjectRef, Store},
+        watcher,
+    },
+};
+
+use crate::{
+    children::{FIELD_MANAGER, MANAGED_BY_LABEL},
+    crds::common::ConnectionSecretRef,
+    error::ReconcileError,
+};
+
+/// Watch-backed view of the Secrets the operator generates, shared by the Index and Key
+/// controllers, so steady-state reconciles read master keys without a GET each time. Only
+/// Secrets carrying `MANAGED_BY_LABEL` are cached; others, such as user-supplied connection
+/// Secrets, are read from the API server.
+#[derive(Clone)]
+pub struct SecretCache {
+    client: Client,
+    store: Store<Secret>,
+}
+
+impl SecretCache {
+    /// Start the reflector on the current runtime and return a handle to its store.
+    pub fn spawn(client: Client) -> Self {
+        let (store, writer) = reflector::store();
+        let api: Api<Secret> = Api::all(client.clone());
+        let config =
+            watcher::Config::default().labels(&format!("{}={}", MANAGED_BY_LABEL, FIELD_MANAGER));
+        let stream = watcher(api, config)
+            .default_backoff()
+            .reflect(writer)
+            .applied_objects();
+        tokio::spawn(async move {
+            stream
+                .for_each(|res| async move {
+                    if let Err(e) = res {
+                        tracing::warn!(error = ?e, "secret watch error");
+                    }
+                })
+                .await;
+        });
+        Self { client, store }
+    }
+
+    /// Cached copy, falling back to the API server for Secrets the watch doesn't cover or
+    /// hasn't delivered yet.
+    pub async fn get(&self, ns: &str, name: &str) -> Result<Option<Secret>, ReconcileError> {
+        if let Some(sec) = self.store.get(&ObjectRef::new(name).within(ns)) {
+            return Ok(Some((*sec).clone()));
+        }
+        let api: Api<Secret> = Api::namespaced(self.client.clone(), ns);
+        Ok(api.get_opt(name).await?)
+    }
+
+    /// Connection URL from `r` in `ns`, trimmed.
+    pub async fn connection_url(
+        &self,
+        ns: &str,
+        r: &ConnectionSecretRef,
+    ) -> Result<String, ReconcileError> {
+        Ok(self
+            .get(ns, &r.name)
+            .await?
+            .and_then(|s| s.data)
+            .and_then(|d| d.get(&r.key).cloned())
+            .and_then(|v| String::from_utf8(v.0).ok())
+            .map(|url| url.trim().to_string())
+            .ok_or_else(|| {
+                anyhow::anyhow!("connection Secret {}/{} has no {}", ns, r.name, r.key)
+            })?)
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use kube::{
+    Api, Client,
+    api::{ApiResource, DynamicObject, GroupVersionKind},
+};
+use tokio::time::Duration;
+
+use crate::{
+    crds::common::{PushSecretSpec, VaultStoreSpec},
+    error::ReconcileError,
+};
+
+const SA_TOKEN_PATH: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+
+fn http() -> Result<reqwest::Client, ReconcileError> {
+    Ok(reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .map_err(anyhow::Error::from)?)
+}
+
+/// Log in with the operator's service account token via Vault's Kubernetes auth method.
+async fn vault_login(
+    http: &reqwest::Client,
+    spec: &VaultStoreSpec,
+) -> Result<String, ReconcileError> {
+    let jwt = tokio::fs::read_to_string(SA_TOKEN_PATH)
+        .await
+        .map_err(|e| anyhow::anyhow!("read service account token: {}", e))?;
+    let resp = http
+        .post(format!(
+            "{}/v1/auth/{}/login",
+            spec.address, spec.auth_mount
+        ))
+        .json(&serde_json::json!({ "role": spec.role, "jwt": jwt.trim() }))
+        .send()
+        .await
+        .map_err(anyhow::Error::from)?
+        .error_for_status()
+        .map_err(anyhow::Error::from)?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(anyhow::Error::from)?;
+    resp["auth"]["client_token"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("vault login returned no client_token").into())
+}
+
+/// Read `field` from the KV v2 secret, `None` when the secret or field is absent.
+pub async fn vault_read(
+    spec: &VaultStoreSpec,
+    field: &str,
+) -> Result<Option<String>, ReconcileError> {
+    let http = http()?;
+    let token = vault_login(&http, spec).await?;
+    let resp = http
+        .get(format!(
+            "{}/v1/{}/data/{}",
+            spec.address, spec.mount, spec.path
+        ))
+        .header("X-Vault-Token", token)
+        .send()
+        .await
+        .map_err(anyhow::Error::from)?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let body = resp
+        .error_for_status()
+        .map_err(anyhow::Error::from)?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(body["data"]["data"][field].as_str().map(str::to_string))
+}
+
+/// Write `data` as a new version of the KV v2 secret unless it already holds these values.
+pub async fn vault_write(
+    spec: &VaultStoreSpec,
+    data: &BTreeMap<String, String>,
+) -> Result<(), ReconcileError> {
+    let http = http()?;
+    let token = vault_login(&http, spec).await?;
+    let url = format!("{}/v1/{}/data/{}", spec.address, spec.mount, spec.path);
+    let current = http
+        .get(&url)
+        .header("X-Vault-Token", &token)
+        .send()
+        .await
+        .map_err(anyhow::Error::from)?;
+    if current.status().is_success() {
+        let body = current
+            .json::<serde_json::Value>()
+            .await
+            .map_err(anyhow::Error::from)?;
+        if data
+            .iter()
+            .all(|(k, v)| body["data"]["data"][k].as_str() == Some(v.as_str()))
+        {
+            return Ok(());
+        }
+    }
+    let _ = http
+        .post(&url)
+        .header("X-Vault-Token", &token)
+        .json(&serde_json::json!({ "data": data }))
+        .send()
+        .await
+        .map_err(anyhow::Error::from)?
+        .error_for_status()
+        .map_err(anyhow::Error::from)?;
+    Ok(())
+}
+
+/// Delete the `PushSecret` `ensure_push_secret` created for `secret_name`, if any.
+pub async fn delete_push_secret(
+    client: &Client,
+    ns: &str,
+    secret_name: &str,
+) -> Result<(), ReconcileError> {
+    let gvk = GroupVersionKind::gvk("external-secrets.io", "v1alpha1", "PushSecret");
+    let ar = ApiResource::from_gvk(&gvk);
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), ns, &ar);
+    match api
+        .delete(&format!("{}-push", secret_name), &Default::default())
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(ae)) if ae.code == 404 => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Server-side apply an External Secrets `PushSecret` copying `secret_key` of Secret
+/// `secret_name` to `spec.remote_key`.
+pub async fn ensure_push_secret(
+    client: &Client,
+    ns: &str,
+    secret_name: &str,
+    secret_key: &str,
+    spec: &PushSecretSpec,
+    owner: Option<OwnerReference>,
+) -> Result<(), ReconcileError> {
+    let gvk = GroupVersionKind::gvk("external-secrets.io", "v1alpha1", "PushSecret");
+    let ar = ApiResource::from_gvk(&gvk);
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), ns, &ar);
+    let name = format!("{}-push", secret_name);
+    let mut obj = DynamicObject::new(&name, &ar)
+        .within(ns)
+        .data(serde_json::json!({
+            "spec": {
+                "refreshInterval": spec.refresh_interval,
+                "secretStoreRefs": [{ "name": spec.store_name, "kind": spec.store_kind }],
+                "selector": { "secret": { "name": secret_name } },
+                "data": [{
+                    "match": {
+                        "secretKey": secret_key,
+                        "remoteRef": { "remoteKey": spec.remote_key, "property": secret_key },
+                    },
+                }],
+            },
+        }));
+    obj.metadata.owner_references = owner.map(|o| vec![o]);
+    let _ = crate::children::apply(&api, &name, &obj).await?;
+    Ok(())
+}
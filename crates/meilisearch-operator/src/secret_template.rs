@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::{api::core::v1::Secret, apimachinery::pkg::apis::meta::v1::OwnerReference};
+use kube::core::ObjectMeta;
+use sha2::{Digest, Sha256};
+
+use crate::{children, crds::common::SecretTemplateSpec, object_store::hex};
+
+/// Records the data key on generated Secrets so readers find it even when `key_name`
+/// changes after creation.
+pub const KEY_NAME_ANNOTATION: &str = "meili.operator.dev/key-name";
+
+/// Digest of the value a generated Secret was written with, so later edits or partial writes
+/// are noticed instead of trusted.
+pub const CHECKSUM_ANNOTATION: &str = "meili.operator.dev/checksum";
+
+/// Data key a Secret built from `template` stores its value under.
+pub fn key_name<'a>(template: Option<&'a SecretTemplateSpec>, default: &'a str) -> &'a str {
+    template
+        .and_then(|t| t.key_name.as_deref())
+        .filter(|k| !k.is_empty())
+        .unwrap_or(default)
+}
+
+/// Secret holding `value`, shaped by `template` (key name, type, labels, annotations) and
+/// carrying `children::managed_labels`.
+pub fn build(
+    name: &str,
+    template: Option<&SecretTemplateSpec>,
+    default_key: &str,
+    value: &str,
+    owner_references: Option<Vec<OwnerReference>>,
+    immutable: bool,
+) -> Secret {
+    let key = key_name(template, default_key);
+    let mut labels = template.map(|t| t.labels.clone()).unwrap_or_default();
+    labels.extend(children::managed_labels(None));
+    let mut annotations = template.map(|t| t.annotations.clone()).unwrap_or_default();
+    annotations.insert(KEY_NAME_ANNOTATION.into(), key.into());
+    annotations.insert(CHECKSUM_ANNOTATION.into(), checksum(value));
+    Secret {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            owner_references,
+            labels: Some(labels),
+            annotations: Some(annotations),
+            ..Default::default()
+        },
+        type_: template.and_then(|t| t.type_.clone()),
+        string_data: Some(BTreeMap::from([(key.to_string(), value.to_string())])),
+        immutable: immutable.then_some(true),
+        ..Default::default()
+    }
+}
+
+/// Value stored in a generated Secret, under its recorded key name or `default_key`.
+pub fn read(secret: &Secret, default_key: &str) -> Option<String> {
+    let key = secret
+        .metadata
+        .annotations
+        .as_ref()
+        .and_then(|a| a.get(KEY_NAME_ANNOTATION))
+        .map(String::as_str)
+        .unwrap_or(default_key);
+    if let Some(v) = secret.string_data.as_ref().and_then(|d| d.get(key)) {
+        return Some(v.clone());
+    }
+    secret
+        .data
+        .as_ref()
+        .and_then(|d| d.get(key))
+        .and_then(|v| String::from_utf8(v.0.clone()).ok())
+}
+
+fn checksum(value: &str) -> String {
+    format!("sha256:{}", hex(&Sha256::digest(value)))
+}
+
+/// Whether the Secret still holds the value its checksum was computed from. Secrets written
+/// before the annotation existed have nothing to compare against and pass.
+pub fn intact(secret: &Secret, default_key: &str) -> bool {
+    let Some(want) = secret
+        .metadata
+        .annotations
+        .as_ref()
+        .and_then(|a| a.get(CHECKSUM_ANNOTATION))
+    else {
+        return true;
+    };
+    read(secret, default_key).is_some_and(|v| checksum(&v) == *want)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_custom_key_name() {
+        let template = SecretTemplateSpec {
+            key_name: Some("MEILI_API_KEY".into()),
+            type_: Some("example.com/api-key".into()),
+            labels: BTreeMap::from([("team".into(), "search".into())]),
+            annotations: BTreeMap::from([("replicator/replicate".into(), "true".into())]),
+        };
+        let sec = build("s", Some(&template), "key", "abc", None, false);
+        assert_eq!(sec.type_.as_deref(), Some("example.com/api-key"));
+        assert!(
+            sec.string_data
+                .as_ref()
+                .unwrap()
+                .contains_key("MEILI_API_KEY")
+        );
+        assert_eq!(read(&sec, "key").as_deref(), Some("abc"));
+
+        let plain = build("s", None, "masterKey", "xyz", None, false);
+        assert_eq!(plain.type_, None);
+        assert_eq!(read(&plain, "masterKey").as_deref(), Some("xyz"));
+    }
+
+    #[test]
+    fn checksum_catches_edited_values() {
+        let mut sec = build("s", None, "key", "abc", None, false);
+        assert!(intact(&sec, "key"));
+        sec.string_data = Some(BTreeMap::from([("key".into(), "abd".into())]));
+        assert!(!intact(&sec, "key"));
+        sec.string_data = None;
+        assert!(!intact(&sec, "key"));
+        sec.metadata.annotations = None;
+        assert!(intact(&sec, "key"));
+    }
+}
@@ -1,49 +1,105 @@
+use std::collections::BTreeSet;
 use std::sync::Arc;
 
 use k8s_openapi::api::apps::v1::StatefulSet;
 use k8s_openapi::api::core::v1::{Secret, Service};
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, OwnerReference};
+use k8s_openapi::chrono::{DateTime, SecondsFormat, Utc};
 use kube::{
-    Api, Client, ResourceExt,
-    runtime::controller::{Action, Controller},
+    Api, Client, Resource, ResourceExt,
+    runtime::{
+        controller::{Action, Controller},
+        events::{EventType, Recorder},
+    },
 };
-use rand::{Rng, distributions::Alphanumeric};
+use rand::{Rng, TryRngCore, rngs::OsRng};
+use sha2::{Digest, Sha256};
 use tokio::time::Duration;
-use tracing::error;
+use tracing::{error, warn};
 
 use crate::{
+    audit::Audit,
+    capabilities,
+    children::{self, ServerChildren, server_selector},
+    clusters::{self, RemoteClusters, Target},
+    conditions::{
+        self, DEGRADED, DELETION_BLOCKED, DRIFT_DETECTED, PENDING_CHANGES, REPLICATION_UNSUPPORTED,
+    },
+    config::OperatorConfig,
     crds::{
+        self, FINALIZER,
+        common::{Phase, SecretStoreKind, SecretStoreSpec, SecretTemplateSpec},
         index::Index,
         key::Key,
-        server::{Server, ServerSpec, ServerStatus},
+        server::{
+            BackupKind, DriftPolicy, HealthCheckSpec, HealthTimeoutAction, IpFamily,
+            IpFamilyPolicy, MIN_MASTER_KEY_BYTES, MaintenanceWindow, MasterKeySpec, Server,
+            ServerDeletionPolicy, ServerSpec, ServerStatus, ServiceMesh, StatefulSetUpdate,
+            StatefulSetUpdateType, UpgradePhase, UpgradeStrategy,
+        },
     },
+    cron, dry_run,
     error::ReconcileError,
+    events, freeze, import, meili_http, object_store, phase, resync, secret_store, secret_template,
+    shutdown, task_cleanup, task_watch, upgrade,
+    version_resolver::{VersionResolver, image_for_version},
+    watch,
 };
 
-const FINALIZER: &str = "meili.operator.dev/finalizer";
+const RESYNC: Duration = Duration::from_secs(300);
 
 #[derive(Clone)]
 pub struct Ctx {
     pub client: Client,
     pub operator_namespace: String,
+    pub children: ServerChildren,
+    pub recorder: Recorder,
+    pub versions: Arc<VersionResolver>,
+    pub config: Arc<OperatorConfig>,
+    pub clusters: RemoteClusters,
 }
 
-pub fn controller(client: Client, _operator_namespace: String) -> Controller<Server> {
+pub fn controller(
+    client: Client,
+    _operator_namespace: String,
+    config: &OperatorConfig,
+) -> (Controller<Server>, ServerChildren) {
     let api: Api<Server> = Api::all(client.clone());
-    Controller::new(api, Default::default()).shutdown_on_signal()
+    ServerChildren::watch(&client, watch::controller(api, config))
 }
 
 pub async fn reconcile(server: Arc<Server>, ctx: Arc<Ctx>) -> Result<Action, ReconcileError> {
     let ns = server.namespace().unwrap();
     let name = server.name_any();
 
+    if dry_run::enabled(&ctx.config, server.as_ref()) {
+        return dry_run_server(&server, &ctx).await;
+    }
+
     // Handle deletion with finalizer (cleanup cross-namespace secret)
     if server.metadata.deletion_timestamp.is_some() {
+        phase::set(
+            &Api::<Server>::namespaced(ctx.client.clone(), &ns),
+            &name,
+            server.status.as_ref().map(|s| s.phase),
+            Phase::Terminating,
+        )
+        .await?;
+        if server.spec.deletion_policy == ServerDeletionPolicy::Block {
+            let (keys, indexes) = dependents(&ctx.client, &ns, &name).await?;
+            if let Some(msg) = blocking_dependents(&keys, &indexes) {
+                return block_deletion(&server, &ctx, msg).await;
+            }
+        }
         // Fast-delete dependent Keys and Indexes that reference this server.
         // We remove their finalizers and delete the CRs since the backing data is going away.
         fast_delete_children(&ctx.client, &ns, &name).await?;
         // delete operator namespace copy secret (cannot use ownerRef across namespaces)
         delete_operator_copy(&ctx.client, &ctx.operator_namespace, &ns, &name).await?;
+        // remote workloads are not garbage collected either
+        if server.spec.cluster_ref.is_some() {
+            delete_remote_workload(&server, &ctx).await?;
+        }
         // remove our finalizer
         remove_finalizer(&ctx.client, &ns, &name).await?;
         return Ok(Action::await_change());
@@ -52,40 +108,1009 @@ pub async fn reconcile(server: Arc<Server>, ctx: Arc<Ctx>) -> Result<Action, Rec
     // Ensure finalizer present early
     ensure_finalizer(&ctx.client, &ns, &name, &server).await?;
 
+    if let Err(msg) = validate_spec(&server.spec) {
+        return reject(&server, &ctx, "InvalidSpec", msg).await;
+    }
+
+    // Nothing changed since the last full reconcile and the owned objects still exist
+    if let Some(prev) = server.status.as_ref()
+        && prev.ready
+        && prev.upgrade.is_none()
+        && prev.dry_run_plan.is_none()
+        && !conditions::is_true(&prev.conditions, PENDING_CHANGES)
+        // Remote children are not in the local watch; rely on the periodic resync for them
+        && (server.spec.cluster_ref.is_some()
+            || ctx.children.present(
+                &ns,
+                &name,
+                prev.active_statefulset.as_deref().unwrap_or(&name),
+            ))
+        && let Some(wait) = resync::remaining(
+            server.metadata.generation,
+            prev.observed_generation,
+            prev.last_synced.as_deref(),
+            RESYNC,
+        )
+    {
+        // Crashed pods leave the objects in place; only the StatefulSet status shows it
+        let active = prev.active_statefulset.as_deref().unwrap_or(&name);
+        if server.spec.cluster_ref.is_some()
+            || workload_state(&ctx.client, &ns, &name, active)
+                .await?
+                .1
+                .is_none()
+        {
+            return Ok(Action::requeue(wait));
+        }
+    }
+
+    // Rollout and the health wait can take minutes on a fresh Server
+    if server
+        .status
+        .as_ref()
+        .is_none_or(|s| s.phase == Phase::Pending)
+    {
+        phase::set(
+            &Api::<Server>::namespaced(ctx.client.clone(), &ns),
+            &name,
+            None,
+            Phase::Provisioning,
+        )
+        .await?;
+    }
+
     // Ensure master key secret in app namespace
-    let owner = owner_ref(&server);
-    let mk = ensure_master_key_secret(&ctx.client, &ns, &name, &owner).await?;
+    let owner = crds::owner_ref(server.as_ref());
+    let immutable = server
+        .spec
+        .immutable_secrets
+        .unwrap_or(ctx.config.immutable_secrets);
+    let mk = ensure_master_key_secret(
+        &ctx.client,
+        &ns,
+        &name,
+        &owner,
+        server.spec.secret_template.as_ref(),
+        &server.spec.master_key.clone().unwrap_or_default(),
+        immutable,
+    )
+    .await?;
+    // A key supplied in the Secret beforehand is used as is, but Meilisearch refuses short ones
+    if mk.len() < MIN_MASTER_KEY_BYTES {
+        let msg = format!(
+            "master key in Secret {}-meili-master is {} bytes, Meilisearch requires at least {}",
+            name,
+            mk.len(),
+            MIN_MASTER_KEY_BYTES
+        );
+        reject(&server, &ctx, "WeakMasterKey", msg).await?;
+        return Ok(Action::requeue(RESYNC));
+    }
     // Mirror master key into operator namespace for management
     ensure_operator_copy(&ctx.client, &ctx.operator_namespace, &ns, &name, &mk).await?;
+    if let Some(store) = server.spec.secret_store.as_ref() {
+        export_master_key(
+            &ctx.client,
+            &ns,
+            &name,
+            store,
+            secret_template::key_name(server.spec.secret_template.as_ref(), "masterKey"),
+            &mk,
+            &owner,
+        )
+        .await?;
+    }
+
+    let target = ctx
+        .clusters
+        .target(&ctx.client, &ns, server.spec.cluster_ref.as_deref())
+        .await?;
+    if target.remote {
+        ensure_remote_master_key(&target, &server, &mk, immutable).await?;
+    }
+    // Owner references cannot point across clusters
+    let child_owner = (!target.remote).then_some(&owner);
+
+    let mut status = server.status.clone().unwrap_or_default();
+    let active = status
+        .active_statefulset
+        .clone()
+        .unwrap_or_else(|| name.clone());
+    let desired = resolve_desired_image(&server, &ctx, &mut status).await?;
+    // Disruptive changes wait for the maintenance window; None while it is open or unset
+    let window_opens = server
+        .spec
+        .maintenance_window
+        .as_ref()
+        .and_then(|w| window_closed(w, Utc::now()));
+    let mut pending = Vec::new();
+    // Blue/green and canary keep the serving StatefulSet on its current image until the
+    // switch-over; validate_spec makes sure they have storage
+    let blue_green = server.spec.upgrade_strategy != UpgradeStrategy::InPlace;
+    let serving_image = match status.current_image.as_ref() {
+        Some(current) if blue_green => current.clone(),
+        Some(current) if window_opens.is_some() && *current != desired => {
+            pending.push(format!("image {}", desired));
+            current.clone()
+        }
+        _ => desired.clone(),
+    };
+    // An upgrade already under way is finished rather than left halfway
+    let start_upgrade = status.upgrade.is_some() || window_opens.is_none();
+    if blue_green && serving_image != desired && !start_upgrade {
+        pending.push(format!("upgrade to {}", desired));
+    }
 
     // Ensure Service + StatefulSet
-    ensure_service(&ctx.client, &ns, &name, server.spec.port, &owner).await?;
-    ensure_statefulset(&ctx.client, &ns, &name, &server.spec, &owner).await?;
+    ensure_service(
+        &target.client,
+        &ns,
+        &name,
+        &active,
+        Some(&name),
+        ServiceNetwork::of(&server.spec),
+        child_owner,
+    )
+    .await?;
+    if active != name {
+        // StatefulSets created by blue/green upgrades are governed by a Service of their own name
+        ensure_service(
+            &target.client,
+            &ns,
+            &active,
+            &active,
+            Some(&name),
+            ServiceNetwork::of(&server.spec),
+            child_owner,
+        )
+        .await?;
+    }
+    let workload = Workload {
+        sts_name: active.clone(),
+        image: serving_image.clone(),
+    };
+    let drift = statefulset_drift(&target.client, &ns, &name, &workload, &server.spec).await?;
+    let correct = server.spec.drift_policy == DriftPolicy::Correct;
+    if drift.is_empty() {
+        conditions::set_condition(
+            &mut status.conditions,
+            DRIFT_DETECTED,
+            false,
+            "InSync",
+            "",
+            server.metadata.generation,
+        );
+    } else {
+        let msg = format!(
+            "StatefulSet {} edited outside the operator: {}; {}",
+            active,
+            drift.join(", "),
+            if correct {
+                "reverting it"
+            } else {
+                "leaving it in place (drift_policy: Warn)"
+            }
+        );
+        let reported = status
+            .conditions
+            .iter()
+            .any(|c| c.type_ == DRIFT_DETECTED && c.status == "True" && c.message == msg);
+        if !reported {
+            events::publish(
+                &ctx.recorder,
+                &server.object_ref(&()),
+                EventType::Warning,
+                "StatefulSetDrift",
+                "Reconcile",
+                &msg,
+            )
+            .await;
+        }
+        conditions::set_condition(
+            &mut status.conditions,
+            DRIFT_DETECTED,
+            true,
+            "StatefulSetEdited",
+            &msg,
+            server.metadata.generation,
+        );
+    }
+    let checksum = statefulset_checksum(&build_statefulset(&name, &workload, &server.spec, None));
+    let held = window_opens.is_some()
+        && status
+            .statefulset_checksum
+            .as_ref()
+            .is_some_and(|applied| *applied != checksum);
+    if held {
+        pending.push(format!("StatefulSet {} changes", active));
+    } else if drift.is_empty() || correct {
+        ensure_statefulset(
+            &target.client,
+            &ns,
+            &name,
+            &workload,
+            &server.spec,
+            child_owner,
+        )
+        .await?;
+        status.statefulset_checksum = Some(checksum);
+    }
+    match window_opens {
+        Some(opens) if !pending.is_empty() => {
+            let msg = format!(
+                "waiting for the maintenance window at {}: {}",
+                opens.to_rfc3339_opts(SecondsFormat::Secs, true),
+                pending.join(", ")
+            );
+            let reported = status
+                .conditions
+                .iter()
+                .any(|c| c.type_ == PENDING_CHANGES && c.status == "True" && c.message == msg);
+            if !reported {
+                events::publish(
+                    &ctx.recorder,
+                    &server.object_ref(&()),
+                    EventType::Normal,
+                    "ChangesPending",
+                    "Reconcile",
+                    &msg,
+                )
+                .await;
+            }
+            conditions::set_condition(
+                &mut status.conditions,
+                PENDING_CHANGES,
+                true,
+                "OutsideMaintenanceWindow",
+                &msg,
+                server.metadata.generation,
+            );
+        }
+        _ => conditions::set_condition(
+            &mut status.conditions,
+            PENDING_CHANGES,
+            false,
+            "NothingPending",
+            "",
+            server.metadata.generation,
+        ),
+    }
+
+    // A Server that was serving and lost its pods is marked at once instead of after the health wait
+    let (ready_replicas, problem) = workload_state(&target.client, &ns, &name, &active).await?;
+    status.ready_replicas = Some(ready_replicas);
+    if let Some(msg) = problem
+        && (status.ready
+            || status
+                .conditions
+                .iter()
+                .any(|c| c.type_ == DEGRADED && c.reason == "WorkloadUnavailable"))
+    {
+        return mark_unavailable(&server, &ctx, status, msg).await;
+    }
 
     // Wait for meilisearch to be healthy
-    let endpoint = format!(
-        "http://{}.{}.svc.cluster.local:{}",
-        name, ns, server.spec.port
-    );
-    wait_meili_healthy(&endpoint, &mk).await?;
+    let internal = target.endpoint(&name, &ns, server.spec.port);
+    let published = clusters::operator_endpoint(&server.spec, &internal);
+    let endpoint = match &ctx.config.dev_endpoints {
+        Some(dev) => clusters::dev_endpoint(&target.client, dev, &server).await?,
+        None => published.clone(),
+    };
+    status.internal_endpoint = Some(internal);
+    let health = server.spec.health_check.clone().unwrap_or_default();
+    if !wait_meili_healthy(&endpoint, &health, server.spec.mesh.is_some()).await? {
+        let msg = format!(
+            "Meilisearch not healthy after {} probes {}s apart",
+            health.max_attempts, health.interval_seconds
+        );
+        if health.on_timeout == HealthTimeoutAction::Error {
+            return Err(anyhow::anyhow!(msg).into());
+        }
+        status.endpoint = Some(published.clone());
+        return mark_unhealthy(&server, &ctx, status, msg).await;
+    }
 
     // Update status
-    let status = ServerStatus {
-        ready: true,
-        endpoint: Some(endpoint),
-        message: None,
+    status.ready = true;
+    status.endpoint = Some(published.clone());
+    status.message = None;
+    status.current_image = Some(serving_image.clone());
+    status.active_statefulset = Some(active.clone());
+    status.dry_run_plan = None;
+    status.observed_generation = server.metadata.generation;
+    status.last_synced = Some(resync::now());
+    status.log_level = Some(
+        server
+            .spec
+            .log_level
+            .map_or("INFO", |l| l.as_str())
+            .to_string(),
+    );
+    status.logs_route = server
+        .spec
+        .experimental_logs_route
+        .then(|| format!("{}/logs/stream", published));
+    match capabilities::detect(&endpoint, &mk).await {
+        Ok(version) => {
+            status.capabilities = capabilities::supported(&version);
+            status.meilisearch_version = Some(version.to_string());
+        }
+        Err(e) => warn!(error = ?e, server = %name, "version detection failed"),
+    }
+    if let Some(cleanup) = server.spec.task_cleanup.as_ref() {
+        let audit = Audit::new(&ctx.recorder, server.as_ref(), &name);
+        if let Err(e) =
+            task_cleanup::maybe_cleanup_tasks(&audit, &endpoint, &mk, cleanup, &mut status).await
+        {
+            warn!(error = ?e, server = %name, "task cleanup failed");
+        }
+    }
+    let (keys, indexes) = dependents(&ctx.client, &ns, &name).await?;
+    match import::scan(&ctx.client, &endpoint, &mk, (&ns, &name), &indexes, &keys).await {
+        Ok((indexes, keys)) => status.unmanaged = import::summary(&indexes, &keys),
+        Err(e) => warn!(error = ?e, server = %name, "unmanaged resource scan failed"),
+    }
+    if let Err(e) = task_watch::report_failed_tasks(
+        &ctx.client,
+        &ctx.recorder,
+        &endpoint,
+        &mk,
+        &indexes,
+        &mut status,
+    )
+    .await
+    {
+        warn!(error = ?e, server = %name, "failed task report failed");
+    }
+    let generation = server.metadata.generation;
+    // Come back when the window opens for what it holds back
+    let mut requeue = match window_opens {
+        Some(opens) if !pending.is_empty() => (opens - Utc::now())
+            .to_std()
+            .map_or(RESYNC, |d| d.min(RESYNC) + Duration::from_secs(1)),
+        _ => RESYNC,
     };
+    if serving_image != desired && start_upgrade {
+        // The upgrade target's own Service, resolved like the Server's
+        let upgrade_target = upgrade::next_statefulset(&name, &active);
+        let target_endpoint = match &ctx.config.dev_endpoints {
+            Some(dev) => {
+                let port = server.spec.port;
+                clusters::dev_service_endpoint(&target.client, dev, &ns, &upgrade_target, port)
+                    .await?
+            }
+            None => Some(target.endpoint(&upgrade_target, &ns, server.spec.port)),
+        };
+        let ctx_up = upgrade::UpgradeCtx {
+            client: &ctx.client,
+            recorder: &ctx.recorder,
+            server: &server,
+            owner: &owner,
+            endpoint: &endpoint,
+            target_endpoint: target_endpoint.as_deref(),
+            master_key: &mk,
+        };
+        requeue = upgrade::step_upgrade(&ctx_up, &desired, &mut status).await?;
+    } else {
+        status.upgrade = None;
+    }
+    let (ready_replicas, unavailable) = workload_state(&target.client, &ns, &name, &active).await?;
+    status.ready_replicas = Some(ready_replicas);
+    let upgrade_failure = status
+        .upgrade
+        .as_ref()
+        .filter(|u| u.phase == UpgradePhase::Failed)
+        .map(|u| u.message.clone().unwrap_or_default());
+    let warning = replicas_warning(&server.spec);
+    // Warn once when the replicas setting appears, whatever else the Server is degraded by
+    if let Some(warning) = warning.as_ref()
+        && !conditions::is_true(&status.conditions, REPLICATION_UNSUPPORTED)
+    {
+        events::publish(
+            &ctx.recorder,
+            &server.object_ref(&()),
+            EventType::Warning,
+            "ReplicationUnsupported",
+            "Reconcile",
+            warning,
+        )
+        .await;
+    }
+    conditions::set_condition(
+        &mut status.conditions,
+        REPLICATION_UNSUPPORTED,
+        warning.is_some(),
+        if warning.is_some() {
+            "MultipleReplicas"
+        } else {
+            "AsExpected"
+        },
+        warning.clone().unwrap_or_default(),
+        generation,
+    );
+    match degraded_reason(unavailable, upgrade_failure, warning) {
+        Some((reason, msg)) => {
+            conditions::set_condition(
+                &mut status.conditions,
+                DEGRADED,
+                true,
+                reason,
+                &msg,
+                generation,
+            );
+            status.message = Some(msg);
+        }
+        None => conditions::set_condition(
+            &mut status.conditions,
+            DEGRADED,
+            false,
+            "AsExpected",
+            "",
+            generation,
+        ),
+    }
+    status.phase = phase::settled(conditions::is_true(&status.conditions, DEGRADED));
+    patch_status(&ctx.client, &ns, &name, &status).await?;
+
+    Ok(Action::requeue(requeue))
+}
+
+/// Image the Server should run: `version` resolved against releases, or the configured image,
+/// pinned to `image_digest` when set.
+async fn resolve_desired_image(
+    server: &Server,
+    ctx: &Ctx,
+    status: &mut ServerStatus,
+) -> Result<String, ReconcileError> {
+    let desired = match server.spec.version.as_deref() {
+        Some(constraint) => {
+            let version = match ctx.versions.resolve(constraint).await {
+                Ok(v) => v,
+                // Keep the last resolution if the release source is unreachable, as long as
+                // the constraint still admits it
+                Err(e) => status
+                    .resolved_version
+                    .as_deref()
+                    .and_then(|v| fallback_version(constraint, v))
+                    .ok_or(e)?,
+            };
+            status.resolved_version = Some(version.to_string());
+            image_for_version(
+                server
+                    .spec
+                    .image
+                    .as_deref()
+                    .unwrap_or(&ctx.config.default_image),
+                &version,
+            )
+        }
+        None => {
+            status.resolved_version = None;
+            default_or_current(
+                &server.spec,
+                status.current_image.as_deref(),
+                &ctx.config.default_image,
+                ctx.config.default_image_rollout,
+            )
+        }
+    };
+    Ok(pin_digest(desired, server.spec.image_digest.as_deref()))
+}
+
+/// The version resolved earlier, if `constraint` still matches it.
+fn fallback_version(constraint: &str, resolved: &str) -> Option<semver::Version> {
+    let version = semver::Version::parse(resolved).ok()?;
+    semver::VersionReq::parse(constraint)
+        .ok()?
+        .matches(&version)
+        .then_some(version)
+}
+
+/// When `window` opens next, or None while it is open.
+fn window_closed(window: &MaintenanceWindow, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    // validate_spec rejects windows that do not parse
+    let schedule = cron::Schedule::parse(&window.schedule).ok()?;
+    let length = task_cleanup::parse_age(&window.duration)?;
+    // Open if it opened within the last `length`
+    let mut after = now - length - k8s_openapi::chrono::Duration::minutes(1);
+    while let Some(start) = schedule.next_after(after).filter(|s| *s <= now) {
+        if now < start + length {
+            return None;
+        }
+        after = start;
+    }
+    schedule.next_after(now)
+}
+
+fn statefulset_checksum(sts: &StatefulSet) -> String {
+    let spec = serde_json::to_vec(&sts.spec).unwrap_or_default();
+    object_store::hex(&Sha256::digest(spec))
+}
+
+/// Report what a reconcile would change without touching the cluster or Meilisearch.
+async fn dry_run_server(server: &Server, ctx: &Ctx) -> Result<Action, ReconcileError> {
+    let ns = server.namespace().unwrap();
+    let name = server.name_any();
+    let mut status = server.status.clone().unwrap_or_default();
+    let mut plan = Vec::new();
+    let copy_name = operator_copy_name(&ns, &name);
+    let blocked = if server.metadata.deletion_timestamp.is_some()
+        && server.spec.deletion_policy == ServerDeletionPolicy::Block
+    {
+        let (keys, indexes) = dependents(&ctx.client, &ns, &name).await?;
+        blocking_dependents(&keys, &indexes)
+    } else {
+        None
+    };
+    if let Some(msg) = blocked {
+        plan.push(format!("keep finalizer: {}", msg));
+    } else if server.metadata.deletion_timestamp.is_some() {
+        plan.push(format!("delete Index and Key CRs referencing {}", name));
+        plan.push(format!(
+            "delete Secret {}/{}",
+            ctx.operator_namespace, copy_name
+        ));
+        plan.push("remove finalizer".into());
+    } else if let Err(msg) = validate_spec(&server.spec) {
+        plan.push(format!("reject spec: {}", msg));
+    } else {
+        if !server.finalizers().iter().any(|f| f == FINALIZER) {
+            plan.push("add finalizer".into());
+        }
+        let secrets: Api<Secret> = Api::namespaced(ctx.client.clone(), &ns);
+        let mk_name = format!("{}-meili-master", name);
+        if secrets.get_opt(&mk_name).await?.is_none() {
+            plan.push(format!("create Secret {}/{}", ns, mk_name));
+        }
+        let op_secrets: Api<Secret> = Api::namespaced(ctx.client.clone(), &ctx.operator_namespace);
+        if op_secrets.get_opt(&copy_name).await?.is_none() {
+            plan.push(format!(
+                "create Secret {}/{}",
+                ctx.operator_namespace, copy_name
+            ));
+        }
+        let target = ctx
+            .clusters
+            .target(&ctx.client, &ns, server.spec.cluster_ref.as_deref())
+            .await?;
+        if let Some(cluster) = server.spec.cluster_ref.as_deref() {
+            plan.push(format!("run workload in Cluster {}", cluster));
+        }
+        let services: Api<Service> = Api::namespaced(target.client.clone(), &ns);
+        if services.get_opt(&name).await?.is_none() {
+            plan.push(format!("create Service {}/{}", ns, name));
+        }
+        let active = status
+            .active_statefulset
+            .clone()
+            .unwrap_or_else(|| name.clone());
+        let desired = resolve_desired_image(server, ctx, &mut status).await?;
+        let statefulsets: Api<StatefulSet> = Api::namespaced(target.client.clone(), &ns);
+        let running = statefulsets.get_opt(&active).await?.map(|sts| {
+            sts.spec
+                .and_then(|s| s.template.spec)
+                .and_then(|p| p.containers.into_iter().next())
+                .and_then(|c| c.image)
+                .unwrap_or_default()
+        });
+        match running {
+            None => plan.push(format!(
+                "create StatefulSet {}/{} running {}",
+                ns, active, desired
+            )),
+            Some(image) if image != desired => plan.push(format!(
+                "upgrade {} from {} to {} ({:?})",
+                active, image, desired, server.spec.upgrade_strategy
+            )),
+            Some(_) => {}
+        }
+    }
+    dry_run::report(
+        &ctx.recorder,
+        &server.object_ref(&()),
+        status.dry_run_plan.as_ref(),
+        &plan,
+    )
+    .await;
+    status.dry_run_plan = Some(plan);
+    patch_status(&ctx.client, &ns, &name, &status).await?;
+    Ok(Action::requeue(dry_run::recheck(
+        &ctx.config,
+        Duration::from_secs(300),
+    )))
+}
+
+async fn patch_status(
+    client: &Client,
+    ns: &str,
+    name: &str,
+    status: &ServerStatus,
+) -> Result<(), ReconcileError> {
     let ss_apply = kube::api::PatchParams::apply("meilisearch-operator");
-    let servers: Api<Server> = Api::namespaced(ctx.client.clone(), &ns);
+    let servers: Api<Server> = Api::namespaced(client.clone(), ns);
     let _ = servers
         .patch_status(
-            &name,
+            name,
             &ss_apply,
             &kube::api::Patch::Merge(serde_json::json!({ "status": status })),
         )
         .await?;
+    Ok(())
+}
+
+/// Record a spec the operator refuses to roll out and wait for the next edit.
+async fn reject(
+    server: &Server,
+    ctx: &Ctx,
+    reason: &str,
+    msg: String,
+) -> Result<Action, ReconcileError> {
+    let ns = server.namespace().unwrap();
+    let name = server.name_any();
+    let mut status = server.status.clone().unwrap_or_default();
+    if !conditions::is_true(&status.conditions, DEGRADED) || status.message.as_ref() != Some(&msg) {
+        events::publish(
+            &ctx.recorder,
+            &server.object_ref(&()),
+            EventType::Warning,
+            reason,
+            "Reconcile",
+            &msg,
+        )
+        .await;
+    }
+    status.phase = Phase::Degraded;
+    status.ready = false;
+    conditions::set_condition(
+        &mut status.conditions,
+        DEGRADED,
+        true,
+        reason,
+        &msg,
+        server.metadata.generation,
+    );
+    status.message = Some(msg);
+    status.observed_generation = server.metadata.generation;
+    patch_status(&ctx.client, &ns, &name, &status).await?;
+    Ok(Action::await_change())
+}
+
+/// Names of the dependents holding up a `deletion_policy: Block` Server, if any.
+fn blocking_dependents(keys: &[Key], indexes: &[Index]) -> Option<String> {
+    let names: Vec<String> = keys
+        .iter()
+        .map(|k| format!("Key {}/{}", k.namespace().unwrap_or_default(), k.name_any()))
+        .chain(indexes.iter().map(|i| {
+            format!(
+                "Index {}/{}",
+                i.namespace().unwrap_or_default(),
+                i.name_any()
+            )
+        }))
+        .collect();
+    (!names.is_empty()).then(|| {
+        format!(
+            "deletion blocked by {} dependent(s): {}; delete them or set deletion_policy: Cascade",
+            names.len(),
+            names.join(", ")
+        )
+    })
+}
+
+/// Keep the finalizer of a `deletion_policy: Block` Server while dependents exist and check
+/// again shortly.
+async fn block_deletion(server: &Server, ctx: &Ctx, msg: String) -> Result<Action, ReconcileError> {
+    let ns = server.namespace().unwrap();
+    let name = server.name_any();
+    let mut status = server.status.clone().unwrap_or_default();
+    if !conditions::is_true(&status.conditions, DELETION_BLOCKED)
+        || status.message.as_ref() != Some(&msg)
+    {
+        events::publish(
+            &ctx.recorder,
+            &server.object_ref(&()),
+            EventType::Warning,
+            "DeletionBlocked",
+            "Delete",
+            &msg,
+        )
+        .await;
+    }
+    status.phase = Phase::Terminating;
+    conditions::set_condition(
+        &mut status.conditions,
+        DELETION_BLOCKED,
+        true,
+        "DependentsExist",
+        &msg,
+        server.metadata.generation,
+    );
+    status.message = Some(msg);
+    patch_status(&ctx.client, &ns, &name, &status).await?;
+    Ok(Action::requeue(Duration::from_secs(30)))
+}
+
+/// Ready pods of the StatefulSet `sts`, and why it or the Service `service` cannot serve.
+async fn workload_state(
+    client: &Client,
+    ns: &str,
+    service: &str,
+    sts: &str,
+) -> Result<(i32, Option<String>), ReconcileError> {
+    let live = Api::<StatefulSet>::namespaced(client.clone(), ns)
+        .get_opt(sts)
+        .await?;
+    let ready = live
+        .as_ref()
+        .and_then(|s| s.status.as_ref())
+        .and_then(|s| s.ready_replicas)
+        .unwrap_or(0);
+    let problem = match &live {
+        None => Some(format!("StatefulSet {} is missing", sts)),
+        Some(live) => unready_statefulset(live),
+    };
+    let problem = match problem {
+        None if Api::<Service>::namespaced(client.clone(), ns)
+            .get_opt(service)
+            .await?
+            .is_none() =>
+        {
+            Some(format!("Service {} is missing", service))
+        }
+        p => p,
+    };
+    Ok((ready, problem))
+}
 
-    Ok(Action::requeue(Duration::from_secs(300)))
+/// Why `sts` is short of ready pods. A rollout still in progress is not a problem; the
+/// health wait covers it.
+fn unready_statefulset(sts: &StatefulSet) -> Option<String> {
+    let status = sts.status.as_ref()?;
+    let rolling = status.observed_generation != sts.metadata.generation
+        || status.update_revision != status.current_revision;
+    let want = sts.spec.as_ref().and_then(|s| s.replicas).unwrap_or(1);
+    let ready = status.ready_replicas.unwrap_or(0);
+    (!rolling && ready < want).then(|| {
+        format!(
+            "StatefulSet {} has {}/{} ready pods",
+            sts.name_any(),
+            ready,
+            want
+        )
+    })
+}
+
+async fn mark_unavailable(
+    server: &Server,
+    ctx: &Ctx,
+    mut status: ServerStatus,
+    msg: String,
+) -> Result<Action, ReconcileError> {
+    if !conditions::is_true(&status.conditions, DEGRADED) || status.message.as_ref() != Some(&msg) {
+        events::publish(
+            &ctx.recorder,
+            &server.object_ref(&()),
+            EventType::Warning,
+            "WorkloadUnavailable",
+            "Reconcile",
+            &msg,
+        )
+        .await;
+    }
+    status.phase = Phase::Degraded;
+    status.ready = false;
+    conditions::set_condition(
+        &mut status.conditions,
+        DEGRADED,
+        true,
+        "WorkloadUnavailable",
+        &msg,
+        server.metadata.generation,
+    );
+    status.message = Some(msg);
+    status.last_synced = Some(resync::now());
+    patch_status(
+        &ctx.client,
+        &server.namespace().unwrap(),
+        &server.name_any(),
+        &status,
+    )
+    .await?;
+    Ok(Action::requeue(Duration::from_secs(30)))
+}
+
+/// Record a Server whose instance missed its health deadline and look again shortly,
+/// for `health_check.on_timeout: Degraded`.
+async fn mark_unhealthy(
+    server: &Server,
+    ctx: &Ctx,
+    mut status: ServerStatus,
+    msg: String,
+) -> Result<Action, ReconcileError> {
+    let ns = server.namespace().unwrap();
+    let name = server.name_any();
+    if !conditions::is_true(&status.conditions, DEGRADED) || status.message.as_ref() != Some(&msg) {
+        events::publish(
+            &ctx.recorder,
+            &server.object_ref(&()),
+            EventType::Warning,
+            "HealthCheckTimeout",
+            "Reconcile",
+            &msg,
+        )
+        .await;
+    }
+    status.phase = Phase::Degraded;
+    status.ready = false;
+    conditions::set_condition(
+        &mut status.conditions,
+        DEGRADED,
+        true,
+        "HealthCheckTimeout",
+        &msg,
+        server.metadata.generation,
+    );
+    status.message = Some(msg);
+    status.observed_generation = server.metadata.generation;
+    status.last_synced = Some(resync::now());
+    patch_status(&ctx.client, &ns, &name, &status).await?;
+    Ok(Action::requeue(Duration::from_secs(30)))
+}
+
+/// Checks the CRD schema cannot express on its own.
+fn validate_spec(spec: &ServerSpec) -> Result<(), String> {
+    let image_digest = spec
+        .image
+        .as_deref()
+        .and_then(|i| i.split_once('@'))
+        .map(|(_, d)| d);
+    for digest in [image_digest, spec.image_digest.as_deref()]
+        .into_iter()
+        .flatten()
+    {
+        if !is_sha256_digest(digest) {
+            return Err(format!(
+                "invalid image digest {:?}, expected sha256:<64 hex>",
+                digest
+            ));
+        }
+    }
+    for (field, value) in [
+        (
+            "http_payload_size_limit",
+            spec.http_payload_size_limit.as_deref(),
+        ),
+        ("max_indexing_memory", spec.max_indexing_memory.as_deref()),
+    ] {
+        if let Some(v) = value
+            && parse_byte_size(v).is_none_or(|b| b == 0)
+        {
+            return Err(format!(
+                "{} {:?} is not a valid size (e.g. 100MB, 2GiB, 512Mi)",
+                field, v
+            ));
+        }
+    }
+    if let Some(window) = spec.maintenance_window.as_ref() {
+        cron::Schedule::parse(&window.schedule)
+            .map_err(|e| format!("maintenance_window.schedule {:?}: {}", window.schedule, e))?;
+        if task_cleanup::parse_age(&window.duration).is_none() {
+            return Err(format!(
+                "maintenance_window.duration {:?} must look like 4h, 90m or 1d",
+                window.duration
+            ));
+        }
+    }
+    if let Some(cleanup) = spec.task_cleanup.as_ref() {
+        if task_cleanup::parse_age(&cleanup.older_than).is_none() {
+            return Err(format!(
+                "task_cleanup.older_than {:?} must look like 7d, 12h, 30m or 90s",
+                cleanup.older_than
+            ));
+        }
+        if cleanup.interval_seconds <= 0 {
+            return Err(format!(
+                "task_cleanup.interval_seconds {} must be positive",
+                cleanup.interval_seconds
+            ));
+        }
+        if let Some(bad) = cleanup
+            .statuses
+            .iter()
+            .find(|s| !matches!(s.as_str(), "succeeded" | "failed" | "canceled"))
+        {
+            return Err(format!(
+                "task_cleanup.statuses: {:?} is not a finished task status",
+                bad
+            ));
+        }
+    }
+    if let Some(store) = spec.secret_store.as_ref() {
+        match store.kind {
+            SecretStoreKind::Vault if store.vault.is_none() => {
+                return Err("secret_store kind Vault requires secret_store.vault".into());
+            }
+            SecretStoreKind::PushSecret if store.push_secret.is_none() => {
+                return Err(
+                    "secret_store kind PushSecret requires secret_store.push_secret".into(),
+                );
+            }
+            _ => {}
+        }
+    }
+    if spec.upgrade_strategy != UpgradeStrategy::InPlace && spec.storage.is_none() {
+        return Err(format!(
+            "upgrade_strategy {:?} requires storage",
+            spec.upgrade_strategy
+        ));
+    }
+    if spec.upgrade_strategy == UpgradeStrategy::Canary && spec.canary.is_none() {
+        return Err("upgrade_strategy Canary requires canary".into());
+    }
+    if spec.cluster_ref.is_some() && spec.upgrade_strategy != UpgradeStrategy::InPlace {
+        return Err("cluster_ref supports only upgrade_strategy InPlace".into());
+    }
+    if let Some(url) = spec.endpoint_override.as_deref()
+        && reqwest::Url::parse(url).is_err()
+    {
+        return Err(format!("endpoint_override {:?} is not a valid URL", url));
+    }
+    if spec.dns_policy.as_deref() == Some("None") && spec.dns_config.is_none() {
+        return Err("dns_policy None requires dns_config".into());
+    }
+    if image_digest.is_some() && spec.image_digest.is_some() {
+        return Err("set the digest either in image or in image_digest, not both".into());
+    }
+    if let Some(constraint) = spec.version.as_deref()
+        && let Err(e) = semver::VersionReq::parse(constraint)
+    {
+        return Err(format!("version {:?}: {}", constraint, e));
+    }
+    if spec.version.is_some() && (image_digest.is_some() || spec.image_digest.is_some()) {
+        return Err("version cannot be combined with a digest-pinned image".into());
+    }
+    Ok(())
+}
+
+fn is_sha256_digest(digest: &str) -> bool {
+    digest.strip_prefix("sha256:").is_some_and(|hex| {
+        hex.len() == 64 && hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    })
+}
+
+/// Append `image_digest` to the image reference so the runtime pulls exactly that manifest.
+fn pin_digest(image: String, digest: Option<&str>) -> String {
+    match digest {
+        Some(d) if !image.contains('@') => format!("{}@{}", image, d),
+        _ => image,
+    }
+}
+
+/// Key and Index CRs referencing `ns/server_name`; Indexes may live in other namespaces.
+async fn dependents(
+    client: &Client,
+    ns: &str,
+    server_name: &str,
+) -> Result<(Vec<Key>, Vec<Index>), ReconcileError> {
+    let lp = kube::api::ListParams::default().labels(&server_selector(server_name));
+    let keys = Api::<Key>::namespaced(client.clone(), ns)
+        .list(&lp)
+        .await?
+        .items
+        .into_iter()
+        .filter(|k| k.spec.server_ref == server_name)
+        .collect();
+    let indexes = Api::<Index>::all(client.clone())
+        .list(&lp)
+        .await?
+        .items
+        .into_iter()
+        .filter(|i| {
+            let own = i.namespace().unwrap_or_default();
+            i.spec.server_ref.name == server_name && i.spec.server_ref.namespace_or(&own) == ns
+        })
+        .collect();
+    Ok((keys, indexes))
 }
 
 async fn fast_delete_children(
@@ -116,38 +1141,45 @@ async fn fast_delete_children(
         Ok(())
     }
 
-    // Delete Keys
-    {
-        let api: Api<Key> = Api::namespaced(client.clone(), ns);
-        let list = api.list(&kube::api::ListParams::default()).await?;
-        for k in list
-            .items
-            .into_iter()
-            .filter(|k| k.spec.server_ref == server_name)
-        {
-            if let Some(n) = k.metadata.name.as_deref() {
-                let _ = remove_finals_and_delete(&api, n).await;
-            }
+    let (keys, indexes) = dependents(client, ns, server_name).await?;
+    let api: Api<Key> = Api::namespaced(client.clone(), ns);
+    for k in keys {
+        if let Some(n) = k.metadata.name.as_deref() {
+            let _ = remove_finals_and_delete(&api, n).await;
         }
     }
-
-    // Delete Indexes
-    {
-        let api: Api<Index> = Api::namespaced(client.clone(), ns);
-        let list = api.list(&kube::api::ListParams::default()).await?;
-        for i in list
-            .items
-            .into_iter()
-            .filter(|i| i.spec.server_ref == server_name)
-        {
-            if let Some(n) = i.metadata.name.as_deref() {
-                let _ = remove_finals_and_delete(&api, n).await;
-            }
+    for i in indexes {
+        let api: Api<Index> = Api::namespaced(client.clone(), &i.namespace().unwrap_or_default());
+        if let Some(n) = i.metadata.name.as_deref() {
+            let _ = remove_finals_and_delete(&api, n).await;
         }
     }
 
     Ok(())
 }
+/// Meilisearch has no replica sync; multiple pods serve divergent data.
+fn replicas_warning(spec: &ServerSpec) -> Option<String> {
+    (spec.replicas > 1).then(|| {
+        format!(
+            "replicas={} runs independent Meilisearch instances without data replication; search results will be inconsistent",
+            spec.replicas
+        )
+    })
+}
+
+/// Reason and message of the Degraded condition: an unavailable workload or a failed upgrade
+/// outranks the replicas warning, which is also reported on its own condition.
+fn degraded_reason(
+    unavailable: Option<String>,
+    upgrade_failure: Option<String>,
+    replicas_warning: Option<String>,
+) -> Option<(&'static str, String)> {
+    unavailable
+        .map(|msg| ("WorkloadUnavailable", msg))
+        .or(upgrade_failure.map(|msg| ("UpgradeFailed", msg)))
+        .or(replicas_warning.map(|msg| ("ReplicationUnsupported", msg)))
+}
+
 pub fn error_policy(_server: Arc<Server>, err: &ReconcileError, _ctx: Arc<Ctx>) -> Action {
     error!(error = ?err, "reconcile failed");
     Action::requeue(Duration::from_secs(30))
@@ -158,32 +1190,40 @@ async fn ensure_master_key_secret(
     ns: &str,
     name: &str,
     owner: &OwnerReference,
+    template: Option<&SecretTemplateSpec>,
+    policy: &MasterKeySpec,
+    immutable: bool,
 ) -> Result<String, ReconcileError> {
     let secrets: Api<Secret> = Api::namespaced(client.clone(), ns);
     let sec_name = format!("{}-meili-master", name);
     if let Some(sec) = secrets.get_opt(&sec_name).await?
-        && let Some(data) = sec.data
-        && let Some(bytes) = data.get("masterKey")
+        && let Some(key) = secret_template::read(&sec, "masterKey")
     {
-        return Ok(String::from_utf8(bytes.0.clone())?);
+        // Secrets created before the labels existed are never rewritten, so label them here
+        if !sec.labels().contains_key(children::MANAGED_BY_LABEL) {
+            let patch =
+                serde_json::json!({"metadata": {"labels": children::managed_labels(Some(name))}});
+            let pp = kube::api::PatchParams {
+                field_manager: Some(children::FIELD_MANAGER.into()),
+                ..Default::default()
+            };
+            let _ = secrets
+                .patch(&sec_name, &pp, &kube::api::Patch::Merge(&patch))
+                .await?;
+        }
+        return Ok(key);
     }
-    let key: String = rand::thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(64)
-        .map(char::from)
-        .collect();
-    let sec = Secret {
-        metadata: kube::core::ObjectMeta {
-            name: Some(sec_name.clone()),
-            owner_references: Some(vec![owner.clone()]),
-            ..Default::default()
-        },
-        string_data: Some(std::collections::BTreeMap::from([(
-            String::from("masterKey"),
-            key.clone(),
-        )])),
-        ..Default::default()
-    };
+    let key = generate_master_key(policy);
+    let mut sec = secret_template::build(
+        &sec_name,
+        template,
+        "masterKey",
+        &key,
+        Some(vec![owner.clone()]),
+        immutable,
+    );
+    sec.labels_mut()
+        .insert(children::SERVER_LABEL.into(), name.into());
     let pp = kube::api::PostParams::default();
     match secrets.create(&pp, &sec).await {
         Ok(_) => Ok(key),
@@ -192,6 +1232,77 @@ async fn ensure_master_key_secret(
     }
 }
 
+/// A key of `policy.length` characters from its alphabet, drawn from the operating system's
+/// CSPRNG.
+fn generate_master_key(policy: &MasterKeySpec) -> String {
+    let alphabet = policy.charset.alphabet();
+    let mut rng = OsRng.unwrap_err();
+    (0..policy.length.max(MIN_MASTER_KEY_BYTES))
+        .map(|_| alphabet[rng.random_range(0..alphabet.len())] as char)
+        .collect()
+}
+
+/// Copy the master key to the external backend selected by `secret_store`.
+async fn export_master_key(
+    client: &Client,
+    ns: &str,
+    name: &str,
+    store: &SecretStoreSpec,
+    key_name: &str,
+    key: &str,
+    owner: &OwnerReference,
+) -> Result<(), ReconcileError> {
+    match store.kind {
+        SecretStoreKind::Kubernetes => Ok(()),
+        SecretStoreKind::Vault => {
+            let vault = store
+                .vault
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("secret_store.vault is required for kind Vault"))?;
+            let data =
+                std::collections::BTreeMap::from([("masterKey".to_string(), key.to_string())]);
+            secret_store::vault_write(vault, &data).await
+        }
+        SecretStoreKind::PushSecret => {
+            let push = store.push_secret.as_ref().ok_or_else(|| {
+                anyhow::anyhow!("secret_store.push_secret is required for kind PushSecret")
+            })?;
+            secret_store::ensure_push_secret(
+                client,
+                ns,
+                &format!("{}-meili-master", name),
+                key_name,
+                push,
+                Some(owner.clone()),
+            )
+            .await
+        }
+    }
+}
+
+/// Name of the master key copy in the operator namespace. The hash of namespace and name
+/// keeps Servers apart whose joined names coincide ("a-b"/"c" and "a"/"b-c"); the readable
+/// part is shortened so the name stays within the 253 characters Secrets allow.
+fn operator_copy_name(ns: &str, name: &str) -> String {
+    const SUFFIX: &str = "-meili-master";
+    let digest = object_store::hex(&Sha256::digest(format!("{}/{}", ns, name)));
+    let hash = &digest[..10];
+    let mut readable = format!("{}-{}", ns, name);
+    readable.truncate(253 - SUFFIX.len() - hash.len() - 1);
+    format!("{}-{}{}", readable.trim_end_matches('-'), hash, SUFFIX)
+}
+
+/// Selector matching the master key copies of Server `ns`/`name`.
+fn operator_copy_selector(ns: &str, name: &str) -> String {
+    format!(
+        "{}={},{}={}",
+        children::SERVER_LABEL,
+        name,
+        children::SERVER_NAMESPACE_LABEL,
+        ns
+    )
+}
+
 async fn ensure_operator_copy(
     client: &Client,
     op_ns: &str,
@@ -200,10 +1311,13 @@ async fn ensure_operator_copy(
     key: &str,
 ) -> Result<(), ReconcileError> {
     let secrets: Api<Secret> = Api::namespaced(client.clone(), op_ns);
-    let sec_name = format!("{}-{}-meili-master", ns, name);
+    let sec_name = operator_copy_name(ns, name);
+    let mut labels = children::managed_labels(Some(name));
+    labels.insert(children::SERVER_NAMESPACE_LABEL.into(), ns.into());
     let sec = Secret {
         metadata: kube::core::ObjectMeta {
-            name: Some(sec_name),
+            name: Some(sec_name.clone()),
+            labels: Some(labels),
             ..Default::default()
         },
         string_data: Some(std::collections::BTreeMap::from([(
@@ -212,97 +1326,379 @@ async fn ensure_operator_copy(
         )])),
         ..Default::default()
     };
-    let params = kube::api::PatchParams::apply("meilisearch-operator").force();
-    let _ = secrets
-        .patch(
-            &sec.metadata.name.clone().unwrap(),
-            &params,
-            &kube::api::Patch::Apply(&sec),
-        )
-        .await?;
-    Ok(())
+    let _ = children::apply(&secrets, &sec_name, &sec).await?;
+    delete_legacy_operator_copy(&secrets, ns, name).await
+}
+
+/// Remove the copy named `<ns>-<name>-meili-master` by earlier releases, unless its labels
+/// show it belongs to another Server whose joined name is the same.
+async fn delete_legacy_operator_copy(
+    secrets: &Api<Secret>,
+    ns: &str,
+    name: &str,
+) -> Result<(), ReconcileError> {
+    let legacy = format!("{}-{}-meili-master", ns, name);
+    let Some(sec) = secrets.get_metadata_opt(&legacy).await? else {
+        return Ok(());
+    };
+    let labels = sec.labels();
+    if labels
+        .get(children::SERVER_LABEL)
+        .is_some_and(|s| s != name)
+        || labels
+            .get(children::SERVER_NAMESPACE_LABEL)
+            .is_some_and(|n| n != ns)
+    {
+        return Ok(());
+    }
+    match secrets.delete(&legacy, &Default::default()).await {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(ae)) if ae.code == 404 => Ok(()),
+        Err(e) => Err(e.into()),
+    }
 }
 
-async fn ensure_service(
+/// Port and IP families of a generated Service.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ServiceNetwork<'a> {
+    pub port: u16,
+    pub ip_families: &'a [IpFamily],
+    pub ip_family_policy: Option<IpFamilyPolicy>,
+    /// Name the port `http` with appProtocol `http`, so the mesh does not have to guess
+    pub mesh: bool,
+}
+
+impl<'a> ServiceNetwork<'a> {
+    pub fn of(spec: &'a ServerSpec) -> Self {
+        Self {
+            port: spec.port,
+            ip_families: &spec.ip_families,
+            ip_family_policy: spec.ip_family_policy,
+            mesh: spec.mesh.is_some(),
+        }
+    }
+
+    /// `port` in the cluster's default IP families.
+    pub fn port(port: u16) -> Self {
+        Self {
+            port,
+            ip_families: &[],
+            ip_family_policy: None,
+            mesh: false,
+        }
+    }
+}
+
+pub(crate) async fn ensure_service(
     client: &Client,
     ns: &str,
     name: &str,
-    port: u16,
-    owner: &OwnerReference,
+    app: &str,
+    server: Option<&str>,
+    network: ServiceNetwork<'_>,
+    owner: Option<&OwnerReference>,
 ) -> Result<(), ReconcileError> {
     let services: Api<Service> = Api::namespaced(client.clone(), ns);
-    let svc = build_service(name, port, owner);
-    let params = kube::api::PatchParams::apply("meilisearch-operator").force();
-    let _ = services
-        .patch(name, &params, &kube::api::Patch::Apply(&svc))
-        .await?;
+    let svc = build_service(name, app, server, network, owner);
+    let _ = children::apply(&services, name, &svc).await?;
     Ok(())
 }
 
-async fn ensure_statefulset(
+/// Spec fields of the StatefulSet the operator sets but someone else last changed, e.g. with
+/// `kubectl edit` or `kubectl set image`, each followed by that field manager.
+async fn statefulset_drift(
     client: &Client,
     ns: &str,
     name: &str,
+    workload: &Workload,
     spec: &ServerSpec,
-    owner: &OwnerReference,
+) -> Result<Vec<String>, ReconcileError> {
+    let api: Api<StatefulSet> = Api::namespaced(client.clone(), ns);
+    let Some(live) = api.get_opt(&workload.sts_name).await? else {
+        return Ok(Vec::new());
+    };
+    let desired = build_statefulset(name, workload, spec, None);
+    Ok(foreign_spec_fields(
+        live.metadata.managed_fields.as_deref().unwrap_or_default(),
+        &serde_json::to_value(&desired.spec).unwrap_or_default(),
+    ))
+}
+
+/// Leaf paths of a managedFields `fieldsV1` set, with list items keyed as `[name=...]`.
+fn field_paths(fields: &serde_json::Value, path: String, out: &mut Vec<String>) {
+    let Some(map) = fields.as_object().filter(|m| m.keys().any(|k| k != ".")) else {
+        out.push(path);
+        return;
+    };
+    for (key, sub) in map {
+        let segment = if let Some(f) = key.strip_prefix("f:") {
+            format!(".{}", f)
+        } else if let Some(k) = key.strip_prefix("k:") {
+            let item: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(k).unwrap_or_default();
+            let item: Vec<String> = item
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v.as_str().map_or(v.to_string(), String::from)))
+                .collect();
+            format!("[{}]", item.join(","))
+        } else {
+            continue;
+        };
+        field_paths(sub, format!("{}{}", path, segment), out);
+    }
+}
+
+/// Leaf paths of a JSON value with list items flattened, i.e. a field path without its keys.
+fn value_paths(value: &serde_json::Value, path: &str, out: &mut BTreeSet<String>) {
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (k, v) in map {
+                value_paths(v, &format!("{}.{}", path, k), out);
+            }
+        }
+        serde_json::Value::Array(items) if !items.is_empty() => {
+            for v in items {
+                value_paths(v, path, out);
+            }
+        }
+        _ => {
+            out.insert(path.to_string());
+        }
+    }
+}
+
+fn foreign_spec_fields(
+    managed: &[k8s_openapi::apimachinery::pkg::apis::meta::v1::ManagedFieldsEntry],
+    desired: &serde_json::Value,
+) -> Vec<String> {
+    let spec_paths = |e: &k8s_openapi::apimachinery::pkg::apis::meta::v1::ManagedFieldsEntry| {
+        let mut paths = Vec::new();
+        if let Some(spec) = e.fields_v1.as_ref().map(|f| &f.0["f:spec"])
+            && !spec.is_null()
+        {
+            field_paths(spec, "spec".into(), &mut paths);
+        }
+        paths
+    };
+    let ours = |e: &&k8s_openapi::apimachinery::pkg::apis::meta::v1::ManagedFieldsEntry| {
+        e.manager.as_deref() == Some(children::FIELD_MANAGER)
+    };
+    let owned: BTreeSet<String> = managed.iter().filter(ours).flat_map(spec_paths).collect();
+    let mut set = BTreeSet::new();
+    value_paths(desired, "spec", &mut set);
+    let sets = |path: &str| {
+        let shape = path
+            .split('[')
+            .map(|s| s.split_once(']').map_or(s, |(_, rest)| rest))
+            .collect::<String>();
+        set.iter().any(|d| {
+            *d == shape
+                || d.strip_prefix(shape.as_str())
+                    .is_some_and(|r| r.starts_with('.'))
+        })
+    };
+    let mut drift = Vec::new();
+    // Scale and status subresources (HPA, the StatefulSet controller) do not edit the spec
+    for entry in managed
+        .iter()
+        .filter(|e| !ours(e) && e.subresource.as_deref().unwrap_or_default().is_empty())
+    {
+        let manager = entry.manager.as_deref().unwrap_or("unknown");
+        drift.extend(
+            spec_paths(entry)
+                .into_iter()
+                .filter(|p| !owned.contains(p) && sets(p))
+                .map(|p| format!("{} ({})", p, manager)),
+        );
+    }
+    drift
+}
+
+pub(crate) async fn ensure_statefulset(
+    client: &Client,
+    ns: &str,
+    name: &str,
+    workload: &Workload,
+    spec: &ServerSpec,
+    owner: Option<&OwnerReference>,
 ) -> Result<(), ReconcileError> {
     let sts_api: Api<StatefulSet> = Api::namespaced(client.clone(), ns);
-    let sts = build_statefulset(name, spec, owner);
-    let params = kube::api::PatchParams::apply("meilisearch-operator").force();
-    let _ = sts_api
-        .patch(name, &params, &kube::api::Patch::Apply(&sts))
-        .await?;
+    let sts = build_statefulset(name, workload, spec, owner);
+    let _ = children::apply(&sts_api, &workload.sts_name, &sts).await?;
     Ok(())
 }
 
-fn build_service(name: &str, port: u16, owner: &OwnerReference) -> Service {
+/// The StatefulSet to render for a Server. Normally this is a single StatefulSet named
+/// after the Server; blue/green upgrades render a second one alongside it, on volumes an
+/// import Job filled beforehand, so it needs nothing restore-specific.
+pub(crate) struct Workload {
+    pub sts_name: String,
+    pub image: String,
+}
+
+pub(crate) fn desired_image(spec: &ServerSpec, default_image: &str) -> String {
+    spec.image.clone().unwrap_or_else(|| default_image.into())
+}
+
+/// `desired_image`, except that a running Server without `image` stays on `current` when the
+/// default changes (e.g. with an operator upgrade), unless `rollout` opts in.
+fn default_or_current(
+    spec: &ServerSpec,
+    current: Option<&str>,
+    default_image: &str,
+    rollout: bool,
+) -> String {
+    match current {
+        Some(current) if spec.image.is_none() && !rollout => current.to_string(),
+        _ => desired_image(spec, default_image),
+    }
+}
+
+fn build_service(
+    name: &str,
+    app: &str,
+    server: Option<&str>,
+    network: ServiceNetwork<'_>,
+    owner: Option<&OwnerReference>,
+) -> Service {
+    let port = network.port;
     Service {
         metadata: kube::core::ObjectMeta {
             name: Some(name.to_string()),
-            owner_references: Some(vec![owner.clone()]),
+            labels: Some(children::managed_labels(server)),
+            owner_references: owner.map(|o| vec![o.clone()]),
             ..Default::default()
         },
         spec: Some(k8s_openapi::api::core::v1::ServiceSpec {
             selector: Some(std::collections::BTreeMap::from([(
                 String::from("app"),
-                name.to_string(),
+                app.to_string(),
             )])),
             ports: Some(vec![k8s_openapi::api::core::v1::ServicePort {
+                name: network.mesh.then(|| "http".into()),
+                app_protocol: network.mesh.then(|| "http".into()),
                 port: port as i32,
                 target_port: Some(
                     k8s_openapi::apimachinery::pkg::util::intstr::IntOrString::Int(port as i32),
                 ),
                 ..Default::default()
             }]),
+            // Left out when unset, so the API server's defaults are not fought over
+            ip_families: (!network.ip_families.is_empty()).then(|| {
+                network
+                    .ip_families
+                    .iter()
+                    .map(|f| f.as_str().to_string())
+                    .collect()
+            }),
+            ip_family_policy: network.ip_family_policy.map(|p| p.as_str().to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Pod annotations that start Meilisearch only once the sidecar proxy is up, so it never
+/// serves (or fails its probes) while traffic cannot reach it yet. Istio probes are also
+/// rewritten to go through the sidecar agent, which works under strict mTLS.
+fn mesh_annotations(mesh: ServiceMesh) -> std::collections::BTreeMap<String, String> {
+    let pairs: &[(&str, &str)] = match mesh {
+        ServiceMesh::Istio => &[
+            (
+                "proxy.istio.io/config",
+                r#"{"holdApplicationUntilProxyStarts": true}"#,
+            ),
+            ("sidecar.istio.io/rewriteAppHTTPProbers", "true"),
+        ],
+        ServiceMesh::Linkerd => &[("config.linkerd.io/proxy-await", "enabled")],
+    };
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+/// `--http-addr` for `port`: every IPv6 (and, on dual-stack nodes, IPv4) address when the
+/// Service includes IPv6, every IPv4 address otherwise.
+fn listen_addr(families: &[IpFamily], port: i32) -> String {
+    if families.contains(&IpFamily::Ipv6) {
+        format!("[::]:{}", port)
+    } else {
+        format!("0.0.0.0:{}", port)
+    }
+}
+
+fn build_statefulset(
+    name: &str,
+    workload: &Workload,
+    spec: &ServerSpec,
+    owner: Option<&OwnerReference>,
+) -> StatefulSet {
+    let sts_name = workload.sts_name.as_str();
+    let image = workload.image.clone();
+    let port = spec.port as i32;
+    let has_storage = spec.storage.is_some();
+    let mut claims = Vec::new();
+    if let Some(size) = spec.storage.as_ref() {
+        claims.push(claim_template("data", size, None));
+    }
+    if let Some(dumps) = spec.dump_storage.as_ref() {
+        claims.push(claim_template(
+            "dumps",
+            &dumps.size,
+            dumps.storage_class_name.clone(),
+        ));
+    }
+    let mut args = vec![
+        "meilisearch".to_string(),
+        "--http-addr".into(),
+        listen_addr(&spec.ip_families, port),
+    ];
+    if !spec.analytics {
+        args.push("--no-analytics".into());
+    }
+    if spec.dump_storage.is_some() {
+        args.extend([
+            "--dump-dir".into(),
+            "/meili_dumps/dumps".into(),
+            "--snapshot-dir".into(),
+            "/meili_dumps/snapshots".into(),
+        ]);
+    }
+    let mut volume_mounts = Vec::new();
+    if has_storage {
+        volume_mounts.push(k8s_openapi::api::core::v1::VolumeMount {
+            name: "data".into(),
+            mount_path: "/meili_data".into(),
+            ..Default::default()
+        });
+    }
+    if spec.dump_storage.is_some() {
+        volume_mounts.push(k8s_openapi::api::core::v1::VolumeMount {
+            name: "dumps".into(),
+            mount_path: "/meili_dumps".into(),
             ..Default::default()
-        }),
-        ..Default::default()
+        });
     }
-}
-
-fn build_statefulset(name: &str, spec: &ServerSpec, owner: &OwnerReference) -> StatefulSet {
-    let image = spec
-        .image
-        .clone()
-        .unwrap_or_else(|| "getmeili/meilisearch:latest".into());
-    let port = spec.port as i32;
-    let has_storage = spec.storage.is_some();
+    let env = meili_env(name, spec);
     StatefulSet {
         metadata: kube::core::ObjectMeta {
-            name: Some(name.to_string()),
-            owner_references: Some(vec![owner.clone()]),
+            name: Some(sts_name.to_string()),
+            labels: Some(children::managed_labels(Some(name))),
+            owner_references: owner.map(|o| vec![o.clone()]),
             ..Default::default()
         },
         spec: Some(k8s_openapi::api::apps::v1::StatefulSetSpec {
-            service_name: Some(name.to_string()),
+            service_name: Some(sts_name.to_string()),
             replicas: Some(spec.replicas),
             selector: LabelSelector {
                 match_labels: Some(std::collections::BTreeMap::from([(
                     String::from("app"),
-                    name.to_string(),
+                    sts_name.to_string(),
                 )])),
                 ..Default::default()
             },
+            update_strategy: spec.update_strategy.as_ref().map(build_update_strategy),
             persistent_volume_claim_retention_policy: Some(
                 k8s_openapi::api::apps::v1::StatefulSetPersistentVolumeClaimRetentionPolicy {
                     when_deleted: Some("Delete".into()),
@@ -313,38 +1709,44 @@ fn build_statefulset(name: &str, spec: &ServerSpec, owner: &OwnerReference) -> S
                 metadata: Some(kube::core::ObjectMeta {
                     labels: Some(std::collections::BTreeMap::from([(
                         String::from("app"),
-                        name.to_string(),
+                        sts_name.to_string(),
                     )])),
+                    annotations: spec.mesh.map(mesh_annotations),
                     ..Default::default()
                 }),
                 spec: Some(k8s_openapi::api::core::v1::PodSpec {
+                    termination_grace_period_seconds: spec.termination_grace_period_seconds,
+                    priority_class_name: spec.priority_class_name.clone(),
+                    dns_policy: spec.dns_policy.clone(),
+                    dns_config: spec.dns_config.clone(),
+                    host_aliases: spec.host_aliases.clone(),
                     containers: vec![k8s_openapi::api::core::v1::Container {
                         name: "meilisearch".into(),
                         image: Some(image),
-                        args: Some(vec![
-                            "meilisearch".into(),
-                            "--http-addr".into(),
-                            format!("0.0.0.0:{}", port),
-                        ]),
+                        image_pull_policy: spec.image_pull_policy.map(|p| p.as_str().to_string()),
+                        lifecycle: spec.pre_stop_backup.map(|kind| {
+                            k8s_openapi::api::core::v1::Lifecycle {
+                                pre_stop: Some(k8s_openapi::api::core::v1::LifecycleHandler {
+                                    exec: Some(k8s_openapi::api::core::v1::ExecAction {
+                                        command: Some(vec![
+                                            "/bin/sh".into(),
+                                            "-c".into(),
+                                            pre_stop_script(kind, spec.port),
+                                        ]),
+                                    }),
+                                    ..Default::default()
+                                }),
+                                ..Default::default()
+                            }
+                        }),
+                        args: Some(args),
                         ports: Some(vec![k8s_openapi::api::core::v1::ContainerPort {
+                            name: spec.mesh.map(|_| "http".into()),
                             container_port: port,
                             ..Default::default()
                         }]),
                         env_from: None,
-                        env: Some(vec![k8s_openapi::api::core::v1::EnvVar {
-                            name: "MEILI_MASTER_KEY".into(),
-                            value_from: Some(k8s_openapi::api::core::v1::EnvVarSource {
-                                secret_key_ref: Some(
-                                    k8s_openapi::api::core::v1::SecretKeySelector {
-                                        name: format!("{}-meili-master", name),
-                                        key: "masterKey".into(),
-                                        optional: Some(false),
-                                    },
-                                ),
-                                ..Default::default()
-                            }),
-                            ..Default::default()
-                        }]),
+                        env: Some(env),
                         liveness_probe: Some(k8s_openapi::api::core::v1::Probe {
                             http_get: Some(k8s_openapi::api::core::v1::HTTPGetAction {
                                 path: Some("/health".into()),
@@ -375,94 +1777,215 @@ fn build_statefulset(name: &str, spec: &ServerSpec, owner: &OwnerReference) -> S
                             timeout_seconds: Some(2),
                             ..Default::default()
                         }),
-                        volume_mounts: if has_storage {
-                            Some(vec![k8s_openapi::api::core::v1::VolumeMount {
-                                name: "data".into(),
-                                mount_path: "/meili_data".into(),
-                                ..Default::default()
-                            }])
-                        } else {
-                            None
-                        },
+                        volume_mounts: (!volume_mounts.is_empty()).then_some(volume_mounts),
                         ..Default::default()
                     }],
-                    volumes: None,
                     ..Default::default()
                 }),
             },
-            volume_claim_templates: if let Some(size) = spec.storage.as_ref() {
-                Some(vec![k8s_openapi::api::core::v1::PersistentVolumeClaim {
-                    metadata: kube::core::ObjectMeta {
-                        name: Some("data".into()),
-                        ..Default::default()
-                    },
-                    spec: Some(k8s_openapi::api::core::v1::PersistentVolumeClaimSpec {
-                        access_modes: Some(vec!["ReadWriteOnce".into()]),
-                        resources: Some(k8s_openapi::api::core::v1::VolumeResourceRequirements {
-                            requests: Some(std::collections::BTreeMap::from([(
-                                String::from("storage"),
-                                k8s_openapi::apimachinery::pkg::api::resource::Quantity(
-                                    size.clone(),
-                                ),
-                            )])),
-                            limits: None,
-                        }),
-                        ..Default::default()
-                    }),
-                    ..Default::default()
-                }])
-            } else {
-                None
-            },
+            volume_claim_templates: (!claims.is_empty()).then_some(claims),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Environment of the Meilisearch container: the master key from the Server's Secret and the
+/// tuning settings.
+pub(crate) fn meili_env(name: &str, spec: &ServerSpec) -> Vec<k8s_openapi::api::core::v1::EnvVar> {
+    let mut env = vec![k8s_openapi::api::core::v1::EnvVar {
+        name: "MEILI_MASTER_KEY".into(),
+        value_from: Some(k8s_openapi::api::core::v1::EnvVarSource {
+            secret_key_ref: Some(k8s_openapi::api::core::v1::SecretKeySelector {
+                name: format!("{}-meili-master", name),
+                key: secret_template::key_name(spec.secret_template.as_ref(), "masterKey").into(),
+                optional: Some(false),
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }];
+    env.extend(tuning_env(spec));
+    env
+}
+
+pub(crate) fn claim_template(
+    name: &str,
+    size: &str,
+    storage_class_name: Option<String>,
+) -> k8s_openapi::api::core::v1::PersistentVolumeClaim {
+    k8s_openapi::api::core::v1::PersistentVolumeClaim {
+        metadata: kube::core::ObjectMeta {
+            name: Some(name.into()),
+            ..Default::default()
+        },
+        spec: Some(k8s_openapi::api::core::v1::PersistentVolumeClaimSpec {
+            access_modes: Some(vec!["ReadWriteOnce".into()]),
+            storage_class_name,
+            resources: Some(k8s_openapi::api::core::v1::VolumeResourceRequirements {
+                requests: Some(std::collections::BTreeMap::from([(
+                    String::from("storage"),
+                    k8s_openapi::apimachinery::pkg::api::resource::Quantity(size.to_string()),
+                )])),
+                limits: None,
+            }),
             ..Default::default()
         }),
         ..Default::default()
     }
 }
 
-async fn wait_meili_healthy(endpoint: &str, master_key: &str) -> Result<(), ReconcileError> {
-    wait_meili_healthy_with(endpoint, master_key, Duration::from_secs(2), 120).await
+/// Typed tuning knobs mapped to Meilisearch environment variables. Sizes are normalized to bytes.
+fn tuning_env(spec: &ServerSpec) -> Vec<k8s_openapi::api::core::v1::EnvVar> {
+    let plain = |name: &str, value: String| k8s_openapi::api::core::v1::EnvVar {
+        name: name.into(),
+        value: Some(value),
+        ..Default::default()
+    };
+    let mut env = Vec::new();
+    if let Some(bytes) = spec
+        .http_payload_size_limit
+        .as_deref()
+        .and_then(parse_byte_size)
+    {
+        env.push(plain("MEILI_HTTP_PAYLOAD_SIZE_LIMIT", bytes.to_string()));
+    }
+    if let Some(bytes) = spec
+        .max_indexing_memory
+        .as_deref()
+        .and_then(parse_byte_size)
+    {
+        env.push(plain("MEILI_MAX_INDEXING_MEMORY", bytes.to_string()));
+    }
+    if let Some(threads) = spec.max_indexing_threads {
+        env.push(plain("MEILI_MAX_INDEXING_THREADS", threads.to_string()));
+    }
+    if let Some(level) = spec.log_level {
+        env.push(plain("MEILI_LOG_LEVEL", level.as_str().into()));
+    }
+    if spec.experimental_logs_route {
+        env.push(plain("MEILI_EXPERIMENTAL_ENABLE_LOGS_ROUTE", "true".into()));
+    }
+    env
+}
+
+/// Parse "100MB", "2 GiB", "512Mi" or a plain byte count.
+pub(crate) fn parse_byte_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (digits, unit) = value.split_at(split);
+    let n: u64 = digits.parse().ok()?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" | "k" => 1_000,
+        "mb" | "m" => 1_000_000,
+        "gb" | "g" => 1_000_000_000,
+        "tb" | "t" => 1_000_000_000_000,
+        "kib" | "ki" => 1 << 10,
+        "mib" | "mi" => 1 << 20,
+        "gib" | "gi" => 1 << 30,
+        "tib" | "ti" => 1 << 40,
+        _ => return None,
+    };
+    n.checked_mul(multiplier)
+}
+
+/// preStop hook body: start a snapshot/dump and block until its task leaves the queue.
+/// Uses busybox wget, which ships with the official Meilisearch image.
+fn pre_stop_script(kind: BackupKind, port: u16) -> String {
+    let route = match kind {
+        BackupKind::Snapshot => "snapshots",
+        BackupKind::Dump => "dumps",
+    };
+    format!(
+        r#"auth="Authorization: Bearer $MEILI_MASTER_KEY"
+base=http://127.0.0.1:{port}
+uid=$(wget -qO- --header "$auth" --post-data '' $base/{route} | sed -n 's/.*"taskUid":\([0-9]*\).*/\1/p')
+[ -n "$uid" ] || exit 0
+while wget -qO- --header "$auth" $base/tasks/$uid | grep -Eq '"status":"(enqueued|processing)"'; do sleep 1; done"#
+    )
+}
+
+fn build_update_strategy(
+    u: &StatefulSetUpdate,
+) -> k8s_openapi::api::apps::v1::StatefulSetUpdateStrategy {
+    match u.type_ {
+        StatefulSetUpdateType::OnDelete => k8s_openapi::api::apps::v1::StatefulSetUpdateStrategy {
+            type_: Some("OnDelete".into()),
+            rolling_update: None,
+        },
+        StatefulSetUpdateType::RollingUpdate => {
+            k8s_openapi::api::apps::v1::StatefulSetUpdateStrategy {
+                type_: Some("RollingUpdate".into()),
+                rolling_update: u.partition.map(|p| {
+                    k8s_openapi::api::apps::v1::RollingUpdateStatefulSetStrategy {
+                        partition: Some(p),
+                        ..Default::default()
+                    }
+                }),
+            }
+        }
+    }
+}
+
+/// Poll `/health` as configured by `health_check`; false once the probes run out.
+async fn wait_meili_healthy(
+    endpoint: &str,
+    health: &HealthCheckSpec,
+    mesh: bool,
+) -> Result<bool, ReconcileError> {
+    wait_meili_healthy_with(
+        endpoint,
+        Duration::from_secs(health.interval_seconds),
+        health.max_attempts,
+        Duration::from_secs(health.timeout_seconds),
+        mesh,
+    )
+    .await
 }
 
-async fn wait_meili_healthy_with(
+/// With `mesh`, each probe opens its own connection, since a pooled one may end at a
+/// sidecar that has since restarted or drained, and it takes two healthy answers in a row:
+/// while the sidecar starts or drains, single probes get through in between its 503s.
+pub(crate) async fn wait_meili_healthy_with(
     endpoint: &str,
-    _master_key: &str,
     interval: Duration,
     max_attempts: u32,
-) -> Result<(), ReconcileError> {
-    let http = reqwest::Client::builder()
-        .timeout(Duration::from_secs(1))
-        .build()
-        .map_err(anyhow::Error::from)?;
+    timeout: Duration,
+    mesh: bool,
+) -> Result<bool, ReconcileError> {
+    // Polled directly: retries and the circuit breaker would only stretch the deadline
+    let http = meili_http::server_client(endpoint);
+    let needed = if mesh { 2 } else { 1 };
     let mut attempts = 0u32;
+    let mut healthy = 0;
     loop {
         attempts += 1;
-        let res = http.get(format!("{}/health", endpoint)).send().await;
-        if let Ok(r) = res
-            && r.status().is_success()
-        {
-            return Ok(());
+        let mut req = http.get(format!("{}/health", endpoint)).timeout(timeout);
+        if mesh {
+            req = req.header(reqwest::header::CONNECTION, "close");
+        }
+        match req.send().await {
+            Ok(r) if r.status().is_success() => {
+                healthy += 1;
+                if healthy >= needed {
+                    return Ok(true);
+                }
+            }
+            _ => healthy = 0,
+        }
+        if attempts >= max_attempts {
+            return Ok(false);
         }
-        if attempts > max_attempts {
-            return Err(ReconcileError::Anyhow(anyhow::anyhow!(
-                "Meilisearch not healthy in time"
-            )));
+        if shutdown::requested() {
+            return Err(shutdown::interrupted("Meilisearch health").into());
         }
         tokio::time::sleep(interval).await;
     }
 }
 
-fn owner_ref(server: &Server) -> OwnerReference {
-    OwnerReference {
-        api_version: "meili.operator.dev/v1beta1".into(),
-        kind: "Server".into(),
-        name: server.metadata.name.clone().unwrap_or_default(),
-        uid: server.metadata.uid.clone().unwrap_or_default(),
-        controller: Some(true),
-        block_owner_deletion: Some(true),
-    }
-}
-
 async fn ensure_finalizer(
     client: &Client,
     ns: &str,
@@ -492,26 +2015,196 @@ async fn remove_finalizer(client: &Client, ns: &str, name: &str) -> Result<(), R
     Ok(())
 }
 
+/// Copy the master key into the remote cluster, creating the namespace if needed; the
+/// local Secret stays the source of truth for Index and Key controllers.
+async fn ensure_remote_master_key(
+    target: &Target,
+    server: &Server,
+    key: &str,
+    immutable: bool,
+) -> Result<(), ReconcileError> {
+    use k8s_openapi::api::core::v1::Namespace;
+    let ns = server.namespace().unwrap();
+    let namespaces: Api<Namespace> = Api::all(target.client.clone());
+    if namespaces.get_opt(&ns).await?.is_none() {
+        let namespace = Namespace {
+            metadata: kube::core::ObjectMeta {
+                name: Some(ns.clone()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        match namespaces
+            .create(&kube::api::PostParams::default(), &namespace)
+            .await
+        {
+            Ok(_) => {}
+            Err(kube::Error::Api(ae)) if ae.code == 409 => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let secrets: Api<Secret> = Api::namespaced(target.client.clone(), &ns);
+    let mut sec = secret_template::build(
+        &format!("{}-meili-master", server.name_any()),
+        server.spec.secret_template.as_ref(),
+        "masterKey",
+        key,
+        None,
+        immutable,
+    );
+    sec.labels_mut()
+        .insert(children::SERVER_LABEL.into(), server.name_any());
+    match secrets
+        .create(&kube::api::PostParams::default(), &sec)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(ae)) if ae.code == 409 => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Delete the StatefulSet, Services and master key copy a Server created in a remote cluster.
+async fn delete_remote_workload(server: &Server, ctx: &Ctx) -> Result<(), ReconcileError> {
+    let ns = server.namespace().unwrap();
+    let name = server.name_any();
+    let target = match ctx
+        .clusters
+        .target(&ctx.client, &ns, server.spec.cluster_ref.as_deref())
+        .await
+    {
+        Ok(t) => t,
+        Err(e) => {
+            // An unreachable or deleted Cluster must not block Server deletion
+            warn!(error = ?e, server = %name, "skipping remote cleanup");
+            return Ok(());
+        }
+    };
+    let active = server
+        .status
+        .as_ref()
+        .and_then(|s| s.active_statefulset.clone())
+        .unwrap_or_else(|| name.clone());
+    let dp = kube::api::DeleteParams::default();
+    let ignore_missing = |res: Result<(), kube::Error>| match res {
+        Err(kube::Error::Api(ae)) if ae.code == 404 => Ok(()),
+        other => other,
+    };
+    let sts_api: Api<StatefulSet> = Api::namespaced(target.client.clone(), &ns);
+    let svc_api: Api<Service> = Api::namespaced(target.client.clone(), &ns);
+    let secrets: Api<Secret> = Api::namespaced(target.client.clone(), &ns);
+    for sts in [&name, &active] {
+        ignore_missing(sts_api.delete(sts, &dp).await.map(|_| ()))?;
+        ignore_missing(svc_api.delete(sts, &dp).await.map(|_| ()))?;
+    }
+    ignore_missing(
+        secrets
+            .delete(&format!("{}-meili-master", name), &dp)
+            .await
+            .map(|_| ()),
+    )?;
+    Ok(())
+}
+
 async fn delete_operator_copy(
     client: &Client,
     op_ns: &str,
     ns: &str,
     name: &str,
 ) -> Result<(), ReconcileError> {
-    use k8s_openapi::api::core::v1::Secret;
     let secrets: Api<Secret> = Api::namespaced(client.clone(), op_ns);
-    let sec_name = format!("{}-{}-meili-master", ns, name);
-    let dp = kube::api::DeleteParams::default();
-    match secrets.delete(&sec_name, &dp).await {
-        Ok(_) => Ok(()),
-        Err(kube::Error::Api(ae)) if ae.code == 404 => Ok(()),
-        Err(e) => Err(e.into()),
+    let lp = kube::api::ListParams::default().labels(&operator_copy_selector(ns, name));
+    for sec in secrets.list_metadata(&lp).await? {
+        match secrets.delete(&sec.name_any(), &Default::default()).await {
+            Ok(_) => {}
+            Err(kube::Error::Api(ae)) if ae.code == 404 => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    delete_legacy_operator_copy(&secrets, ns, name).await
+}
+
+/// How often the operator namespace is swept for master key copies of deleted Servers.
+pub const COPY_SWEEP_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Sweep at startup and then every [`COPY_SWEEP_INTERVAL`], logging failures. Sweeps are
+/// skipped during a change freeze.
+pub async fn run_copy_sweep(client: Client, op_ns: String, config: Arc<OperatorConfig>) {
+    loop {
+        if freeze::active(&config) {
+            tokio::time::sleep(freeze::RETRY).await;
+            continue;
+        }
+        match collect_orphaned_copies(&client, &op_ns).await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!(deleted = n, "removed master key copies of deleted Servers"),
+            Err(e) => warn!(error = ?e, "master key copy sweep failed"),
+        }
+        tokio::time::sleep(COPY_SWEEP_INTERVAL).await;
+    }
+}
+
+/// Delete master key copies in the operator namespace whose Server no longer exists. Copies
+/// are normally removed by the Server finalizer, but an operator that stops between deleting
+/// the copy and releasing the finalizer, or a finalizer removed by hand, leaves them behind.
+/// Only Secrets carrying the labels `ensure_operator_copy` writes are looked at.
+pub async fn collect_orphaned_copies(
+    client: &Client,
+    op_ns: &str,
+) -> Result<usize, ReconcileError> {
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), op_ns);
+    let lp = kube::api::ListParams::default().labels(&format!(
+        "{}={},{},{}",
+        children::MANAGED_BY_LABEL,
+        children::FIELD_MANAGER,
+        children::SERVER_LABEL,
+        children::SERVER_NAMESPACE_LABEL
+    ));
+    let mut deleted = 0;
+    for sec in secrets.list_metadata(&lp).await? {
+        let Some((ns, name)) = copy_source(&sec) else {
+            continue;
+        };
+        let servers: Api<Server> = Api::namespaced(client.clone(), &ns);
+        if servers.get_metadata_opt(&name).await?.is_some() {
+            continue;
+        }
+        match secrets.delete(&sec.name_any(), &Default::default()).await {
+            Ok(_) => deleted += 1,
+            Err(kube::Error::Api(ae)) if ae.code == 404 => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(deleted)
+}
+
+/// Namespace and name of the Server an operator-namespace Secret is the master key copy of,
+/// from the labels `ensure_operator_copy` writes; `None` when it is not such a copy. Secrets
+/// owned by an object, such as a Key's Secret, are never copies.
+fn copy_source<K: Resource>(sec: &K) -> Option<(String, String)> {
+    let labels = sec.labels();
+    if sec
+        .meta()
+        .owner_references
+        .as_ref()
+        .is_some_and(|o| !o.is_empty())
+        || labels.contains_key(children::OWNER_LABEL)
+        || labels.get(children::MANAGED_BY_LABEL).map(String::as_str)
+            != Some(children::FIELD_MANAGER)
+        || !sec.name_any().ends_with("-meili-master")
+    {
+        return None;
     }
+    Some((
+        labels.get(children::SERVER_NAMESPACE_LABEL)?.clone(),
+        labels.get(children::SERVER_LABEL)?.clone(),
+    ))
 }
 
 #[cfg(test)]
 mod tests_server_controller {
     use super::*;
+    use crate::crds::server::{CanarySpec, MasterKeyCharset};
     use axum::http::{StatusCode, header::CONTENT_TYPE};
     use axum::{Router, routing::get};
     use std::net::SocketAddr;
@@ -519,8 +2212,8 @@ mod tests_server_controller {
 
     fn owner() -> OwnerReference {
         OwnerReference {
-            api_version: "meili.operator.dev/v1beta1".into(),
-            kind: "Server".into(),
+            api_version: Server::api_version(&()).into_owned(),
+            kind: Server::kind(&()).into_owned(),
             name: "test".into(),
             uid: "uid".into(),
             controller: Some(true),
@@ -536,18 +2229,78 @@ mod tests_server_controller {
             storage: Some("5Gi".into()),
             service_type: "ClusterIP".into(),
             port: 7700,
+            ..Default::default()
         };
-        let svc = build_service("meili-a", 7700, &owner());
+        let svc = build_service(
+            "meili-a",
+            "meili-a",
+            Some("meili-a"),
+            ServiceNetwork::port(7700),
+            Some(&owner()),
+        );
         assert_eq!(svc.metadata.name.as_deref(), Some("meili-a"));
         assert_eq!(
             svc.spec.as_ref().unwrap().ports.as_ref().unwrap()[0].port,
             7700
         );
+        assert!(svc.spec.as_ref().unwrap().ip_families.is_none());
+        let dual = ServerSpec {
+            ip_families: vec![IpFamily::Ipv6, IpFamily::Ipv4],
+            ip_family_policy: Some(IpFamilyPolicy::PreferDualStack),
+            ..spec.clone()
+        };
+        let dual_svc = build_service("meili-a", "meili-a", None, ServiceNetwork::of(&dual), None);
+        let dual_spec = dual_svc.spec.unwrap();
+        assert_eq!(dual_spec.ip_families.unwrap(), ["IPv6", "IPv4"]);
+        assert_eq!(
+            dual_spec.ip_family_policy.as_deref(),
+            Some("PreferDualStack")
+        );
+        assert_eq!(listen_addr(&dual.ip_families, 7700), "[::]:7700");
+        let meshed = ServerSpec {
+            mesh: Some(ServiceMesh::Istio),
+            ..spec.clone()
+        };
+        let port = &build_service(
+            "meili-a",
+            "meili-a",
+            None,
+            ServiceNetwork::of(&meshed),
+            None,
+        )
+        .spec
+        .unwrap()
+        .ports
+        .unwrap()[0];
+        assert_eq!(port.app_protocol.as_deref(), Some("http"));
+        let pod = build_statefulset("meili-a", &primary("meili-a", &meshed), &meshed, None)
+            .spec
+            .unwrap()
+            .template;
+        assert!(
+            pod.metadata.unwrap().annotations.unwrap()["proxy.istio.io/config"]
+                .contains("holdApplicationUntilProxyStarts")
+        );
+        assert_eq!(listen_addr(&spec.ip_families, 7700), "0.0.0.0:7700");
 
-        let sts = build_statefulset("meili-a", &spec, &owner());
+        let sts = build_statefulset("meili-a", &primary("meili-a", &spec), &spec, Some(&owner()));
+        for labels in [svc.labels(), sts.labels()] {
+            assert_eq!(
+                labels["app.kubernetes.io/managed-by"],
+                "meilisearch-operator"
+            );
+            assert_eq!(labels[children::SERVER_LABEL], "meili-a");
+        }
+        assert!(sts.spec.as_ref().unwrap().update_strategy.is_none());
         let tmpl = sts.spec.as_ref().unwrap().template.clone();
         let c = &tmpl.spec.as_ref().unwrap().containers[0];
         assert_eq!(c.args.as_ref().unwrap()[0], "meilisearch");
+        assert!(
+            c.args
+                .as_ref()
+                .unwrap()
+                .contains(&"--no-analytics".to_string())
+        );
         assert!(matches!(
             sts.spec
                 .as_ref()
@@ -581,8 +2334,377 @@ mod tests_server_controller {
             tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
 
         let endpoint = format!("http://{}", local);
-        let res = wait_meili_healthy_with(&endpoint, "unused", Duration::from_millis(10), 5).await;
-        assert!(res.is_ok());
+        let wait = |endpoint: String| async move {
+            wait_meili_healthy_with(
+                &endpoint,
+                Duration::from_millis(10),
+                5,
+                Duration::from_secs(1),
+                false,
+            )
+            .await
+            .unwrap()
+        };
+        assert!(wait(endpoint.clone()).await);
         server.abort();
+        let _ = server.await;
+        assert!(!wait(endpoint).await);
+    }
+
+    fn primary(name: &str, spec: &ServerSpec) -> Workload {
+        Workload {
+            sts_name: name.into(),
+            image: desired_image(spec, crate::config::DEFAULT_IMAGE),
+        }
+    }
+
+    #[test]
+    fn running_servers_keep_their_image_when_the_default_moves() {
+        let default = crate::config::DEFAULT_IMAGE;
+        let latest = "getmeili/meilisearch:latest";
+        let mut spec = ServerSpec::default();
+        assert_eq!(default_or_current(&spec, None, default, false), default);
+        assert_eq!(
+            default_or_current(&spec, Some(latest), default, false),
+            latest
+        );
+        assert_eq!(
+            default_or_current(&spec, Some(latest), default, true),
+            default
+        );
+        spec.image = Some("getmeili/meilisearch:v1.12.0".into());
+        assert_eq!(
+            default_or_current(&spec, Some(latest), default, false),
+            "getmeili/meilisearch:v1.12.0"
+        );
+    }
+
+    #[test]
+    fn validates_and_pins_digests() {
+        let digest = format!("sha256:{}", "a".repeat(64));
+        let mut spec = ServerSpec {
+            image: Some("getmeili/meilisearch:v1.12.0".into()),
+            image_digest: Some(digest.clone()),
+            ..Default::default()
+        };
+        assert!(validate_spec(&spec).is_ok());
+        assert_eq!(
+            pin_digest(
+                desired_image(&spec, crate::config::DEFAULT_IMAGE),
+                spec.image_digest.as_deref()
+            ),
+            format!("getmeili/meilisearch:v1.12.0@{}", digest)
+        );
+        spec.image_digest = Some("sha256:nothex".into());
+        assert!(validate_spec(&spec).is_err());
+        spec.image_digest = None;
+        spec.image = Some(format!("getmeili/meilisearch@{}", digest));
+        spec.version = Some("~1.12".into());
+        assert!(validate_spec(&spec).is_err());
+        spec.image = None;
+        assert!(validate_spec(&spec).is_ok());
+        spec.version = Some("one point twelve".into());
+        assert!(validate_spec(&spec).is_err());
+    }
+
+    #[test]
+    fn canary_strategy_requires_canary_settings() {
+        let mut spec = ServerSpec {
+            upgrade_strategy: UpgradeStrategy::Canary,
+            storage: Some("10Gi".into()),
+            ..Default::default()
+        };
+        assert!(validate_spec(&spec).is_err());
+        spec.canary = Some(CanarySpec {
+            http_route: "search".into(),
+            weight: 10,
+            analysis_seconds: 600,
+            max_error_rate: 0.05,
+        });
+        assert!(validate_spec(&spec).is_ok());
+        spec.storage = None;
+        assert!(validate_spec(&spec).is_err());
+    }
+
+    #[test]
+    fn replicas_warning_does_not_hide_other_degradations() {
+        let warning = replicas_warning(&ServerSpec {
+            replicas: 3,
+            ..Default::default()
+        });
+        assert_eq!(
+            degraded_reason(Some("0/3 pods ready".into()), None, warning.clone()),
+            Some(("WorkloadUnavailable", "0/3 pods ready".into()))
+        );
+        assert_eq!(
+            degraded_reason(None, Some("import failed".into()), warning.clone()),
+            Some(("UpgradeFailed", "import failed".into()))
+        );
+        assert_eq!(
+            degraded_reason(None, None, warning.clone()).map(|(reason, _)| reason),
+            Some("ReplicationUnsupported")
+        );
+        assert_eq!(degraded_reason(None, None, None), None);
+    }
+
+    #[test]
+    fn blue_green_requires_storage() {
+        let mut spec = ServerSpec {
+            upgrade_strategy: UpgradeStrategy::BlueGreen,
+            ..Default::default()
+        };
+        assert!(validate_spec(&spec).is_err());
+        spec.storage = Some("10Gi".into());
+        assert!(validate_spec(&spec).is_ok());
+        spec.upgrade_strategy = UpgradeStrategy::InPlace;
+        spec.storage = None;
+        assert!(validate_spec(&spec).is_ok());
+    }
+
+    #[test]
+    fn falls_back_only_to_a_version_the_constraint_admits() {
+        assert_eq!(
+            fallback_version("~1.12", "1.12.3"),
+            Some(semver::Version::new(1, 12, 3))
+        );
+        assert_eq!(fallback_version("~1.13", "1.12.3"), None);
+        assert_eq!(fallback_version("not a constraint", "1.12.3"), None);
+    }
+
+    #[test]
+    fn renders_partitioned_rolling_update() {
+        let spec = ServerSpec {
+            update_strategy: Some(StatefulSetUpdate {
+                type_: StatefulSetUpdateType::RollingUpdate,
+                partition: Some(1),
+            }),
+            ..Default::default()
+        };
+        let sts = build_statefulset("meili-a", &primary("meili-a", &spec), &spec, Some(&owner()));
+        let strategy = sts.spec.unwrap().update_strategy.unwrap();
+        assert_eq!(strategy.type_.as_deref(), Some("RollingUpdate"));
+        assert_eq!(strategy.rolling_update.unwrap().partition, Some(1));
+    }
+
+    #[test]
+    fn pre_stop_hook_waits_for_dump() {
+        let spec = ServerSpec {
+            pre_stop_backup: Some(BackupKind::Dump),
+            termination_grace_period_seconds: Some(600),
+            ..Default::default()
+        };
+        let sts = build_statefulset("meili-a", &primary("meili-a", &spec), &spec, Some(&owner()));
+        let pod = sts.spec.unwrap().template.spec.unwrap();
+        assert_eq!(pod.termination_grace_period_seconds, Some(600));
+        let cmd = pod.containers[0]
+            .lifecycle
+            .clone()
+            .unwrap()
+            .pre_stop
+            .unwrap()
+            .exec
+            .unwrap()
+            .command
+            .unwrap();
+        assert!(cmd[2].contains("http://127.0.0.1:7700"));
+        assert!(cmd[2].contains("$base/dumps"));
+        assert!(cmd[2].contains(r#"sed -n 's/.*"taskUid":\([0-9]*\).*/\1/p'"#));
+    }
+
+    #[test]
+    fn parses_byte_sizes() {
+        assert_eq!(parse_byte_size("100MB"), Some(100_000_000));
+        assert_eq!(parse_byte_size("2 GiB"), Some(2 << 30));
+        assert_eq!(parse_byte_size("512Mi"), Some(512 << 20));
+        assert_eq!(parse_byte_size("1048576"), Some(1_048_576));
+        assert_eq!(parse_byte_size("1.5GB"), None);
+        assert_eq!(parse_byte_size("lots"), None);
+    }
+
+    #[test]
+    fn warns_only_when_replicated() {
+        let mut spec = ServerSpec::default();
+        assert!(replicas_warning(&spec).is_none());
+        spec.replicas = 3;
+        assert!(replicas_warning(&spec).unwrap().contains("replicas=3"));
+    }
+
+    #[test]
+    fn names_blocking_dependents() {
+        assert!(blocking_dependents(&[], &[]).is_none());
+        let key: Key = serde_json::from_value(serde_json::json!({
+            "metadata": {"name": "search", "namespace": "apps"},
+            "spec": {
+                "server_ref": "meili",
+                "actions": ["search"],
+                "indexes": ["*"],
+                "secret_namespace": "apps",
+                "secret_name": "search-key",
+            },
+        }))
+        .unwrap();
+        let msg = blocking_dependents(&[key], &[]).unwrap();
+        assert!(msg.contains("1 dependent(s): Key apps/search"));
+    }
+
+    #[test]
+    fn reports_fields_edited_by_other_managers() {
+        let spec = ServerSpec::default();
+        let desired = build_statefulset("meili", &primary("meili", &spec), &spec, None);
+        let container = r#"k:{"name":"meilisearch"}"#;
+        let managed: Vec<k8s_openapi::apimachinery::pkg::apis::meta::v1::ManagedFieldsEntry> =
+            serde_json::from_value(serde_json::json!([
+                {"manager": "meilisearch-operator", "operation": "Apply", "fieldsV1": {"f:spec": {
+                    "f:template": {"f:spec": {"f:containers": {container: {
+                        ".": {}, "f:name": {}, "f:args": {}}}}}}}},
+                {"manager": "kubectl-edit", "operation": "Update", "fieldsV1": {"f:spec": {
+                    "f:template": {
+                        "f:metadata": {"f:annotations": {"f:kubectl.kubernetes.io/restartedAt": {}}},
+                        "f:spec": {"f:containers": {container: {"f:image": {}, "f:args": {}}}}}}}},
+                {"manager": "hpa", "operation": "Update", "subresource": "scale",
+                    "fieldsV1": {"f:spec": {"f:replicas": {}}}},
+            ]))
+            .unwrap();
+        assert_eq!(
+            foreign_spec_fields(&managed, &serde_json::to_value(&desired.spec).unwrap()),
+            vec!["spec.template.spec.containers[name=meilisearch].image (kubectl-edit)"]
+        );
+    }
+
+    #[test]
+    fn counts_crashed_pods_outside_rollouts() {
+        let sts = |ready: i32, update_revision: &str| -> StatefulSet {
+            serde_json::from_value(serde_json::json!({
+                "metadata": {"name": "meili", "generation": 2},
+                "spec": {"replicas": 1, "serviceName": "meili", "selector": {},
+                    "template": {}},
+                "status": {"replicas": 1, "readyReplicas": ready, "observedGeneration": 2,
+                    "currentRevision": "meili-1", "updateRevision": update_revision},
+            }))
+            .unwrap()
+        };
+        assert!(unready_statefulset(&sts(1, "meili-1")).is_none());
+        assert_eq!(
+            unready_statefulset(&sts(0, "meili-1")).unwrap(),
+            "StatefulSet meili has 0/1 ready pods"
+        );
+        assert!(unready_statefulset(&sts(0, "meili-2")).is_none());
+    }
+
+    #[test]
+    fn operator_copy_names_do_not_collide() {
+        let a = operator_copy_name("a-b", "c");
+        let b = operator_copy_name("a", "b-c");
+        assert_ne!(a, b);
+        assert!(a.starts_with("a-b-c-") && a.ends_with("-meili-master"));
+        let long = operator_copy_name(&"n".repeat(63), &"s".repeat(253));
+        assert_eq!(long.len(), 253);
+        assert_eq!(long, operator_copy_name(&"n".repeat(63), &"s".repeat(253)));
+    }
+
+    #[test]
+    fn master_keys_follow_the_policy() {
+        let key = generate_master_key(&MasterKeySpec::default());
+        assert_eq!(key.len(), 64);
+        assert!(key.chars().all(|c| c.is_ascii_alphanumeric()));
+        let hex = generate_master_key(&MasterKeySpec {
+            length: 32,
+            charset: MasterKeyCharset::Hex,
+        });
+        assert_eq!(hex.len(), 32);
+        assert!(
+            hex.chars()
+                .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())
+        );
+        assert_ne!(
+            hex,
+            generate_master_key(&MasterKeySpec {
+                length: 32,
+                charset: MasterKeyCharset::Hex
+            })
+        );
+        // Never below what Meilisearch accepts, whatever the CR says
+        let short = MasterKeySpec {
+            length: 4,
+            charset: MasterKeyCharset::Base64Url,
+        };
+        assert_eq!(generate_master_key(&short).len(), MIN_MASTER_KEY_BYTES);
+    }
+
+    #[test]
+    fn orphan_sweep_recognises_only_master_key_copies() {
+        let secret = |name: &str, labels: &[(&str, &str)]| Secret {
+            metadata: kube::core::ObjectMeta {
+                name: Some(name.into()),
+                labels: Some(
+                    labels
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                ),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let copy_labels = |ns: &str, name: &str| {
+            let mut labels = children::managed_labels(Some(name));
+            labels.insert(children::SERVER_NAMESPACE_LABEL.into(), ns.into());
+            labels
+        };
+        let mut copy = secret(&operator_copy_name("search", "meili"), &[]);
+        copy.metadata.labels = Some(copy_labels("search", "meili"));
+        assert_eq!(copy_source(&copy), Some(("search".into(), "meili".into())));
+        // Look-alikes without the copy labels are never swept, whatever their name says
+        let unlabelled = secret("search-meili-meili-master", &[]);
+        assert_eq!(copy_source(&unlabelled), None);
+        let foreign = secret(
+            "search-meili-meili-master",
+            &[
+                (children::SERVER_LABEL, "meili"),
+                (children::SERVER_NAMESPACE_LABEL, "search"),
+            ],
+        );
+        assert_eq!(copy_source(&foreign), None);
+        // A Server in the operator namespace keeps its own master key Secret there
+        let mut own = secret("meili-meili-master", &[]);
+        own.metadata.labels = Some(children::managed_labels(Some("meili")));
+        assert_eq!(copy_source(&own), None);
+        let mut owned = copy.clone();
+        owned.metadata.owner_references = Some(vec![owner()]);
+        assert_eq!(copy_source(&owned), None);
+        let mut key = copy.clone();
+        key.labels_mut()
+            .insert(children::OWNER_LABEL.into(), "uid".into());
+        assert_eq!(copy_source(&key), None);
+        let mut tls = copy;
+        tls.metadata.name = Some("tls-cert".into());
+        assert_eq!(copy_source(&tls), None);
+    }
+
+    #[test]
+    fn maintenance_window_holds_changes_until_it_opens() {
+        let window = MaintenanceWindow {
+            schedule: "0 2 * * 6".into(),
+            duration: "4h".into(),
+        };
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        // Saturday 2025-03-01, 03:30 and 06:00
+        assert_eq!(window_closed(&window, at("2025-03-01T03:30:00Z")), None);
+        assert_eq!(
+            window_closed(&window, at("2025-03-01T06:00:00Z")),
+            Some(at("2025-03-08T02:00:00Z"))
+        );
+        assert_eq!(
+            window_closed(&window, at("2025-03-05T12:00:00Z")),
+            Some(at("2025-03-08T02:00:00Z"))
+        );
+        let bad = ServerSpec {
+            maintenance_window: Some(MaintenanceWindow {
+                schedule: "0 2 * *".into(),
+                duration: "4h".into(),
+            }),
+            ..Default::default()
+        };
+        assert!(validate_spec(&bad).is_err());
     }
 }
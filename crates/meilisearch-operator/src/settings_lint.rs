@@ -0,0 +1,128 @@
+use std::collections::BTreeSet;
+
+use crate::crds::index::IndexSettings;
+
+/// Settings combinations Meilisearch accepts but that rarely do what was meant, one message
+/// per finding.
+pub fn lint(settings: &IndexSettings) -> Vec<String> {
+    let mut findings = Vec::new();
+    // `None` or ["*"] means every attribute
+    let explicit = |list: &Option<Vec<String>>| -> Option<BTreeSet<String>> {
+        list.as_ref()
+            .filter(|l| !l.iter().any(|a| a == "*"))
+            .map(|l| l.iter().cloned().collect())
+    };
+    let searchable = explicit(&settings.searchable_attributes);
+    let displayed = explicit(&settings.displayed_attributes);
+
+    for (field, list) in [
+        ("searchable_attributes", &settings.searchable_attributes),
+        ("displayed_attributes", &settings.displayed_attributes),
+        ("filterable_attributes", &settings.filterable_attributes),
+        ("sortable_attributes", &settings.sortable_attributes),
+        ("ranking_rules", &settings.ranking_rules),
+    ] {
+        let mut seen = BTreeSet::new();
+        for item in list.iter().flatten() {
+            if !seen.insert(item) {
+                findings.push(format!("{} lists {:?} more than once", field, item));
+            }
+        }
+    }
+    if let (Some(distinct), Some(searchable)) = (&settings.distinct_attribute, &searchable)
+        && !searchable.contains(distinct)
+    {
+        findings.push(format!(
+            "distinct_attribute {:?} is not in searchable_attributes",
+            distinct
+        ));
+    }
+    if let (Some(distinct), Some(displayed)) = (&settings.distinct_attribute, &displayed)
+        && !displayed.contains(distinct)
+    {
+        findings.push(format!(
+            "distinct_attribute {:?} is not in displayed_attributes, so hits do not show why they were deduplicated",
+            distinct
+        ));
+    }
+    let stop_words: BTreeSet<String> = settings
+        .stop_words
+        .iter()
+        .flatten()
+        .map(|w| w.to_lowercase())
+        .collect();
+    for attr in settings.filterable_attributes.iter().flatten() {
+        if stop_words.contains(&attr.to_lowercase()) {
+            findings.push(format!(
+                "filterable attribute {:?} is also a stop word",
+                attr
+            ));
+        }
+    }
+    for word in settings.synonyms.iter().flat_map(|s| s.keys()) {
+        if stop_words.contains(&word.to_lowercase()) {
+            findings.push(format!(
+                "synonym {:?} is a stop word and never matches",
+                word
+            ));
+        }
+    }
+    if let Some(rules) = &settings.ranking_rules
+        && !rules.iter().any(|r| r == "sort")
+        && settings
+            .sortable_attributes
+            .as_ref()
+            .is_some_and(|s| !s.is_empty())
+    {
+        findings.push(
+            "sortable_attributes are set but ranking_rules lack \"sort\", so sorting has no effect"
+                .into(),
+        );
+    }
+    if let (Some(typo), Some(searchable)) = (&settings.typo_tolerance, &searchable) {
+        for attr in typo.disable_on_attributes.iter().flatten() {
+            if !searchable.contains(attr) {
+                findings.push(format!(
+                    "typo_tolerance.disable_on_attributes {:?} is not in searchable_attributes",
+                    attr
+                ));
+            }
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn flags_contradicting_settings() {
+        let settings = IndexSettings {
+            searchable_attributes: Some(vec!["title".into(), "title".into()]),
+            filterable_attributes: Some(vec!["the".into(), "genre".into()]),
+            sortable_attributes: Some(vec!["year".into()]),
+            ranking_rules: Some(vec!["words".into(), "typo".into()]),
+            distinct_attribute: Some("sku".into()),
+            stop_words: Some(vec!["The".into(), "a".into()]),
+            synonyms: Some(BTreeMap::from([("a".into(), vec!["an".into()])])),
+            ..Default::default()
+        };
+        let findings = lint(&settings);
+        assert_eq!(findings.len(), 5, "{:?}", findings);
+        assert!(findings[0].contains("more than once"));
+        assert!(
+            findings
+                .iter()
+                .any(|f| f.contains("\"the\" is also a stop word"))
+        );
+
+        let fine = IndexSettings {
+            searchable_attributes: Some(vec!["*".into()]),
+            distinct_attribute: Some("sku".into()),
+            ..Default::default()
+        };
+        assert!(lint(&fine).is_empty());
+    }
+}
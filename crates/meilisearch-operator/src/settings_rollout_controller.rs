@@ -0,0 +1,297 @@
+use std::sync::Arc;
+
+use kube::{
+    Api, Client, Resource, ResourceExt,
+    api::{ListParams, Patch, PatchParams},
+    runtime::{
+        controller::{Action, Controller},
+        events::{EventType, Recorder},
+    },
+};
+use serde_json::{Value, json};
+use tokio::time::Duration;
+use tracing::error;
+
+use crate::{
+    config::OperatorConfig,
+    crds::{
+        common::Phase,
+        index::{Index, IndexSettings},
+        settings_rollout::{
+            RolloutPhase, RolloutTarget, RolloutTargetState, SettingsRollout, SettingsRolloutSpec,
+        },
+    },
+    error::ReconcileError,
+    events, freeze,
+    secret_cache::SecretCache,
+    status, watch,
+};
+
+#[derive(Clone)]
+pub struct Ctx {
+    pub client: Client,
+    pub secrets: SecretCache,
+    pub recorder: Recorder,
+    pub config: Arc<OperatorConfig>,
+}
+
+pub fn controller(client: Client, config: &OperatorConfig) -> Controller<SettingsRollout> {
+    watch::controller(Api::<SettingsRollout>::all(client), config)
+}
+
+/// Poll interval while patched Indexes are applying the settings.
+const WAIT: Duration = Duration::from_secs(10);
+/// Resync interval once complete, picking up Indexes labelled since.
+const RESYNC: Duration = Duration::from_secs(600);
+
+/// Write the settings into the selected Index CRs, at most `max_unavailable` at a time, and
+/// follow each until its reconcile has applied them. A failed Index stops the rollout until
+/// the spec changes.
+pub async fn reconcile(
+    rollout: Arc<SettingsRollout>,
+    ctx: Arc<Ctx>,
+) -> Result<Action, ReconcileError> {
+    if freeze::active(&ctx.config) {
+        return Ok(Action::requeue(freeze::RETRY));
+    }
+    let ns = rollout.namespace().unwrap();
+    let api: Api<SettingsRollout> = Api::namespaced(ctx.client.clone(), &ns);
+    let indexes: Api<Index> = Api::namespaced(ctx.client.clone(), &ns);
+    let spec = &rollout.spec;
+    let mut status = rollout.status.clone().unwrap_or_default();
+    if status.observed_generation != rollout.metadata.generation {
+        // A new patch starts over on every Index
+        status.targets.clear();
+        status.phase = RolloutPhase::Pending;
+    }
+    status.observed_generation = rollout.metadata.generation;
+
+    let lp = ListParams::default().labels(&label_selector(spec));
+    let selected: Vec<Index> = indexes
+        .list(&lp)
+        .await?
+        .items
+        .into_iter()
+        .filter(|i| on_selected_server(spec, &ns, i))
+        .collect();
+    sync_targets(
+        &mut status.targets,
+        &selected.iter().map(|i| i.name_any()).collect::<Vec<_>>(),
+    );
+
+    // Follow the Indexes already patched
+    for target in &mut status.targets {
+        if target.state != RolloutTargetState::Applying {
+            continue;
+        }
+        let Some(idx) = selected.iter().find(|i| i.name_any() == target.name) else {
+            continue;
+        };
+        let Some(idx_status) = idx.status.as_ref() else {
+            continue;
+        };
+        // Still on the generation before the patch
+        if idx_status.observed_generation < target.generation {
+            continue;
+        }
+        if idx_status.ready {
+            target.state = RolloutTargetState::Succeeded;
+            target.task_uid = idx_status.last_settings_task_uid;
+            target.message = None;
+        } else if idx_status.phase == Phase::Degraded {
+            target.state = RolloutTargetState::Failed;
+            target.message = idx_status.message.clone();
+        }
+    }
+
+    let failed = status
+        .targets
+        .iter()
+        .find(|t| t.state == RolloutTargetState::Failed)
+        .cloned();
+    if failed.is_none() {
+        let patch = settings_patch(&spec.settings);
+        for i in next_batch(&status.targets, spec.max_unavailable) {
+            let target = &mut status.targets[i];
+            match indexes
+                .patch(&target.name, &PatchParams::default(), &Patch::Merge(&patch))
+                .await
+            {
+                Ok(idx) => {
+                    target.state = RolloutTargetState::Applying;
+                    target.generation = idx.metadata.generation;
+                    events::publish(
+                        &ctx.recorder,
+                        &idx.object_ref(&()),
+                        EventType::Normal,
+                        "SettingsRolledOut",
+                        "Reconcile",
+                        format!("settings written by SettingsRollout {}", rollout.name_any()),
+                    )
+                    .await;
+                }
+                // Deleted since it was listed; dropped on the next pass
+                Err(kube::Error::Api(e)) if e.code == 404 => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    let succeeded = status
+        .targets
+        .iter()
+        .filter(|t| t.state == RolloutTargetState::Succeeded)
+        .count();
+    status.progress = Some(format!("{}/{}", succeeded, status.targets.len()));
+    let previous = rollout.status.as_ref().map(|s| s.phase);
+    let action = if let Some(target) = failed {
+        status.phase = RolloutPhase::Failed;
+        status.message = Some(format!(
+            "Index {} failed to apply the settings: {}",
+            target.name,
+            target.message.as_deref().unwrap_or("degraded")
+        ));
+        Action::await_change()
+    } else if succeeded == status.targets.len() {
+        status.phase = RolloutPhase::Completed;
+        status.message = Some(format!("{} indexes updated", succeeded));
+        Action::requeue(RESYNC)
+    } else {
+        status.phase = RolloutPhase::Progressing;
+        status.message = Some(format!(
+            "{} of {} indexes updated",
+            succeeded,
+            status.targets.len()
+        ));
+        Action::requeue(WAIT)
+    };
+    if previous != Some(status.phase)
+        && matches!(status.phase, RolloutPhase::Completed | RolloutPhase::Failed)
+    {
+        let (type_, reason) = match status.phase {
+            RolloutPhase::Failed => (EventType::Warning, "RolloutFailed"),
+            _ => (EventType::Normal, "RolloutCompleted"),
+        };
+        events::publish(
+            &ctx.recorder,
+            &rollout.object_ref(&()),
+            type_,
+            reason,
+            "Reconcile",
+            status.message.clone().unwrap_or_default(),
+        )
+        .await;
+    }
+    status::apply(&api, rollout.as_ref(), &status).await?;
+    Ok(action)
+}
+
+pub fn error_policy(
+    _rollout: Arc<SettingsRollout>,
+    err: &ReconcileError,
+    _ctx: Arc<Ctx>,
+) -> Action {
+    error!(error = ?err, "settings rollout reconcile failed");
+    Action::requeue(Duration::from_secs(60))
+}
+
+fn label_selector(spec: &SettingsRolloutSpec) -> String {
+    spec.selector
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn on_selected_server(spec: &SettingsRolloutSpec, ns: &str, idx: &Index) -> bool {
+    let server = &idx.spec.server_ref;
+    spec.server_refs.is_empty()
+        || spec
+            .server_refs
+            .iter()
+            .any(|r| r.name == server.name && r.namespace_or(ns) == server.namespace_or(ns))
+}
+
+/// Line `targets` up with the Indexes now selected: new ones are appended as Pending, ones
+/// no longer selected are dropped.
+fn sync_targets(targets: &mut Vec<RolloutTarget>, selected: &[String]) {
+    targets.retain(|t| selected.contains(&t.name));
+    for name in selected {
+        if !targets.iter().any(|t| &t.name == name) {
+            targets.push(RolloutTarget {
+                name: name.clone(),
+                state: RolloutTargetState::Pending,
+                generation: None,
+                task_uid: None,
+                message: None,
+            });
+        }
+    }
+}
+
+/// Positions of the Pending targets to patch now, keeping at most `max_unavailable` Applying.
+fn next_batch(targets: &[RolloutTarget], max_unavailable: u32) -> Vec<usize> {
+    let applying = targets
+        .iter()
+        .filter(|t| t.state == RolloutTargetState::Applying)
+        .count();
+    let slots = (max_unavailable.max(1) as usize).saturating_sub(applying);
+    targets
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.state == RolloutTargetState::Pending)
+        .map(|(i, _)| i)
+        .take(slots)
+        .collect()
+}
+
+/// Merge patch setting the fields of `settings` that are set, leaving the others alone.
+fn settings_patch(settings: &IndexSettings) -> Value {
+    let mut fields = serde_json::to_value(settings).unwrap_or_default();
+    if let Some(map) = fields.as_object_mut() {
+        map.retain(|_, v| !v.is_null());
+    }
+    json!({ "spec": { "settings": fields } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(name: &str, state: RolloutTargetState) -> RolloutTarget {
+        RolloutTarget {
+            name: name.into(),
+            state,
+            generation: None,
+            task_uid: None,
+            message: None,
+        }
+    }
+
+    #[test]
+    fn rolls_out_in_batches_of_max_unavailable() {
+        let mut targets = vec![
+            target("a", RolloutTargetState::Succeeded),
+            target("b", RolloutTargetState::Applying),
+            target("gone", RolloutTargetState::Pending),
+        ];
+        sync_targets(
+            &mut targets,
+            &["a".into(), "b".into(), "c".into(), "d".into()],
+        );
+        let names: Vec<&str> = targets.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c", "d"]);
+        assert_eq!(next_batch(&targets, 1), Vec::<usize>::new());
+        assert_eq!(next_batch(&targets, 2), vec![2]);
+        assert_eq!(next_batch(&targets, 5), vec![2, 3]);
+
+        let patch = settings_patch(&IndexSettings {
+            stop_words: Some(vec!["the".into()]),
+            ..Default::default()
+        });
+        assert_eq!(
+            patch,
+            json!({"spec": {"settings": {"stop_words": ["the"]}}})
+        );
+    }
+}
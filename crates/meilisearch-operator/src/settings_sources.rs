@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::{Api, Client};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    crds::{common::ConfigMapKeyRef, index::IndexSettings},
+    object_store::hex,
+};
+
+/// Fill `synonyms` and `stop_words` from the ConfigMaps named by `synonyms_from` and
+/// `stop_words_from`. Returns a digest of what was read (`None` without sources), or `Err`
+/// with a message when a ConfigMap, key or its content is unusable.
+pub async fn resolve(
+    client: &Client,
+    ns: &str,
+    settings: &mut IndexSettings,
+) -> Result<Result<Option<String>, String>, kube::Error> {
+    if settings.synonyms_from.is_none() && settings.stop_words_from.is_none() {
+        return Ok(Ok(None));
+    }
+    let api: Api<ConfigMap> = Api::namespaced(client.clone(), ns);
+    let mut digest = Sha256::new();
+    if let Some(source) = settings.synonyms_from.clone() {
+        let text = match read(&api, &source).await? {
+            Ok(t) => t,
+            Err(e) => return Ok(Err(e)),
+        };
+        digest.update(&text);
+        let mut synonyms = match parse_synonyms(&text) {
+            Ok(s) => s,
+            Err(e) => return Ok(Err(format!("synonyms_from {}: {}", source.name, e))),
+        };
+        // Inline synonyms win for words both define
+        synonyms.extend(settings.synonyms.take().unwrap_or_default());
+        settings.synonyms = Some(synonyms);
+    }
+    if let Some(source) = settings.stop_words_from.clone() {
+        let text = match read(&api, &source).await? {
+            Ok(t) => t,
+            Err(e) => return Ok(Err(e)),
+        };
+        digest.update(&text);
+        let mut words = match parse_stop_words(&text) {
+            Ok(w) => w,
+            Err(e) => return Ok(Err(format!("stop_words_from {}: {}", source.name, e))),
+        };
+        for word in settings.stop_words.take().unwrap_or_default() {
+            if !words.contains(&word) {
+                words.push(word);
+            }
+        }
+        settings.stop_words = Some(words);
+    }
+    Ok(Ok(Some(hex(&digest.finalize()))))
+}
+
+async fn read(
+    api: &Api<ConfigMap>,
+    source: &ConfigMapKeyRef,
+) -> Result<Result<String, String>, kube::Error> {
+    let Some(cm) = api.get_opt(&source.name).await? else {
+        return Ok(Err(format!("ConfigMap {} not found", source.name)));
+    };
+    Ok(cm
+        .data
+        .and_then(|mut d| d.remove(&source.key))
+        .ok_or_else(|| format!("ConfigMap {} has no key {}", source.name, source.key)))
+}
+
+/// A JSON object of word to synonyms, or one group per line: "tv, television, telly" makes
+/// the words synonyms of each other, "nyc => new york" only maps the left side.
+fn parse_synonyms(text: &str) -> Result<BTreeMap<String, Vec<String>>, String> {
+    if text.trim_start().starts_with('{') {
+        return serde_json::from_str(text).map_err(|e| e.to_string());
+    }
+    let list = |s: &str| -> Vec<String> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|w| !w.is_empty())
+            .map(String::from)
+            .collect()
+    };
+    let mut synonyms: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (n, line) in lines(text) {
+        let mut add = |word: &str, to: &[String]| {
+            let entry = synonyms.entry(word.to_string()).or_default();
+            for s in to.iter().filter(|s| s.as_str() != word) {
+                if !entry.contains(s) {
+                    entry.push(s.clone());
+                }
+            }
+        };
+        match line.split_once("=>") {
+            Some((from, to)) => {
+                let to = list(to);
+                if to.is_empty() {
+                    return Err(format!("line {}: nothing after =>", n));
+                }
+                for word in list(from) {
+                    add(&word, &to);
+                }
+            }
+            None => {
+                let group = list(line);
+                if group.len() < 2 {
+                    return Err(format!("line {}: a group needs at least two words", n));
+                }
+                for word in &group {
+                    add(word, &group);
+                }
+            }
+        }
+    }
+    Ok(synonyms)
+}
+
+/// A JSON array of words, or one word per line.
+fn parse_stop_words(text: &str) -> Result<Vec<String>, String> {
+    if text.trim_start().starts_with('[') {
+        return serde_json::from_str(text).map_err(|e| e.to_string());
+    }
+    Ok(lines(text).map(|(_, w)| w.to_string()).collect())
+}
+
+/// Non-empty lines that are not `#` comments, numbered from 1.
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, l)| !l.is_empty() && !l.starts_with('#'))
+        .map(|(i, l)| (i + 1, l))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_word_lists() {
+        let synonyms =
+            parse_synonyms("# media\ntv, television\n\nnyc => new york, big apple\n").unwrap();
+        assert_eq!(synonyms["tv"], vec!["television"]);
+        assert_eq!(synonyms["television"], vec!["tv"]);
+        assert_eq!(synonyms["nyc"], vec!["new york", "big apple"]);
+        assert!(!synonyms.contains_key("new york"));
+        assert_eq!(
+            parse_synonyms(r#"{"car": ["auto"]}"#).unwrap()["car"],
+            vec!["auto"]
+        );
+        assert!(parse_synonyms("lonely\n").is_err());
+        assert!(parse_synonyms("a =>\n").is_err());
+
+        assert_eq!(
+            parse_stop_words("the\n# comment\n a \n").unwrap(),
+            vec!["the", "a"]
+        );
+        assert_eq!(
+            parse_stop_words(r#"["of", "to"]"#).unwrap(),
+            vec!["of", "to"]
+        );
+        assert!(parse_stop_words("[1]").is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::time::Duration;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Drain budget after SIGTERM; keep it below the pod's terminationGracePeriodSeconds.
+pub fn drain_timeout() -> Duration {
+    std::env::var("SHUTDOWN_TIMEOUT_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map_or(Duration::from_secs(25), Duration::from_secs)
+}
+
+/// Resolves on SIGTERM or Ctrl-C and marks the process as shutting down.
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        let mut term = signal(SignalKind::terminate()).expect("install SIGTERM handler");
+        tokio::select! {
+            _ = term.recv() => {},
+            _ = tokio::signal::ctrl_c() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// True once shutdown began. Long polls check it and return early; the next operator
+/// instance resumes from what is already recorded in Meilisearch and status.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Error for a wait abandoned because the operator is stopping.
+pub fn interrupted(what: &str) -> anyhow::Error {
+    anyhow::anyhow!("operator shutting down while waiting for {}", what)
+}
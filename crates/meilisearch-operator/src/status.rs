@@ -0,0 +1,112 @@
+use std::fmt::Debug;
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Condition;
+use kube::{
+    Api, Resource, ResourceExt,
+    api::{Patch, PatchParams},
+};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::json;
+use tracing::debug;
+
+use crate::{children::FIELD_MANAGER, error::ReconcileError};
+
+/// Field manager of the failed-task reporter's `LastTaskFailed` condition.
+pub const TASK_WATCH_MANAGER: &str = "meilisearch-operator-task-watch";
+/// Field manager of the `TimedOut` condition written when a reconcile runs out of time.
+pub const TIMEOUT_MANAGER: &str = "meilisearch-operator-timeout";
+
+/// Server-side apply `status` as the complete status of `obj`. The patch carries the
+/// resourceVersion `obj` was read at, so a status computed from a stale copy is refused
+/// instead of overwriting a newer write; it is dropped and the watch event for the newer
+/// version reconciles again. Returns whether the write went through.
+pub async fn apply<K, S>(api: &Api<K>, obj: &K, status: &S) -> Result<bool, ReconcileError>
+where
+    K: Resource<DynamicType = ()> + Clone + DeserializeOwned + Debug,
+    S: Serialize,
+{
+    let patch = status_patch(obj, status);
+    let pp = PatchParams::apply(FIELD_MANAGER).force();
+    match api
+        .patch_status(&obj.name_any(), &pp, &Patch::Apply(&patch))
+        .await
+    {
+        Ok(_) => Ok(true),
+        // Forced applies only conflict on the resourceVersion
+        Err(kube::Error::Api(e)) if e.code == 409 => {
+            debug!(name = %obj.name_any(), kind = %K::kind(&()), "status write skipped, object changed since it was read");
+            Ok(false)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Merge-patch `status` into the status of `name` as `FIELD_MANAGER`, for resources whose
+/// status has a single writer and is rebuilt from the previous one.
+pub async fn merge<K, S>(api: &Api<K>, name: &str, status: &S) -> Result<(), ReconcileError>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+    S: Serialize,
+{
+    let pp = PatchParams::apply(FIELD_MANAGER);
+    let _ = api
+        .patch_status(name, &pp, &Patch::Merge(json!({ "status": status })))
+        .await?;
+    Ok(())
+}
+
+fn status_patch<K, S>(obj: &K, status: &S) -> serde_json::Value
+where
+    K: Resource<DynamicType = ()>,
+    S: Serialize,
+{
+    json!({
+        "apiVersion": K::api_version(&()),
+        "kind": K::kind(&()),
+        "metadata": {"resourceVersion": obj.resource_version()},
+        "status": status,
+    })
+}
+
+/// Server-side apply one condition as `manager`, leaving the other conditions and status
+/// fields to their own writers.
+pub async fn apply_condition<K>(
+    api: &Api<K>,
+    name: &str,
+    manager: &str,
+    condition: &Condition,
+) -> Result<(), ReconcileError>
+where
+    K: Resource<DynamicType = ()> + Clone + DeserializeOwned + Debug,
+{
+    let patch = json!({
+        "apiVersion": K::api_version(&()),
+        "kind": K::kind(&()),
+        "status": {"conditions": [condition]},
+    });
+    api.patch_status(
+        name,
+        &PatchParams::apply(manager).force(),
+        &Patch::Apply(&patch),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crds::server::{Server, ServerStatus};
+
+    #[test]
+    fn status_patches_carry_the_read_version() {
+        let mut server = Server::new("meili", Default::default());
+        server.metadata.resource_version = Some("42".into());
+        let patch = status_patch(&server, &ServerStatus::default());
+        assert_eq!(patch["apiVersion"], "meili.operator.dev/v1beta1");
+        assert_eq!(patch["metadata"]["resourceVersion"], "42");
+        // Unset fields are sent as null so the applied status replaces the previous one
+        assert!(patch["status"]["message"].is_null());
+        assert!(patch["status"].as_object().unwrap().contains_key("message"));
+    }
+}
@@ -0,0 +1,446 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_nats::jetstream::message::Acker;
+use kube::{
+    Api, Client, ResourceExt,
+    runtime::{
+        controller::{Action, Controller},
+        events::Recorder,
+    },
+};
+use serde_json::Value;
+use tokio::{
+    task::JoinHandle,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    clusters,
+    config::OperatorConfig,
+    crds::{
+        common::Phase,
+        index::Index,
+        stream_sink::{StreamSink, StreamSinkSpec, StreamSinkStatus},
+    },
+    error::ReconcileError,
+    freeze, index_controller,
+    meili_http::{self, MeiliSend},
+    nats,
+    secret_cache::SecretCache,
+    shutdown, status, watch,
+};
+
+/// Consumers running in this operator instance, keyed by namespace/name.
+#[derive(Clone, Default)]
+pub struct Consumers(Arc<Mutex<HashMap<String, Running>>>);
+
+struct Running {
+    generation: Option<i64>,
+    handle: JoinHandle<()>,
+}
+
+#[derive(Clone)]
+pub struct Ctx {
+    pub client: Client,
+    pub secrets: SecretCache,
+    pub recorder: Recorder,
+    pub config: Arc<OperatorConfig>,
+    pub consumers: Consumers,
+}
+
+pub fn controller(client: Client, config: &OperatorConfig) -> Controller<StreamSink> {
+    watch::controller(Api::<StreamSink>::all(client), config)
+}
+
+/// Backoff between connection attempts and while the Index or Server is not usable.
+const RETRY: Duration = Duration::from_secs(15);
+/// How often a sink is checked for a consumer that stopped.
+const CHECK: Duration = Duration::from_secs(300);
+/// Counters are written to status at most this often.
+const STATUS_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Make sure a consumer for the sink's current generation is running. The consumer itself
+/// connects, subscribes, batches and reports; it looks the sink up before every batch and at
+/// least every 15s, and stops when the sink is deleted or edited.
+pub async fn reconcile(sink: Arc<StreamSink>, ctx: Arc<Ctx>) -> Result<Action, ReconcileError> {
+    if freeze::active(&ctx.config) {
+        return Ok(Action::requeue(freeze::RETRY));
+    }
+    let ns = sink.namespace().unwrap();
+    let name = sink.name_any();
+    let key = format!("{}/{}", ns, name);
+    let generation = sink.metadata.generation;
+    let mut consumers = ctx.consumers.0.lock().expect("consumers lock");
+    if let Some(running) = consumers.get(&key) {
+        if running.generation == generation && !running.handle.is_finished() {
+            return Ok(Action::requeue(CHECK));
+        }
+        running.handle.abort();
+    }
+    info!(sink = %key, ?generation, "starting stream consumer");
+    let handle = tokio::spawn(consume(sink, ctx.clone()));
+    consumers.insert(key, Running { generation, handle });
+    Ok(Action::requeue(CHECK))
+}
+
+pub fn error_policy(_sink: Arc<StreamSink>, err: &ReconcileError, _ctx: Arc<Ctx>) -> Action {
+    error!(error = ?err, "stream sink reconcile failed");
+    Action::requeue(Duration::from_secs(60))
+}
+
+/// Consumer loop: (re)connect until the sink goes away or the operator shuts down.
+async fn consume(sink: Arc<StreamSink>, ctx: Arc<Ctx>) {
+    let ns = sink.namespace().unwrap();
+    let name = sink.name_any();
+    let api: Api<StreamSink> = Api::namespaced(ctx.client.clone(), &ns);
+    let mut report = Reporter {
+        api,
+        name,
+        generation: sink.metadata.generation,
+        status: sink.status.clone().unwrap_or_default(),
+        written: None,
+        checked: None,
+    };
+    if report.status.observed_generation != sink.metadata.generation {
+        report.status = StreamSinkStatus {
+            observed_generation: sink.metadata.generation,
+            ..Default::default()
+        };
+    }
+    while !shutdown::requested() {
+        let result = run(&sink, &ctx, &ns, &mut report).await;
+        report.status.connected = false;
+        match result {
+            Ok(()) => {
+                let _ = report.write(true).await;
+                return;
+            }
+            Err(Stop::Gone) => break,
+            Err(Stop::Wait(phase, msg)) => {
+                report.status.phase = phase;
+                report.status.message = Some(msg);
+            }
+            Err(Stop::Failed(e)) => {
+                warn!(error = ?e, sink = %report.name, "stream consumer failed, reconnecting");
+                report.status.phase = Phase::Degraded;
+                report.status.message = Some(format!("{:#}", e));
+                report.status.last_error = Some(format!("{:#}", e));
+            }
+        }
+        if matches!(report.write(true).await, Err(Stop::Gone)) {
+            break;
+        }
+        tokio::time::sleep(RETRY).await;
+    }
+    // Forget a deleted sink, unless a consumer for a newer generation already took the slot
+    let key = format!("{}/{}", ns, report.name);
+    let mut consumers = ctx.consumers.0.lock().expect("consumers lock");
+    if consumers
+        .get(&key)
+        .is_some_and(|r| r.generation == report.generation)
+    {
+        consumers.remove(&key);
+    }
+}
+
+enum Stop {
+    /// The sink was deleted or edited; a newer consumer takes over
+    Gone,
+    /// Index or Server not usable yet
+    Wait(Phase, String),
+    Failed(anyhow::Error),
+}
+
+impl From<anyhow::Error> for Stop {
+    fn from(e: anyhow::Error) -> Self {
+        Stop::Failed(e)
+    }
+}
+
+impl From<ReconcileError> for Stop {
+    fn from(e: ReconcileError) -> Self {
+        Stop::Failed(e.into())
+    }
+}
+
+/// One connection's worth of consuming. Returns `Ok` only on operator shutdown.
+async fn run(sink: &StreamSink, ctx: &Ctx, ns: &str, report: &mut Reporter) -> Result<(), Stop> {
+    let spec = &sink.spec;
+    if freeze::active(&ctx.config) {
+        return Err(frozen());
+    }
+    let Some(idx) = Api::<Index>::namespaced(ctx.client.clone(), ns)
+        .get_opt(&spec.index_ref)
+        .await
+        .map_err(ReconcileError::from)?
+    else {
+        return Err(Stop::Wait(
+            Phase::Pending,
+            format!("Index {}/{} not found", ns, spec.index_ref),
+        ));
+    };
+    let server_ns = idx.spec.server_ref.namespace_or(ns).to_string();
+    let server = &idx.spec.server_ref.name;
+    if let Some(msg) = clusters::server_not_ready(&ctx.client, &server_ns, server).await? {
+        return Err(Stop::Wait(Phase::WaitingForServer, msg));
+    }
+    let target = Target {
+        endpoint: clusters::server_endpoint(&ctx.client, &ctx.config, &server_ns, server).await?,
+        master_key: index_controller::get_master_key(&ctx.secrets, &server_ns, server).await?,
+        uid: idx.spec.uid.clone(),
+    };
+
+    let url = ctx
+        .secrets
+        .connection_url(ns, &spec.nats.connection_secret_ref)
+        .await?;
+    let mut conn =
+        nats::Connection::subscribe(&url, &spec.nats.subject, spec.nats.queue_group.as_deref())
+            .await?;
+    report.status.connected = true;
+    report.status.phase = Phase::Ready;
+    report.status.message = Some(format!("subscribed to {}", spec.nats.subject));
+    report.write(true).await?;
+
+    let max_wait = Duration::from_millis(spec.batch.max_wait_ms);
+    let mut pending = Pending::default();
+    loop {
+        // Wake up for the batch deadline, status reports and shutdown checks
+        let wait = pending
+            .since
+            .map_or(Duration::from_secs(1), |s| {
+                (s + max_wait).saturating_duration_since(Instant::now())
+            })
+            .min(Duration::from_secs(1));
+        if let Ok(msg) = tokio::time::timeout(wait, conn.next()).await {
+            let msg = msg?;
+            report.status.received_messages += 1;
+            match decode(spec, &msg.payload) {
+                Some(ops) => {
+                    for op in ops {
+                        if pending.conflicts(&op) {
+                            pending.flush(&target, spec, &conn, report).await?;
+                        }
+                        pending.push(op);
+                    }
+                    pending.acks.extend(msg.acker);
+                }
+                None => {
+                    report.status.skipped_messages += 1;
+                    report.status.last_error = Some(format!(
+                        "skipped a message on {} that is not a document, array of documents or delete",
+                        msg.subject
+                    ));
+                    // Redelivering it would not help
+                    conn.ack(msg.acker.into_iter().collect()).await?;
+                }
+            }
+        }
+        // Disconnect without writing or acknowledging the batch; JetStream redelivers it
+        if freeze::active(&ctx.config) {
+            return Err(frozen());
+        }
+        let due = pending.since.is_some_and(|s| {
+            s.elapsed() >= max_wait || pending.len() >= spec.batch.max_documents as usize
+        });
+        if due || shutdown::requested() {
+            pending.flush(&target, spec, &conn, report).await?;
+        }
+        if shutdown::requested() {
+            return Ok(());
+        }
+        report.status.connected = conn.connected();
+        report.write(false).await?;
+    }
+}
+
+fn frozen() -> Stop {
+    Stop::Wait(
+        Phase::Pending,
+        "change freeze in effect; consumer paused".into(),
+    )
+}
+
+/// Writes counters to status, throttled, and notices when the sink is gone.
+struct Reporter {
+    api: Api<StreamSink>,
+    name: String,
+    generation: Option<i64>,
+    status: StreamSinkStatus,
+    written: Option<(Instant, StreamSinkStatus)>,
+    /// Last time the sink was seen unchanged
+    checked: Option<Instant>,
+}
+
+impl Reporter {
+    async fn write(&mut self, now: bool) -> Result<(), Stop> {
+        if let Some((at, last)) = &self.written
+            && (last == &self.status || (!now && at.elapsed() < STATUS_INTERVAL))
+        {
+            // Nothing to write, but an idle consumer still has to notice a deleted sink
+            if self
+                .checked
+                .is_none_or(|at| at.elapsed() >= STATUS_INTERVAL)
+            {
+                self.alive().await?;
+            }
+            return Ok(());
+        }
+        self.alive().await?;
+        status::merge(&self.api, &self.name, &self.status)
+            .await
+            .map_err(|e| Stop::Failed(e.into()))?;
+        self.written = Some((Instant::now(), self.status.clone()));
+        Ok(())
+    }
+
+    /// Stop once the sink is deleted or its spec changed under us.
+    async fn alive(&mut self) -> Result<(), Stop> {
+        match self.api.get_opt(&self.name).await {
+            Ok(Some(s)) if s.metadata.generation == self.generation => {
+                self.checked = Some(Instant::now());
+                Ok(())
+            }
+            Ok(_) => Err(Stop::Gone),
+            Err(e) => Err(Stop::Failed(e.into())),
+        }
+    }
+}
+
+struct Target {
+    endpoint: String,
+    master_key: String,
+    uid: String,
+}
+
+#[derive(Debug, PartialEq)]
+enum Op {
+    Upsert(Value),
+    Delete(Value),
+}
+
+/// Documents buffered for the next request. Upserts and deletes are never mixed in one batch,
+/// so they reach Meilisearch in the order they arrived.
+#[derive(Default)]
+struct Pending {
+    upserts: Vec<Value>,
+    deletes: Vec<Value>,
+    /// JetStream deliveries to acknowledge once the batch is enqueued
+    acks: Vec<Acker>,
+    since: Option<Instant>,
+}
+
+impl Pending {
+    fn len(&self) -> usize {
+        self.upserts.len() + self.deletes.len()
+    }
+
+    fn conflicts(&self, op: &Op) -> bool {
+        match op {
+            Op::Upsert(_) => !self.deletes.is_empty(),
+            Op::Delete(_) => !self.upserts.is_empty(),
+        }
+    }
+
+    fn push(&mut self, op: Op) {
+        self.since.get_or_insert_with(Instant::now);
+        match op {
+            Op::Upsert(doc) => self.upserts.push(doc),
+            Op::Delete(id) => self.deletes.push(id),
+        }
+    }
+
+    async fn flush(
+        &mut self,
+        target: &Target,
+        spec: &StreamSinkSpec,
+        conn: &nats::Connection,
+        report: &mut Reporter,
+    ) -> Result<(), Stop> {
+        let batch = std::mem::take(self);
+        if batch.len() > 0 {
+            // A deleted sink must not write to the index; the batch stays unacknowledged
+            report.alive().await?;
+            let req = if batch.deletes.is_empty() {
+                meili_http::client()
+                    .post(format!(
+                        "{}/indexes/{}/documents",
+                        target.endpoint, target.uid
+                    ))
+                    .query(&[("primaryKey", spec.primary_key.as_str())])
+                    .json(&batch.upserts)
+            } else {
+                meili_http::client()
+                    .post(format!(
+                        "{}/indexes/{}/documents/delete-batch",
+                        target.endpoint, target.uid
+                    ))
+                    .json(&batch.deletes)
+            };
+            let task: Value = req
+                .bearer_auth(&target.master_key)
+                .send_checked()
+                .await
+                .map_err(anyhow::Error::from)?
+                .json()
+                .await
+                .map_err(anyhow::Error::from)?;
+            report.status.last_task_uid = task["taskUid"].as_u64();
+            report.status.upserted_documents += batch.upserts.len() as u64;
+            report.status.deleted_documents += batch.deletes.len() as u64;
+            report.status.batches += 1;
+        }
+        conn.ack(batch.acks).await?;
+        Ok(())
+    }
+}
+
+/// A message is one document or an array of them; documents with `delete_field: true` are
+/// deletes of their primary key. `None` if any part of the message doesn't fit.
+fn decode(spec: &StreamSinkSpec, payload: &[u8]) -> Option<Vec<Op>> {
+    let docs = match serde_json::from_slice(payload).ok()? {
+        Value::Array(docs) => docs,
+        doc => vec![doc],
+    };
+    docs.into_iter()
+        .map(|doc| {
+            let Value::Object(mut doc) = doc else {
+                return None;
+            };
+            if doc.get(&spec.delete_field) == Some(&Value::Bool(true)) {
+                return doc.remove(&spec.primary_key).map(Op::Delete);
+            }
+            doc.remove(&spec.delete_field);
+            Some(Op::Upsert(Value::Object(doc)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn decodes_messages() {
+        let spec: StreamSinkSpec = serde_json::from_value(json!({
+            "index_ref": "movies",
+            "nats": {"connection_secret_ref": {"name": "nats"}, "subject": "movies.>"},
+        }))
+        .unwrap();
+        assert_eq!(
+            decode(&spec, br#"{"id": 1, "title": "Dune", "_deleted": false}"#),
+            Some(vec![Op::Upsert(json!({"id": 1, "title": "Dune"}))])
+        );
+        assert_eq!(
+            decode(&spec, br#"[{"id": 2}, {"id": 3, "_deleted": true}]"#),
+            Some(vec![Op::Upsert(json!({"id": 2})), Op::Delete(json!(3))])
+        );
+        // A delete without an id, a non-object and invalid JSON are skipped whole
+        assert_eq!(decode(&spec, br#"[{"id": 2}, {"_deleted": true}]"#), None);
+        assert_eq!(decode(&spec, b"42"), None);
+        assert_eq!(decode(&spec, b"{"), None);
+    }
+}
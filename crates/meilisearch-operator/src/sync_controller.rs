@@ -0,0 +1,537 @@
+use k8s_openapi::chrono::{self, DateTime, SecondsFormat, Utc};
+use kube::{
+    Api, Client, Resource, ResourceExt,
+    runtime::{
+        controller::{Action, Controller},
+        events::{EventType, Recorder},
+    },
+};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::error;
+
+use crate::{
+    clusters,
+    config::OperatorConfig,
+    crds::{
+        common::Phase,
+        database_sync::{DatabaseSync, DatabaseSyncSpec, DatabaseSyncStatus},
+        index::Index,
+    },
+    cron,
+    error::ReconcileError,
+    events, freeze, index_controller,
+    meili_http::{self, MeiliSend},
+    postgres, resync,
+    secret_cache::SecretCache,
+    shutdown, status, watch,
+};
+
+#[derive(Clone)]
+pub struct Ctx {
+    pub client: Client,
+    pub secrets: SecretCache,
+    pub recorder: Recorder,
+    pub config: Arc<OperatorConfig>,
+}
+
+pub fn controller(client: Client, config: &OperatorConfig) -> Controller<DatabaseSync> {
+    watch::controller(Api::<DatabaseSync>::all(client), config)
+}
+
+/// Retry interval while the Index or its Server is missing or not Ready.
+const WAIT: Duration = Duration::from_secs(60);
+
+/// Run the query and upsert every row as a document, deleting soft-deleted rows, on the
+/// `schedule` or once per `interval_seconds`. Rows removed from the table outright are only
+/// noticed with `rebuild`, which loads a scratch index and swaps it in.
+pub async fn reconcile(sync: Arc<DatabaseSync>, ctx: Arc<Ctx>) -> Result<Action, ReconcileError> {
+    if freeze::active(&ctx.config) {
+        return Ok(Action::requeue(freeze::RETRY));
+    }
+    let ns = sync.namespace().unwrap();
+    let name = sync.name_any();
+    let api: Api<DatabaseSync> = Api::namespaced(ctx.client.clone(), &ns);
+    let mut status = sync.status.clone().unwrap_or_default();
+    let every = Duration::from_secs(sync.spec.interval_seconds);
+    let schedule = match sync
+        .spec
+        .schedule
+        .as_deref()
+        .map(parse_schedule)
+        .transpose()
+    {
+        Ok(s) => s,
+        Err(e) => {
+            status.phase = Phase::Degraded;
+            status.message = Some(format!("invalid schedule: {}", e));
+            status::merge(&api, &name, &status).await?;
+            return Ok(Action::await_change());
+        }
+    };
+    if let Some(left) = due_in(
+        schedule.as_ref(),
+        every,
+        sync.metadata.generation,
+        &status,
+        Utc::now(),
+    ) {
+        return Ok(Action::requeue(left));
+    }
+
+    let Some(idx) = Api::<Index>::namespaced(ctx.client.clone(), &ns)
+        .get_opt(&sync.spec.index_ref)
+        .await?
+    else {
+        status.phase = Phase::Pending;
+        status.message = Some(format!("Index {}/{} not found", ns, sync.spec.index_ref));
+        status::merge(&api, &name, &status).await?;
+        return Ok(Action::requeue(WAIT));
+    };
+    let server_ns = idx.spec.server_ref.namespace_or(&ns).to_string();
+    let server = &idx.spec.server_ref.name;
+    if let Some(msg) = clusters::server_not_ready(&ctx.client, &server_ns, server).await? {
+        status.phase = Phase::WaitingForServer;
+        status.message = Some(msg);
+        status::merge(&api, &name, &status).await?;
+        return Ok(Action::requeue(WAIT));
+    }
+    let endpoint = clusters::server_endpoint(&ctx.client, &ctx.config, &server_ns, server).await?;
+    let master_key = index_controller::get_master_key(&ctx.secrets, &server_ns, server).await?;
+    let url = ctx
+        .secrets
+        .connection_url(&ns, &sync.spec.postgres.connection_secret_ref)
+        .await?;
+
+    let target = Target {
+        endpoint: &endpoint,
+        master_key: &master_key,
+        uid: &idx.spec.uid,
+        spec: &sync.spec,
+        fresh: false,
+    };
+    let run = if sync.spec.rebuild {
+        rebuild(&url, &target).await
+    } else {
+        copy_rows(&url, &target).await
+    };
+    let totals = match run {
+        Ok(Some(totals)) => totals,
+        // Shutting down; documents sent so far are upserts, so the next sync just repeats them
+        Ok(None) => return Ok(Action::requeue(Duration::from_secs(5))),
+        Err(e) => {
+            let msg = format!("sync failed: {:#}", e);
+            events::publish(
+                &ctx.recorder,
+                &sync.object_ref(&()),
+                EventType::Warning,
+                "SyncFailed",
+                "Sync",
+                &msg,
+            )
+            .await;
+            status.phase = Phase::Degraded;
+            status.message = Some(msg);
+            status::merge(&api, &name, &status).await?;
+            return Ok(Action::requeue(WAIT));
+        }
+    };
+
+    status.phase = Phase::Ready;
+    status.message = Some(format!(
+        "synced {} rows: {} upserted, {} deleted",
+        totals.rows, totals.upserted, totals.deleted
+    ));
+    let now = Utc::now();
+    let next = match &schedule {
+        Some(s) => s.next_after(now),
+        None => chrono::Duration::from_std(every).ok().map(|d| now + d),
+    };
+    status.last_sync = Some(resync::now());
+    status.next_sync = next.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true));
+    status.last_sync_rows = Some(totals.rows);
+    status.upserted_documents = Some(totals.upserted);
+    status.deleted_documents = Some(totals.deleted);
+    status.last_task_uid = totals.last_task_uid.or(status.last_task_uid);
+    status.observed_generation = sync.metadata.generation;
+    status::merge(&api, &name, &status).await?;
+    Ok(Action::requeue(
+        due_in(
+            schedule.as_ref(),
+            every,
+            sync.metadata.generation,
+            &status,
+            now,
+        )
+        .unwrap_or(every),
+    ))
+}
+
+fn parse_schedule(expr: &str) -> Result<cron::Schedule, String> {
+    let schedule = cron::Schedule::parse(expr)?;
+    if schedule.next_after(Utc::now()).is_none() {
+        return Err(format!("{:?} never fires", expr));
+    }
+    Ok(schedule)
+}
+
+/// Time until the next run, or `None` when one is due: the spec changed, the schedule fired
+/// since the last run, or `every` passed.
+fn due_in(
+    schedule: Option<&cron::Schedule>,
+    every: Duration,
+    generation: Option<i64>,
+    status: &DatabaseSyncStatus,
+    now: DateTime<Utc>,
+) -> Option<Duration> {
+    let Some(schedule) = schedule else {
+        return resync::remaining(
+            generation,
+            status.observed_generation,
+            status.last_sync.as_deref(),
+            every,
+        );
+    };
+    if generation.is_none() || generation != status.observed_generation {
+        return None;
+    }
+    let last = DateTime::parse_from_rfc3339(status.last_sync.as_deref()?).ok()?;
+    let next = schedule.next_after(last.with_timezone(&Utc))?;
+    (next - now).to_std().ok().filter(|d| !d.is_zero())
+}
+
+pub fn error_policy(_sync: Arc<DatabaseSync>, err: &ReconcileError, _ctx: Arc<Ctx>) -> Action {
+    error!(error = ?err, "database sync failed");
+    Action::requeue(Duration::from_secs(60))
+}
+
+struct Target<'a> {
+    endpoint: &'a str,
+    master_key: &'a str,
+    uid: &'a str,
+    spec: &'a DatabaseSyncSpec,
+    /// Loading a fresh index: soft-deleted rows are left out rather than deleted
+    fresh: bool,
+}
+
+#[derive(Default)]
+struct Totals {
+    rows: u64,
+    upserted: u64,
+    deleted: u64,
+    last_task_uid: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Op {
+    Upsert(Map<String, Value>),
+    Delete(Value),
+}
+
+/// Stream the query's rows into the index. `None` if interrupted by shutdown.
+async fn copy_rows(url: &str, target: &Target<'_>) -> anyhow::Result<Option<Totals>> {
+    let spec = target.spec;
+    let conn = postgres::Connection::connect(url).await?;
+    let mut rows = conn.query(&spec.postgres.query).await?;
+    let mut totals = Totals::default();
+    let mut upserts = Vec::new();
+    let mut deletes = Vec::new();
+    let batch = spec.batch_size as usize;
+    while let Some(row) = rows.next().await? {
+        totals.rows += 1;
+        // Upserts and deletes go out in row order, never mixed in one request
+        match document(spec, &rows.columns, row)? {
+            Op::Upsert(doc) => {
+                send(target, &mut deletes, "documents/delete-batch", &mut totals).await?;
+                upserts.push(Value::Object(doc));
+            }
+            Op::Delete(_) if target.fresh => totals.deleted += 1,
+            Op::Delete(id) => {
+                send(target, &mut upserts, "documents", &mut totals).await?;
+                deletes.push(id);
+            }
+        }
+        if upserts.len() >= batch {
+            send(target, &mut upserts, "documents", &mut totals).await?;
+        }
+        if deletes.len() >= batch {
+            send(target, &mut deletes, "documents/delete-batch", &mut totals).await?;
+        }
+        if shutdown::requested() {
+            return Ok(None);
+        }
+    }
+    send(target, &mut upserts, "documents", &mut totals).await?;
+    send(target, &mut deletes, "documents/delete-batch", &mut totals).await?;
+    Ok(Some(totals))
+}
+
+/// Load the rows into `<uid>-sync`, created with the live index's settings, wait for it to
+/// finish indexing and swap it in. A failed batch aborts the run before the swap.
+async fn rebuild(url: &str, live: &Target<'_>) -> anyhow::Result<Option<Totals>> {
+    let http = meili_http::client();
+    let scratch = format!("{}-sync", live.uid);
+    let call = |req: reqwest::RequestBuilder| async {
+        anyhow::Ok(
+            req.bearer_auth(live.master_key)
+                .send_checked()
+                .await?
+                .json::<Value>()
+                .await?,
+        )
+    };
+    let wait = |task: Value| async move {
+        index_controller::wait_task(http, live.endpoint, live.master_key, &task).await
+    };
+
+    // Leftover from an interrupted run; the task fails harmlessly if absent
+    let _ = wait(call(http.delete(format!("{}/indexes/{}", live.endpoint, scratch))).await?).await;
+    wait(
+        call(
+            http.post(format!("{}/indexes", live.endpoint))
+                .json(&serde_json::json!({ "uid": scratch, "primaryKey": live.spec.primary_key })),
+        )
+        .await?,
+    )
+    .await?;
+    let settings =
+        call(http.get(format!("{}/indexes/{}/settings", live.endpoint, live.uid))).await?;
+    wait(
+        call(
+            http.patch(format!("{}/indexes/{}/settings", live.endpoint, scratch))
+                .json(&settings),
+        )
+        .await?,
+    )
+    .await?;
+
+    let target = Target {
+        uid: &scratch,
+        fresh: true,
+        ..*live
+    };
+    let Some(totals) = copy_rows(url, &target).await? else {
+        return Ok(None);
+    };
+    // Tasks on one index run in order, so the last batch finishing means all did
+    if let Some(uid) = totals.last_task_uid {
+        loop {
+            let task = call(http.get(format!("{}/tasks/{}", live.endpoint, uid))).await?;
+            if !matches!(task["status"].as_str(), Some("enqueued" | "processing")) {
+                break;
+            }
+            if shutdown::requested() {
+                return Ok(None);
+            }
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+        let failed = call(http.get(format!("{}/tasks", live.endpoint)).query(&[
+            ("indexUids", scratch.as_str()),
+            ("statuses", "failed,canceled"),
+            ("types", "documentAdditionOrUpdate"),
+            ("limit", "1"),
+        ]))
+        .await?;
+        if let Some(task) = failed["results"].as_array().and_then(|r| r.first()) {
+            anyhow::bail!(
+                "batch task {} {} on {}, not swapping: {}",
+                task["uid"],
+                task["status"].as_str().unwrap_or_default(),
+                scratch,
+                task["error"]["message"]
+                    .as_str()
+                    .unwrap_or("no error message")
+            );
+        }
+    }
+    wait(
+        call(
+            http.post(format!("{}/swap-indexes", live.endpoint))
+                .json(&serde_json::json!([{ "indexes": [live.uid, scratch] }])),
+        )
+        .await?,
+    )
+    .await?;
+    wait(call(http.delete(format!("{}/indexes/{}", live.endpoint, scratch))).await?).await?;
+    Ok(Some(totals))
+}
+
+/// POST `items` to `route` under the index, if there are any, and clear them.
+async fn send(
+    target: &Target<'_>,
+    items: &mut Vec<Value>,
+    route: &str,
+    totals: &mut Totals,
+) -> anyhow::Result<()> {
+    if items.is_empty() {
+        return Ok(());
+    }
+    let mut req = meili_http::client()
+        .post(format!(
+            "{}/indexes/{}/{}",
+            target.endpoint, target.uid, route
+        ))
+        .bearer_auth(target.master_key)
+        .json(items);
+    if route == "documents" {
+        req = req.query(&[("primaryKey", target.spec.primary_key.as_str())]);
+    }
+    let task: Value = req.send_checked().await?.json().await?;
+    totals.last_task_uid = task["taskUid"].as_u64();
+    match route {
+        "documents" => totals.upserted += items.len() as u64,
+        _ => totals.deleted += items.len() as u64,
+    }
+    items.clear();
+    Ok(())
+}
+
+/// Turn a row into an upsert or, for soft-deleted rows, a delete of its primary key. Numbers,
+/// booleans and json/jsonb are typed; everything else, arrays included, stays a string.
+fn document(
+    spec: &DatabaseSyncSpec,
+    columns: &[postgres::Column],
+    row: Vec<Option<String>>,
+) -> anyhow::Result<Op> {
+    let mut doc = Map::new();
+    let mut deleted = false;
+    for (col, value) in columns.iter().zip(row) {
+        let value = match value {
+            None => Value::Null,
+            Some(text) => typed(col.type_oid, text),
+        };
+        if spec.soft_delete_column.as_deref() == Some(col.name.as_str()) {
+            deleted = value == Value::Bool(true);
+            continue;
+        }
+        let field = spec.field_mapping.get(&col.name).unwrap_or(&col.name);
+        doc.insert(field.clone(), value);
+    }
+    if deleted {
+        let id = doc
+            .remove(&spec.primary_key)
+            .filter(|v| !v.is_null())
+            .ok_or_else(|| anyhow::anyhow!("soft-deleted row has no {}", spec.primary_key))?;
+        return Ok(Op::Delete(id));
+    }
+    Ok(Op::Upsert(doc))
+}
+
+fn typed(type_oid: u32, text: String) -> Value {
+    match type_oid {
+        // bool
+        16 => Value::Bool(text == "t"),
+        // int2, int4, int8, oid
+        20 | 21 | 23 | 26 => text.parse::<i64>().map_or(Value::String(text), Value::from),
+        // float4, float8, numeric; NaN and infinities stay strings
+        700 | 701 | 1700 => match text
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+        {
+            Some(n) => Value::Number(n),
+            None => Value::String(text),
+        },
+        // json, jsonb
+        114 | 3802 => serde_json::from_str(&text).unwrap_or(Value::String(text)),
+        _ => Value::String(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn maps_rows_to_documents() {
+        let spec: DatabaseSyncSpec = serde_json::from_value(json!({
+            "index_ref": "movies",
+            "postgres": {"connection_secret_ref": {"name": "pg"}, "query": "select * from movies"},
+            "primary_key": "movie_id",
+            "field_mapping": {"id": "movie_id"},
+            "soft_delete_column": "deleted",
+        }))
+        .unwrap();
+        let columns = [
+            ("id", 23),
+            ("title", 25),
+            ("rating", 1700),
+            ("meta", 3802),
+            ("deleted", 16),
+        ]
+        .map(|(name, type_oid)| postgres::Column {
+            name: name.into(),
+            type_oid,
+        });
+        let row = |id: &str, deleted: &str| {
+            vec![
+                Some(id.into()),
+                Some("Dune".into()),
+                Some("8.5".into()),
+                Some(r#"{"tags": ["sf"]}"#.into()),
+                Some(deleted.into()),
+            ]
+        };
+        assert_eq!(
+            document(&spec, &columns, row("1", "f")).unwrap(),
+            Op::Upsert(
+                serde_json::from_value(json!({
+                    "movie_id": 1, "title": "Dune", "rating": 8.5, "meta": {"tags": ["sf"]}
+                }))
+                .unwrap()
+            )
+        );
+        assert_eq!(
+            document(&spec, &columns, row("2", "t")).unwrap(),
+            Op::Delete(json!(2))
+        );
+        let mut null_id = row("1", "t");
+        null_id[0] = None;
+        assert!(document(&spec, &columns, null_id).is_err());
+    }
+
+    #[test]
+    fn schedule_decides_when_due() {
+        let nightly = cron::Schedule::parse("30 2 * * *").unwrap();
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let status = DatabaseSyncStatus {
+            last_sync: Some("2024-06-01T02:31:00Z".into()),
+            observed_generation: Some(1),
+            ..Default::default()
+        };
+        let every = Duration::from_secs(3600);
+        assert_eq!(
+            due_in(
+                Some(&nightly),
+                every,
+                Some(1),
+                &status,
+                at("2024-06-01T12:00:00Z")
+            ),
+            Some(Duration::from_secs(14 * 3600 + 30 * 60))
+        );
+        assert_eq!(
+            due_in(
+                Some(&nightly),
+                every,
+                Some(1),
+                &status,
+                at("2024-06-02T02:30:00Z")
+            ),
+            None
+        );
+        // An edited spec runs right away
+        assert_eq!(
+            due_in(
+                Some(&nightly),
+                every,
+                Some(2),
+                &status,
+                at("2024-06-01T12:00:00Z")
+            ),
+            None
+        );
+    }
+}
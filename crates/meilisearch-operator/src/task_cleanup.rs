@@ -0,0 +1,91 @@
+use k8s_openapi::chrono::{DateTime, Duration as ChronoDuration, SecondsFormat, Utc};
+
+use crate::{
+    audit::Audit,
+    crds::server::{ServerStatus, TaskCleanupSpec},
+    error::ReconcileError,
+    meili_http::{self, MeiliSend},
+};
+
+/// Delete finished tasks older than `spec.older_than` once per `spec.interval_seconds`,
+/// so the task database doesn't grow unbounded. Records the outcome in status.
+pub(crate) async fn maybe_cleanup_tasks(
+    audit: &Audit<'_>,
+    endpoint: &str,
+    master_key: &str,
+    spec: &TaskCleanupSpec,
+    status: &mut ServerStatus,
+) -> Result<(), ReconcileError> {
+    let now = Utc::now();
+    let due = status
+        .last_task_cleanup
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .is_none_or(|last| {
+            now - last.with_timezone(&Utc) >= ChronoDuration::seconds(spec.interval_seconds)
+        });
+    if !due {
+        return Ok(());
+    }
+    let older_than = parse_age(&spec.older_than)
+        .ok_or_else(|| anyhow::anyhow!("invalid task_cleanup.older_than {:?}", spec.older_than))?;
+    let before = (now - older_than).to_rfc3339_opts(SecondsFormat::Secs, true);
+
+    let http = meili_http::client();
+    let statuses = spec.statuses.join(",");
+    let res = async {
+        http.delete(format!("{}/tasks", endpoint))
+            .bearer_auth(master_key)
+            .query(&[
+                ("statuses", statuses.as_str()),
+                ("beforeEnqueuedAt", &before),
+            ])
+            .send_checked()
+            .await?
+            .json::<serde_json::Value>()
+            .await
+            .map_err(meili_http::Error::from)
+    }
+    .await;
+    audit
+        .record(
+            "tasks.delete",
+            &format!("{} tasks before {}", statuses, before),
+            &res,
+        )
+        .await;
+    let task = res.map_err(anyhow::Error::from)?;
+    status.last_task_cleanup = Some(now.to_rfc3339_opts(SecondsFormat::Secs, true));
+    status.last_task_cleanup_task_uid = task["taskUid"].as_u64();
+    Ok(())
+}
+
+/// Parse ages like "7d", "12h", "30m" or "90s". Zero and negative ages are rejected.
+pub(crate) fn parse_age(value: &str) -> Option<ChronoDuration> {
+    let value = value.trim();
+    let (n, unit) = value.split_at(value.len().checked_sub(1)?);
+    let n: i64 = n.parse().ok().filter(|n| *n > 0)?;
+    match unit {
+        "d" => ChronoDuration::try_days(n),
+        "h" => ChronoDuration::try_hours(n),
+        "m" => ChronoDuration::try_minutes(n),
+        "s" => ChronoDuration::try_seconds(n),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ages() {
+        assert_eq!(parse_age("7d"), Some(ChronoDuration::days(7)));
+        assert_eq!(parse_age("12h"), Some(ChronoDuration::hours(12)));
+        assert_eq!(parse_age("7"), None);
+        assert_eq!(parse_age(""), None);
+        // A non-positive age would put beforeEnqueuedAt in the future
+        assert_eq!(parse_age("-7d"), None);
+        assert_eq!(parse_age("0h"), None);
+    }
+}
@@ -0,0 +1,175 @@
+use kube::{
+    Api, Client, Resource, ResourceExt,
+    runtime::events::{EventType, Recorder},
+};
+
+use crate::{
+    conditions::{self, LAST_TASK_FAILED},
+    crds::{index::Index, server::ServerStatus},
+    error::ReconcileError,
+    events,
+    meili_http::{self, ApiError, MeiliSend},
+    status,
+};
+
+/// Failed tasks fetched per pass; older ones beyond this are not reported.
+const PAGE: usize = 100;
+
+/// Report tasks that failed since `status.last_failed_task_uid` as Warning Events and a
+/// `LastTaskFailed` condition on the `indexes` they ran against, and clear the condition once
+/// a later task of that index succeeded. The first pass only records where to start, so the
+/// history of a long-running server is not replayed.
+pub(crate) async fn report_failed_tasks(
+    client: &Client,
+    recorder: &Recorder,
+    endpoint: &str,
+    master_key: &str,
+    indexes: &[Index],
+    status: &mut ServerStatus,
+) -> Result<(), ReconcileError> {
+    let failed = list_tasks(endpoint, master_key, &[("statuses", "failed")]).await?;
+    let newest = failed.iter().filter_map(|t| t["uid"].as_u64()).max();
+    let Some(cursor) = status.last_failed_task_uid else {
+        status.last_failed_task_uid = Some(newest.unwrap_or(0));
+        return Ok(());
+    };
+
+    let mut reported = Vec::new();
+    // Oldest first, so the condition ends up describing the latest failure
+    for task in failed.iter().rev() {
+        let Some(uid) = task["uid"].as_u64().filter(|u| *u > cursor) else {
+            continue;
+        };
+        let Some(index_uid) = task["indexUid"].as_str() else {
+            continue;
+        };
+        let error = task_error(task, uid);
+        let msg = format!(
+            "{} task {} failed: {}",
+            task["type"].as_str().unwrap_or("unknown"),
+            uid,
+            error
+        );
+        for idx in indexes.iter().filter(|i| i.spec.uid == index_uid) {
+            events::publish(
+                recorder,
+                &idx.object_ref(&()),
+                EventType::Warning,
+                "TaskFailed",
+                "MeilisearchTask",
+                &msg,
+            )
+            .await;
+            set_condition(client, idx, true, &error.reason(), &msg).await?;
+            reported.push(idx.spec.uid.clone());
+        }
+    }
+
+    // Indexes whose last reported failure has since been followed by a successful task
+    for idx in indexes.iter().filter(|i| {
+        !reported.contains(&i.spec.uid)
+            && i.status
+                .as_ref()
+                .is_some_and(|s| conditions::is_true(&s.conditions, LAST_TASK_FAILED))
+    }) {
+        let latest = list_tasks(
+            endpoint,
+            master_key,
+            &[
+                ("indexUids", idx.spec.uid.as_str()),
+                ("statuses", "succeeded,failed"),
+                ("limit", "1"),
+            ],
+        )
+        .await?;
+        if latest
+            .first()
+            .is_some_and(|t| t["status"].as_str() == Some("succeeded"))
+        {
+            set_condition(client, idx, false, "TaskSucceeded", "").await?;
+        }
+    }
+
+    status.last_failed_task_uid = Some(newest.map_or(cursor, |n| n.max(cursor)));
+    Ok(())
+}
+
+/// Meilisearch's error for a failed task, or a generic one when it sent none.
+pub(crate) fn task_error(task: &serde_json::Value, uid: u64) -> ApiError {
+    serde_json::from_value(task["error"].clone()).unwrap_or(ApiError {
+        message: format!("task {} failed", uid),
+        code: "task_failed".into(),
+        kind: "internal".into(),
+        link: String::new(),
+    })
+}
+
+async fn list_tasks(
+    endpoint: &str,
+    master_key: &str,
+    query: &[(&str, &str)],
+) -> Result<Vec<serde_json::Value>, ReconcileError> {
+    let limit = PAGE.to_string();
+    let mut page = meili_http::client()
+        .get(format!("{}/tasks", endpoint))
+        .bearer_auth(master_key)
+        .query(query);
+    if !query.iter().any(|(k, _)| *k == "limit") {
+        page = page.query(&[("limit", limit.as_str())]);
+    }
+    let body = page
+        .send_checked()
+        .await
+        .map_err(anyhow::Error::from)?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(body["results"].as_array().cloned().unwrap_or_default())
+}
+
+async fn set_condition(
+    client: &Client,
+    idx: &Index,
+    failed: bool,
+    reason: &str,
+    msg: &str,
+) -> Result<(), ReconcileError> {
+    let mut conditions = idx
+        .status
+        .as_ref()
+        .map(|s| s.conditions.clone())
+        .unwrap_or_default();
+    conditions::set_condition(
+        &mut conditions,
+        LAST_TASK_FAILED,
+        failed,
+        reason,
+        msg,
+        idx.metadata.generation,
+    );
+    let api: Api<Index> = Api::namespaced(client.clone(), &idx.namespace().unwrap_or_default());
+    match conditions.iter().find(|c| c.type_ == LAST_TASK_FAILED) {
+        Some(cond) => {
+            status::apply_condition(&api, &idx.name_any(), status::TASK_WATCH_MANAGER, cond).await
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_errors_fall_back_to_a_generic_one() {
+        let task = serde_json::json!({"uid": 12, "error": {
+            "message": "Document doesn't have a `id` attribute",
+            "code": "missing_document_id",
+            "type": "invalid_request",
+            "link": "https://docs.meilisearch.com/errors#missing_document_id"
+        }});
+        assert_eq!(task_error(&task, 12).reason(), "MissingDocumentId");
+        let bare = serde_json::json!({"uid": 13});
+        assert_eq!(task_error(&bare, 13).code, "task_failed");
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use k8s_openapi::{
+    api::core::v1::Secret,
+    chrono::{DateTime, SecondsFormat, Utc},
+};
+use kube::{
+    Api, Client, Resource, ResourceExt,
+    api::{Patch, PatchParams},
+    runtime::{
+        controller::{Action, Controller},
+        events::Recorder,
+    },
+};
+use serde_json::{Map, Value, json};
+use sha2::{Digest, Sha256};
+use tokio::time::Duration;
+use tracing::error;
+
+use crate::{
+    children,
+    config::OperatorConfig,
+    crds::{
+        FINALIZER,
+        common::Phase,
+        index::{AdoptionPolicy, Index, IndexSpec},
+        key::{Key, KeySpec, KeyStatus},
+        tenant::{Tenant, TenantSpec, TenantStatus},
+    },
+    error::ReconcileError,
+    freeze,
+    object_store::hex,
+    secret_cache::SecretCache,
+    secret_template, status, tenant_token, watch,
+};
+
+#[derive(Clone)]
+pub struct Ctx {
+    pub client: Client,
+    pub secrets: SecretCache,
+    pub recorder: Recorder,
+    pub config: Arc<OperatorConfig>,
+}
+
+pub fn controller(client: Client, config: &OperatorConfig) -> Controller<Tenant> {
+    watch::controller(Api::<Tenant>::all(client), config)
+}
+
+/// Retry interval while the tenant's Index or Key CRs are not ready yet.
+const WAIT: Duration = Duration::from_secs(15);
+/// Resync interval once everything is Ready.
+const RESYNC: Duration = Duration::from_secs(300);
+
+/// Stamp out the tenant's Index CRs and Key CR, sign its tenant token once the key exists,
+/// and report how far along they are.
+pub async fn reconcile(tenant: Arc<Tenant>, ctx: Arc<Ctx>) -> Result<Action, ReconcileError> {
+    if freeze::active(&ctx.config) {
+        return Ok(Action::requeue(freeze::RETRY));
+    }
+    let ns = tenant.namespace().unwrap();
+    let name = tenant.name_any();
+    let api: Api<Tenant> = Api::namespaced(ctx.client.clone(), &ns);
+    let owner = tenant.uid().unwrap_or_default();
+
+    // Index CRs go with the Tenant through their ownerReference; the Key CR may live in the
+    // Server's namespace, so it is deleted explicitly.
+    if tenant.metadata.deletion_timestamp.is_some() {
+        children::prune_owned::<Key>(&ctx.client, &owner, &BTreeSet::new()).await?;
+        let pp = PatchParams::default();
+        let patch = json!({"metadata": {"finalizers": null}});
+        let _ = api.patch(&name, &pp, &Patch::Merge(&patch)).await?;
+        return Ok(Action::await_change());
+    }
+    if !tenant.finalizers().iter().any(|f| f == FINALIZER) {
+        let mut finals = tenant.finalizers().to_vec();
+        finals.push(FINALIZER.to_string());
+        let patch = json!({"metadata": {"finalizers": finals}});
+        let _ = api
+            .patch(&name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await?;
+    }
+
+    let spec = &tenant.spec;
+    let mut status = tenant.status.clone().unwrap_or_default();
+    status.observed_generation = tenant.metadata.generation;
+    let server_ns = spec.server_ref.namespace_or(&ns).to_string();
+    if server_ns != ns && !ctx.config.allow_cross_namespace_refs {
+        status.phase = Phase::Degraded;
+        status.message = Some(format!(
+            "server_ref points at namespace {}; cross-namespace references are disabled",
+            server_ns
+        ));
+        status::merge(&api, &name, &status).await?;
+        return Ok(Action::await_change());
+    }
+    let owner_ref = tenant.controller_owner_ref(&()).unwrap();
+
+    let mut keep = BTreeSet::new();
+    let mut uids = Vec::new();
+    let mut ready = 0;
+    let mut degraded = Vec::new();
+    let mut waiting = Vec::new();
+    for t in &spec.indexes {
+        let cr = format!("{}-{}", name, t.name);
+        let uid = index_uid(spec, &t.name);
+        keep.insert((ns.clone(), cr.clone()));
+        uids.push(uid.clone());
+        // Index.uid is immutable, so a new tenant_id replaces the CR once its finalizer has
+        // cleaned up the old index; a new Server is handled by the Index's own retarget
+        let stale = |old: &Index| old.spec.uid != uid;
+        if children::replace_stale(&ctx.client, &ns, &cr, stale).await? {
+            waiting.push(format!("Index {} is being replaced", cr));
+            continue;
+        }
+        let mut idx = Index::new(
+            &cr,
+            IndexSpec {
+                server_ref: spec.server_ref.clone(),
+                uid: uid.clone(),
+                primary_key: t.primary_key.clone(),
+                settings: t.settings.clone(),
+                template_ref: t.template_ref.clone(),
+                deletion_policy: t.deletion_policy,
+                admin_key: None,
+                keys: vec![],
+                retention: None,
+                adoption_policy: AdoptionPolicy::default(),
+                frozen: false,
+                min_documents: None,
+            },
+        );
+        idx.metadata.owner_references = Some(vec![owner_ref.clone()]);
+        let idx = children::apply_owned(&ctx.client, &ns, &owner, idx).await?;
+        match idx.status.as_ref() {
+            Some(s) if s.ready => ready += 1,
+            Some(s) if s.phase == Phase::Degraded => degraded.push(format!(
+                "Index {}: {}",
+                cr,
+                s.message.as_deref().unwrap_or("degraded")
+            )),
+            _ => {}
+        }
+    }
+    // Indexes dropped from the spec
+    children::prune_owned::<Index>(&ctx.client, &owner, &keep).await?;
+
+    let key_cr = if server_ns == ns {
+        format!("{}-key", name)
+    } else {
+        format!("{}-{}-key", ns, name)
+    };
+    let key_secret = spec
+        .key
+        .secret_name
+        .clone()
+        .unwrap_or_else(|| format!("{}-key", name));
+    let mut key = Key::new(
+        &key_cr,
+        KeySpec {
+            server_ref: spec.server_ref.name.clone(),
+            name: Some(format!("tenant {}", spec.tenant_id)),
+            description: Some(format!("Key for Tenant {}/{}", ns, name)),
+            actions: spec.key.actions.clone(),
+            indexes: uids.clone(),
+            expires_at: spec.key.expires_at.clone(),
+            secret_namespace: ns.clone(),
+            secret_name: key_secret.clone(),
+            secret_store: None,
+            immutable_secret: None,
+            secret_template: None,
+            retain_secret: false,
+            adopt_existing_secret: false,
+        },
+    );
+    if server_ns == ns {
+        key.metadata.owner_references = Some(vec![owner_ref.clone()]);
+    }
+    // Key.server_ref is immutable too; a Tenant moved to another Server in the same
+    // namespace gets a new Key CR once the old one removed its key; until then it reads as
+    // not ready
+    let server = &spec.server_ref.name;
+    let stale = |old: &Key| &old.spec.server_ref != server;
+    let key_status = if children::replace_stale(&ctx.client, &server_ns, &key_cr, stale).await? {
+        KeyStatus::default()
+    } else {
+        let key = children::apply_owned(&ctx.client, &server_ns, &owner, key).await?;
+        key.status.unwrap_or_default()
+    };
+    // A Key left behind in the old namespace after server_ref moved
+    children::prune_owned::<Key>(
+        &ctx.client,
+        &owner,
+        &BTreeSet::from([(server_ns.clone(), key_cr.clone())]),
+    )
+    .await?;
+    status.key_ready = key_status.ready;
+    status.key_uid = key_status.uid.clone();
+
+    if ready < uids.len() {
+        waiting.push(format!("{}/{} indexes ready", ready, uids.len()));
+    }
+    if !key_status.ready {
+        waiting.push(format!("Key {}/{} not ready", server_ns, key_cr));
+    }
+    let mut next_sign = None;
+    match (&spec.token, key_status.uid.as_deref()) {
+        (Some(_), Some(key_uid)) if key_status.ready => {
+            let secret = Api::<Secret>::namespaced(ctx.client.clone(), &ns)
+                .get_opt(&key_secret)
+                .await?;
+            match secret
+                .as_ref()
+                .and_then(|s| secret_template::read(s, "key"))
+            {
+                Some(api_key) => {
+                    next_sign = ensure_token(
+                        &ctx.client,
+                        &tenant,
+                        &uids,
+                        &api_key,
+                        key_uid,
+                        &mut status,
+                        Utc::now(),
+                    )
+                    .await?;
+                }
+                None => waiting.push(format!("key Secret {} not written yet", key_secret)),
+            }
+        }
+        (Some(_), _) => {}
+        (None, _) => {
+            status.token_expires_at = None;
+            status.token_checksum = None;
+        }
+    }
+
+    status.indexes = uids.clone();
+    status.ready_indexes = Some(format!("{}/{}", ready, uids.len()));
+    let action = if !degraded.is_empty() {
+        status.phase = Phase::Degraded;
+        status.message = Some(degraded.join("; "));
+        Action::requeue(WAIT)
+    } else if !waiting.is_empty() {
+        status.phase = Phase::Pending;
+        status.message = Some(format!("waiting: {}", waiting.join(", ")));
+        Action::requeue(WAIT)
+    } else {
+        status.phase = Phase::Ready;
+        status.message = Some(format!(
+            "{} indexes, key {}",
+            uids.len(),
+            key_status.uid.as_deref().unwrap_or_default()
+        ));
+        Action::requeue(next_sign.map_or(RESYNC, |d: Duration| d.min(RESYNC)))
+    };
+    status::merge(&api, &name, &status).await?;
+    Ok(action)
+}
+
+pub fn error_policy(_tenant: Arc<Tenant>, err: &ReconcileError, _ctx: Arc<Ctx>) -> Action {
+    error!(error = ?err, "tenant reconcile failed");
+    Action::requeue(Duration::from_secs(60))
+}
+
+fn index_uid(spec: &TenantSpec, index: &str) -> String {
+    format!("{}-{}", spec.tenant_id, index)
+}
+
+/// `searchRules` of the tenant token: every tenant index, restricted by the token filter.
+fn search_rules(spec: &TenantSpec, uids: &[String]) -> Value {
+    let filter = spec
+        .token
+        .as_ref()
+        .and_then(|t| t.filter.as_deref())
+        .map(|f| f.replace("{tenant_id}", &spec.tenant_id));
+    let rule = match filter {
+        Some(f) => json!({ "filter": f }),
+        None => Value::Object(Map::new()),
+    };
+    Value::Object(uids.iter().map(|uid| (uid.clone(), rule.clone())).collect())
+}
+
+/// Re-sign the tenant token when the key, rules or lifetime changed, or two thirds of its
+/// lifetime have passed. Returns how long until it needs signing again.
+async fn ensure_token(
+    client: &Client,
+    tenant: &Tenant,
+    uids: &[String],
+    api_key: &str,
+    key_uid: &str,
+    status: &mut TenantStatus,
+    now: DateTime<Utc>,
+) -> Result<Option<Duration>, ReconcileError> {
+    let spec = &tenant.spec;
+    let token_spec = spec.token.as_ref().expect("token spec");
+    let secret_name = token_spec
+        .secret_name
+        .clone()
+        .unwrap_or_else(|| format!("{}-token", tenant.name_any()));
+    let rules = search_rules(spec, uids);
+    let mut digest = Sha256::new();
+    digest.update(api_key);
+    digest.update(key_uid);
+    digest.update(rules.to_string());
+    digest.update(format!(
+        "{:?}/{}",
+        token_spec.expires_in_seconds, secret_name
+    ));
+    let checksum = hex(&digest.finalize());
+
+    let lifetime = token_spec.expires_in_seconds.map(Duration::from_secs);
+    let expires_at = status
+        .token_expires_at
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc));
+    let refresh_at = |exp: DateTime<Utc>, life: Duration| {
+        exp - k8s_openapi::chrono::Duration::from_std(life / 3).unwrap_or_default()
+    };
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), &tenant.namespace().unwrap());
+    let current = status.token_checksum.as_deref() == Some(checksum.as_str())
+        && secrets.get_opt(&secret_name).await?.is_some()
+        && match (lifetime, expires_at) {
+            (Some(life), Some(exp)) => now < refresh_at(exp, life),
+            (None, None) => true,
+            _ => false,
+        };
+    if !current {
+        let exp =
+            lifetime.map(|l| now + k8s_openapi::chrono::Duration::from_std(l).unwrap_or_default());
+        let token = tenant_token::sign(api_key, key_uid, &rules, exp.map(|e| e.timestamp()));
+        let sec = secret_template::build(
+            &secret_name,
+            None,
+            "token",
+            &token,
+            tenant.controller_owner_ref(&()).map(|o| vec![o]),
+            false,
+        );
+        let _ = children::apply(&secrets, &secret_name, &sec).await?;
+        status.token_checksum = Some(checksum);
+        status.token_expires_at = exp.map(|e| e.to_rfc3339_opts(SecondsFormat::Secs, true));
+    }
+    Ok(match (lifetime, status.token_expires_at.as_deref()) {
+        (Some(life), Some(exp)) => DateTime::parse_from_rfc3339(exp)
+            .ok()
+            .map(|exp| refresh_at(exp.with_timezone(&Utc), life))
+            .and_then(|at| (at - now).to_std().ok()),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_token_to_tenant_indexes() {
+        let spec: TenantSpec = serde_json::from_value(json!({
+            "server_ref": { "name": "meili" },
+            "tenant_id": "acme",
+            "indexes": [{ "name": "products" }, { "name": "orders" }],
+            "token": { "filter": "tenant = {tenant_id}" },
+        }))
+        .unwrap();
+        assert_eq!(spec.key.actions, vec!["search"]);
+        let uids: Vec<String> = spec
+            .indexes
+            .iter()
+            .map(|t| index_uid(&spec, &t.name))
+            .collect();
+        assert_eq!(uids, vec!["acme-products", "acme-orders"]);
+        assert_eq!(
+            search_rules(&spec, &uids),
+            json!({
+                "acme-products": { "filter": "tenant = acme" },
+                "acme-orders": { "filter": "tenant = acme" },
+            })
+        );
+    }
+}
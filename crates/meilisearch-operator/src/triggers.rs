@@ -0,0 +1,100 @@
+use kube::{Api, Resource, ResourceExt};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+use crate::{crds::common::TriggerOutcome, error::ReconcileError};
+
+/// Set to one of the [`Trigger`] values to run an action once; the controller clears it.
+pub const TRIGGER_ANNOTATION: &str = "meili.operator.dev/trigger";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    Reindex,
+    ResyncSettings,
+    RotateKey,
+}
+
+impl Trigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reindex => "reindex",
+            Self::ResyncSettings => "resync-settings",
+            Self::RotateKey => "rotate-key",
+        }
+    }
+}
+
+/// The pending trigger on `obj`, or the unrecognized annotation value.
+pub fn requested<K: Resource>(obj: &K) -> Option<Result<Trigger, String>> {
+    let value = obj.annotations().get(TRIGGER_ANNOTATION)?;
+    Some(match value.trim() {
+        "reindex" => Ok(Trigger::Reindex),
+        "resync-settings" => Ok(Trigger::ResyncSettings),
+        "rotate-key" => Ok(Trigger::RotateKey),
+        other => Err(other.to_string()),
+    })
+}
+
+/// Remove the trigger annotation so the action runs only once. Returns the updated object.
+pub async fn clear<K>(api: &Api<K>, name: &str) -> Result<K, ReconcileError>
+where
+    K: Resource + Clone + serde::de::DeserializeOwned + std::fmt::Debug,
+{
+    let patch = serde_json::json!({"metadata": {"annotations": {TRIGGER_ANNOTATION: null}}});
+    Ok(api
+        .patch(
+            name,
+            &kube::api::PatchParams::default(),
+            &kube::api::Patch::Merge(&patch),
+        )
+        .await?)
+}
+
+pub fn outcome(action: &str, result: &Result<String, ReconcileError>) -> TriggerOutcome {
+    let (result, message) = match result {
+        Ok(msg) => ("Succeeded", msg.clone()),
+        Err(e) => ("Failed", e.to_string()),
+    };
+    TriggerOutcome {
+        action: action.to_string(),
+        result: result.to_string(),
+        message: Some(message),
+        completed_at: OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crds::key::{Key, KeySpec};
+
+    #[test]
+    fn parses_trigger_annotation() {
+        let mut key = Key::new(
+            "k",
+            KeySpec {
+                server_ref: "s".into(),
+                name: None,
+                description: None,
+                actions: vec![],
+                indexes: vec![],
+                expires_at: None,
+                secret_namespace: "ns".into(),
+                secret_name: "sec".into(),
+                secret_store: None,
+                immutable_secret: None,
+                secret_template: None,
+                retain_secret: false,
+                adopt_existing_secret: false,
+            },
+        );
+        assert_eq!(requested(&key), None);
+        key.annotations_mut()
+            .insert(TRIGGER_ANNOTATION.into(), "rotate-key".into());
+        assert_eq!(requested(&key), Some(Ok(Trigger::RotateKey)));
+        key.annotations_mut()
+            .insert(TRIGGER_ANNOTATION.into(), "explode".into());
+        assert_eq!(requested(&key), Some(Err("explode".into())));
+    }
+}
@@ -0,0 +1,896 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::apps::v1::StatefulSet;
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, Service};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, OwnerReference};
+use k8s_openapi::chrono::{DateTime, Utc};
+use kube::{
+    Api, Client, Resource, ResourceExt,
+    api::{ApiResource, DeleteParams, DynamicObject, GroupVersionKind, ListParams, PostParams},
+    runtime::events::{EventType, Recorder},
+};
+use tokio::time::Duration;
+
+use crate::{
+    audit::Audit,
+    children,
+    crds::server::{
+        Server, ServerSpec, ServerStatus, ServiceMesh, UpgradePhase, UpgradeStatus, UpgradeStrategy,
+    },
+    error::ReconcileError,
+    events,
+    meili_http::{self, MeiliSend},
+    server_controller::{
+        ServiceNetwork, Workload, claim_template, ensure_service, ensure_statefulset, meili_env,
+    },
+};
+
+/// Give the restored StatefulSet roughly ten minutes to import the dump and pass checks.
+const MAX_VERIFY_ATTEMPTS: u32 = 60;
+const STEP_REQUEUE: Duration = Duration::from_secs(10);
+const CANARY_REQUEUE: Duration = Duration::from_secs(30);
+/// Searches issued against the canary on every analysis pass
+const PROBES_PER_PASS: u32 = 5;
+/// Don't judge the error rate on fewer samples than this
+const MIN_PROBES: u32 = 10;
+
+pub(crate) struct UpgradeCtx<'a> {
+    pub client: &'a Client,
+    pub recorder: &'a Recorder,
+    pub server: &'a Server,
+    pub owner: &'a OwnerReference,
+    /// Endpoint of the Service, i.e. the currently serving StatefulSet
+    pub endpoint: &'a str,
+    /// Endpoint of the upgrade target's own Service, resolved like `endpoint`; `None` when
+    /// the operator runs outside the cluster and can't reach it
+    pub target_endpoint: Option<&'a str>,
+    pub master_key: &'a str,
+}
+
+/// Name of the StatefulSet the next blue/green upgrade rolls out to.
+pub(crate) fn next_statefulset(name: &str, active: &str) -> String {
+    if active == format!("{}-green", name) {
+        format!("{}-blue", name)
+    } else {
+        format!("{}-green", name)
+    }
+}
+
+/// Advance a blue/green or canary upgrade by one step: dump the serving instance, import
+/// the dump onto fresh volumes with one-shot Jobs, start a parallel StatefulSet running the
+/// new image on them, verify it, optionally send a share of traffic to it while probing,
+/// then switch the Service over and remove the old StatefulSet. Returns how soon to requeue.
+pub(crate) async fn step_upgrade(
+    ctx: &UpgradeCtx<'_>,
+    desired_image: &str,
+    status: &mut ServerStatus,
+) -> Result<Duration, ReconcileError> {
+    let server = ctx.server;
+    let ns = server.namespace().unwrap();
+    let name = server.name_any();
+    let active = status
+        .active_statefulset
+        .clone()
+        .unwrap_or_else(|| name.clone());
+    let target = next_statefulset(&name, &active);
+
+    let mut up = match status.upgrade.take() {
+        Some(u) if u.target_image == desired_image => u,
+        previous => {
+            // Spec moved on (or this is a fresh upgrade): discard any half-built target.
+            if let Some(p) = previous {
+                discard_target(ctx.client, &ns, &p.target_statefulset).await?;
+            }
+            events::publish(
+                ctx.recorder,
+                &server.object_ref(&()),
+                EventType::Normal,
+                "UpgradeStarted",
+                "Upgrade",
+                format!("blue/green upgrade to {} via {}", desired_image, target),
+            )
+            .await;
+            UpgradeStatus {
+                phase: UpgradePhase::Dumping,
+                target_image: desired_image.to_string(),
+                target_statefulset: target.clone(),
+                dump_task_uid: None,
+                dump_uid: None,
+                verify_attempts: 0,
+                canary_started_at: None,
+                probes: 0,
+                probe_failures: 0,
+                message: None,
+            }
+        }
+    };
+
+    let http = meili_http::client();
+    let requeue = match up.phase {
+        UpgradePhase::Dumping => match up.dump_task_uid {
+            None => {
+                let res = create_dump(http, ctx.endpoint, ctx.master_key).await;
+                Audit::new(ctx.recorder, ctx.server, &ctx.server.name_any())
+                    .record("dumps.create", "pre-upgrade dump", &res)
+                    .await;
+                up.dump_task_uid = Some(res?);
+                STEP_REQUEUE
+            }
+            Some(task_uid) => {
+                let task = get_task(http, ctx.endpoint, ctx.master_key, task_uid).await?;
+                match task.status.as_str() {
+                    "succeeded" => {
+                        up.dump_uid = task.details.and_then(|d| d.dump_uid);
+                        if up.dump_uid.is_none() {
+                            fail(ctx, &mut up, "dump task finished without a dumpUid").await?;
+                        } else {
+                            up.phase = UpgradePhase::Importing;
+                        }
+                    }
+                    "failed" | "canceled" => {
+                        let msg = task
+                            .error
+                            .map(|e| e.message)
+                            .unwrap_or_else(|| format!("dump task {}", task.status));
+                        fail(ctx, &mut up, &msg).await?;
+                    }
+                    _ => {}
+                }
+                STEP_REQUEUE
+            }
+        },
+        UpgradePhase::Importing => {
+            let dump_uid = up.dump_uid.clone().unwrap_or_default();
+            match run_imports(ctx, &ns, &active, &up, &dump_uid).await? {
+                ImportState::Running => {}
+                ImportState::Done => {
+                    // Release the source volumes before the old StatefulSet goes away
+                    delete_import_jobs(ctx.client, &ns, &up.target_statefulset).await?;
+                    up.phase = UpgradePhase::Provisioning;
+                }
+                ImportState::Failed(msg) => fail(ctx, &mut up, &msg).await?,
+            }
+            STEP_REQUEUE
+        }
+        UpgradePhase::Provisioning => {
+            // The same StatefulSet the Server renders once it is active, so promotion does
+            // not restart the verified pods
+            let workload = Workload {
+                sts_name: up.target_statefulset.clone(),
+                image: up.target_image.clone(),
+            };
+            // The target's governing Service doubles as the endpoint used for verification.
+            ensure_service(
+                ctx.client,
+                &ns,
+                &workload.sts_name,
+                &workload.sts_name,
+                Some(&name),
+                ServiceNetwork::of(&server.spec),
+                Some(ctx.owner),
+            )
+            .await?;
+            ensure_statefulset(
+                ctx.client,
+                &ns,
+                &name,
+                &workload,
+                &server.spec,
+                Some(ctx.owner),
+            )
+            .await?;
+            up.phase = UpgradePhase::Verifying;
+            STEP_REQUEUE
+        }
+        UpgradePhase::Verifying => {
+            let verified = match target_endpoint(ctx, &ns, &up) {
+                Ok(target) => verify(http, ctx.endpoint, target, ctx.master_key).await,
+                Err(e) => Err(e),
+            };
+            match verified {
+                Ok(()) if server.spec.upgrade_strategy == UpgradeStrategy::Canary => {
+                    let canary = server.spec.canary.as_ref().ok_or_else(|| {
+                        anyhow::anyhow!("upgrade_strategy Canary requires spec.canary")
+                    })?;
+                    set_route_weight(ctx, &ns, &name, &up.target_statefulset, canary.weight)
+                        .await?;
+                    up.phase = UpgradePhase::Canary;
+                    up.canary_started_at = Some(Utc::now().to_rfc3339());
+                    up.message = None;
+                    events::publish(
+                        ctx.recorder,
+                        &server.object_ref(&()),
+                        EventType::Normal,
+                        "CanaryStarted",
+                        "Upgrade",
+                        format!(
+                            "{}% of {} traffic routed to {}",
+                            canary.weight, canary.http_route, up.target_statefulset
+                        ),
+                    )
+                    .await;
+                }
+                Ok(()) => {
+                    switch_over(ctx, &ns, &name, &active, &up).await?;
+                    status.active_statefulset = Some(up.target_statefulset.clone());
+                    status.current_image = Some(up.target_image.clone());
+                    events::publish(
+                        ctx.recorder,
+                        &server.object_ref(&()),
+                        EventType::Normal,
+                        "UpgradeCompleted",
+                        "Upgrade",
+                        format!(
+                            "traffic switched to {} running {}",
+                            up.target_statefulset, up.target_image
+                        ),
+                    )
+                    .await;
+                    return Ok(Duration::from_secs(300));
+                }
+                Err(e) => {
+                    up.verify_attempts += 1;
+                    up.message = Some(e.to_string());
+                    if up.verify_attempts >= MAX_VERIFY_ATTEMPTS {
+                        let msg =
+                            format!("verification of {} failed: {}", up.target_statefulset, e);
+                        fail(ctx, &mut up, &msg).await?;
+                    }
+                }
+            }
+            if up.phase == UpgradePhase::Canary {
+                CANARY_REQUEUE
+            } else {
+                STEP_REQUEUE
+            }
+        }
+        UpgradePhase::Canary => {
+            let canary =
+                server.spec.canary.clone().ok_or_else(|| {
+                    anyhow::anyhow!("upgrade_strategy Canary requires spec.canary")
+                })?;
+            let target = target_endpoint(ctx, &ns, &up)?;
+            for _ in 0..PROBES_PER_PASS {
+                up.probes += 1;
+                if smoke_search(http, target, ctx.master_key).await.is_err() {
+                    up.probe_failures += 1;
+                }
+            }
+            let rate = f64::from(up.probe_failures) / f64::from(up.probes);
+            if up.probes >= MIN_PROBES && rate > canary.max_error_rate {
+                set_route_weight(ctx, &ns, &name, &up.target_statefulset, 0).await?;
+                let msg = format!(
+                    "canary rolled back: {}/{} probe searches failed",
+                    up.probe_failures, up.probes
+                );
+                fail(ctx, &mut up, &msg).await?;
+                Duration::from_secs(300)
+            } else if up.probes >= MIN_PROBES
+                && rate <= canary.max_error_rate
+                && canary_elapsed(&up) >= canary.analysis_seconds
+            {
+                switch_over(ctx, &ns, &name, &active, &up).await?;
+                set_route_weight(ctx, &ns, &name, &up.target_statefulset, 0).await?;
+                status.active_statefulset = Some(up.target_statefulset.clone());
+                status.current_image = Some(up.target_image.clone());
+                events::publish(
+                    ctx.recorder,
+                    &server.object_ref(&()),
+                    EventType::Normal,
+                    "UpgradeCompleted",
+                    "Upgrade",
+                    format!(
+                        "canary promoted after {} probes ({} failed); {} now serving {}",
+                        up.probes, up.probe_failures, up.target_statefulset, up.target_image
+                    ),
+                )
+                .await;
+                return Ok(Duration::from_secs(300));
+            } else {
+                CANARY_REQUEUE
+            }
+        }
+        // Stay on the old image until the spec changes again.
+        UpgradePhase::Failed => Duration::from_secs(300),
+    };
+    status.upgrade = Some(up);
+    Ok(requeue)
+}
+
+/// Where the operator reaches the upgrade target.
+fn target_endpoint<'a>(
+    ctx: &UpgradeCtx<'a>,
+    ns: &str,
+    up: &UpgradeStatus,
+) -> anyhow::Result<&'a str> {
+    ctx.target_endpoint.ok_or_else(|| {
+        anyhow::anyhow!(
+            "no endpoint for {}/{} outside the cluster; add it to DEV_ENDPOINTS",
+            ns,
+            up.target_statefulset
+        )
+    })
+}
+
+/// Give up on the upgrade and remove what was built for it; the serving StatefulSet is
+/// left alone.
+async fn fail(
+    ctx: &UpgradeCtx<'_>,
+    up: &mut UpgradeStatus,
+    msg: &str,
+) -> Result<(), ReconcileError> {
+    let ns = ctx.server.namespace().unwrap();
+    discard_target(ctx.client, &ns, &up.target_statefulset).await?;
+    up.phase = UpgradePhase::Failed;
+    up.message = Some(msg.to_string());
+    events::publish(
+        ctx.recorder,
+        &ctx.server.object_ref(&()),
+        EventType::Warning,
+        "UpgradeFailed",
+        "Upgrade",
+        msg,
+    )
+    .await;
+    Ok(())
+}
+
+/// Delete the import Jobs, StatefulSet, Service and volumes of an upgrade target.
+async fn discard_target(client: &Client, ns: &str, target: &str) -> Result<(), ReconcileError> {
+    delete_import_jobs(client, ns, target).await?;
+    delete_statefulset(client, ns, target).await?;
+    delete_service(client, ns, target).await?;
+    let claims: Api<PersistentVolumeClaim> = Api::namespaced(client.clone(), ns);
+    let lp = ListParams::default().labels(&format!("app={}", target));
+    let _ = claims
+        .delete_collection(&DeleteParams::default(), &lp)
+        .await?;
+    Ok(())
+}
+
+/// Label on the import Jobs of an upgrade target, valued with the target's name.
+const IMPORT_FOR_LABEL: &str = "meili.operator.dev/import-for";
+/// Bound on one replica's dump import.
+const IMPORT_DEADLINE_SECONDS: i64 = 3600;
+
+enum ImportState {
+    Running,
+    Done,
+    Failed(String),
+}
+
+/// Create the target's data volumes and one import Job per replica, if missing, and report
+/// how the Jobs are doing.
+async fn run_imports(
+    ctx: &UpgradeCtx<'_>,
+    ns: &str,
+    source: &str,
+    up: &UpgradeStatus,
+    dump_uid: &str,
+) -> Result<ImportState, ReconcileError> {
+    let server = ctx.server;
+    let spec = &server.spec;
+    let target = &up.target_statefulset;
+    let claims: Api<PersistentVolumeClaim> = Api::namespaced(ctx.client.clone(), ns);
+    let jobs: Api<Job> = Api::namespaced(ctx.client.clone(), ns);
+    let size = spec
+        .storage
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("blue/green upgrades need spec.storage"))?;
+    let mut done = true;
+    for replica in 0..spec.replicas.max(1) {
+        let mut claim = claim_template("data", size, None);
+        claim.metadata.name = Some(format!("data-{}-{}", target, replica));
+        // The StatefulSet takes the claim over under the name it would give it
+        claim.metadata.labels = Some(BTreeMap::from([("app".into(), target.clone())]));
+        create_if_missing(&claims, &claim).await?;
+        let job = import_job(
+            &server.name_any(),
+            target,
+            replica,
+            &up.target_image,
+            source,
+            dump_uid,
+            spec,
+            ctx.owner,
+        );
+        let job = create_if_missing(&jobs, &job).await?;
+        let conditions = job.status.and_then(|s| s.conditions).unwrap_or_default();
+        let holds = |type_: &str| {
+            conditions
+                .iter()
+                .any(|c| c.type_ == type_ && c.status == "True")
+        };
+        if holds("Failed") {
+            return Ok(ImportState::Failed(format!(
+                "dump import Job {} failed",
+                job.metadata.name.unwrap_or_default()
+            )));
+        }
+        done &= holds("Complete");
+    }
+    Ok(if done {
+        ImportState::Done
+    } else {
+        ImportState::Running
+    })
+}
+
+async fn create_if_missing<K>(api: &Api<K>, obj: &K) -> Result<K, ReconcileError>
+where
+    K: Resource + Clone + serde::de::DeserializeOwned + serde::Serialize + std::fmt::Debug,
+{
+    let name = obj.meta().name.clone().unwrap_or_default();
+    if let Some(existing) = api.get_opt(&name).await? {
+        return Ok(existing);
+    }
+    match api.create(&PostParams::default(), obj).await {
+        Ok(created) => Ok(created),
+        Err(kube::Error::Api(ae)) if ae.code == 409 => Ok(api.get(&name).await?),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn delete_import_jobs(client: &Client, ns: &str, target: &str) -> Result<(), ReconcileError> {
+    let jobs: Api<Job> = Api::namespaced(client.clone(), ns);
+    let lp = ListParams::default().labels(&format!("{}={}", IMPORT_FOR_LABEL, target));
+    let _ = jobs
+        .delete_collection(&DeleteParams::background(), &lp)
+        .await?;
+    Ok(())
+}
+
+/// Shell run by an import Job: start Meilisearch on the target volume with the dump, wait
+/// until it answers (the import finishes before it starts listening), then stop it.
+fn import_script(dump_uid: &str) -> String {
+    format!(
+        r#"meilisearch --db-path /meili_data/data.ms --http-addr 127.0.0.1:7700 --no-analytics \
+  --import-dump /source_data/dumps/{dump_uid}.dump --ignore-dump-if-db-exists &
+pid=$!
+until wget -q -O /dev/null http://127.0.0.1:7700/health; do
+  kill -0 "$pid" 2>/dev/null || {{ wait "$pid"; exit 1; }}
+  sleep 2
+done
+kill "$pid"
+wait "$pid" || true
+"#
+    )
+}
+
+/// One-shot Job importing the dump from the source StatefulSet's first pod volume onto
+/// `data-<target>-<replica>`. The source volume is ReadWriteOnce, so the Job runs next to
+/// the source pod and mounts it read-only.
+#[allow(clippy::too_many_arguments)]
+fn import_job(
+    name: &str,
+    target: &str,
+    replica: i32,
+    image: &str,
+    source: &str,
+    dump_uid: &str,
+    spec: &ServerSpec,
+    owner: &OwnerReference,
+) -> Job {
+    use k8s_openapi::api::batch::v1::JobSpec;
+    use k8s_openapi::api::core::v1::{
+        Affinity, Container, PersistentVolumeClaimVolumeSource, PodAffinity, PodAffinityTerm,
+        PodSpec, PodTemplateSpec, Volume, VolumeMount,
+    };
+    // Dumps live under dumps/ on either the dedicated dump volume or the data volume.
+    let dump_claim = if spec.dump_storage.is_some() {
+        "dumps"
+    } else {
+        "data"
+    };
+    let claim = |volume: &str, claim: String, read_only: bool| Volume {
+        name: volume.into(),
+        persistent_volume_claim: Some(PersistentVolumeClaimVolumeSource {
+            claim_name: claim,
+            read_only: Some(read_only),
+        }),
+        ..Default::default()
+    };
+    let mut labels = children::managed_labels(Some(name));
+    labels.insert(IMPORT_FOR_LABEL.into(), target.into());
+    // A sidecar proxy would keep the pod, and so the Job, running
+    let annotations = spec.mesh.map(|mesh| {
+        BTreeMap::from([match mesh {
+            ServiceMesh::Istio => ("sidecar.istio.io/inject".to_string(), "false".to_string()),
+            ServiceMesh::Linkerd => ("linkerd.io/inject".to_string(), "disabled".to_string()),
+        }])
+    });
+    Job {
+        metadata: kube::core::ObjectMeta {
+            name: Some(format!("{}-import-{}", target, replica)),
+            labels: Some(labels.clone()),
+            owner_references: Some(vec![owner.clone()]),
+            ..Default::default()
+        },
+        spec: Some(JobSpec {
+            backoff_limit: Some(2),
+            active_deadline_seconds: Some(IMPORT_DEADLINE_SECONDS),
+            template: PodTemplateSpec {
+                metadata: Some(kube::core::ObjectMeta {
+                    labels: Some(labels),
+                    annotations,
+                    ..Default::default()
+                }),
+                spec: Some(PodSpec {
+                    restart_policy: Some("Never".into()),
+                    priority_class_name: spec.priority_class_name.clone(),
+                    containers: vec![Container {
+                        name: "import".into(),
+                        image: Some(image.to_string()),
+                        image_pull_policy: spec.image_pull_policy.map(|p| p.as_str().to_string()),
+                        command: Some(vec!["/bin/sh".into(), "-c".into(), import_script(dump_uid)]),
+                        env: Some(meili_env(name, spec)),
+                        volume_mounts: Some(vec![
+                            VolumeMount {
+                                name: "data".into(),
+                                mount_path: "/meili_data".into(),
+                                ..Default::default()
+                            },
+                            VolumeMount {
+                                name: "source".into(),
+                                mount_path: "/source_data".into(),
+                                read_only: Some(true),
+                                ..Default::default()
+                            },
+                        ]),
+                        ..Default::default()
+                    }],
+                    volumes: Some(vec![
+                        claim("data", format!("data-{}-{}", target, replica), false),
+                        claim("source", format!("{}-{}-0", dump_claim, source), true),
+                    ]),
+                    affinity: Some(Affinity {
+                        pod_affinity: Some(PodAffinity {
+                            required_during_scheduling_ignored_during_execution: Some(vec![
+                                PodAffinityTerm {
+                                    label_selector: Some(LabelSelector {
+                                        match_labels: Some(BTreeMap::from([(
+                                            "statefulset.kubernetes.io/pod-name".to_string(),
+                                            format!("{}-0", source),
+                                        )])),
+                                        ..Default::default()
+                                    }),
+                                    topology_key: "kubernetes.io/hostname".into(),
+                                    ..Default::default()
+                                },
+                            ]),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+async fn switch_over(
+    ctx: &UpgradeCtx<'_>,
+    ns: &str,
+    name: &str,
+    old: &str,
+    up: &UpgradeStatus,
+) -> Result<(), ReconcileError> {
+    ensure_service(
+        ctx.client,
+        ns,
+        name,
+        &up.target_statefulset,
+        Some(name),
+        ServiceNetwork::of(&ctx.server.spec),
+        Some(ctx.owner),
+    )
+    .await?;
+    delete_statefulset(ctx.client, ns, old).await?;
+    // The original StatefulSet is governed by the main Service; later ones have their own.
+    if old != name {
+        delete_service(ctx.client, ns, old).await?;
+    }
+    Ok(())
+}
+
+async fn delete_service(client: &Client, ns: &str, name: &str) -> Result<(), ReconcileError> {
+    let services: Api<Service> = Api::namespaced(client.clone(), ns);
+    match services.delete(name, &Default::default()).await {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(ae)) if ae.code == 404 => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn canary_elapsed(up: &UpgradeStatus) -> i64 {
+    up.canary_started_at
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| (Utc::now() - t.with_timezone(&Utc)).num_seconds())
+        .unwrap_or(0)
+}
+
+/// Route `weight`% of the HTTPRoute's traffic for the Server Service to `canary`;
+/// a weight of 0 removes the canary backend again.
+async fn set_route_weight(
+    ctx: &UpgradeCtx<'_>,
+    ns: &str,
+    name: &str,
+    canary: &str,
+    weight: i32,
+) -> Result<(), ReconcileError> {
+    let Some(spec) = ctx.server.spec.canary.as_ref() else {
+        return Ok(());
+    };
+    let gvk = GroupVersionKind::gvk("gateway.networking.k8s.io", "v1", "HTTPRoute");
+    let routes: Api<DynamicObject> =
+        Api::namespaced_with(ctx.client.clone(), ns, &ApiResource::from_gvk(&gvk));
+    let route = routes.get(&spec.http_route).await?;
+    let mut rules = route.data["spec"]["rules"].clone();
+    weight_backends(&mut rules, name, canary, weight, ctx.server.spec.port);
+    let patch = serde_json::json!({"spec": {"rules": rules}});
+    let _ = routes
+        .patch(
+            &spec.http_route,
+            &kube::api::PatchParams::default(),
+            &kube::api::Patch::Merge(&patch),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Rewrite backendRefs of every rule that targets `service` so that `weight`% goes to `canary`.
+fn weight_backends(
+    rules: &mut serde_json::Value,
+    service: &str,
+    canary: &str,
+    weight: i32,
+    port: u16,
+) {
+    let Some(rules) = rules.as_array_mut() else {
+        return;
+    };
+    for rule in rules {
+        let Some(refs) = rule.get_mut("backendRefs").and_then(|r| r.as_array_mut()) else {
+            continue;
+        };
+        if !refs.iter().any(|r| r["name"] == service) {
+            continue;
+        }
+        refs.retain(|r| r["name"] != canary);
+        for r in refs.iter_mut().filter(|r| r["name"] == service) {
+            if let Some(obj) = r.as_object_mut() {
+                if weight > 0 {
+                    obj.insert("weight".into(), (100 - weight).into());
+                } else {
+                    obj.remove("weight");
+                }
+            }
+        }
+        if weight > 0 {
+            refs.push(serde_json::json!({"name": canary, "port": port, "weight": weight}));
+        }
+    }
+}
+
+async fn delete_statefulset(client: &Client, ns: &str, name: &str) -> Result<(), ReconcileError> {
+    let api: Api<StatefulSet> = Api::namespaced(client.clone(), ns);
+    match api.delete(name, &Default::default()).await {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(ae)) if ae.code == 404 => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// -------- Meilisearch HTTP helpers --------
+
+#[derive(Debug, serde::Deserialize)]
+struct TaskRef {
+    #[serde(rename = "taskUid")]
+    task_uid: u64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TaskView {
+    status: String,
+    details: Option<TaskDetails>,
+    error: Option<TaskError>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TaskDetails {
+    #[serde(rename = "dumpUid")]
+    dump_uid: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TaskError {
+    message: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct IndexesPage {
+    results: Vec<IndexRef>,
+    total: usize,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct IndexRef {
+    uid: String,
+}
+
+async fn create_dump(
+    http: &reqwest::Client,
+    endpoint: &str,
+    master_key: &str,
+) -> Result<u64, ReconcileError> {
+    let task = http
+        .post(format!("{}/dumps", endpoint))
+        .bearer_auth(master_key)
+        .send_checked()
+        .await
+        .map_err(anyhow::Error::from)?
+        .json::<TaskRef>()
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(task.task_uid)
+}
+
+async fn get_task(
+    http: &reqwest::Client,
+    endpoint: &str,
+    master_key: &str,
+    uid: u64,
+) -> Result<TaskView, ReconcileError> {
+    let task = http
+        .get(format!("{}/tasks/{}", endpoint, uid))
+        .bearer_auth(master_key)
+        .send_checked()
+        .await
+        .map_err(anyhow::Error::from)?
+        .json::<TaskView>()
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(task)
+}
+
+async fn list_indexes(
+    http: &reqwest::Client,
+    endpoint: &str,
+    master_key: &str,
+) -> anyhow::Result<IndexesPage> {
+    Ok(http
+        .get(format!("{}/indexes?limit=1", endpoint))
+        .bearer_auth(master_key)
+        .send_checked()
+        .await?
+        .json::<IndexesPage>()
+        .await?)
+}
+
+/// Health, index count parity with the serving instance, and a smoke search.
+async fn verify(
+    http: &reqwest::Client,
+    current: &str,
+    target: &str,
+    master_key: &str,
+) -> anyhow::Result<()> {
+    http.get(format!("{}/health", target))
+        .send_checked()
+        .await?;
+    let expected = list_indexes(http, current, master_key).await?;
+    let restored = list_indexes(http, target, master_key).await?;
+    if restored.total != expected.total {
+        anyhow::bail!("restored {} of {} indexes", restored.total, expected.total);
+    }
+    if let Some(idx) = restored.results.first() {
+        search(http, target, master_key, &idx.uid).await?;
+    }
+    Ok(())
+}
+
+async fn search(
+    http: &reqwest::Client,
+    endpoint: &str,
+    master_key: &str,
+    index_uid: &str,
+) -> anyhow::Result<()> {
+    http.post(format!("{}/indexes/{}/search", endpoint, index_uid))
+        .bearer_auth(master_key)
+        .json(&serde_json::json!({"q": "", "limit": 1}))
+        .send_checked()
+        .await?;
+    Ok(())
+}
+
+/// Health check plus an empty search on the first index, if there is one.
+async fn smoke_search(
+    http: &reqwest::Client,
+    endpoint: &str,
+    master_key: &str,
+) -> anyhow::Result<()> {
+    http.get(format!("{}/health", endpoint))
+        .send_checked()
+        .await?;
+    if let Some(idx) = list_indexes(http, endpoint, master_key)
+        .await?
+        .results
+        .first()
+    {
+        search(http, endpoint, master_key, &idx.uid).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alternates_target_statefulset() {
+        assert_eq!(next_statefulset("meili", "meili"), "meili-green");
+        assert_eq!(next_statefulset("meili", "meili-green"), "meili-blue");
+        assert_eq!(next_statefulset("meili", "meili-blue"), "meili-green");
+    }
+
+    #[test]
+    fn shifts_and_restores_route_weights() {
+        let mut rules = serde_json::json!([
+            {"backendRefs": [{"name": "meili", "port": 7700}]},
+            {"backendRefs": [{"name": "other", "port": 80}]}
+        ]);
+        weight_backends(&mut rules, "meili", "meili-green", 10, 7700);
+        assert_eq!(rules[0]["backendRefs"][0]["weight"], 90);
+        assert_eq!(rules[0]["backendRefs"][1]["name"], "meili-green");
+        assert_eq!(rules[0]["backendRefs"][1]["weight"], 10);
+        assert_eq!(rules[1]["backendRefs"].as_array().unwrap().len(), 1);
+
+        weight_backends(&mut rules, "meili", "meili-green", 0, 7700);
+        assert_eq!(
+            rules[0]["backendRefs"],
+            serde_json::json!([{"name": "meili", "port": 7700}])
+        );
+    }
+
+    #[test]
+    fn import_job_restores_the_dump_next_to_the_source() {
+        let spec = ServerSpec {
+            storage: Some("5Gi".into()),
+            ..Default::default()
+        };
+        let owner = OwnerReference {
+            name: "meili-a".into(),
+            ..Default::default()
+        };
+        let job = import_job(
+            "meili-a",
+            "meili-a-green",
+            0,
+            "getmeili/meilisearch:v1.12.0",
+            "meili-a",
+            "20250101-000000000",
+            &spec,
+            &owner,
+        );
+        assert_eq!(job.metadata.name.as_deref(), Some("meili-a-green-import-0"));
+        let pod = job.spec.unwrap().template.spec.unwrap();
+        let script = pod.containers[0].command.clone().unwrap()[2].clone();
+        assert!(script.contains("/source_data/dumps/20250101-000000000.dump"));
+        let claims: Vec<String> = pod
+            .volumes
+            .unwrap()
+            .iter()
+            .map(|v| v.persistent_volume_claim.clone().unwrap().claim_name)
+            .collect();
+        assert_eq!(claims, ["data-meili-a-green-0", "data-meili-a-0"]);
+        assert!(pod.affinity.is_some());
+        // master key still comes from the Server's secret
+        let env = pod.containers[0].env.clone().unwrap();
+        let secret = env[0].value_from.clone().unwrap().secret_key_ref.unwrap();
+        assert_eq!(secret.name, "meili-a-meili-master");
+    }
+}
@@ -0,0 +1,150 @@
+use semver::{Version, VersionReq};
+use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+use crate::error::ReconcileError;
+
+const DEFAULT_RELEASES_URL: &str =
+    "https://api.github.com/repos/meilisearch/meilisearch/releases?per_page=100";
+const CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Resolves `spec.version` constraints against published Meilisearch releases.
+///
+/// The release list is fetched from GitHub by default; `MEILI_RELEASES_URL` may point at any
+/// endpoint returning either GitHub-style releases or a registry `tags/list` document.
+pub struct VersionResolver {
+    http: reqwest::Client,
+    source_url: String,
+    cache: Mutex<Option<(Instant, Vec<Version>)>>,
+}
+
+impl VersionResolver {
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("MEILI_RELEASES_URL").unwrap_or_else(|_| DEFAULT_RELEASES_URL.into()),
+        )
+    }
+
+    pub fn new(source_url: String) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .user_agent("meilisearch-operator")
+            .build()
+            .expect("build http client");
+        Self {
+            http,
+            source_url,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Highest published version satisfying `constraint`, e.g. "~1.12" or "^1".
+    pub async fn resolve(&self, constraint: &str) -> Result<Version, ReconcileError> {
+        let req = VersionReq::parse(constraint)
+            .map_err(|e| anyhow::anyhow!("invalid version constraint {:?}: {}", constraint, e))?;
+        let versions = self.versions().await?;
+        pick(&versions, &req).ok_or_else(|| {
+            anyhow::anyhow!("no Meilisearch release satisfies {:?}", constraint).into()
+        })
+    }
+
+    async fn versions(&self) -> Result<Vec<Version>, ReconcileError> {
+        let mut cache = self.cache.lock().await;
+        if let Some((fetched, versions)) = cache.as_ref()
+            && fetched.elapsed() < CACHE_TTL
+        {
+            return Ok(versions.clone());
+        }
+        let body = self
+            .http
+            .get(&self.source_url)
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?
+            .error_for_status()
+            .map_err(anyhow::Error::from)?
+            .json::<serde_json::Value>()
+            .await
+            .map_err(anyhow::Error::from)?;
+        let versions = parse_versions(&body);
+        *cache = Some((Instant::now(), versions.clone()));
+        Ok(versions)
+    }
+}
+
+/// Accepts GitHub releases (`[{"tag_name": "v1.12.0", ...}]`) or registry tags (`{"tags": [...]}`).
+fn parse_versions(body: &serde_json::Value) -> Vec<Version> {
+    let tags: Vec<&str> = if let Some(releases) = body.as_array() {
+        releases
+            .iter()
+            .filter(|r| r["draft"] != true && r["prerelease"] != true)
+            .filter_map(|r| r["tag_name"].as_str())
+            .collect()
+    } else {
+        body["tags"]
+            .as_array()
+            .map(|t| t.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default()
+    };
+    tags.into_iter()
+        .filter_map(|t| Version::parse(t.trim_start_matches('v')).ok())
+        .collect()
+}
+
+fn pick(versions: &[Version], req: &VersionReq) -> Option<Version> {
+    versions
+        .iter()
+        .filter(|v| v.pre.is_empty() && req.matches(v))
+        .max()
+        .cloned()
+}
+
+/// Image reference for a resolved version, keeping the repository of `image`.
+pub fn image_for_version(image: &str, version: &Version) -> String {
+    format!("{}:v{}", image_repository(image), version)
+}
+
+/// Strip tag and digest from an image reference, leaving registry ports intact.
+fn image_repository(image: &str) -> &str {
+    let image = image.split('@').next().unwrap_or(image);
+    match (image.rfind(':'), image.rfind('/')) {
+        (Some(colon), Some(slash)) if colon > slash => &image[..colon],
+        (Some(colon), None) => &image[..colon],
+        _ => image,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_highest_matching_release() {
+        let body = serde_json::json!([
+            {"tag_name": "v1.12.0"},
+            {"tag_name": "v1.12.8"},
+            {"tag_name": "v1.13.0"},
+            {"tag_name": "v1.12.9-rc.0", "prerelease": true},
+        ]);
+        let versions = parse_versions(&body);
+        let req = VersionReq::parse("~1.12").unwrap();
+        assert_eq!(pick(&versions, &req), Some(Version::new(1, 12, 8)));
+
+        let tags = parse_versions(&serde_json::json!({"tags": ["latest", "v1.11.1", "v1.11.3"]}));
+        let req = VersionReq::parse("^1.11").unwrap();
+        assert_eq!(pick(&tags, &req), Some(Version::new(1, 11, 3)));
+    }
+
+    #[test]
+    fn keeps_image_repository() {
+        let v = Version::new(1, 12, 8);
+        assert_eq!(
+            image_for_version("getmeili/meilisearch:v1.24.0", &v),
+            "getmeili/meilisearch:v1.12.8"
+        );
+        assert_eq!(
+            image_for_version("registry.local:5000/meili:v1.0.0", &v),
+            "registry.local:5000/meili:v1.12.8"
+        );
+    }
+}
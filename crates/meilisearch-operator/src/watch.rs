@@ -0,0 +1,107 @@
+use std::fmt::Debug;
+
+use kube::{
+    Api, Client, Resource, ResourceExt,
+    runtime::{
+        Controller, Predicate, WatchStreamExt,
+        controller::Config as ControllerConfig,
+        predicates,
+        reflector::{self, ObjectRef},
+        watcher,
+    },
+};
+use serde::de::DeserializeOwned;
+use tokio::time::Duration;
+
+use crate::{config::OperatorConfig, crds::server::Server, error::ReconcileError};
+
+/// Controller for a CRD that skips watch events changing only status (including the
+/// operator's own status patches) and debounces bursts of updates into one reconcile.
+///
+/// Spec edits bump `generation`; triggers, dry-run toggles and finalizers are metadata, so
+/// those are hashed too. Periodic resyncs still come from the reconcilers' requeues. The
+/// number of reconciles in flight is capped per kind (see [`OperatorConfig::workers`]).
+pub fn controller<K>(api: Api<K>, config: &OperatorConfig) -> Controller<K>
+where
+    K: Resource<DynamicType = ()> + Clone + DeserializeOwned + Debug + Send + Sync + 'static,
+{
+    let (reader, writer) = reflector::store();
+    let changes = predicates::generation
+        .combine(predicates::annotations)
+        .combine(predicates::labels)
+        .combine(predicates::finalizers)
+        .combine(deletion::<K>);
+    let stream = watcher(api, watcher::Config::default())
+        .default_backoff()
+        .reflect(writer)
+        .applied_objects()
+        .predicate_filter(changes);
+    Controller::for_stream(stream, reader)
+        .with_config(
+            ControllerConfig::default()
+                .debounce(config.reconcile_debounce)
+                .concurrency(config.workers(&K::kind(&()))),
+        )
+        .shutdown_on_signal()
+}
+
+/// Reconcile the dependents of a Server (as `server_of` names it: namespace, name) when it
+/// appears, becomes Ready or stops being Ready, so CRs waiting for it don't sit out a requeue.
+pub fn follow_servers<K>(
+    controller: Controller<K>,
+    client: Client,
+    server_of: fn(&K) -> (String, String),
+) -> Controller<K>
+where
+    K: Resource<DynamicType = ()> + Clone + DeserializeOwned + Debug + Send + Sync + 'static,
+{
+    let dependents = controller.store();
+    let readiness = |s: &Server| Some(s.status.as_ref().is_some_and(|st| st.ready) as u64);
+    let servers = watcher(Api::<Server>::all(client), watcher::Config::default())
+        .default_backoff()
+        .touched_objects()
+        .predicate_filter(readiness);
+    controller.watches_stream(servers, move |server| {
+        let target = (server.namespace().unwrap_or_default(), server.name_any());
+        dependents
+            .state()
+            .into_iter()
+            .filter(|obj| server_of(obj) == target)
+            .map(|obj| ObjectRef::from_obj(obj.as_ref()))
+            .collect::<Vec<_>>()
+    })
+}
+
+fn deletion<K: Resource>(obj: &K) -> Option<u64> {
+    obj.meta().deletion_timestamp.as_ref().map(|_| 1)
+}
+
+/// Run a reconcile, giving up with [`ReconcileError::TimedOut`] after `limit`. Dropping the
+/// future aborts whatever Meilisearch call it was waiting on.
+pub async fn bounded<T>(
+    limit: Option<Duration>,
+    reconcile: impl Future<Output = Result<T, ReconcileError>>,
+) -> Result<T, ReconcileError> {
+    match limit {
+        Some(limit) => tokio::time::timeout(limit, reconcile)
+            .await
+            .unwrap_or(Err(ReconcileError::TimedOut(limit))),
+        None => reconcile.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn bounded_gives_up_after_limit() {
+        let slow = async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        };
+        let res = bounded(Some(Duration::from_millis(10)), slow).await;
+        assert!(matches!(res, Err(ReconcileError::TimedOut(_))));
+        assert_eq!(bounded(None, async { Ok(1) }).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    Method, Request, Response, StatusCode,
+    body::{Bytes, Incoming},
+    service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use kube::{
+    Client,
+    core::{DynamicObject, admission::AdmissionReview, conversion::ConversionReview},
+};
+use tokio::net::TcpListener;
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        self,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    },
+};
+use tracing::{debug, warn};
+
+use crate::{admission, conversion};
+
+/// Where cert-manager (or any other issuer) mounts the serving certificate.
+pub const DEFAULT_CERT_DIR: &str = "/tmp/k8s-webhook-server/serving-certs";
+
+/// Serve the CRD conversion (`/convert`) and `server_ref` validation (`/validate`) webhooks on
+/// `addr` over TLS with `tls.crt`/`tls.key` from `cert_dir`.
+pub async fn serve(addr: SocketAddr, cert_dir: PathBuf, client: Client) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let cert_dir = cert_dir.clone();
+        let client = client.clone();
+        tokio::spawn(async move {
+            // Certificates are re-read per connection so rotations apply without a restart
+            let acceptor = match tls_acceptor(&cert_dir) {
+                Ok(a) => a,
+                Err(e) => {
+                    warn!(error = ?e, "webhook certificate unavailable");
+                    return;
+                }
+            };
+            let tls = match acceptor.accept(stream).await {
+                Ok(tls) => tls,
+                Err(e) => {
+                    debug!(error = ?e, %peer, "webhook TLS handshake failed");
+                    return;
+                }
+            };
+            if let Err(e) = hyper::server::conn::http1::Builder::new()
+                .serve_connection(
+                    TokioIo::new(tls),
+                    service_fn(|req| handle(client.clone(), req)),
+                )
+                .await
+            {
+                debug!(error = ?e, %peer, "webhook connection closed");
+            }
+        });
+    }
+}
+
+async fn handle(
+    client: Client,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let path = req.uri().path().to_string();
+    if req.method() != Method::POST || !matches!(path.as_str(), "/convert" | "/validate") {
+        return Ok(reply(StatusCode::NOT_FOUND, Bytes::new()));
+    }
+    let body = match req.into_body().collect().await {
+        Ok(b) => b.to_bytes(),
+        Err(e) => return Ok(reply(StatusCode::BAD_REQUEST, e.to_string().into())),
+    };
+    let out = if path == "/convert" {
+        let review: ConversionReview = match serde_json::from_slice(&body) {
+            Ok(r) => r,
+            Err(e) => return Ok(reply(StatusCode::BAD_REQUEST, e.to_string().into())),
+        };
+        serde_json::to_vec(&conversion::review(review))
+    } else {
+        let review: AdmissionReview<DynamicObject> = match serde_json::from_slice(&body) {
+            Ok(r) => r,
+            Err(e) => return Ok(reply(StatusCode::BAD_REQUEST, e.to_string().into())),
+        };
+        serde_json::to_vec(&admission::review(&client, review).await)
+    };
+    Ok(reply(StatusCode::OK, out.unwrap_or_default().into()))
+}
+
+fn reply(status: StatusCode, body: Bytes) -> Response<Full<Bytes>> {
+    let mut resp = Response::new(Full::new(body));
+    *resp.status_mut() = status;
+    resp.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    resp
+}
+
+fn tls_acceptor(cert_dir: &Path) -> anyhow::Result<TlsAcceptor> {
+    let certs =
+        CertificateDer::pem_file_iter(cert_dir.join("tls.crt"))?.collect::<Result<Vec<_>, _>>()?;
+    let key = PrivateKeyDer::from_pem_file(cert_dir.join("tls.key"))?;
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
@@ -0,0 +1,238 @@
+//! End-to-end reconcile flows against a real cluster, normally the kind cluster from
+//! `make e2e-up` with the operator already deployed. Run with `make e2e` or
+//! `cargo test --features e2e --test e2e -- --nocapture`.
+#![cfg(feature = "e2e")]
+
+use std::process::{Child, Command, Stdio};
+
+use k8s_openapi::api::{
+    apps::v1::StatefulSet,
+    core::v1::{Namespace, Secret},
+};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::{
+    Api, Client, CustomResourceExt, ResourceExt,
+    api::{DeleteParams, Patch, PatchParams, PostParams},
+};
+use meilisearch_operator::crds::{
+    cluster::Cluster,
+    database_sync::DatabaseSync,
+    index::{self, Index},
+    index_export::IndexExport,
+    index_import::IndexImport,
+    index_template::IndexTemplate,
+    key::Key,
+    search_gateway::SearchGateway,
+    server::Server,
+    stream_sink::StreamSink,
+    tenant::Tenant,
+};
+use rand::{Rng, distr::Alphanumeric};
+use serde_json::json;
+use tokio::time::{Duration, Instant, sleep};
+
+const SERVER: &str = "meili";
+const LOCAL_PORT: u16 = 17700;
+
+/// Poll `check` every two seconds until it yields a value or `timeout` passes.
+async fn wait_for<T, F, Fut>(what: &str, timeout: Duration, mut check: F) -> T
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Option<T>>,
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(v) = check().await {
+            return v;
+        }
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        sleep(Duration::from_secs(2)).await;
+    }
+}
+
+async fn install_crds(client: &Client) {
+    let crds: Api<CustomResourceDefinition> = Api::all(client.clone());
+    let pp = PatchParams::apply("meilisearch-operator-e2e").force();
+    for crd in [
+        Server::crd(),
+        index::crd(),
+        Key::crd(),
+        Cluster::crd(),
+        IndexExport::crd(),
+        IndexImport::crd(),
+        StreamSink::crd(),
+        DatabaseSync::crd(),
+        SearchGateway::crd(),
+        Tenant::crd(),
+        IndexTemplate::crd(),
+    ] {
+        crds.patch(&crd.name_any(), &pp, &Patch::Apply(&crd))
+            .await
+            .expect("apply CRD");
+    }
+}
+
+/// `kubectl port-forward` to the Server's Service, killed on drop.
+struct PortForward(Child);
+
+impl PortForward {
+    fn start(ns: &str) -> Self {
+        let child = Command::new("kubectl")
+            .args([
+                "-n",
+                ns,
+                "port-forward",
+                &format!("svc/{}", SERVER),
+                &format!("{}:7700", LOCAL_PORT),
+            ])
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("spawn kubectl port-forward");
+        Self(child)
+    }
+}
+
+impl Drop for PortForward {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+    }
+}
+
+#[tokio::test]
+async fn reconcile_flows() {
+    let client = Client::try_default().await.expect("kubeconfig");
+    install_crds(&client).await;
+
+    let suffix: String = rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(6)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .collect();
+    let ns = format!("meili-e2e-{}", suffix);
+    let namespaces: Api<Namespace> = Api::all(client.clone());
+    let namespace = serde_json::from_value(json!({"metadata": {"name": ns}})).unwrap();
+    namespaces
+        .create(&PostParams::default(), &namespace)
+        .await
+        .expect("create namespace");
+
+    let servers: Api<Server> = Api::namespaced(client.clone(), &ns);
+    let keys: Api<Key> = Api::namespaced(client.clone(), &ns);
+    let indexes: Api<Index> = Api::namespaced(client.clone(), &ns);
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), &ns);
+    let statefulsets: Api<StatefulSet> = Api::namespaced(client.clone(), &ns);
+    let pp = PostParams::default();
+
+    // Server becomes ready once Meilisearch answers /health
+    let server: Server = serde_json::from_value(json!({
+        "apiVersion": "meili.operator.dev/v1beta1",
+        "kind": "Server",
+        "metadata": {"name": SERVER},
+        "spec": {},
+    }))
+    .unwrap();
+    servers.create(&pp, &server).await.expect("create Server");
+    wait_for("Server ready", Duration::from_secs(300), || async {
+        let s = servers.get(SERVER).await.ok()?;
+        s.status.filter(|st| st.ready).map(|_| ())
+    })
+    .await;
+
+    // Key writes the created key into its Secret
+    let key: Key = serde_json::from_value(json!({
+        "apiVersion": "meili.operator.dev/v1alpha1",
+        "kind": "Key",
+        "metadata": {"name": "search"},
+        "spec": {
+            "server_ref": SERVER,
+            "actions": ["search"],
+            "indexes": ["*"],
+            "secret_namespace": ns,
+            "secret_name": "search-key",
+        },
+    }))
+    .unwrap();
+    keys.create(&pp, &key).await.expect("create Key");
+    let search_key = wait_for("Key Secret", Duration::from_secs(120), || async {
+        let sec = secrets.get_opt("search-key").await.ok()??;
+        sec.data?.get("key").map(|v| v.0.clone())
+    })
+    .await;
+    assert_eq!(
+        search_key.len(),
+        64,
+        "search key has the Meilisearch key length"
+    );
+
+    // Index creates the index and applies its settings
+    let idx: Index = serde_json::from_value(json!({
+        "apiVersion": "meili.operator.dev/v1beta1",
+        "kind": "Index",
+        "metadata": {"name": "movies"},
+        "spec": {
+            "server_ref": {"name": SERVER},
+            "uid": "movies",
+            "primary_key": "id",
+            "settings": {"filterable_attributes": ["genre"], "stop_words": ["the"]},
+            "deletion_policy": "Delete",
+        },
+    }))
+    .unwrap();
+    indexes.create(&pp, &idx).await.expect("create Index");
+    wait_for("Index ready", Duration::from_secs(120), || async {
+        let i = indexes.get("movies").await.ok()?;
+        i.status.filter(|st| st.ready).map(|_| ())
+    })
+    .await;
+
+    let master_key = secrets
+        .get(&format!("{}-meili-master", SERVER))
+        .await
+        .expect("master key Secret")
+        .data
+        .and_then(|d| d.get("masterKey").map(|v| v.0.clone()))
+        .expect("masterKey data");
+    let master_key = String::from_utf8(master_key).unwrap();
+    let _forward = PortForward::start(&ns);
+    let http = reqwest::Client::new();
+    let settings: serde_json::Value =
+        wait_for("index settings", Duration::from_secs(60), || async {
+            http.get(format!(
+                "http://localhost:{}/indexes/movies/settings",
+                LOCAL_PORT
+            ))
+            .bearer_auth(&master_key)
+            .send()
+            .await
+            .ok()?
+            .json()
+            .await
+            .ok()
+        })
+        .await;
+    assert_eq!(settings["filterableAttributes"], json!(["genre"]));
+    assert_eq!(settings["stopWords"], json!(["the"]));
+
+    // Deleting the Server removes its dependents and workload
+    servers
+        .delete(SERVER, &DeleteParams::default())
+        .await
+        .expect("delete Server");
+    wait_for(
+        "dependents cleaned up",
+        Duration::from_secs(180),
+        || async {
+            let gone = servers.get_opt(SERVER).await.ok()?.is_none()
+                && indexes.get_opt("movies").await.ok()?.is_none()
+                && keys.get_opt("search").await.ok()?.is_none()
+                && statefulsets.get_opt(SERVER).await.ok()?.is_none();
+            gone.then_some(())
+        },
+    )
+    .await;
+
+    namespaces
+        .delete(&ns, &DeleteParams::default())
+        .await
+        .expect("delete namespace");
+}
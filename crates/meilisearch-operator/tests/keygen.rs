@@ -1,7 +1,7 @@
 #[test]
 fn generates_64_char_key() {
-    use rand::{Rng, distributions::Alphanumeric};
-    let key: String = rand::thread_rng()
+    use rand::{Rng, distr::Alphanumeric};
+    let key: String = rand::rng()
         .sample_iter(&Alphanumeric)
         .take(64)
         .map(char::from)